@@ -99,7 +99,7 @@ where
         .iter()
         .map(|range| match range {
             Some(range) => {
-                (range.max.clone() - range.min.clone()) * na::convert(rand::random())
+                (range.max.clone() - range.min.clone()) * na::convert(rand::random::<f64>())
                     + range.min.clone()
             }
             None => na::convert::<f64, T>(rand::random::<f64>() - 0.5) * na::convert(2.0 * PI),
@@ -123,6 +123,49 @@ fn bench_rctree(c: &mut Criterion) {
     });
 }
 
+fn create_serial_chain_30() -> k::Chain<f64> {
+    let nodes = (0..30)
+        .map(|i| {
+            let axis = match i % 3 {
+                0 => na::Vector3::x_axis(),
+                1 => na::Vector3::y_axis(),
+                _ => na::Vector3::z_axis(),
+            };
+            k::NodeBuilder::new()
+                .name(&format!("joint{i}"))
+                .translation(na::Translation3::new(0.0, 0.0, 0.1))
+                .joint_type(k::JointType::Rotational { axis })
+                .into_node()
+        })
+        .collect::<Vec<_>>();
+    for i in 1..nodes.len() {
+        nodes[i].set_parent(&nodes[i - 1]);
+    }
+    k::Chain::from_root(nodes[0].clone())
+}
+
+fn bench_rctree_30(c: &mut Criterion) {
+    let chain = create_serial_chain_30();
+    let angles = generate_random_joint_angles_from_limits(&[None; 30]);
+    c.bench_function("bench_rctree_30", |b| {
+        b.iter(|| {
+            chain.set_joint_positions(&angles).unwrap();
+            black_box(chain.update_transforms());
+        });
+    });
+}
+
+fn bench_flat_chain_30(c: &mut Criterion) {
+    let mut chain = create_serial_chain_30().to_flat();
+    let angles = generate_random_joint_angles_from_limits(&[None; 30]);
+    c.bench_function("bench_flat_chain_30", |b| {
+        b.iter(|| {
+            chain.set_joint_positions(&angles).unwrap();
+            black_box(chain.update_transforms());
+        });
+    });
+}
+
 fn bench_rctree_set_joints(c: &mut Criterion) {
     let chain = k::Chain::<f64>::from_urdf_file("urdf/sample.urdf").unwrap();
     let limits = chain
@@ -229,6 +272,8 @@ fn bench_rctree_concurrent_set_get_joints(c: &mut Criterion) {
 criterion_group!(
     benches,
     bench_rctree,
+    bench_rctree_30,
+    bench_flat_chain_30,
     bench_rctree_get_joints,
     bench_rctree_set_joints,
     bench_rctree_concurrent_set_joints,
@@ -0,0 +1,761 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+use super::chain::*;
+use super::errors::*;
+use super::funcs::*;
+use super::ik::*;
+use super::joint::*;
+use na::{Isometry3, Point3, RealField, Unit, UnitQuaternion, Vector2, Vector3};
+use nalgebra as na;
+use simba::scalar::SubsetOf;
+
+/// Tolerance to check the structure of the arm [m]
+const STRUCTURE_EPS: f64 = 1.0e-6;
+/// Tolerance to check the orientation of the wrist solutions [rad]
+const WRIST_ANGLE_EPS: f64 = 1.0e-5;
+
+/// Analytic IK solver for 6 DoF arms with a spherical wrist
+///
+/// The arm must have six rotational joints, and at the zero positions
+///
+/// - the axis of the 1st joint is perpendicular to the axis of the 2nd joint,
+/// - the axes of the 2nd and 3rd joints are parallel,
+/// - the axes of the last three joints intersect at one point (the wrist center).
+///
+/// The position of the wrist center is solved by the first three joints and
+/// the orientation by the last three joints, so there are up to eight solutions
+/// (shoulder, elbow and wrist flips). The structure is detected from the arm
+/// in every call, and `Error::UnsupportedKinematicsError` is returned if it is not supported.
+///
+/// # Examples
+///
+/// ```
+/// use k::prelude::*;
+/// use k::*;
+///
+/// let axes = [
+///     Vector3::z_axis(),
+///     Vector3::y_axis(),
+///     Vector3::y_axis(),
+///     Vector3::z_axis(),
+///     Vector3::y_axis(),
+///     Vector3::z_axis(),
+/// ];
+/// let offsets = [0.0, 0.3, 0.4, 0.15, 0.15, 0.0];
+/// let nodes = axes
+///     .iter()
+///     .zip(offsets.iter())
+///     .map(|(axis, z)| {
+///         NodeBuilder::new()
+///             .translation(Translation3::new(0.0, 0.0, *z))
+///             .joint_type(JointType::Rotational { axis: *axis })
+///             .into_node()
+///     })
+///     .collect::<Vec<_>>();
+/// for i in 1..nodes.len() {
+///     nodes[i].set_parent(&nodes[i - 1]);
+/// }
+/// let arm = SerialChain::<f64>::from_end(&nodes[5]);
+/// arm.set_joint_positions(&[0.1, 0.2, 0.3, 0.4, 0.5, 0.6]).unwrap();
+/// let target = arm.end_transform();
+/// arm.set_joint_positions(&[0.0, 0.3, 0.1, 0.5, 0.3, 0.5]).unwrap();
+///
+/// let solver = SphericalWristIkSolver::default();
+/// assert_eq!(solver.solutions(&arm, &target).unwrap().len(), 8);
+/// solver.solve(&arm, &target).unwrap();
+/// let (distance, angle) = arm.pose_error_magnitudes(&target);
+/// assert!(distance < 1e-6 && angle < 1e-6);
+/// ```
+#[derive(Debug, Clone)]
+pub struct SphericalWristIkSolver<T: RealField> {
+    /// If the distance is smaller than this value, it is reached (meters)
+    pub position_tolerance: T,
+    /// If the angle distance is smaller than this value, it is reached (radians)
+    ///
+    /// The solutions are checked by both magnitudes of `SerialChain::pose_error_magnitudes`,
+    /// as `JacobianIkSolver`.
+    pub orientation_tolerance: T,
+}
+
+impl<T> SphericalWristIkSolver<T>
+where
+    T: RealField + SubsetOf<f64>,
+{
+    /// Create instance of `SphericalWristIkSolver`.
+    ///
+    /// The tolerances are used to check the solutions by forward kinematics.
+    pub fn new(position_tolerance: T, orientation_tolerance: T) -> Self {
+        Self {
+            position_tolerance,
+            orientation_tolerance,
+        }
+    }
+
+    /// Set the position tolerance (meters) and the orientation tolerance (radians)
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut solver = k::SphericalWristIkSolver::<f64>::default();
+    /// solver.set_tolerances(0.01, 1.0f64.to_radians());
+    /// assert_eq!(solver.position_tolerance, 0.01);
+    /// ```
+    pub fn set_tolerances(&mut self, position_tolerance: T, orientation_tolerance: T) {
+        self.position_tolerance = position_tolerance;
+        self.orientation_tolerance = orientation_tolerance;
+    }
+
+    /// Calculate all the solutions for `target_pose`
+    ///
+    /// The positions are in `(-pi, pi]`, and the joint limits are not checked.
+    /// Only the solutions which reach `target_pose` within the tolerances are returned,
+    /// so the result is empty if the target is out of reach.
+    /// The joint positions of `arm` are not changed.
+    pub fn solutions(
+        &self,
+        arm: &SerialChain<T>,
+        target_pose: &Isometry3<T>,
+    ) -> Result<Vec<Vec<T>>, Error> {
+        let orig_positions = arm.joint_positions();
+        let structure = WristStructure::new(arm);
+        let solutions = structure.map(|structure| {
+            structure
+                .solve(target_pose)
+                .into_iter()
+                .filter(|positions| {
+                    arm.set_joint_positions_unchecked(positions);
+                    let (distance, angle) = arm.pose_error_magnitudes(target_pose);
+                    distance < self.position_tolerance && angle < self.orientation_tolerance
+                })
+                .collect()
+        });
+        arm.set_joint_positions_unchecked(&orig_positions);
+        solutions
+    }
+
+    /// Calculate the solution which is the nearest to the current positions within the limits
+    ///
+    /// Each position is shifted by `2pi` to be close to the current position if it is
+    /// within the limits. The joint positions of `arm` are not changed.
+    pub fn nearest_solution(
+        &self,
+        arm: &SerialChain<T>,
+        target_pose: &Isometry3<T>,
+    ) -> Result<Option<Vec<T>>, Error> {
+        let current = arm.joint_positions();
+        let limits = arm
+            .iter_joints()
+            .map(|joint| joint.limits.clone())
+            .collect::<Vec<_>>();
+        let distance = |positions: &[T]| {
+            positions
+                .iter()
+                .zip(current.iter())
+                .fold(T::zero(), |sum, (p, c)| {
+                    sum + (p.clone() - c.clone()) * (p.clone() - c.clone())
+                })
+        };
+        Ok(self
+            .solutions(arm, target_pose)?
+            .into_iter()
+            .filter_map(|positions| {
+                positions
+                    .into_iter()
+                    .zip(current.iter().zip(limits.iter()))
+                    .map(|(position, (current, limits))| {
+                        let shifted =
+                            current.clone() + wrap_angle(position.clone() - current.clone());
+                        match limits {
+                            None => Some(shifted),
+                            Some(range) if range.is_valid(shifted.clone()) => Some(shifted),
+                            Some(range) if range.is_valid(position.clone()) => Some(position),
+                            Some(_) => None,
+                        }
+                    })
+                    .collect::<Option<Vec<_>>>()
+            })
+            .min_by(|a, b| {
+                distance(a)
+                    .partial_cmp(&distance(b))
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            }))
+    }
+}
+
+impl<T> Default for SphericalWristIkSolver<T>
+where
+    T: RealField + SubsetOf<f64>,
+{
+    fn default() -> Self {
+        Self::new(na::convert(0.001), na::convert(0.005))
+    }
+}
+
+impl<T> InverseKinematicsSolver<T> for SphericalWristIkSolver<T>
+where
+    T: RealField + SubsetOf<f64>,
+{
+    /// Set the nearest solution to the joint positions of `arm`
+    ///
+    /// Only the default constraints (all true) are supported.
+    fn solve_with_constraints(
+        &self,
+        arm: &SerialChain<T>,
+        target_pose: &Isometry3<T>,
+        constraints: &Constraints,
+    ) -> Result<(), Error> {
+        if define_operational_space(constraints) != [true; 6]
+            || !constraints.ignored_joint_names.is_empty()
+        {
+            return Err(Error::UnsupportedKinematicsError {
+                reason: "constraints are not supported by the analytic solver".to_owned(),
+            });
+        }
+        match self.nearest_solution(arm, target_pose)? {
+            Some(positions) => arm.set_joint_positions(&positions),
+            None => {
+                let end = arm.calc_end_transform();
+                let position_diff = &target_pose.translation.vector - &end.translation.vector;
+                let rotation_diff = end
+                    .rotation
+                    .rotation_to(&target_pose.rotation)
+                    .scaled_axis();
+                Err(Error::NotConvergedError {
+                    num_tried: 1,
+                    position_diff: position_diff.map(|v| na::try_convert(v).unwrap_or_default()),
+                    rotation_diff: rotation_diff.map(|v| na::try_convert(v).unwrap_or_default()),
+                })
+            }
+        }
+    }
+}
+
+/// Axes and points of the arm at the zero positions, in the world frame
+struct WristStructure<T: RealField> {
+    axes: Vec<Vector3<T>>,
+    p1: Vector3<T>,
+    p2: Vector3<T>,
+    p3: Vector3<T>,
+    /// 1 if the axes of the 2nd and 3rd joints have the same direction, -1 if opposite
+    sign3: T,
+    wrist_center: Vector3<T>,
+    /// the wrist center in the end frame
+    wrist_center_in_end: Point3<T>,
+    end_rotation: UnitQuaternion<T>,
+}
+
+impl<T> WristStructure<T>
+where
+    T: RealField + SubsetOf<f64>,
+{
+    fn new(arm: &SerialChain<T>) -> Result<Self, Error> {
+        let unsupported = |reason: &str| Error::UnsupportedKinematicsError {
+            reason: reason.to_owned(),
+        };
+        if arm.dof() != 6 {
+            return Err(Error::PreconditionError {
+                dof: arm.dof(),
+                necessary_dof: 6,
+            });
+        }
+        let eps: T = na::convert(STRUCTURE_EPS);
+        arm.set_joint_positions_unchecked(&vec![T::zero(); 6]);
+        let end = arm.calc_end_transform();
+        arm.update_transforms();
+        let mut axes = Vec::with_capacity(6);
+        let mut points = Vec::with_capacity(6);
+        for node in arm.iter() {
+            let joint = node.joint();
+            match &joint.joint_type {
+                JointType::Fixed => {}
+                JointType::Rotational { axis } => {
+                    let trans = joint.world_transform().expect("cache must exist");
+                    axes.push((trans.rotation * axis).into_inner());
+                    points.push(trans.translation.vector);
+                }
+                JointType::Linear { .. } => {
+                    return Err(Error::NotRotationalJointError {
+                        joint_name: joint.name.clone(),
+                    })
+                }
+            }
+        }
+        if axes[0].dot(&axes[1]).abs() > eps {
+            return Err(unsupported("the 1st and 2nd axes must be perpendicular"));
+        }
+        if axes[1].cross(&axes[2]).norm() > eps {
+            return Err(unsupported("the 2nd and 3rd axes must be parallel"));
+        }
+        if axes[3].cross(&axes[4]).norm() < eps || axes[4].cross(&axes[5]).norm() < eps {
+            return Err(unsupported("the wrist axes must not be parallel"));
+        }
+        let (c4, c5) = closest_points(
+            &Point3::from(points[3].clone()),
+            &axes[3],
+            &Point3::from(points[4].clone()),
+            &axes[4],
+        );
+        let wrist_center = (&c4.coords + &c5.coords) * na::convert::<f64, T>(0.5);
+        let from6 = &wrist_center - &points[5];
+        if (&c4 - &c5).norm() > eps || (&from6 - &axes[5] * axes[5].dot(&from6)).norm() > eps {
+            return Err(unsupported("the wrist axes must intersect at one point"));
+        }
+        let sign3 = if axes[1].dot(&axes[2]) > T::zero() {
+            T::one()
+        } else {
+            -T::one()
+        };
+        Ok(Self {
+            p1: points[0].clone(),
+            p2: points[1].clone(),
+            p3: points[2].clone(),
+            sign3,
+            wrist_center_in_end: end.inverse() * Point3::from(wrist_center.clone()),
+            wrist_center,
+            end_rotation: end.rotation,
+            axes,
+        })
+    }
+
+    /// Calculate the candidates of the solutions (not checked)
+    fn solve(&self, target_pose: &Isometry3<T>) -> Vec<Vec<T>> {
+        let eps: T = na::convert(STRUCTURE_EPS);
+        let a1 = &self.axes[0];
+        let a2 = &self.axes[1];
+        let target_wrist_center = (target_pose * &self.wrist_center_in_end).coords;
+        let mut solutions = Vec::new();
+
+        // q1: keep the offset along the 2nd axis
+        let r = &target_wrist_center - &self.p1;
+        let e2 = a1.cross(a2);
+        let (rx, ry) = (r.dot(a2), r.dot(&e2));
+        let rho = rx.clone().hypot(ry.clone());
+        let offset = (&self.wrist_center - &self.p1).dot(a2);
+        let q1_candidates = if rho < eps {
+            // singular: the wrist center is on the 1st axis
+            vec![T::zero()]
+        } else {
+            let psi = ry.atan2(rx);
+            match safe_acos(offset / rho, eps.clone()) {
+                Some(alpha) => vec![psi.clone() + alpha.clone(), psi - alpha],
+                None => Vec::new(),
+            }
+        };
+
+        // 2D coordinates on the plane perpendicular to the 2nd axis
+        let v = a2.cross(a1);
+        let to_2d = |x: &Vector3<T>| Vector2::new(x.dot(a1), x.dot(&v));
+        let d1 = to_2d(&(&self.p3 - &self.p2));
+        let d2 = to_2d(&(&self.wrist_center - &self.p3));
+        let (n1, n2) = (d1.norm(), d2.norm());
+        if n1 < eps || n2 < eps {
+            return solutions;
+        }
+        let angle_2d = |x: &Vector2<T>| x.y.clone().atan2(x.x.clone());
+        for q1 in q1_candidates {
+            let rot1 = rotation(a1, q1.clone());
+            let x = rot1.inverse() * &r + &self.p1;
+            let w = to_2d(&(&x - &self.p2));
+            let c = (w.norm_squared() - n1.clone() * n1.clone() - n2.clone() * n2.clone())
+                / (na::convert::<f64, T>(2.0) * n1.clone() * n2.clone());
+            let Some(beta) = safe_acos(c, eps.clone()) else {
+                continue;
+            };
+            for theta3 in [beta.clone(), -beta] {
+                let theta3 = theta3 + angle_2d(&d1) - angle_2d(&d2);
+                let rotated_d2 = na::Rotation2::new(theta3.clone()) * &d2;
+                let theta2 = angle_2d(&w) - angle_2d(&(&d1 + rotated_d2));
+                let q3 = self.sign3.clone() * theta3.clone();
+                let rot123 =
+                    &rot1 * rotation(a2, theta2.clone()) * rotation(&self.axes[2], q3.clone());
+                let m =
+                    rot123.inverse() * target_pose.rotation.clone() * self.end_rotation.inverse();
+                for (q4, q5, q6) in self.solve_wrist(&m) {
+                    solutions.push(
+                        [q1.clone(), theta2.clone(), q3.clone(), q4, q5, q6]
+                            .into_iter()
+                            .map(wrap_angle)
+                            .collect(),
+                    );
+                }
+            }
+        }
+        solutions
+    }
+
+    /// Solve `rot(a4, q4) * rot(a5, q5) * rot(a6, q6) = m`
+    fn solve_wrist(&self, m: &UnitQuaternion<T>) -> Vec<(T, T, T)> {
+        solve_three_axes(&self.axes[3], &self.axes[4], &self.axes[5], m)
+    }
+}
+
+impl<T> SerialChain<T>
+where
+    T: RealField + SubsetOf<f64>,
+{
+    /// Move the last three joints (the wrist) to make the orientation of the end `target_rotation`
+    ///
+    /// The other joints are not moved. The last three movable joints must be rotational,
+    /// and the adjacent axes must not be parallel. The axes do not need to intersect,
+    /// because only the orientation is solved. The solution which is the nearest to the current
+    /// positions within the limits is used. `NotConvergedError` is returned if the orientation
+    /// can not be achieved, and the positions are not changed in that case.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let axes = [
+    ///     Vector3::z_axis(),
+    ///     Vector3::y_axis(),
+    ///     Vector3::z_axis(),
+    ///     Vector3::y_axis(),
+    ///     Vector3::z_axis(),
+    /// ];
+    /// let nodes = axes
+    ///     .iter()
+    ///     .map(|axis| {
+    ///         NodeBuilder::new()
+    ///             .translation(Translation3::new(0.0, 0.0, 0.2))
+    ///             .joint_type(JointType::Rotational { axis: *axis })
+    ///             .into_node()
+    ///     })
+    ///     .collect::<Vec<_>>();
+    /// for i in 1..nodes.len() {
+    ///     nodes[i].set_parent(&nodes[i - 1]);
+    /// }
+    /// let arm = SerialChain::<f64>::from_end(&nodes[4]);
+    /// arm.set_joint_positions(&[0.3, 0.5, 0.0, 0.0, 0.0]).unwrap();
+    /// let target = UnitQuaternion::from_euler_angles(0.1, 0.2, 0.3);
+    /// arm.solve_wrist_orientation(&target).unwrap();
+    /// assert!(arm.end_transform().rotation.angle_to(&target) < 1e-6);
+    /// assert_eq!(arm.joint_positions()[..2], [0.3, 0.5]);
+    /// ```
+    pub fn solve_wrist_orientation(
+        &self,
+        target_rotation: &UnitQuaternion<T>,
+    ) -> Result<(), Error> {
+        let dof = self.dof();
+        if dof < 3 {
+            return Err(Error::PreconditionError {
+                dof,
+                necessary_dof: 3,
+            });
+        }
+        let current = self.joint_positions();
+        let mut zero_wrist = current.clone();
+        for position in &mut zero_wrist[dof - 3..] {
+            *position = T::zero();
+        }
+        self.set_joint_positions_unchecked(&zero_wrist);
+        let end_rotation = self.calc_end_transform().rotation;
+        self.update_transforms();
+        let axes = self
+            .iter_movable_nodes()
+            .skip(dof - 3)
+            .map(|node| {
+                let joint = node.joint();
+                match &joint.joint_type {
+                    JointType::Rotational { axis } => {
+                        let trans = joint.world_transform().expect("cache must exist");
+                        Ok((trans.rotation * axis).into_inner())
+                    }
+                    _ => Err(Error::NotRotationalJointError {
+                        joint_name: joint.name.clone(),
+                    }),
+                }
+            })
+            .collect::<Result<Vec<_>, _>>();
+        self.set_joint_positions_unchecked(&current);
+        let axes = axes?;
+        let eps: T = na::convert(STRUCTURE_EPS);
+        if axes[0].cross(&axes[1]).norm() < eps || axes[1].cross(&axes[2]).norm() < eps {
+            return Err(Error::UnsupportedKinematicsError {
+                reason: "the adjacent wrist axes must not be parallel".to_owned(),
+            });
+        }
+
+        let limits = self
+            .iter_joints()
+            .skip(dof - 3)
+            .map(|joint| joint.limits.clone())
+            .collect::<Vec<_>>();
+        let angle_eps: T = na::convert(WRIST_ANGLE_EPS);
+        let m = target_rotation * end_rotation.inverse();
+        let distance = |positions: &[T]| {
+            positions
+                .iter()
+                .zip(current[dof - 3..].iter())
+                .fold(T::zero(), |sum, (p, c)| {
+                    sum + (p.clone() - c.clone()) * (p.clone() - c.clone())
+                })
+        };
+        let nearest = solve_three_axes(&axes[0], &axes[1], &axes[2], &m)
+            .into_iter()
+            .filter(|(q4, q5, q6)| {
+                let rotation = rotation(&axes[0], q4.clone())
+                    * rotation(&axes[1], q5.clone())
+                    * rotation(&axes[2], q6.clone());
+                rotation.angle_to(&m) < angle_eps
+            })
+            .filter_map(|(q4, q5, q6)| {
+                [q4, q5, q6]
+                    .into_iter()
+                    .zip(current[dof - 3..].iter().zip(limits.iter()))
+                    .map(|(position, (current, limits))| {
+                        let shifted =
+                            current.clone() + wrap_angle(position.clone() - current.clone());
+                        match limits {
+                            None => Some(shifted),
+                            Some(range) if range.is_valid(shifted.clone()) => Some(shifted),
+                            Some(range) if range.is_valid(position.clone()) => Some(position),
+                            Some(_) => None,
+                        }
+                    })
+                    .collect::<Option<Vec<_>>>()
+            })
+            .min_by(|a, b| {
+                distance(a)
+                    .partial_cmp(&distance(b))
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            });
+        match nearest {
+            Some(wrist) => {
+                let mut positions = current;
+                positions.splice(dof - 3.., wrist);
+                self.set_joint_positions(&positions)
+            }
+            None => {
+                let rotation_diff = self
+                    .calc_end_transform()
+                    .rotation
+                    .rotation_to(target_rotation)
+                    .scaled_axis();
+                Err(Error::NotConvergedError {
+                    num_tried: 1,
+                    position_diff: na::Vector3::zeros(),
+                    rotation_diff: rotation_diff.map(|v| na::try_convert(v).unwrap_or_default()),
+                })
+            }
+        }
+    }
+}
+
+/// Solve `rot(a1, q1) * rot(a2, q2) * rot(a3, q3) = m` (candidates, not checked)
+fn solve_three_axes<T: RealField>(
+    a1: &Vector3<T>,
+    a2: &Vector3<T>,
+    a3: &Vector3<T>,
+    m: &UnitQuaternion<T>,
+) -> Vec<(T, T, T)> {
+    // any vector perpendicular to a3
+    let perpendicular = {
+        let c = a3.cross(a2);
+        if c.norm() > na::convert(STRUCTURE_EPS) {
+            c.normalize()
+        } else {
+            a3.cross(a1).normalize()
+        }
+    };
+    subproblem2(a1, a2, a3, &(m * a3))
+        .into_iter()
+        .map(|(q1, q2)| {
+            let rest = (rotation(a1, q1.clone()) * rotation(a2, q2.clone())).inverse() * m;
+            let q3 = subproblem1(a3, &perpendicular, &(rest * &perpendicular));
+            (q1, q2, q3)
+        })
+        .collect()
+}
+
+fn rotation<T: RealField>(axis: &Vector3<T>, angle: T) -> UnitQuaternion<T> {
+    UnitQuaternion::from_axis_angle(&Unit::new_unchecked(axis.clone()), angle)
+}
+
+/// acos which allows the input slightly out of `[-1, 1]`
+fn safe_acos<T: RealField>(x: T, eps: T) -> Option<T> {
+    if x > T::one() + eps.clone() || x < -T::one() - eps {
+        None
+    } else {
+        Some(x.clamp(-T::one(), T::one()).acos())
+    }
+}
+
+/// Paden-Kahan subproblem 1: the angle to rotate `p` to `q` around `axis`
+fn subproblem1<T: RealField>(axis: &Vector3<T>, p: &Vector3<T>, q: &Vector3<T>) -> T {
+    let u = p - axis * axis.dot(p);
+    let v = q - axis * axis.dot(q);
+    axis.dot(&u.cross(&v)).atan2(u.dot(&v))
+}
+
+/// Paden-Kahan subproblem 2: the angles which satisfy `rot(w1, t1) * rot(w2, t2) * p = q`
+fn subproblem2<T: RealField>(
+    w1: &Vector3<T>,
+    w2: &Vector3<T>,
+    p: &Vector3<T>,
+    q: &Vector3<T>,
+) -> Vec<(T, T)> {
+    let w12 = w1.dot(w2);
+    let denom = w12.clone() * w12.clone() - T::one();
+    let alpha = (w12.clone() * w2.dot(p) - w1.dot(q)) / denom.clone();
+    let beta = (w12.clone() * w1.dot(q) - w2.dot(p)) / denom;
+    let cross = w1.cross(w2);
+    let gamma2 = (p.norm_squared()
+        - alpha.clone() * alpha.clone()
+        - beta.clone() * beta.clone()
+        - na::convert::<f64, T>(2.0) * alpha.clone() * beta.clone() * w12)
+        / cross.norm_squared();
+    let gamma = gamma2.max(T::zero()).sqrt();
+    [gamma.clone(), -gamma]
+        .into_iter()
+        .map(|gamma| {
+            let z = w1 * alpha.clone() + w2 * beta.clone() + &cross * gamma;
+            (subproblem1(w1, &z, q), subproblem1(w2, p, &z))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::*;
+    #[cfg(target_family = "wasm")]
+    use wasm_bindgen_test::wasm_bindgen_test as test;
+
+    fn create_arm() -> SerialChain<f64> {
+        let joints = [
+            (Vector3::z_axis(), na::Translation3::new(0.0, 0.0, 0.1)),
+            (Vector3::y_axis(), na::Translation3::new(0.0, 0.1, 0.3)),
+            (-Vector3::y_axis(), na::Translation3::new(0.0, 0.0, 0.4)),
+            (Vector3::z_axis(), na::Translation3::new(0.05, 0.0, 0.15)),
+            (Vector3::y_axis(), na::Translation3::new(0.0, 0.0, 0.15)),
+            (Vector3::z_axis(), na::Translation3::new(0.0, 0.0, 0.0)),
+        ];
+        let mut nodes = joints
+            .iter()
+            .map(|(axis, translation)| {
+                NodeBuilder::new()
+                    .translation(*translation)
+                    .joint_type(JointType::Rotational { axis: *axis })
+                    .into_node()
+            })
+            .collect::<Vec<_>>();
+        nodes.push(
+            NodeBuilder::new()
+                .translation(na::Translation3::new(0.0, 0.0, 0.1))
+                .into_node(),
+        );
+        for i in 1..nodes.len() {
+            nodes[i].set_parent(&nodes[i - 1]);
+        }
+        SerialChain::from_end(&nodes[6])
+    }
+
+    #[test]
+    fn test_spherical_wrist_ik() {
+        let arm = create_arm();
+        let solver = SphericalWristIkSolver::new(1.0e-6, 1.0e-6);
+        for expected in [
+            [0.1, 0.2, 0.3, 0.4, 0.5, 0.6],
+            [-1.0, 0.8, -1.2, 2.0, -0.7, -2.5],
+            [2.5, -0.5, 1.5, -1.0, 1.2, 0.3],
+        ] {
+            arm.set_joint_positions(&expected).unwrap();
+            let target = arm.end_transform();
+            let solutions = solver.solutions(&arm, &target).unwrap();
+            assert_eq!(solutions.len(), 8);
+            assert!(solutions.iter().any(|solution| solution
+                .iter()
+                .zip(expected.iter())
+                .all(|(a, b)| (a - b).abs() < 1.0e-6)));
+            // the nearest solution is the current positions
+            let nearest = solver.nearest_solution(&arm, &target).unwrap().unwrap();
+            for (a, b) in nearest.iter().zip(expected.iter()) {
+                assert!((a - b).abs() < 1.0e-6);
+            }
+            assert_eq!(arm.joint_positions(), expected.to_vec());
+        }
+
+        // out of reach
+        let target = Isometry3::translation(3.0, 0.0, 0.0);
+        assert!(solver.solutions(&arm, &target).unwrap().is_empty());
+        assert!(solver.solve(&arm, &target).is_err());
+    }
+
+    #[test]
+    fn test_spherical_wrist_ik_tolerances() {
+        let arm = create_arm();
+        arm.set_joint_positions(&[0.1, 0.2, 0.3, 0.4, 0.5, 0.6])
+            .unwrap();
+        let target = arm.end_transform();
+        let mut solver = SphericalWristIkSolver::new(1.0e-6, 1.0e-6);
+        assert_eq!(solver.solutions(&arm, &target).unwrap().len(), 8);
+        // the solutions are checked by both tolerances
+        solver.set_tolerances(1.0, 0.0);
+        assert!(solver.solutions(&arm, &target).unwrap().is_empty());
+        solver.set_tolerances(0.0, 1.0);
+        assert!(solver.solutions(&arm, &target).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_solve_wrist_orientation() {
+        let arm = create_arm();
+        for (base, wrist) in [
+            ([0.1, 0.2, 0.3], [0.4, 0.5, 0.6]),
+            ([-1.0, 0.8, -1.2], [2.0, -0.7, -2.5]),
+        ] {
+            arm.set_joint_positions(&[base.to_vec(), wrist.to_vec()].concat())
+                .unwrap();
+            let target = arm.end_transform().rotation;
+            arm.set_joint_positions(&[base.to_vec(), vec![0.0, 0.3, 0.0]].concat())
+                .unwrap();
+            arm.solve_wrist_orientation(&target).unwrap();
+            let positions = arm.joint_positions();
+            assert_eq!(positions[..3], base);
+            assert!(arm.end_transform().rotation.angle_to(&target) < 1.0e-6);
+        }
+
+        // the 1st and 2nd wrist axes are parallel
+        let nodes = [Vector3::z_axis(), Vector3::z_axis(), Vector3::y_axis()]
+            .iter()
+            .map(|axis| {
+                NodeBuilder::new()
+                    .translation(na::Translation3::new(0.0, 0.0, 0.1))
+                    .joint_type(JointType::Rotational { axis: *axis })
+                    .into_node()
+            })
+            .collect::<Vec<_>>();
+        nodes[1].set_parent(&nodes[0]);
+        nodes[2].set_parent(&nodes[1]);
+        let arm = SerialChain::from_end(&nodes[2]);
+        assert!(matches!(
+            arm.solve_wrist_orientation(&UnitQuaternion::identity()),
+            Err(Error::UnsupportedKinematicsError { .. })
+        ));
+    }
+
+    #[test]
+    fn test_spherical_wrist_ik_unsupported() {
+        let arm = create_arm();
+        let node = arm.iter().nth(4).unwrap();
+        node.set_origin(Isometry3::translation(0.0, 0.1, 0.15));
+        let solver = SphericalWristIkSolver::<f64>::default();
+        assert!(matches!(
+            solver.solutions(&arm, &Isometry3::identity()),
+            Err(Error::UnsupportedKinematicsError { .. })
+        ));
+    }
+}
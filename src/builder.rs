@@ -0,0 +1,217 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+use super::chain::*;
+use super::errors::*;
+use super::joint::*;
+use super::link::*;
+use super::node::*;
+use na::RealField;
+use nalgebra as na;
+use simba::scalar::SubsetOf;
+use std::collections::HashMap;
+
+/// Build a whole `Chain` by the names of the joints
+///
+/// The parents are connected when `finalize()` is called,
+/// so the joints can be added in any order.
+///
+/// # Examples
+///
+/// ```
+/// use k::*;
+///
+/// let chain = ChainBuilder::<f64>::new()
+///     .add_joint(
+///         NodeBuilder::new()
+///             .name("elbow")
+///             .translation(Translation3::new(0.0, 0.0, 0.5))
+///             .joint_type(JointType::Rotational { axis: Vector3::y_axis() })
+///             .finalize(),
+///         Some("shoulder"),
+///     )
+///     .add_joint(
+///         NodeBuilder::new()
+///             .name("shoulder")
+///             .joint_type(JointType::Rotational { axis: Vector3::y_axis() })
+///             .finalize(),
+///         None,
+///     )
+///     .finalize()
+///     .unwrap();
+/// assert_eq!(chain.dof(), 2);
+/// assert_eq!(chain.iter().next().unwrap().joint().name, "shoulder");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ChainBuilder<T: RealField> {
+    #[allow(clippy::type_complexity)]
+    joints: Vec<(Joint<T>, Option<Link<T>>, Option<String>)>,
+}
+
+impl<T> ChainBuilder<T>
+where
+    T: RealField + SubsetOf<f64>,
+{
+    pub fn new() -> Self {
+        Self { joints: Vec::new() }
+    }
+
+    /// Add a joint with the name of its parent joint
+    ///
+    /// `None` parent means that it is the root.
+    pub fn add_joint(mut self, joint: Joint<T>, parent: Option<&str>) -> Self {
+        self.joints.push((joint, None, parent.map(str::to_owned)));
+        self
+    }
+
+    /// Add a joint and its link with the name of its parent joint
+    pub fn add_joint_with_link(
+        mut self,
+        joint: Joint<T>,
+        link: Link<T>,
+        parent: Option<&str>,
+    ) -> Self {
+        self.joints
+            .push((joint, Some(link), parent.map(str::to_owned)));
+        self
+    }
+
+    /// Connect the joints and create `Chain`
+    ///
+    /// It fails if the names are duplicated, a parent is not found,
+    /// there is not exactly one root, or there is a cycle.
+    pub fn finalize(self) -> Result<Chain<T>, Error> {
+        // check the topology by the names before connecting the nodes,
+        // so that no reference cycle is left on the errors
+        let mut name_to_parent = HashMap::new();
+        for (joint, _, parent) in &self.joints {
+            if name_to_parent
+                .insert(joint.name.as_str(), parent.as_deref())
+                .is_some()
+            {
+                return Err(Error::DuplicateJointNameError {
+                    joint_name: joint.name.clone(),
+                });
+            }
+        }
+        let mut num_roots = 0;
+        for (joint, _, parent) in &self.joints {
+            let name = &joint.name;
+            match parent {
+                Some(parent) if parent == name => {
+                    return Err(Error::InvalidTreeError {
+                        reason: format!("{name} is the parent of itself"),
+                    })
+                }
+                Some(parent) if !name_to_parent.contains_key(parent.as_str()) => {
+                    return Err(Error::InvalidTreeError {
+                        reason: format!("parent {parent} of {name} is not found"),
+                    })
+                }
+                Some(_) => {}
+                None => num_roots += 1,
+            }
+        }
+        if num_roots != 1 {
+            return Err(Error::InvalidTreeError {
+                reason: format!("must have exactly one root, but has {num_roots}"),
+            });
+        }
+        // with the single root, a joint is in a cycle if the root is not reached
+        for (joint, _, _) in &self.joints {
+            let mut current = joint.name.as_str();
+            for _ in 0..=name_to_parent.len() {
+                match name_to_parent[current] {
+                    Some(parent) => current = parent,
+                    None => break,
+                }
+            }
+            if name_to_parent[current].is_some() {
+                return Err(Error::InvalidTreeError {
+                    reason: "there is a cycle".to_owned(),
+                });
+            }
+        }
+
+        let mut name_to_node = HashMap::new();
+        let mut nodes = Vec::with_capacity(self.joints.len());
+        for (joint, link, parent) in self.joints {
+            let name = joint.name.clone();
+            let node = Node::new(joint);
+            node.set_link(link);
+            name_to_node.insert(name, node.clone());
+            nodes.push((node, parent));
+        }
+        let mut root = None;
+        for (node, parent) in nodes {
+            match parent {
+                Some(parent) => node.set_parent(&name_to_node[&parent]),
+                None => root = Some(node),
+            }
+        }
+        Ok(Chain::from_root(root.expect("the root is checked")))
+    }
+}
+
+impl<T> Chain<T>
+where
+    T: RealField + SubsetOf<f64>,
+{
+    /// Create `Chain` from the joints and the map from the name of a joint to the name of its parent
+    ///
+    /// The joint which is not in `parents` is the root. It fails in the same cases as
+    /// `ChainBuilder::finalize()`: the names are duplicated, a parent is not found,
+    /// there is not exactly one root, or there is a cycle.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    /// use std::collections::HashMap;
+    ///
+    /// let joints = ["j0", "j1", "j2"]
+    ///     .iter()
+    ///     .map(|name| {
+    ///         let joint = NodeBuilder::new()
+    ///             .name(name)
+    ///             .joint_type(JointType::Rotational { axis: Vector3::z_axis() })
+    ///             .finalize();
+    ///         (joint, None)
+    ///     })
+    ///     .collect();
+    /// let parents = HashMap::from([
+    ///     ("j1".to_owned(), "j0".to_owned()),
+    ///     ("j2".to_owned(), "j0".to_owned()),
+    /// ]);
+    /// let chain = Chain::<f64>::from_parent_map(joints, &parents).unwrap();
+    /// assert_eq!(chain.dof(), 3);
+    /// assert_eq!(chain.find("j0").unwrap().children().len(), 2);
+    /// ```
+    pub fn from_parent_map(
+        joints: Vec<(Joint<T>, Option<Link<T>>)>,
+        parents: &HashMap<String, String>,
+    ) -> Result<Self, Error> {
+        joints
+            .into_iter()
+            .fold(ChainBuilder::new(), |builder, (joint, link)| {
+                let parent = parents.get(&joint.name).cloned();
+                match link {
+                    Some(link) => builder.add_joint_with_link(joint, link, parent.as_deref()),
+                    None => builder.add_joint(joint, parent.as_deref()),
+                }
+            })
+            .finalize()
+    }
+}
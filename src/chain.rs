@@ -13,14 +13,28 @@
   See the License for the specific language governing permissions and
   limitations under the License.
 */
+use super::collision::CollisionShape;
 use super::errors::*;
+use super::euler::*;
+use super::fk_cache::*;
+use super::funcs::*;
 use super::joint::*;
 use super::node::*;
-use na::{Isometry3, RealField};
+use super::telemetry::*;
+use na::{
+    DMatrix, DVector, Isometry3, Matrix3, Matrix4, Matrix6, Point3, RealField, Vector3, Vector6,
+};
 use nalgebra as na;
 use simba::scalar::SubsetOf;
+use std::collections::{HashMap, HashSet};
 use std::fmt::{self, Display};
 use std::ops::Deref;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::sync::Mutex;
+
+/// The limit which is used for the joints without limits by `Chain::joint_limit_vectors`
+pub const UNLIMITED_JOINT_LIMIT: f64 = 1.0e10;
 
 /// Kinematic Chain using `Node`
 ///
@@ -86,6 +100,113 @@ pub struct Chain<T: RealField> {
     nodes: Vec<Node<T>>,
     movable_nodes: Vec<Node<T>>,
     dof: usize,
+    gravity: Vector3<T>,
+    /// (link name, mass, center of mass in the link frame)
+    payloads: Vec<(String, T, Vector3<T>)>,
+    /// pairs of the link names which are not checked for collision, sorted in each pair
+    pub(crate) collision_ignore_pairs: HashSet<(String, String)>,
+    /// (link name, origin, shape) which are added by `add_collision_shape()`
+    pub(crate) collision_shapes: Vec<(String, Isometry3<T>, CollisionShape<T>)>,
+    /// positions which are used by `go_home()`, by the names of the joints
+    pub(crate) home_positions: HashMap<String, T>,
+    telemetry: Option<SharedTelemetry<T>>,
+    /// depth of the calculations which do not report the positions and the limit hits
+    /// to the telemetry
+    position_reports_muted: AtomicUsize,
+    /// named frames: (the name of the parent link, the offset from the link)
+    frames: HashMap<String, (String, Isometry3<T>)>,
+    limit_mode: LimitMode,
+}
+
+/// How `Chain::set_joint_positions()` treats the positions out of the limits
+///
+/// It is applied by `Chain::set_joint_positions()` and the methods of `Chain` built on it,
+/// see [`Chain::set_limit_mode`]. `Node::set_joint_position()`, the explicit
+/// `set_joint_positions_clamped()` and `set_joint_positions_unchecked()`, and the IK solvers
+/// keep their own behavior.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LimitMode {
+    /// Returns `Error::OutOfLimitError` (default)
+    #[default]
+    Error,
+    /// Clamps the positions into the limits
+    Clamp,
+    /// Sets the positions as they are
+    Ignore,
+}
+
+/// End poses in the structure-of-arrays layout, returned by `Chain::end_transforms_soa()`
+///
+/// The `i`-th pose is the translation `(x[i], y[i], z[i])` and
+/// the unit quaternion `(qw[i], qx[i], qy[i], qz[i])`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EndPosesSoa<T: RealField> {
+    pub x: Vec<T>,
+    pub y: Vec<T>,
+    pub z: Vec<T>,
+    pub qw: Vec<T>,
+    pub qx: Vec<T>,
+    pub qy: Vec<T>,
+    pub qz: Vec<T>,
+}
+
+impl<T: RealField> EndPosesSoa<T> {
+    /// Create empty arrays with `capacity`
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            x: Vec::with_capacity(capacity),
+            y: Vec::with_capacity(capacity),
+            z: Vec::with_capacity(capacity),
+            qw: Vec::with_capacity(capacity),
+            qx: Vec::with_capacity(capacity),
+            qy: Vec::with_capacity(capacity),
+            qz: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Number of the poses
+    pub fn len(&self) -> usize {
+        self.x.len()
+    }
+
+    /// Returns true if there are no poses
+    pub fn is_empty(&self) -> bool {
+        self.x.is_empty()
+    }
+
+    /// Append `pose`
+    pub fn push(&mut self, pose: &Isometry3<T>) {
+        let t = &pose.translation.vector;
+        let q = pose.rotation.quaternion();
+        self.x.push(t.x.clone());
+        self.y.push(t.y.clone());
+        self.z.push(t.z.clone());
+        self.qw.push(q.w.clone());
+        self.qx.push(q.i.clone());
+        self.qy.push(q.j.clone());
+        self.qz.push(q.k.clone());
+    }
+
+    /// Reconstruct the `index`-th pose
+    pub fn get(&self, index: usize) -> Option<Isometry3<T>> {
+        if index >= self.len() {
+            return None;
+        }
+        let rotation = na::UnitQuaternion::new_unchecked(na::Quaternion::new(
+            self.qw[index].clone(),
+            self.qx[index].clone(),
+            self.qy[index].clone(),
+            self.qz[index].clone(),
+        ));
+        Some(Isometry3::from_parts(
+            na::Translation3::new(
+                self.x[index].clone(),
+                self.y[index].clone(),
+                self.z[index].clone(),
+            ),
+            rotation,
+        ))
+    }
 }
 
 impl<T: RealField + SubsetOf<f64>> Chain<T> {
@@ -181,6 +302,15 @@ impl<T: RealField + SubsetOf<f64>> Chain<T> {
             dof: movable_nodes.len(),
             movable_nodes,
             nodes,
+            gravity: default_gravity(),
+            payloads: Vec::new(),
+            collision_ignore_pairs: HashSet::new(),
+            collision_shapes: Vec::new(),
+            home_positions: HashMap::new(),
+            telemetry: None,
+            position_reports_muted: AtomicUsize::new(0),
+            frames: HashMap::new(),
+            limit_mode: LimitMode::default(),
         }
     }
 
@@ -245,9 +375,172 @@ impl<T: RealField + SubsetOf<f64>> Chain<T> {
         self.nodes[0].origin()
     }
 
+    /// Set the pose of the floating base
+    ///
+    /// The base pose is kept separately from the origin of the root joint (the origin in URDF),
+    /// and it is applied before the origin, so all the world transforms are moved with it.
+    /// It is set to the root of the whole tree, which has no parent, even if this chain
+    /// starts from a joint under it (e.g. `from_end_to_root`), so it is shared by all the
+    /// chains of the tree.
+    ///
+    /// The base pose is not a joint: it is not counted in `dof()`, it is not changed by
+    /// `set_joint_positions()`, and the IK solvers and the jacobian do not include it.
+    /// There is no 6-DOF floating joint type. If the base motion must be a part of the
+    /// joints (e.g. for a whole-body controller), model it by three `JointType::Linear` and
+    /// three `JointType::Rotational` joints above the root link instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let chain = Chain::<f64>::from_urdf_file("urdf/sample.urdf").unwrap();
+    /// let origin = chain.origin();
+    /// let base = Isometry3::translation(1.0, 0.0, 0.0);
+    /// chain.set_base_pose(base);
+    /// assert_eq!(chain.base_pose(), base);
+    /// assert_eq!(chain.origin(), origin);
+    /// // not counted as joints
+    /// assert_eq!(chain.dof(), 12);
+    /// ```
+    pub fn set_base_pose(&self, pose: na::Isometry3<T>) {
+        let root = self.tree_root();
+        root.lock().joint.set_base_pose(pose);
+        // the world transforms of the descendants depend on the root
+        for node in root.iter_descendants() {
+            node.joint().clear_caches();
+        }
+    }
+
+    /// Get the pose of the floating base, the identity by default
+    pub fn base_pose(&self) -> na::Isometry3<T> {
+        self.tree_root().joint().base_pose()
+    }
+
+    /// The root of the whole tree, which may not be included in this chain
+    fn tree_root(&self) -> Node<T> {
+        self.nodes[0]
+            .iter_ancestors()
+            .last()
+            .expect("ancestors contain the node itself")
+    }
+
+    /// Set the gravity vector used by the dynamics functions
+    ///
+    /// The default is `(0, 0, -9.81)` in the world coordinate.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let l0 = Node::new(Joint::new("fixed0", JointType::Fixed));
+    /// let mut c = Chain::<f64>::from_root(l0);
+    /// assert_eq!(c.gravity(), &Vector3::new(0.0, 0.0, -9.81));
+    /// c.set_gravity(Vector3::new(0.0, 0.0, -1.62));
+    /// assert_eq!(c.gravity().z, -1.62);
+    /// ```
+    pub fn set_gravity(&mut self, gravity: Vector3<T>) {
+        self.gravity = gravity;
+    }
+
+    /// Set the callbacks which observe FK, IK iterations and the joint limits
+    ///
+    /// See `Telemetry` for the events. The telemetry is shared with the clones of this chain.
+    pub fn set_telemetry(&mut self, telemetry: Arc<dyn Telemetry<T>>) {
+        self.telemetry = Some(SharedTelemetry(telemetry));
+    }
+
+    /// Remove the telemetry which is set by `set_telemetry`
+    pub fn clear_telemetry(&mut self) {
+        self.telemetry = None;
+    }
+
+    pub(crate) fn telemetry(&self) -> Option<&dyn Telemetry<T>> {
+        self.telemetry.as_ref().map(|t| t.0.as_ref())
+    }
+
+    /// Call `f` without reporting the positions and the limit hits to the telemetry
+    ///
+    /// It is for the calculations which change the positions temporarily or step by step.
+    pub(crate) fn without_position_reports<R>(&self, f: impl FnOnce() -> R) -> R {
+        struct Unmute<'a>(&'a AtomicUsize);
+        impl Drop for Unmute<'_> {
+            fn drop(&mut self) {
+                self.0.fetch_sub(1, Ordering::SeqCst);
+            }
+        }
+        self.position_reports_muted.fetch_add(1, Ordering::SeqCst);
+        let _unmute = Unmute(&self.position_reports_muted);
+        f()
+    }
+
+    /// Report the current positions to `Telemetry::on_set_joint_positions`
+    pub(crate) fn report_joint_positions(&self) {
+        if self.position_reports_muted.load(Ordering::SeqCst) > 0 {
+            return;
+        }
+        if let Some(telemetry) = self.telemetry() {
+            telemetry.on_set_joint_positions(&self.joint_positions());
+        }
+    }
+
+    /// Report the position out of the limits to `Telemetry::on_limit_hit`
+    fn report_limit_hit(&self, joint_name: &str, position: T) {
+        if self.position_reports_muted.load(Ordering::SeqCst) > 0 {
+            return;
+        }
+        if let Some(telemetry) = self.telemetry() {
+            telemetry.on_limit_hit(joint_name, position);
+        }
+    }
+
+    /// Report the positions out of the limits to `Telemetry::on_limit_hit`
+    fn report_limit_hits(&self, positions_vec: &[T]) {
+        if self.telemetry().is_none() {
+            return;
+        }
+        for (joint, position) in self.iter_joints().zip(positions_vec.iter()) {
+            if let Some(range) = &joint.limits {
+                if !range.is_valid(position.clone()) {
+                    self.report_limit_hit(&joint.name, position.clone());
+                }
+            }
+        }
+    }
+
+    /// Replace the nodes with the ones of `other`, keeping the settings of this chain
+    ///
+    /// The gravity, the telemetry and the home positions are kept, and the payloads,
+    /// the collision ignore pairs, the collision shapes and the frames of the links which
+    /// do not exist any more are dropped.
+    pub(crate) fn replace_nodes(&mut self, other: Chain<T>) {
+        self.nodes = other.nodes;
+        self.movable_nodes = other.movable_nodes;
+        self.dof = other.dof;
+        let link_names = self
+            .iter_links()
+            .map(|link| link.name.clone())
+            .collect::<HashSet<_>>();
+        self.payloads
+            .retain(|(name, _, _)| link_names.contains(name));
+        self.collision_ignore_pairs
+            .retain(|(a, b)| link_names.contains(a) && link_names.contains(b));
+        self.collision_shapes
+            .retain(|(name, _, _)| link_names.contains(name));
+        self.frames
+            .retain(|_, (link_name, _)| link_names.contains(link_name));
+    }
+
+    /// Get the gravity vector used by the dynamics functions
+    pub fn gravity(&self) -> &Vector3<T> {
+        &self.gravity
+    }
+
     /// Iterate for all joint nodes
     ///
     /// The order is from parent to children. You can assume that parent is already iterated.
+    /// For `Chain::from_root`, it is the depth-first order of `Node::iter_descendants`.
     ///
     /// # Examples
     ///
@@ -270,11 +563,60 @@ impl<T: RealField + SubsetOf<f64>> Chain<T> {
     /// Iterate for movable joints
     ///
     /// Fixed joints are ignored. If you want to manipulate on Fixed,
-    /// use `iter()` instead of `iter_joints()`
+    /// use `iter()` instead of `iter_joints()`.
+    /// The order is the same with `joint_names()`.
     pub fn iter_joints(&self) -> impl Iterator<Item = JointRefGuard<'_, T>> {
         self.movable_nodes.iter().map(|node| node.joint())
     }
 
+    /// Iterate for the nodes of the movable joints
+    ///
+    /// The order is the same with `iter_joints()`. Unlike `iter_joints()`, the nodes
+    /// are not locked, so the parents, children and links can be used.
+    /// `SerialChain` also has this method by `Deref`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let l0 = NodeBuilder::new()
+    ///     .joint_type(JointType::Rotational { axis: Vector3::y_axis() })
+    ///     .into_node();
+    /// let l1 = NodeBuilder::new()
+    ///     .translation(Translation3::new(0.0, 0.0, 1.0))
+    ///     .into_node();
+    /// l1.set_parent(&l0);
+    /// let arm = SerialChain::<f64>::from_end(&l1);
+    /// let nodes = arm.iter_movable_nodes().collect::<Vec<_>>();
+    /// assert_eq!(nodes.len(), arm.dof());
+    /// assert_eq!(nodes[0].children()[0], l1);
+    /// ```
+    pub fn iter_movable_nodes(&self) -> impl Iterator<Item = &Node<T>> {
+        self.movable_nodes.iter()
+    }
+
+    /// Get the node of the `index`-th movable joint
+    ///
+    /// The order is the same with `joint_names()` and `joint_positions()`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let chain = Chain::<f64>::from_urdf_file("urdf/sample.urdf").unwrap();
+    /// let arm = SerialChain::from_end(chain.find("l_wrist_pitch").unwrap());
+    /// let names = arm.joint_names();
+    /// for (i, name) in names.iter().enumerate() {
+    ///     assert_eq!(&arm.joint_node(i).unwrap().joint().name, name);
+    /// }
+    /// assert!(arm.joint_node(names.len()).is_none());
+    /// ```
+    pub fn joint_node(&self, index: usize) -> Option<&Node<T>> {
+        self.movable_nodes.get(index)
+    }
+
     /// Iterate for links
     pub fn iter_links(&self) -> impl Iterator<Item = LinkRefGuard<'_, T>> {
         self.nodes.iter().filter_map(|node| {
@@ -360,6 +702,141 @@ impl<T: RealField + SubsetOf<f64>> Chain<T> {
         })
     }
 
+    /// Get the movable joints in the subtree under the link `root_link`
+    ///
+    /// The joint which moves `root_link` itself is not included. The order is the same as
+    /// `iter()`. It returns `None` if `root_link` is not found.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let chain = k::Chain::<f64>::from_urdf_file("urdf/sample.urdf").unwrap();
+    /// let joints = chain.subtree_joints("l_elbow1").unwrap();
+    /// let names = joints.iter().map(|node| node.joint().name.clone()).collect::<Vec<_>>();
+    /// assert_eq!(names, vec!["l_wrist_yaw", "l_wrist_pitch"]);
+    /// ```
+    pub fn subtree_joints(&self, root_link: &str) -> Option<Vec<Node<T>>> {
+        let root = self.find_link(root_link)?;
+        Some(
+            root.iter_descendants()
+                .skip(1)
+                .filter(|node| node.joint().is_movable() && self.nodes.contains(node))
+                .collect(),
+        )
+    }
+
+    /// Add a named frame which is attached to the link `parent_link` with `offset`
+    ///
+    /// The frames are like the frames of TF, for example cameras, tools or markers.
+    /// They do not add any nodes, so they do not change the joints or the links.
+    /// If the frame `name` already exists, it is replaced.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    /// use k::link::*;
+    ///
+    /// let l0 = NodeBuilder::new()
+    ///     .translation(Translation3::new(0.0, 0.0, 1.0))
+    ///     .into_node();
+    /// l0.set_link(Some(LinkBuilder::new().name("base").finalize()));
+    /// let mut chain = Chain::<f64>::from_root(l0);
+    /// chain
+    ///     .add_frame("camera", "base", Isometry3::translation(0.1, 0.0, 0.0))
+    ///     .unwrap();
+    /// let camera = chain.frame_transform("camera").unwrap();
+    /// assert_eq!(camera.translation.vector, Vector3::new(0.1, 0.0, 1.0));
+    /// assert!(chain.frame_transform("no_exist").is_none());
+    /// assert!(chain.add_frame("marker", "no_exist", Isometry3::identity()).is_err());
+    /// ```
+    pub fn add_frame(
+        &mut self,
+        name: &str,
+        parent_link: &str,
+        offset: Isometry3<T>,
+    ) -> Result<(), Error> {
+        if self.find_link(parent_link).is_none() {
+            return Err(Error::InvalidLinkNameError {
+                link_name: parent_link.to_owned(),
+            });
+        }
+        self.frames
+            .insert(name.to_owned(), (parent_link.to_owned(), offset));
+        Ok(())
+    }
+
+    /// Remove the frame which is added by `add_frame`
+    pub fn remove_frame(&mut self, name: &str) {
+        self.frames.remove(name);
+    }
+
+    /// Get the world transform of the frame `name` which is added by `add_frame`
+    ///
+    /// The world transforms are updated if necessary.
+    pub fn frame_transform(&self, name: &str) -> Option<Isometry3<T>> {
+        let (link_name, offset) = self.frames.get(name)?;
+        self.update_transforms();
+        let link_transform = self.find_link(link_name)?.world_transform()?;
+        Some(link_transform * offset)
+    }
+
+    /// Get the names of the movable joints
+    ///
+    /// This is the order of the positions of `joint_positions()`, `set_joint_positions()`
+    /// and other methods which deal with the movable joints. It is the order of `iter()`
+    /// without the fixed joints unless it is changed by `reorder_joints()`,
+    /// so it is the same for the chains which are created in the same way.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let l0 = NodeBuilder::new()
+    ///     .name("j0")
+    ///     .joint_type(JointType::Rotational { axis: Vector3::z_axis() })
+    ///     .into_node();
+    /// let l1 = NodeBuilder::new().name("fixed").into_node();
+    /// let l2 = NodeBuilder::new()
+    ///     .name("j2")
+    ///     .joint_type(JointType::Rotational { axis: Vector3::z_axis() })
+    ///     .into_node();
+    /// l1.set_parent(&l0);
+    /// l2.set_parent(&l1);
+    /// let chain = Chain::<f64>::from_root(l0);
+    /// assert_eq!(chain.joint_names(), vec!["j0", "j2"]);
+    /// ```
+    pub fn joint_names(&self) -> Vec<String> {
+        self.iter_joints().map(|joint| joint.name.clone()).collect()
+    }
+
+    /// Get the index of the movable joint `name` in `joint_positions()`
+    ///
+    /// Returns `None` if there is no movable joint with the name.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let chain = Chain::<f64>::from_urdf_file("urdf/sample.urdf").unwrap();
+    /// assert_eq!(chain.joint_index("l_shoulder_yaw"), Some(0));
+    /// assert_eq!(chain.joint_name_at(0).unwrap(), "l_shoulder_yaw");
+    /// assert_eq!(chain.joint_index("root"), None);
+    /// assert_eq!(chain.joint_name_at(chain.dof()), None);
+    /// ```
+    pub fn joint_index(&self, name: &str) -> Option<usize> {
+        self.iter_joints().position(|joint| joint.name == name)
+    }
+
+    /// Get the name of the `index`-th movable joint, the reverse of `joint_index()`
+    pub fn joint_name_at(&self, index: usize) -> Option<String> {
+        self.movable_nodes
+            .get(index)
+            .map(|node| node.joint().name.clone())
+    }
+
     /// Get the positions of the joints
     ///
     /// `FixedJoint` is ignored. the length is the same with `dof()`
@@ -375,7 +852,8 @@ impl<T: RealField + SubsetOf<f64>> Chain<T> {
 
     /// Set the positions of the joints
     ///
-    /// `FixedJoints` are ignored. the input number must be equal with `dof()`
+    /// `FixedJoints` are ignored. the input number must be equal with `dof()`.
+    /// See [`Chain::set_limit_mode`] for the positions out of the limits.
     pub fn set_joint_positions(&self, positions_vec: &[T]) -> Result<(), Error> {
         if positions_vec.len() != self.dof {
             return Err(Error::SizeMismatchError {
@@ -383,441 +861,4529 @@ impl<T: RealField + SubsetOf<f64>> Chain<T> {
                 required: self.dof,
             });
         }
-        for (joint, position) in self.movable_nodes.iter().zip(positions_vec.iter()) {
-            joint.set_joint_position(position.clone())?;
+        match self.limit_mode {
+            LimitMode::Error => {
+                for (joint, position) in self.movable_nodes.iter().zip(positions_vec.iter()) {
+                    let result = joint.set_joint_position(position.clone());
+                    if let Err(Error::OutOfLimitError { joint_name, .. }) = &result {
+                        self.report_limit_hit(joint_name, position.clone());
+                    }
+                    result?;
+                }
+            }
+            LimitMode::Clamp => {
+                self.report_limit_hits(positions_vec);
+                self.without_position_reports(|| self.set_joint_positions_clamped(positions_vec))
+            }
+            LimitMode::Ignore => self.set_joint_positions_unchecked(positions_vec),
         }
+        self.report_joint_positions();
         Ok(())
     }
 
-    /// Set the clamped positions of the joints
+    /// Calculate the differences of the `measured` positions from the current positions
     ///
-    /// This function is safe, in contrast to `set_joint_positions_unchecked`.
-    pub fn set_joint_positions_clamped(&self, positions_vec: &[T]) {
-        for (joint, position) in self.movable_nodes.iter().zip(positions_vec.iter()) {
-            joint.set_joint_position_clamped(position.clone());
+    /// It returns `measured - joint_positions()` for each joint. The differences of
+    /// the rotational joints without limits (continuous joints) are wrapped into `(-pi, pi]`.
+    /// The length of `measured` must be `dof()`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let l0 = NodeBuilder::new()
+    ///     .joint_type(JointType::Rotational { axis: Vector3::z_axis() })
+    ///     .into_node();
+    /// let chain = Chain::<f64>::from_root(l0);
+    /// chain.set_joint_positions(&[0.5]).unwrap();
+    /// assert!((chain.joint_residual(&[0.7]).unwrap()[0] - 0.2).abs() < 1e-10);
+    /// ```
+    pub fn joint_residual(&self, measured: &[T]) -> Result<Vec<T>, Error> {
+        if measured.len() != self.dof {
+            return Err(Error::SizeMismatchError {
+                input: measured.len(),
+                required: self.dof,
+            });
         }
+        Ok(self
+            .iter_joints()
+            .zip(measured.iter())
+            .map(|(joint, measured)| {
+                let diff = measured.clone()
+                    - joint
+                        .joint_position()
+                        .expect("Must be a bug: movable joint must have position");
+                if matches!(joint.joint_type, JointType::Rotational { .. })
+                    && joint.limits.is_none()
+                {
+                    wrap_angle(diff)
+                } else {
+                    diff
+                }
+            })
+            .collect())
     }
 
-    /// Fast, but without check, dangerous `set_joint_positions`
-    #[inline]
-    pub fn set_joint_positions_unchecked(&self, positions_vec: &[T]) {
-        for (joint, position) in self.movable_nodes.iter().zip(positions_vec.iter()) {
-            joint.set_joint_position_unchecked(position.clone());
-        }
+    /// Set how `set_joint_positions()` treats the positions out of the limits
+    ///
+    /// The default is `LimitMode::Error`. The mode is also used by the methods which set
+    /// the given positions by `set_joint_positions()`: `set_joint_positions_deg()`,
+    /// `move_joint_positions()`, `move_joint_position()`, `go_home()`, `apply_configuration()`,
+    /// `apply_joint_state()`, `with_positions()`, `mass_matrix()`, `path_is_collision_free()`
+    /// and `replay()`. With `LimitMode::Clamp` or `LimitMode::Ignore`, they don't return
+    /// `Error::OutOfLimitError`.
+    ///
+    /// It is not used by `Node::set_joint_position()`, `set_joint_positions_clamped()`,
+    /// `set_joint_positions_unchecked()` and the IK solvers. The IK solvers always keep
+    /// the joints within the limits.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let l0 = NodeBuilder::new()
+    ///     .joint_type(JointType::Rotational { axis: Vector3::z_axis() })
+    ///     .limits(Some((-1.0..=1.0).into()))
+    ///     .into_node();
+    /// let mut chain = Chain::<f64>::from_root(l0);
+    /// assert!(chain.set_joint_positions(&[2.0]).is_err());
+    /// chain.set_limit_mode(LimitMode::Clamp);
+    /// chain.set_joint_positions(&[2.0]).unwrap();
+    /// assert_eq!(chain.joint_positions(), vec![1.0]);
+    /// ```
+    pub fn set_limit_mode(&mut self, mode: LimitMode) {
+        self.limit_mode = mode;
     }
 
-    /// Update world_transform() of the joints
-    pub fn update_transforms(&self) -> Vec<Isometry3<T>> {
-        self.iter()
-            .map(|node| {
-                // copy the cache once to resolve a deadlock of 'node' guards
-                let cached_world_transform = node.joint().world_transform();
-                cached_world_transform.unwrap_or_else(|| {
-                    // clear child caches
-                    // recursive clearing is not necessary thanks to the order of Chain::iter()
-                    for child in node.children().iter() {
-                        child.joint().clear_caches();
-                    }
+    /// How `set_joint_positions()` treats the positions out of the limits
+    pub fn limit_mode(&self) -> LimitMode {
+        self.limit_mode
+    }
 
-                    // calculate the transformation
-                    let parent_transform = node.parent_world_transform().expect("cache must exist");
-                    let trans = parent_transform * node.joint().local_transform();
-                    node.joint().set_world_transform(trans.clone());
-                    trans
-                })
+    /// Get the positions of the joints in degrees
+    ///
+    /// The positions of the rotational joints are converted to degrees, and the positions
+    /// of the linear joints are returned as they are (meters).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let l0 = NodeBuilder::new()
+    ///     .joint_type(JointType::Rotational { axis: Vector3::z_axis() })
+    ///     .into_node();
+    /// let l1 = NodeBuilder::new()
+    ///     .joint_type(JointType::Linear { axis: Vector3::z_axis() })
+    ///     .into_node();
+    /// l1.set_parent(&l0);
+    /// let chain = Chain::<f64>::from_root(l0);
+    /// chain.set_joint_positions_deg(&[90.0, 0.5]).unwrap();
+    /// assert!((chain.joint_positions()[0] - std::f64::consts::FRAC_PI_2).abs() < 1e-10);
+    /// assert_eq!(chain.joint_positions()[1], 0.5);
+    /// assert!((chain.joint_positions_deg()[0] - 90.0).abs() < 1e-10);
+    /// ```
+    pub fn joint_positions_deg(&self) -> Vec<T> {
+        let to_deg = na::convert::<f64, T>(180.0) / T::pi();
+        self.iter_joints()
+            .map(|joint| {
+                let position = joint
+                    .joint_position()
+                    .expect("Must be a bug: movable joint must have position");
+                match joint.joint_type {
+                    JointType::Rotational { .. } => position * to_deg.clone(),
+                    _ => position,
+                }
             })
             .collect()
     }
 
-    /// Update world_velocity() of the joints
-    pub fn update_velocities(&self) -> Vec<Velocity<T>> {
-        self.update_transforms();
-        self.iter()
-            .map(|node| {
-                // copy the cache to resolve a deadlock of 'node' guards
-                let cached_world_velocity = node.joint().world_velocity();
-                cached_world_velocity.unwrap_or_else(|| {
-                    let parent_transform = node
-                        .parent_world_transform()
-                        .expect("transform cache must exist");
-                    let parent_velocity = node
-                        .parent_world_velocity()
-                        .expect("velocity cache must exist");
-                    let velocity = match &node.joint().joint_type {
-                        JointType::Fixed => parent_velocity,
-                        JointType::Rotational { axis } => {
-                            let parent = node.parent().expect("parent must exist");
-                            let parent_vel = parent.joint().origin().translation.vector.clone();
-                            Velocity::from_parts(
-                                parent_velocity.translation
-                                    + parent_velocity.rotation.cross(
-                                        &(parent_transform.rotation.to_rotation_matrix()
-                                            * parent_vel),
-                                    ),
-                                parent_velocity.rotation
-                                    + node
-                                        .world_transform()
-                                        .expect("cache must exist")
-                                        .rotation
-                                        .to_rotation_matrix()
-                                        * (axis.clone().into_inner()
-                                            * node.joint().joint_velocity().unwrap()),
-                            )
-                        }
-                        JointType::Linear { axis } => Velocity::from_parts(
-                            parent_velocity.translation
-                                + node
-                                    .world_transform()
-                                    .expect("cache must exist")
-                                    .rotation
-                                    .to_rotation_matrix()
-                                    * (axis.clone().into_inner()
-                                        * node.joint().joint_velocity().unwrap()),
-                            // TODO: Is this true??
-                            parent_velocity.rotation,
-                        ),
-                    };
-                    node.joint().set_world_velocity(velocity.clone());
-                    velocity
-                })
+    /// Set the positions of the joints in degrees
+    ///
+    /// The positions of the rotational joints are converted from degrees to radians,
+    /// and the positions of the linear joints are used as they are (meters).
+    /// Otherwise it is the same as `set_joint_positions`.
+    pub fn set_joint_positions_deg(&self, positions_vec: &[T]) -> Result<(), Error> {
+        if positions_vec.len() != self.dof {
+            return Err(Error::SizeMismatchError {
+                input: positions_vec.len(),
+                required: self.dof,
+            });
+        }
+        let to_rad = T::pi() / na::convert::<f64, T>(180.0);
+        let positions = self
+            .iter_joints()
+            .zip(positions_vec.iter())
+            .map(|(joint, position)| match joint.joint_type {
+                JointType::Rotational { .. } => position.clone() * to_rad.clone(),
+                _ => position.clone(),
             })
-            .collect()
+            .collect::<Vec<_>>();
+        self.set_joint_positions(&positions)
     }
 
-    /// Update transforms of the links
-    pub fn update_link_transforms(&self) {
-        self.update_transforms();
-        self.iter().for_each(|node| {
-            let parent_transform = node.parent_world_transform().expect("cache must exist");
-            let mut node_mut = node.lock();
-            if let Some(ref mut link) = node_mut.link {
-                let inertial_trans = parent_transform.clone() * link.inertial.origin();
-                link.inertial.set_world_transform(inertial_trans);
-                for c in &mut link.collisions {
-                    let c_trans = parent_transform.clone() * c.origin();
-                    c.set_world_transform(c_trans);
+    /// Get the lower and upper limits of the joints as vectors
+    ///
+    /// The order is the same as `joint_positions()`. The joints without limits
+    /// have `-UNLIMITED_JOINT_LIMIT` and `UNLIMITED_JOINT_LIMIT`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let l0 = NodeBuilder::new()
+    ///     .joint_type(JointType::Rotational { axis: Vector3::z_axis() })
+    ///     .limits(Some((-1.0..=0.5).into()))
+    ///     .into_node();
+    /// let l1 = NodeBuilder::new()
+    ///     .joint_type(JointType::Linear { axis: Vector3::z_axis() })
+    ///     .into_node();
+    /// l1.set_parent(&l0);
+    /// let chain = Chain::<f64>::from_root(l0);
+    /// let (lower, upper) = chain.joint_limit_vectors();
+    /// assert_eq!(lower, vec![-1.0, -UNLIMITED_JOINT_LIMIT]);
+    /// assert_eq!(upper, vec![0.5, UNLIMITED_JOINT_LIMIT]);
+    /// ```
+    pub fn joint_limit_vectors(&self) -> (Vec<T>, Vec<T>) {
+        let unlimited: T = na::convert(UNLIMITED_JOINT_LIMIT);
+        self.iter_joints()
+            .map(|joint| match &joint.limits {
+                Some(range) => (range.min.clone(), range.max.clone()),
+                None => (-unlimited.clone(), unlimited.clone()),
+            })
+            .unzip()
+    }
+
+    /// Get the gradient of the joint limit avoidance cost at the current positions
+    ///
+    /// The cost is `1/2 * sum(((q - mid) / (max - min))^2)`, where `mid` is the middle of the
+    /// limits, so the gradient is `(q - mid) / (max - min)^2`. It is zero for the joints without limits.
+    /// Use `create_joint_limit_avoidance_nullspace_function` to use it with `JacobianIkSolver`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let l0 = NodeBuilder::new()
+    ///     .joint_type(JointType::Rotational { axis: Vector3::z_axis() })
+    ///     .limits(Some((0.0..=2.0).into()))
+    ///     .into_node();
+    /// let l1 = NodeBuilder::new()
+    ///     .joint_type(JointType::Rotational { axis: Vector3::z_axis() })
+    ///     .into_node();
+    /// l1.set_parent(&l0);
+    /// let chain = Chain::<f64>::from_root(l0);
+    /// chain.set_joint_positions(&[1.5, 1.0]).unwrap();
+    /// assert_eq!(chain.joint_limit_avoidance_gradient(), vec![0.125, 0.0]);
+    /// ```
+    pub fn joint_limit_avoidance_gradient(&self) -> Vec<T> {
+        let limits = self
+            .iter_joints()
+            .map(|joint| joint.limits.clone())
+            .collect::<Vec<_>>();
+        joint_limit_avoidance_gradient(&limits, &self.joint_positions())
+    }
+
+    /// Iterate the joint positions on the regular grid within the limits
+    ///
+    /// Each joint takes `resolution_per_joint` values from the lower limit to the upper limit
+    /// (the middle if it is `1`), so `resolution_per_joint.pow(dof())` positions are yielded
+    /// lazily. The last joint changes fastest. Rotational joints without limits use
+    /// `[-pi, pi]` and the other joints without limits keep their current positions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let l0 = NodeBuilder::new()
+    ///     .joint_type(JointType::Rotational { axis: Vector3::z_axis() })
+    ///     .limits(Some((0.0..=1.0).into()))
+    ///     .into_node();
+    /// let chain = Chain::<f64>::from_root(l0);
+    /// let configs = chain.grid_sample_configs(3).collect::<Vec<_>>();
+    /// assert_eq!(configs, vec![vec![0.0], vec![0.5], vec![1.0]]);
+    /// ```
+    pub fn grid_sample_configs(&self, resolution_per_joint: usize) -> impl Iterator<Item = Vec<T>> {
+        let grids = self
+            .iter_joints()
+            .map(|joint| {
+                let (min, max) = match (&joint.limits, &joint.joint_type) {
+                    (Some(range), _) => (range.min.clone(), range.max.clone()),
+                    (None, JointType::Rotational { .. }) => (-T::pi(), T::pi()),
+                    _ => {
+                        let position = joint
+                            .joint_position()
+                            .expect("Must be a bug: movable joint must have position");
+                        return vec![position; resolution_per_joint.min(1)];
+                    }
+                };
+                match resolution_per_joint {
+                    0 => return vec![],
+                    1 => return vec![(min + max) / na::convert(2.0)],
+                    _ => {}
                 }
-                for v in &mut link.visuals {
-                    let v_trans = parent_transform.clone() * v.origin();
-                    v.set_world_transform(v_trans);
+                let step = (max - min.clone()) / na::convert((resolution_per_joint - 1) as f64);
+                (0..resolution_per_joint)
+                    .map(|i| min.clone() + step.clone() * na::convert(i as f64))
+                    .collect()
+            })
+            .collect::<Vec<Vec<T>>>();
+        // the indices of the next positions, or None if finished
+        let mut indices = (!grids.iter().any(|grid| grid.is_empty())).then(|| vec![0; grids.len()]);
+        std::iter::from_fn(move || {
+            let current = indices.as_mut()?;
+            let positions = current
+                .iter()
+                .zip(grids.iter())
+                .map(|(i, grid)| grid[*i].clone())
+                .collect::<Vec<_>>();
+            // count up from the last joint
+            let mut j = current.len();
+            loop {
+                if j == 0 {
+                    indices = None;
+                    break;
+                }
+                j -= 1;
+                current[j] += 1;
+                if current[j] < grids[j].len() {
+                    break;
                 }
+                current[j] = 0;
             }
-        });
+            Some(positions)
+        })
     }
-}
 
-impl<T> Clone for Chain<T>
-where
-    T: RealField + SubsetOf<f64>,
-{
-    fn clone(&self) -> Self {
-        // first node must be root
-        if self.nodes.is_empty() {
-            return Self {
-                nodes: vec![],
-                movable_nodes: vec![],
-                dof: 0,
-            };
+    /// Shrink the limits of all joints toward their middles, to use as soft limits
+    ///
+    /// The new range of each joint is `fraction` times the original range with the same
+    /// middle, for example `0.9` removes 5% from each end. The joints without limits are
+    /// not changed. The current positions are clamped by the new limits, and the mimic
+    /// joints are set from their clamped parents.
+    /// `fraction` is clamped to `[0, 1]`, so the limits are never widened.
+    ///
+    /// The original range is the limits when this is called first, so calling this again
+    /// replaces the margin instead of shrinking the limits again, and `1.0` restores them.
+    /// If `Joint::limits` is changed after this, the changed limits are the new original.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let l0 = NodeBuilder::new()
+    ///     .joint_type(JointType::Rotational { axis: Vector3::z_axis() })
+    ///     .limits(Some((-1.0..=3.0).into()))
+    ///     .into_node();
+    /// let chain = Chain::<f64>::from_root(l0);
+    /// chain.apply_limit_margin(0.5);
+    /// assert_eq!(chain.joint_limit_vectors(), (vec![0.0], vec![2.0]));
+    /// chain.apply_limit_margin(0.5);
+    /// assert_eq!(chain.joint_limit_vectors(), (vec![0.0], vec![2.0]));
+    /// ```
+    pub fn apply_limit_margin(&self, fraction: T) {
+        let fraction = fraction.clamp(T::zero(), T::one());
+        for node in &self.movable_nodes {
+            node.lock().joint.set_limit_margin(fraction.clone());
         }
-        assert!(self.nodes[0].is_root());
-        // Clone everything
-        let mut new_nodes = self
-            .nodes
-            .iter()
-            .map(|n| {
-                let node = Node::new(n.joint().clone());
-                node.set_link(n.link().clone());
-                node
-            })
-            .collect::<Vec<_>>();
-        // Connect to new nodes
-        for i in 0..new_nodes.len() {
-            if let Some(p) = self.nodes[i].parent() {
-                // get index of p
-                let parent_index = self.nodes.iter().position(|x| *x == p).unwrap();
-                new_nodes[i].set_parent(&new_nodes[parent_index]);
+        for node in &self.movable_nodes {
+            if node.mimic_parent().is_some() {
+                continue;
             }
-            if let Some(m) = self.nodes[i].mimic_parent() {
-                let parent_index = self.nodes.iter().position(|x| *x == m).unwrap();
-                new_nodes[i].set_mimic_parent(
-                    &new_nodes[parent_index],
-                    self.nodes[i].lock().mimic.clone().unwrap(),
-                );
+            if let Some(position) = node.joint_position() {
+                node.set_joint_position_clamped(position);
+            }
+        }
+        // after all parents are clamped
+        for node in &self.movable_nodes {
+            let Some(parent) = node.mimic_parent() else {
+                continue;
+            };
+            let mimic = node.mimic_mapping();
+            if let (Some(position), Some(mimic)) = (parent.joint_position(), mimic) {
+                node.set_joint_position_clamped(mimic.mimic_position(position));
             }
         }
-        //
-        // first node must be root
-        assert!(new_nodes[0].is_root());
-        Chain::from_root(new_nodes.remove(0))
     }
-}
-
-#[derive(Debug)]
-/// Kinematic chain without any branch.
-///
-/// All joints are connected sequentially.
-pub struct SerialChain<T: RealField> {
-    inner: Chain<T>,
-}
 
-impl<T> SerialChain<T>
-where
-    T: RealField + SubsetOf<f64>,
-{
-    /// Convert Chain into SerialChain without any check
+    /// Set the clamped positions of the joints
     ///
-    /// If the input Chain has any branches it causes serious bugs.
+    /// This function is safe, in contrast to `set_joint_positions_unchecked`.
+    pub fn set_joint_positions_clamped(&self, positions_vec: &[T]) {
+        self.report_limit_hits(positions_vec);
+        for (joint, position) in self.movable_nodes.iter().zip(positions_vec.iter()) {
+            joint.set_joint_position_clamped(position.clone());
+        }
+        self.report_joint_positions();
+    }
+
+    /// Fast, but without check, dangerous `set_joint_positions`
     ///
-    pub fn new_unchecked(inner: Chain<T>) -> Self {
-        Self { inner }
+    /// The positions are not reported to the telemetry.
+    #[inline]
+    pub fn set_joint_positions_unchecked(&self, positions_vec: &[T]) {
+        for (joint, position) in self.movable_nodes.iter().zip(positions_vec.iter()) {
+            joint.set_joint_position_unchecked(position.clone());
+        }
     }
-    /// Convert Chain into SerialChain
+
+    /// Move the joints by `deltas` from the current positions
     ///
-    /// If the input Chain has any branches it fails.
+    /// It is `set_joint_positions` with the current positions plus `deltas`.
+    /// The length of `deltas` must be `dof()`.
     ///
     /// # Examples
     ///
     /// ```
-    /// let node = k::NodeBuilder::<f32>::new().into_node();
-    /// let chain = k::Chain::from_root(node);
-    /// assert!(k::SerialChain::try_new(chain).is_some());
+    /// use k::*;
+    ///
+    /// let l0 = NodeBuilder::new()
+    ///     .joint_type(JointType::Rotational { axis: Vector3::z_axis() })
+    ///     .limits(Some((-1.0..=1.0).into()))
+    ///     .into_node();
+    /// let chain = Chain::<f64>::from_root(l0);
+    /// chain.move_joint_positions(&[0.5]).unwrap();
+    /// chain.move_joint_positions(&[0.25]).unwrap();
+    /// assert_eq!(chain.joint_positions(), vec![0.75]);
+    /// assert!(chain.move_joint_positions(&[0.5]).is_err());
+    /// chain.move_joint_positions_clamped(&[0.5]).unwrap();
+    /// assert_eq!(chain.joint_positions(), vec![1.0]);
     /// ```
+    pub fn move_joint_positions(&self, deltas: &[T]) -> Result<(), Error> {
+        self.set_joint_positions(&self.moved_positions(deltas)?)
+    }
+
+    /// Move the joints by `deltas` from the current positions, clamped by the limits
+    ///
+    /// The length of `deltas` must be `dof()`.
+    pub fn move_joint_positions_clamped(&self, deltas: &[T]) -> Result<(), Error> {
+        self.set_joint_positions_clamped(&self.moved_positions(deltas)?);
+        Ok(())
+    }
+
+    /// Move the movable joint `joint_name` by `delta` from the current position
+    ///
+    /// It fails if the joint is not found.
+    ///
+    /// # Examples
     ///
     /// ```
-    /// let node0 = k::NodeBuilder::<f32>::new().into_node();
-    /// let node1 = k::NodeBuilder::new().into_node();
-    /// let node2 = k::NodeBuilder::new().into_node();
-    /// node1.set_parent(&node0);
-    /// node2.set_parent(&node0);
-    /// let chain = k::Chain::from_root(node0);
-    /// assert!(k::SerialChain::try_new(chain).is_none());
+    /// use k::*;
+    ///
+    /// let l0 = NodeBuilder::new()
+    ///     .name("j0")
+    ///     .joint_type(JointType::Rotational { axis: Vector3::z_axis() })
+    ///     .limits(Some((-1.0..=1.0).into()))
+    ///     .into_node();
+    /// let mut chain = Chain::<f64>::from_root(l0);
+    /// chain.move_joint_position("j0", 0.75).unwrap();
+    /// assert!(chain.move_joint_position("j0", 0.5).is_err());
+    /// chain.set_limit_mode(LimitMode::Clamp);
+    /// chain.move_joint_position("j0", 0.5).unwrap();
+    /// assert_eq!(chain.joint_positions(), vec![1.0]);
     /// ```
-    pub fn try_new(inner: Chain<T>) -> Option<Self> {
-        {
-            let num = inner.iter().count();
-            for node in inner.iter().take(num - 1) {
-                if node.children().len() != 1 {
-                    return None;
-                }
-            }
+    pub fn move_joint_position(&self, joint_name: &str, delta: T) -> Result<(), Error> {
+        let index = self
+            .movable_nodes
+            .iter()
+            .position(|node| node.joint().name == joint_name)
+            .ok_or_else(|| Error::InvalidJointNameError {
+                joint_name: joint_name.to_owned(),
+            })?;
+        let mut deltas = vec![T::zero(); self.dof];
+        deltas[index] = delta;
+        self.move_joint_positions(&deltas)
+    }
+
+    fn moved_positions(&self, deltas: &[T]) -> Result<Vec<T>, Error> {
+        if deltas.len() != self.dof {
+            return Err(Error::SizeMismatchError {
+                input: deltas.len(),
+                required: self.dof,
+            });
         }
-        Some(Self { inner })
+        Ok(self
+            .joint_positions()
+            .into_iter()
+            .zip(deltas)
+            .map(|(position, delta)| position + delta.clone())
+            .collect())
     }
-    /// Create SerialChain from the end `Node`
+
+    /// Check that the straight path in the joint space from `start` to `goal` is collision free
+    ///
+    /// `is_collision_free` checks the current positions of the chain, for example by
+    /// `min_self_distance()`, or by a collision checking library for `collision_check_pairs()`
+    /// if the links have meshes.
+    /// The path is divided into `resolution` segments and all `resolution + 1` positions
+    /// including `start` and `goal` are checked.
+    /// The positions of the chain are restored after the check.
     ///
     /// # Examples
     ///
     /// ```
-    /// let node = k::NodeBuilder::<f32>::new().into_node();
-    /// let s_chain = k::SerialChain::from_end(&node);
+    /// use k::*;
+    ///
+    /// let l0 = NodeBuilder::new()
+    ///     .joint_type(JointType::Rotational { axis: Vector3::z_axis() })
+    ///     .into_node();
+    /// let l1 = NodeBuilder::new()
+    ///     .translation(Translation3::new(1.0, 0.0, 0.0))
+    ///     .into_node();
+    /// l1.set_parent(&l0);
+    /// let chain = Chain::<f64>::from_root(l0);
+    /// // an obstacle around (0, 1)
+    /// let is_free = |chain: &Chain<f64>| {
+    ///     chain.update_transforms();
+    ///     let end = chain.iter().last().unwrap().world_transform().unwrap();
+    ///     (end.translation.vector - Vector3::new(0.0, 1.0, 0.0)).norm() > 0.1
+    /// };
+    /// assert!(chain.path_is_collision_free(&[0.0], &[1.0], 10, is_free).unwrap());
+    /// assert!(!chain.path_is_collision_free(&[0.0], &[2.0], 10, is_free).unwrap());
+    /// assert_eq!(chain.joint_positions(), vec![0.0]);
     /// ```
-    pub fn from_end(end_joint: &Node<T>) -> SerialChain<T> {
-        SerialChain {
-            inner: Chain::from_end(end_joint),
+    pub fn path_is_collision_free<F>(
+        &self,
+        start: &[T],
+        goal: &[T],
+        resolution: usize,
+        mut is_collision_free: F,
+    ) -> Result<bool, Error>
+    where
+        F: FnMut(&Self) -> bool,
+    {
+        for positions in [start, goal] {
+            if positions.len() != self.dof() {
+                return Err(Error::SizeMismatchError {
+                    input: positions.len(),
+                    required: self.dof(),
+                });
+            }
         }
+        let original = self.joint_positions();
+        let resolution = resolution.max(1);
+        let mut result = Ok(true);
+        for i in 0..=resolution {
+            let ratio = na::convert::<f64, T>(i as f64 / resolution as f64);
+            let positions = start
+                .iter()
+                .zip(goal)
+                .map(|(s, g)| s.clone() + (g.clone() - s.clone()) * ratio.clone())
+                .collect::<Vec<_>>();
+            if let Err(err) = self.without_position_reports(|| self.set_joint_positions(&positions))
+            {
+                result = Err(err);
+                break;
+            }
+            if !is_collision_free(self) {
+                result = Ok(false);
+                break;
+            }
+        }
+        self.set_joint_positions_unchecked(&original);
+        result
     }
 
-    /// Create SerialChain from the end `Node` and root `Node`.
+    /// Change the order of the movable joints
+    ///
+    /// `order` must contain all the names of the movable joints exactly once.
+    /// After this, `iter_joints()`, `joint_positions()`, `set_joint_positions()` and
+    /// other methods which deal with movable joints use this order.
+    /// The structure of the tree is not changed.
     ///
     /// # Examples
     ///
     /// ```
-    /// let node0 = k::NodeBuilder::<f32>::new().into_node();
-    /// let node1 = k::NodeBuilder::<f32>::new().into_node();
-    /// let node2 = k::NodeBuilder::<f32>::new().into_node();
-    /// let node3 = k::NodeBuilder::<f32>::new().into_node();
-    /// use k::connect;
-    /// connect![node0 => node1 => node2 => node3];
-    /// let s_chain = k::SerialChain::from_end_to_root(&node2, &node1);
-    /// assert_eq!(s_chain.iter().count(), 2);
+    /// use k::*;
+    ///
+    /// let l0 = NodeBuilder::new()
+    ///     .name("j0")
+    ///     .joint_type(JointType::Rotational { axis: Vector3::z_axis() })
+    ///     .into_node();
+    /// let l1 = NodeBuilder::new()
+    ///     .name("j1")
+    ///     .joint_type(JointType::Rotational { axis: Vector3::z_axis() })
+    ///     .into_node();
+    /// l1.set_parent(&l0);
+    /// let mut chain = Chain::<f64>::from_root(l0);
+    /// chain.set_joint_positions(&[0.1, 0.2]).unwrap();
+    /// chain.reorder_joints(&["j1".to_owned(), "j0".to_owned()]).unwrap();
+    /// assert_eq!(chain.joint_positions(), vec![0.2, 0.1]);
     /// ```
-    pub fn from_end_to_root(end_joint: &Node<T>, root_joint: &Node<T>) -> SerialChain<T> {
-        SerialChain {
-            inner: Chain::from_end_to_root(end_joint, root_joint),
+    pub fn reorder_joints(&mut self, order: &[String]) -> Result<(), Error> {
+        if order.len() != self.dof {
+            return Err(Error::SizeMismatchError {
+                input: order.len(),
+                required: self.dof,
+            });
+        }
+        let mut movable_nodes = Vec::with_capacity(self.dof);
+        for (i, joint_name) in order.iter().enumerate() {
+            if order[..i].contains(joint_name) {
+                return Err(Error::DuplicateJointNameError {
+                    joint_name: joint_name.to_owned(),
+                });
+            }
+            let node = self
+                .movable_nodes
+                .iter()
+                .find(|node| node.joint().name == *joint_name)
+                .ok_or_else(|| Error::InvalidJointNameError {
+                    joint_name: joint_name.to_owned(),
+                })?;
+            movable_nodes.push(node.clone());
+        }
+        self.movable_nodes = movable_nodes;
+        Ok(())
+    }
+
+    /// Get the velocities of the joints
+    ///
+    /// `FixedJoint` is ignored. the length is the same with `dof()`
+    pub fn joint_velocities(&self) -> Vec<T> {
+        self.iter_joints()
+            .map(|joint| {
+                joint
+                    .joint_velocity()
+                    .expect("Must be a bug: movable joint must have velocity")
+            })
+            .collect()
+    }
+
+    /// Set the velocities of the joints
+    ///
+    /// `FixedJoints` are ignored. the input number must be equal with `dof()`
+    pub fn set_joint_velocities(&self, velocities_vec: &[T]) -> Result<(), Error> {
+        if velocities_vec.len() != self.dof {
+            return Err(Error::SizeMismatchError {
+                input: velocities_vec.len(),
+                required: self.dof,
+            });
+        }
+        for (joint, velocity) in self.movable_nodes.iter().zip(velocities_vec.iter()) {
+            joint.set_joint_velocity(velocity.clone())?;
+        }
+        Ok(())
+    }
+
+    /// Update world_transform() of the joints
+    pub fn update_transforms(&self) -> Vec<Isometry3<T>> {
+        let transforms = self.update_world_transforms();
+        if let Some(telemetry) = self.telemetry() {
+            telemetry.on_fk(&transforms);
+        }
+        transforms
+    }
+
+    /// `update_transforms()` without the telemetry
+    fn update_world_transforms(&self) -> Vec<Isometry3<T>> {
+        self.iter()
+            .map(|node| {
+                // copy the cache once to resolve a deadlock of 'node' guards
+                let cached_world_transform = node.joint().world_transform();
+                cached_world_transform.unwrap_or_else(|| {
+                    // clear child caches
+                    // recursive clearing is not necessary thanks to the order of Chain::iter()
+                    for child in node.children().iter() {
+                        child.joint().clear_caches();
+                    }
+
+                    // calculate the transformation
+                    let parent_transform = node.parent_world_transform().expect("cache must exist");
+                    let trans = parent_transform * node.joint().local_transform();
+                    node.joint().set_world_transform(trans.clone());
+                    trans
+                })
+            })
+            .collect()
+    }
+
+    /// Calculate the world transforms of the joints without writing the caches
+    ///
+    /// It returns the same as `update_transforms()`, but `world_transform()` of the joints
+    /// are not changed (and not used), so it has no side effects.
+    /// It is slower than `update_transforms()` because it always calculates all transforms.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let chain = Chain::<f64>::from_urdf_file("urdf/sample.urdf").unwrap();
+    /// let transforms = chain.compute_transforms();
+    /// assert!(chain.iter().all(|node| node.world_transform().is_none()));
+    /// assert_eq!(transforms, chain.update_transforms());
+    /// ```
+    pub fn compute_transforms(&self) -> Vec<Isometry3<T>> {
+        let nodes = self.iter().collect::<Vec<_>>();
+        let mut transforms: Vec<Isometry3<T>> = Vec::with_capacity(nodes.len());
+        for node in &nodes {
+            let parent = node.parent();
+            let parent_transform = match &parent {
+                None => Isometry3::identity(),
+                Some(parent) => match nodes.iter().position(|x| *x == parent) {
+                    // parents come first in Chain::iter()
+                    Some(index) => transforms[index].clone(),
+                    // the root of a sub tree
+                    None => parent
+                        .iter_ancestors()
+                        .fold(Isometry3::identity(), |trans, ancestor| {
+                            ancestor.joint().local_transform() * trans
+                        }),
+                },
+            };
+            transforms.push(parent_transform * node.joint().local_transform());
+        }
+        transforms
+    }
+
+    /// Normalize the rotations of the cached world transforms
+    ///
+    /// The world transforms are products of the local transforms, so the rounding errors
+    /// accumulate along the chain and the rotations are not exactly unit quaternions.
+    /// It is negligible for `f64`, but it can matter for deep chains with `f32`.
+    /// Call it after `update_transforms()` if `max_rotation_nonorthogonality()` is large.
+    /// The joints without the cache are ignored.
+    pub fn renormalize_transforms(&self) {
+        for node in self.iter() {
+            let joint = node.joint();
+            if let Some(mut trans) = joint.world_transform() {
+                trans.rotation.renormalize();
+                joint.set_world_transform(trans);
+            }
+        }
+    }
+
+    /// The largest error of the norm of the rotations (unit quaternions) of the cached world transforms
+    ///
+    /// It is zero if the rotations are exact, and the joints without the cache are ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let chain = k::Chain::<f32>::from_urdf_file("urdf/sample.urdf").unwrap();
+    /// chain.update_transforms();
+    /// chain.renormalize_transforms();
+    /// assert!(chain.max_rotation_nonorthogonality() < 1.0e-6);
+    /// ```
+    pub fn max_rotation_nonorthogonality(&self) -> T {
+        self.iter()
+            .filter_map(|node| node.joint().world_transform())
+            .map(|trans| (trans.rotation.quaternion().norm() - T::one()).abs())
+            .fold(T::zero(), |a, b| a.max(b))
+    }
+
+    /// Update world_transform() of `changed_joint_name` and its descendants only
+    ///
+    /// When only one joint is changed, it is cheaper than `update_transforms()`, which checks
+    /// the caches of all joints. The world transforms of the other joints are not touched,
+    /// so they must be up to date. If the world transform of the parent is not calculated,
+    /// it falls back to `update_transforms()`. The world velocities of the updated joints
+    /// are cleared, and the telemetry gets all world transforms as `update_transforms()`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let chain = Chain::<f64>::from_urdf_file("urdf/sample.urdf").unwrap();
+    /// chain.update_transforms();
+    /// let wrist = chain.find("l_wrist_pitch").unwrap();
+    /// wrist.set_joint_position(0.5).unwrap();
+    /// chain.update_transforms_from("l_wrist_pitch").unwrap();
+    /// assert!(wrist.world_transform().is_some());
+    /// assert!(chain.update_transforms_from("no_exist").is_err());
+    /// ```
+    pub fn update_transforms_from(&self, changed_joint_name: &str) -> Result<(), Error> {
+        let node = self
+            .find(changed_joint_name)
+            .ok_or_else(|| Error::InvalidJointNameError {
+                joint_name: changed_joint_name.to_owned(),
+            })?;
+        if node.parent_world_transform().is_none() {
+            self.update_transforms();
+            return Ok(());
+        }
+        // parents come before their children in the pre-order
+        for descendant in node.iter_descendants() {
+            if !self.nodes.contains(&descendant) {
+                continue;
+            }
+            let parent_transform = descendant
+                .parent_world_transform()
+                .expect("cache must exist");
+            let trans = parent_transform * descendant.joint().local_transform();
+            let joint = descendant.joint();
+            joint.clear_caches();
+            joint.set_world_transform(trans);
+        }
+        if let Some(telemetry) = self.telemetry() {
+            telemetry.on_fk(&self.update_world_transforms());
+        }
+        Ok(())
+    }
+
+    /// Update the world transforms and get them as homogeneous matrices
+    ///
+    /// The order is the same with `iter()`. It is useful to upload the transforms to GPU.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let l0 = NodeBuilder::new()
+    ///     .joint_type(JointType::Rotational { axis: Vector3::z_axis() })
+    ///     .into_node();
+    /// let l1 = NodeBuilder::new()
+    ///     .translation(Translation3::new(1.0, 0.0, 0.0))
+    ///     .into_node();
+    /// l1.set_parent(&l0);
+    /// let chain = Chain::<f64>::from_root(l0);
+    /// let matrices = chain.world_transform_matrices();
+    /// assert_eq!(matrices.len(), 2);
+    /// assert_eq!(matrices[1][(0, 3)], 1.0);
+    /// ```
+    pub fn world_transform_matrices(&self) -> Vec<Matrix4<T>> {
+        self.update_transforms()
+            .iter()
+            .map(|trans| trans.to_homogeneous())
+            .collect()
+    }
+
+    /// Get the names of the movable joints which move the link `link_name`
+    ///
+    /// They are the joints on the path from the root to the link, in the order from the root,
+    /// so they are the joints of the non-zero columns of `link_jacobian()`.
+    /// Returns `None` if the link is not found.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let chain = k::Chain::<f64>::from_urdf_file("urdf/sample.urdf").unwrap();
+    /// let names = chain.contributing_joints("l_shoulder3").unwrap();
+    /// assert_eq!(names, vec!["l_shoulder_yaw", "l_shoulder_pitch", "l_shoulder_roll"]);
+    /// ```
+    pub fn contributing_joints(&self, link_name: &str) -> Option<Vec<String>> {
+        let node = self.find_link(link_name)?;
+        let ancestors = node.iter_ancestors().collect::<Vec<_>>();
+        Some(
+            self.iter_movable_nodes()
+                .filter(|movable| ancestors.contains(movable))
+                .map(|movable| movable.joint().name.clone())
+                .collect(),
+        )
+    }
+
+    /// Calculate the jacobian of the link `link_name`
+    ///
+    /// It is a 6 x `dof()` matrix which maps the velocities of the movable joints to
+    /// the velocity (translation and rotation) of the origin of the link, like `jacobian()`
+    /// for the end of `SerialChain`. The columns of the joints which are not the ancestors
+    /// of the link are zero. Returns `None` if the link is not found.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let chain = Chain::<f64>::from_urdf_file("urdf/sample.urdf").unwrap();
+    /// let j = chain.link_jacobian("l_elbow1").unwrap();
+    /// assert_eq!(j.ncols(), chain.dof());
+    /// // the right arm does not move the left elbow
+    /// let index = chain.joint_names().iter().position(|name| name == "r_shoulder_yaw").unwrap();
+    /// assert_eq!(j.column(index).norm(), 0.0);
+    /// ```
+    pub fn link_jacobian(&self, link_name: &str) -> Option<DMatrix<T>> {
+        let node = self.find_link(link_name)?;
+        self.update_transforms();
+        let p_n = node.world_transform()?.translation.vector;
+        let ancestors = node.iter_ancestors().collect::<Vec<_>>();
+        let columns = self
+            .iter_movable_nodes()
+            .map(|movable| {
+                if ancestors.contains(movable) {
+                    jacobian_column(&movable.joint(), &p_n)
+                } else {
+                    std::array::from_fn(|_| T::zero())
+                }
+            })
+            .collect::<Vec<_>>();
+        Some(DMatrix::from_fn(6, columns.len(), |r, c| {
+            columns[c][r].clone()
+        }))
+    }
+
+    /// Update world_velocity() of the joints
+    pub fn update_velocities(&self) -> Vec<Velocity<T>> {
+        self.update_transforms();
+        self.iter()
+            .map(|node| {
+                // copy the cache to resolve a deadlock of 'node' guards
+                let cached_world_velocity = node.joint().world_velocity();
+                cached_world_velocity.unwrap_or_else(|| {
+                    let parent_transform = node
+                        .parent_world_transform()
+                        .expect("transform cache must exist");
+                    let parent_velocity = node
+                        .parent_world_velocity()
+                        .expect("velocity cache must exist");
+                    let velocity = match &node.joint().joint_type {
+                        JointType::Fixed => parent_velocity,
+                        JointType::Rotational { axis } => {
+                            let parent = node.parent().expect("parent must exist");
+                            let parent_vel = parent.joint().origin().translation.vector.clone();
+                            Velocity::from_parts(
+                                parent_velocity.translation
+                                    + parent_velocity.rotation.cross(
+                                        &(parent_transform.rotation.to_rotation_matrix()
+                                            * parent_vel),
+                                    ),
+                                parent_velocity.rotation
+                                    + node
+                                        .world_transform()
+                                        .expect("cache must exist")
+                                        .rotation
+                                        .to_rotation_matrix()
+                                        * (axis.clone().into_inner()
+                                            * node.joint().joint_velocity().unwrap()),
+                            )
+                        }
+                        JointType::Linear { axis } => Velocity::from_parts(
+                            parent_velocity.translation
+                                + node
+                                    .world_transform()
+                                    .expect("cache must exist")
+                                    .rotation
+                                    .to_rotation_matrix()
+                                    * (axis.clone().into_inner()
+                                        * node.joint().joint_velocity().unwrap()),
+                            // TODO: Is this true??
+                            parent_velocity.rotation,
+                        ),
+                    };
+                    node.joint().set_world_velocity(velocity.clone());
+                    velocity
+                })
+            })
+            .collect()
+    }
+
+    /// Update transforms of the links
+    pub fn update_link_transforms(&self) {
+        self.update_transforms();
+        self.iter().for_each(|node| {
+            let parent_transform = node.parent_world_transform().expect("cache must exist");
+            let mut node_mut = node.lock();
+            if let Some(ref mut link) = node_mut.link {
+                let inertial_trans = parent_transform.clone() * link.inertial.origin();
+                link.inertial.set_world_transform(inertial_trans);
+                for c in &mut link.collisions {
+                    let c_trans = parent_transform.clone() * c.origin();
+                    c.set_world_transform(c_trans);
+                }
+                for v in &mut link.visuals {
+                    let v_trans = parent_transform.clone() * v.origin();
+                    v.set_world_transform(v_trans);
+                }
+            }
+        });
+    }
+
+    /// Attach a point mass to the link, like an object grasped by a gripper
+    ///
+    /// `com_offset` is the position of the mass in the frame of the link.
+    /// The payload is used by `center_of_mass`, `total_mass` and `gravity_torques`.
+    /// If the link already has a payload, it is replaced.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    /// use k::link::*;
+    ///
+    /// let l0 = NodeBuilder::new().into_node();
+    /// l0.set_link(Some(LinkBuilder::new().name("hand").inertial(Inertial::from_mass(1.0)).finalize()));
+    /// let mut chain = Chain::<f64>::from_root(l0);
+    /// chain.set_payload("hand", 0.5, Vector3::new(0.0, 0.0, 0.1)).unwrap();
+    /// assert_eq!(chain.total_mass(), 1.5);
+    /// chain.clear_payload("hand");
+    /// assert_eq!(chain.total_mass(), 1.0);
+    /// assert!(chain.set_payload("no_exist", 0.5, Vector3::zeros()).is_err());
+    /// ```
+    pub fn set_payload(
+        &mut self,
+        link_name: &str,
+        mass: T,
+        com_offset: Vector3<T>,
+    ) -> Result<(), Error> {
+        if self.find_link(link_name).is_none() {
+            return Err(Error::InvalidLinkNameError {
+                link_name: link_name.to_owned(),
+            });
+        }
+        self.clear_payload(link_name);
+        self.payloads.push((link_name.to_owned(), mass, com_offset));
+        Ok(())
+    }
+
+    /// Remove the payload of the link which is set by `set_payload`
+    pub fn clear_payload(&mut self, link_name: &str) {
+        self.payloads.retain(|(name, _, _)| name != link_name);
+    }
+
+    /// Calculate the total mass of the links and the payloads
+    pub fn total_mass(&self) -> T {
+        let links = self
+            .iter_links()
+            .fold(T::zero(), |sum, link| sum + link.inertial.mass.clone());
+        self.payloads
+            .iter()
+            .fold(links, |sum, (_, mass, _)| sum + mass.clone())
+    }
+
+    /// (node, mass, world position) of the links and the payloads
+    ///
+    /// `update_transforms()` must be called before.
+    pub(crate) fn point_masses(&self) -> Vec<(Node<T>, T, Vector3<T>)> {
+        let links = self.iter().filter_map(|node| {
+            let trans = node.world_transform()?;
+            let link = node.link();
+            let link = link.as_ref()?;
+            let com = trans * link.inertial.origin().translation.clone();
+            Some((
+                node.clone(),
+                link.inertial.mass.clone(),
+                com.translation.vector,
+            ))
+        });
+        let payloads = self.payloads.iter().filter_map(|(name, mass, offset)| {
+            let node = self.find_link(name)?;
+            let com = node.world_transform()? * Point3::from(offset.clone());
+            Some((node.clone(), mass.clone(), com.coords))
+        });
+        links.chain(payloads).collect()
+    }
+
+    /// Calculate the joint torques (forces for linear joints) to hold the
+    /// current positions against the gravity of `gravity()`
+    ///
+    /// The length is the same with `dof()`.
+    /// Only the links included in this `Chain` are considered.
+    pub fn gravity_torques(&self) -> Vec<T> {
+        self.gravity_torques_with(&self.gravity)
+    }
+
+    /// Calculate the joint torques to hold the current positions against `gravity`
+    ///
+    /// Use this instead of `gravity_torques()` to override the stored gravity.
+    pub fn gravity_torques_with(&self, gravity: &Vector3<T>) -> Vec<T> {
+        self.update_transforms();
+        // (node, mass, world position of the center of mass)
+        let masses = self.point_masses();
+        self.movable_nodes
+            .iter()
+            .map(|joint_node| {
+                let t_i = joint_node.world_transform().expect("cache must exist");
+                let (axis, is_rotational) = match &joint_node.joint().joint_type {
+                    JointType::Rotational { axis } => (t_i.rotation * axis, true),
+                    JointType::Linear { axis } => (t_i.rotation * axis, false),
+                    JointType::Fixed => panic!("impossible, bug of gravity_torques"),
+                };
+                let p_i = t_i.translation.vector;
+                masses
+                    .iter()
+                    .filter(|(node, _, _)| node.iter_ancestors().any(|a| a == *joint_node))
+                    .fold(T::zero(), |torque, (_, mass, com)| {
+                        let force = gravity * mass.clone();
+                        let contribution = if is_rotational {
+                            axis.cross(&(com - p_i.clone())).dot(&force)
+                        } else {
+                            axis.dot(&force)
+                        };
+                        torque - contribution
+                    })
+            })
+            .collect()
+    }
+
+    /// Project the center of mass onto the ground plane
+    ///
+    /// The ground plane passes through the world origin and its normal is `ground_normal`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let chain = Chain::<f64>::from_urdf_file("urdf/sample.urdf").unwrap();
+    /// let projected = chain.com_ground_projection(Vector3::z());
+    /// assert_eq!(projected.z, 0.0);
+    /// ```
+    pub fn com_ground_projection(&self, ground_normal: Vector3<T>) -> Point3<T> {
+        let normal = ground_normal.normalize();
+        let com = center_of_mass(self);
+        Point3::from(com.clone() - normal.clone() * normal.dot(&com))
+    }
+
+    /// Check if the center of mass is in the support polygon of the contact points
+    ///
+    /// The support polygon is the convex hull of `contacts` projected onto the ground,
+    /// whose normal is the opposite of `gravity()`. The order of `contacts` does not matter.
+    /// It returns false if the contacts do not surround any area.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let chain = Chain::<f64>::from_urdf_file("urdf/sample.urdf").unwrap();
+    /// let com = chain.com_ground_projection(Vector3::z());
+    /// let square = |x: f64, y: f64| {
+    ///     [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)]
+    ///         .iter()
+    ///         .map(|(dx, dy)| nalgebra::Point3::new(x + dx * 0.1, y + dy * 0.1, 0.0))
+    ///         .collect::<Vec<_>>()
+    /// };
+    /// assert!(chain.com_within_polygon(&square(com.x, com.y)));
+    /// assert!(!chain.com_within_polygon(&square(com.x + 1.0, com.y)));
+    /// ```
+    pub fn com_within_polygon(&self, contacts: &[Point3<T>]) -> bool {
+        let normal = if self.gravity.norm() > T::default_epsilon() {
+            -self.gravity.normalize()
+        } else {
+            Vector3::z()
+        };
+        let com = self.com_ground_projection(normal.clone());
+        point_within_support_polygon(&com, contacts, &normal)
+    }
+
+    /// Move the joints to the resting pose under `gravity`, like a hanging arm without motors
+    ///
+    /// The potential energy of the links and the payloads is minimized by the gradient descent
+    /// for `iterations` steps, where the gradient is `gravity_torques_with(gravity)`.
+    /// The positions are clamped by the limits, so it can stop at the limits.
+    /// The step size is adapted so that the energy always decreases, which avoids
+    /// the unstable equilibria like an inverted pendulum unless it starts there.
+    ///
+    /// The objective is the potential energy, not the sum of the squared gravity torques.
+    /// The torques are also zero at the unstable equilibria, and the gradient of their
+    /// squared sum is zero where the torques are the largest (e.g. a horizontal pendulum),
+    /// so it would not find the resting pose.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    /// use k::link::*;
+    ///
+    /// let l0 = NodeBuilder::new()
+    ///     .joint_type(JointType::Rotational { axis: Vector3::y_axis() })
+    ///     .into_node();
+    /// let l1 = NodeBuilder::new()
+    ///     .translation(Translation3::new(1.0, 0.0, 0.0))
+    ///     .into_node();
+    /// l1.set_link(Some(LinkBuilder::new().inertial(Inertial::from_mass(1.0)).finalize()));
+    /// l1.set_parent(&l0);
+    /// let chain = Chain::<f64>::from_root(l0);
+    /// chain.find_minimum_energy_pose(Vector3::new(0.0, 0.0, -9.81), 100);
+    /// assert!((chain.joint_positions()[0] - std::f64::consts::FRAC_PI_2).abs() < 1e-3);
+    /// ```
+    pub fn find_minimum_energy_pose(&self, gravity: Vector3<T>, iterations: usize) {
+        let min_step: T = na::convert(1.0e-9);
+        let mut step: T = na::convert(0.1);
+        let mut energy = self.potential_energy_with(&gravity);
+        for _ in 0..iterations {
+            let torques = self.gravity_torques_with(&gravity);
+            let max_torque = torques
+                .iter()
+                .fold(T::zero(), |max, torque| max.max(torque.clone().abs()));
+            if max_torque <= T::default_epsilon() || step < min_step {
+                break;
+            }
+            // the largest change of the positions is `step`
+            let positions = self.joint_positions();
+            let moved = positions
+                .iter()
+                .zip(torques.iter())
+                .map(|(q, torque)| q.clone() - torque.clone() / max_torque.clone() * step.clone())
+                .collect::<Vec<_>>();
+            self.without_position_reports(|| self.set_joint_positions_clamped(&moved));
+            let new_energy = self.potential_energy_with(&gravity);
+            if new_energy < energy {
+                energy = new_energy;
+                step *= na::convert::<f64, T>(1.5);
+            } else {
+                self.set_joint_positions_unchecked(&positions);
+                step *= na::convert::<f64, T>(0.5);
+            }
+        }
+        self.report_joint_positions();
+    }
+
+    /// Potential energy of the links and the payloads under `gravity` from the world origin
+    fn potential_energy_with(&self, gravity: &Vector3<T>) -> T {
+        self.update_transforms();
+        self.point_masses()
+            .iter()
+            .fold(T::zero(), |sum, (_, mass, com)| {
+                sum - gravity.dot(com) * mass.clone()
+            })
+    }
+
+    /// Calculate the kinetic energy of the links and the payloads with the joint velocities `qdot`
+    ///
+    /// It is the sum of `1/2 m v^2 + 1/2 w^T I w` of the links, where `v` is the velocity
+    /// of the center of mass and `w` is the angular velocity, calculated from the jacobian
+    /// at the current positions. The payloads are treated as point masses.
+    /// The length of `qdot` must be `dof()`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    /// use k::link::*;
+    ///
+    /// let l0 = NodeBuilder::new()
+    ///     .joint_type(JointType::Rotational { axis: Vector3::z_axis() })
+    ///     .into_node();
+    /// l0.set_link(Some(LinkBuilder::new().inertial(Inertial::from_mass(1.0)).finalize()));
+    /// let chain = Chain::<f64>::from_root(l0);
+    /// // the inertia of `from_mass` is the identity
+    /// assert!((chain.kinetic_energy(&[2.0]).unwrap() - 2.0).abs() < 1e-10);
+    /// assert!(chain.kinetic_energy(&[]).is_err());
+    /// ```
+    pub fn kinetic_energy(&self, qdot: &[T]) -> Result<T, Error> {
+        if qdot.len() != self.dof {
+            return Err(Error::SizeMismatchError {
+                input: qdot.len(),
+                required: self.dof,
+            });
+        }
+        self.update_transforms();
+        // (linear, angular) velocity of the point on the node
+        let velocity = |node: &Node<T>, point: &Vector3<T>| {
+            let ancestors = node.iter_ancestors().collect::<Vec<_>>();
+            self.movable_nodes.iter().zip(qdot.iter()).fold(
+                (Vector3::zeros(), Vector3::zeros()),
+                |(v, w): (Vector3<T>, Vector3<T>), (movable, dq)| {
+                    if !ancestors.contains(movable) {
+                        return (v, w);
+                    }
+                    let column = jacobian_column(&movable.joint(), point);
+                    let dv = Vector3::new(column[0].clone(), column[1].clone(), column[2].clone());
+                    let dw = Vector3::new(column[3].clone(), column[4].clone(), column[5].clone());
+                    (v + dv * dq.clone(), w + dw * dq.clone())
+                },
+            )
+        };
+        let half: T = na::convert(0.5);
+        let translational = self
+            .point_masses()
+            .iter()
+            .fold(T::zero(), |sum, (node, mass, com)| {
+                let (v, _) = velocity(node, com);
+                sum + half.clone() * mass.clone() * v.norm_squared()
+            });
+        let rotational = self.iter().fold(T::zero(), |sum, node| {
+            let trans = node.world_transform().expect("cache must exist");
+            let inertia = match node.link().as_ref() {
+                Some(link) => {
+                    let rotation = (trans.rotation.clone()
+                        * link.inertial.origin().rotation.clone())
+                    .to_rotation_matrix();
+                    rotation.matrix() * &link.inertial.inertia * rotation.matrix().transpose()
+                }
+                None => return sum,
+            };
+            let (_, w) = velocity(node, &trans.translation.vector);
+            sum + half.clone() * w.dot(&(inertia * &w))
+        });
+        Ok(translational + rotational)
+    }
+
+    /// Calculate the joint space inertia (mass) matrix `M` at the positions `q`
+    ///
+    /// It is the dof x dof symmetric matrix of the links and the payloads in this chain,
+    /// so `1/2 qdot^T M qdot` is the kinetic energy. It is calculated by the composite rigid
+    /// body algorithm. The positions of the chain are restored after the calculation.
+    /// The length of `q` must be `dof()`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    /// use k::link::*;
+    ///
+    /// let l0 = NodeBuilder::new()
+    ///     .joint_type(JointType::Rotational { axis: Vector3::z_axis() })
+    ///     .into_node();
+    /// // 2kg at 0.5m from the axis
+    /// let inertial = Inertial::new(
+    ///     Isometry3::translation(0.5, 0.0, 0.0),
+    ///     2.0,
+    ///     nalgebra::Matrix3::identity() * 0.1,
+    /// );
+    /// l0.set_link(Some(LinkBuilder::new().inertial(inertial).finalize()));
+    /// let chain = Chain::<f64>::from_root(l0);
+    /// let m = chain.mass_matrix(&[0.3]).unwrap();
+    /// assert!((m[(0, 0)] - (0.1 + 2.0 * 0.5 * 0.5)).abs() < 1e-10);
+    /// ```
+    pub fn mass_matrix(&self, q: &[T]) -> Result<DMatrix<T>, Error> {
+        self.with_positions(q, |chain| chain.joint_space_inertia())
+    }
+
+    /// Joint space inertia matrix at the current positions by the composite rigid body algorithm
+    ///
+    /// The spatial vectors are in the world coordinate and about the world origin.
+    pub(crate) fn joint_space_inertia(&self) -> DMatrix<T> {
+        self.update_transforms();
+        // (node, mass, center of mass, rotational inertia about the center of mass)
+        let links = self.iter().filter_map(|node| {
+            let trans = node.world_transform()?;
+            let link = node.link();
+            let link = link.as_ref()?;
+            let origin = link.inertial.origin();
+            let com = trans.clone() * origin.translation.clone();
+            let rotation = (trans.rotation * origin.rotation.clone()).to_rotation_matrix();
+            let inertia =
+                rotation.matrix() * &link.inertial.inertia * rotation.matrix().transpose();
+            Some((
+                node.clone(),
+                link.inertial.mass.clone(),
+                com.translation.vector,
+                inertia,
+            ))
+        });
+        let payloads = self.payloads.iter().filter_map(|(name, mass, offset)| {
+            let node = self.find_link(name)?;
+            let com = node.world_transform()? * Point3::from(offset.clone());
+            Some((node.clone(), mass.clone(), com.coords, Matrix3::zeros()))
+        });
+        let bodies = links.chain(payloads).collect::<Vec<_>>();
+        // motion subspace (angular, linear velocity at the world origin) of the movable joints
+        let subspaces = self
+            .movable_nodes
+            .iter()
+            .map(|joint_node| {
+                let trans = joint_node.world_transform().expect("cache must exist");
+                match &joint_node.joint().joint_type {
+                    JointType::Rotational { axis } => {
+                        let axis = trans.rotation.clone() * axis.clone().into_inner();
+                        let linear = trans.translation.vector.cross(&axis);
+                        (axis, linear)
+                    }
+                    JointType::Linear { axis } => (
+                        Vector3::zeros(),
+                        trans.rotation.clone() * axis.clone().into_inner(),
+                    ),
+                    JointType::Fixed => panic!("impossible, bug of joint_space_inertia"),
+                }
+            })
+            .collect::<Vec<_>>();
+        let mut inertia = DMatrix::zeros(self.dof, self.dof);
+        for (j, joint_node) in self.movable_nodes.iter().enumerate() {
+            // composite rigid body of the subtree of the joint:
+            // (mass, first moment of mass, rotational inertia about the world origin)
+            let (mass, moment, rotational) = bodies
+                .iter()
+                .filter(|(node, _, _, _)| node.iter_ancestors().any(|a| a == *joint_node))
+                .fold(
+                    (T::zero(), Vector3::zeros(), Matrix3::zeros()),
+                    |(m, h, i): (T, Vector3<T>, Matrix3<T>), (_, mass, com, inertia)| {
+                        let parallel = (Matrix3::identity() * com.norm_squared()
+                            - com * com.transpose())
+                            * mass.clone();
+                        (
+                            m + mass.clone(),
+                            h + com * mass.clone(),
+                            i + inertia + parallel,
+                        )
+                    },
+                );
+            // spatial force (moment about the world origin, force) of the unit joint velocity
+            let (angular, linear) = &subspaces[j];
+            let moment_of_force = &rotational * angular + moment.cross(linear);
+            let force = linear * mass - moment.cross(angular);
+            // the order of movable_nodes can be changed by reorder_joints()
+            for (i, ancestor) in self.movable_nodes.iter().enumerate() {
+                if !joint_node.iter_ancestors().any(|a| a == *ancestor) {
+                    continue;
+                }
+                let (angular_i, linear_i) = &subspaces[i];
+                let value = angular_i.dot(&moment_of_force) + linear_i.dot(&force);
+                inertia[(i, j)] = value.clone();
+                inertia[(j, i)] = value;
+            }
+        }
+        inertia
+    }
+
+    /// Create a fast forward kinematics function of the link `end_link_name`
+    ///
+    /// The origins and the types of the ancestors of the link are captured once,
+    /// so the returned function does not lock any nodes. It takes the positions
+    /// of the movable joints (the same order as `set_joint_positions`) and
+    /// returns the world transform of the joint which moves the link.
+    /// The joints which are not in this chain keep the current positions.
+    ///
+    /// # Panics
+    ///
+    /// The returned function panics if the length of the input is not `dof()`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let l0 = NodeBuilder::new()
+    ///     .name("j0")
+    ///     .joint_type(JointType::Rotational { axis: Vector3::z_axis() })
+    ///     .into_node();
+    /// let l1 = NodeBuilder::new()
+    ///     .name("j1")
+    ///     .translation(Translation3::new(1.0, 0.0, 0.0))
+    ///     .joint_type(JointType::Linear { axis: Vector3::x_axis() })
+    ///     .into_node();
+    /// l1.set_parent(&l0);
+    /// l1.set_link(Some(link::LinkBuilder::new().name("link1").finalize()));
+    /// let chain = Chain::<f64>::from_root(l0);
+    /// let fk = chain.compile_fk("link1").unwrap();
+    /// let trans = fk(&[std::f64::consts::FRAC_PI_2, 1.0]);
+    /// assert!((trans.translation.vector.y - 2.0).abs() < 1e-10);
+    /// assert!(chain.compile_fk("no_exist").is_err());
+    /// ```
+    pub fn compile_fk(
+        &self,
+        end_link_name: &str,
+    ) -> Result<impl Fn(&[T]) -> Isometry3<T> + Send + Sync, Error> {
+        let end = self
+            .find_link(end_link_name)
+            .ok_or_else(|| Error::InvalidLinkNameError {
+                link_name: end_link_name.to_owned(),
+            })?;
+        Ok(self.compile_fk_of(end))
+    }
+
+    /// Create a fast forward kinematics function of the joint `end_joint_name`
+    fn compile_fk_by_joint_name(
+        &self,
+        end_joint_name: &str,
+    ) -> Result<impl Fn(&[T]) -> Isometry3<T> + Send + Sync, Error> {
+        let end = self
+            .find(end_joint_name)
+            .ok_or_else(|| Error::InvalidJointNameError {
+                joint_name: end_joint_name.to_owned(),
+            })?;
+        Ok(self.compile_fk_of(end))
+    }
+
+    fn compile_fk_of(&self, end: &Node<T>) -> impl Fn(&[T]) -> Isometry3<T> + Send + Sync {
+        let index_of = |node: &Node<T>| self.movable_nodes.iter().position(|n| n == node);
+        let mut joints = end
+            .iter_ancestors()
+            .map(|node| {
+                // the index of the input position, and the mimic
+                let input = match node.mimic_parent() {
+                    Some(parent) => index_of(&parent).map(|i| (i, node.mimic_mapping().unwrap())),
+                    None => index_of(&node)
+                        .map(|i| (i, MimicMapping::Linear(Mimic::new(T::one(), T::zero())))),
+                };
+                let joint = node.joint();
+                let position = joint.joint_position().unwrap_or_else(T::zero);
+                (
+                    // the base pose is applied before the origin as `Joint::local_transform`
+                    joint.base_pose() * joint.origin().clone(),
+                    joint.joint_type.clone(),
+                    position,
+                    input,
+                )
+            })
+            .collect::<Vec<_>>();
+        joints.reverse();
+        let dof = self.dof;
+        move |positions: &[T]| {
+            assert_eq!(
+                positions.len(),
+                dof,
+                "the length of the positions must be the dof"
+            );
+            joints.iter().fold(
+                Isometry3::identity(),
+                |trans, (origin, joint_type, position, input)| {
+                    let position = match input {
+                        Some((i, mimic)) => mimic.mimic_position(positions[*i].clone()),
+                        None => position.clone(),
+                    };
+                    trans * origin * joint_type.transform(position)
+                },
+            )
+        }
+    }
+
+    /// Calculate the world transforms of `end_joint_name` for many positions into `out`
+    ///
+    /// `out` is cleared and filled in the order of `positions_list`, so its capacity is
+    /// reused when it is called repeatedly. Each positions must have `dof()` elements,
+    /// in the same order as `set_joint_positions`. The positions of the chain are not changed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let l0 = NodeBuilder::new()
+    ///     .name("j0")
+    ///     .joint_type(JointType::Linear { axis: Vector3::x_axis() })
+    ///     .into_node();
+    /// let chain = Chain::<f64>::from_root(l0);
+    /// let mut out = Vec::new();
+    /// chain.end_transforms_into(&[vec![0.5], vec![1.0]], "j0", &mut out).unwrap();
+    /// assert_eq!(out.len(), 2);
+    /// assert_eq!(out[1].translation.vector.x, 1.0);
+    /// ```
+    pub fn end_transforms_into(
+        &self,
+        positions_list: &[Vec<T>],
+        end_joint_name: &str,
+        out: &mut Vec<Isometry3<T>>,
+    ) -> Result<(), Error> {
+        if let Some(positions) = positions_list.iter().find(|p| p.len() != self.dof()) {
+            return Err(Error::SizeMismatchError {
+                input: positions.len(),
+                required: self.dof(),
+            });
+        }
+        let fk = self.compile_fk_by_joint_name(end_joint_name)?;
+        out.clear();
+        out.extend(positions_list.iter().map(|positions| fk(positions)));
+        Ok(())
+    }
+
+    /// Calculate the world transforms of `end_joint_name` for many positions as structure of arrays
+    ///
+    /// It is the same as `end_transforms_into`, but the translations and the quaternions
+    /// are stored in the separate arrays, which is suitable for SIMD post-processing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let l0 = NodeBuilder::new()
+    ///     .name("j0")
+    ///     .joint_type(JointType::Linear { axis: Vector3::x_axis() })
+    ///     .into_node();
+    /// let chain = Chain::<f64>::from_root(l0);
+    /// let poses = chain.end_transforms_soa(&[vec![0.5], vec![1.0]], "j0").unwrap();
+    /// assert_eq!(poses.x, vec![0.5, 1.0]);
+    /// assert_eq!(poses.qw, vec![1.0, 1.0]);
+    /// ```
+    pub fn end_transforms_soa(
+        &self,
+        positions_list: &[Vec<T>],
+        end_joint_name: &str,
+    ) -> Result<EndPosesSoa<T>, Error> {
+        if let Some(positions) = positions_list.iter().find(|p| p.len() != self.dof()) {
+            return Err(Error::SizeMismatchError {
+                input: positions.len(),
+                required: self.dof(),
+            });
+        }
+        let fk = self.compile_fk_by_joint_name(end_joint_name)?;
+        let mut poses = EndPosesSoa::with_capacity(positions_list.len());
+        for positions in positions_list {
+            poses.push(&fk(positions));
+        }
+        Ok(poses)
+    }
+}
+
+pub(crate) fn joint_limit_avoidance_gradient<T>(
+    limits: &[Option<Range<T>>],
+    positions: &[T],
+) -> Vec<T>
+where
+    T: RealField,
+{
+    limits
+        .iter()
+        .zip(positions)
+        .map(|(limit, position)| match limit {
+            Some(range) if range.max > range.min => {
+                let width = range.max.clone() - range.min.clone();
+                let mid = (range.max.clone() + range.min.clone()) / na::convert(2.0);
+                (position.clone() - mid) / (width.clone() * width)
+            }
+            _ => T::zero(),
+        })
+        .collect()
+}
+
+/// Check if `point` is in the convex hull of `contacts` on the plane of `normal`
+fn point_within_support_polygon<T: RealField>(
+    point: &Point3<T>,
+    contacts: &[Point3<T>],
+    normal: &Vector3<T>,
+) -> bool {
+    // 2D coordinates on the plane
+    let u = if normal.x.clone().abs() < normal.z.clone().abs() {
+        Vector3::x().cross(normal)
+    } else {
+        Vector3::z().cross(normal)
+    }
+    .normalize();
+    let v = normal.cross(&u).normalize();
+    let to_2d = |p: &Point3<T>| (p.coords.dot(&u), p.coords.dot(&v));
+    let mut points = contacts.iter().map(to_2d).collect::<Vec<_>>();
+    points.sort_by(|a, b| {
+        a.0.partial_cmp(&b.0)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then(a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+    });
+    let cross = |o: &(T, T), a: &(T, T), b: &(T, T)| {
+        (a.0.clone() - o.0.clone()) * (b.1.clone() - o.1.clone())
+            - (a.1.clone() - o.1.clone()) * (b.0.clone() - o.0.clone())
+    };
+    // counter-clockwise convex hull by the monotone chain algorithm
+    let mut hull: Vec<(T, T)> = Vec::new();
+    let reversed = points.iter().rev().cloned().collect::<Vec<_>>();
+    for half in [&points, &reversed] {
+        let start = hull.len();
+        for p in half.iter() {
+            while hull.len() >= start + 2
+                && cross(&hull[hull.len() - 2], &hull[hull.len() - 1], p) <= T::zero()
+            {
+                hull.pop();
+            }
+            hull.push(p.clone());
+        }
+        hull.pop();
+    }
+    if hull.len() < 3 {
+        return false;
+    }
+    let target = to_2d(point);
+    (0..hull.len()).all(|i| cross(&hull[i], &hull[(i + 1) % hull.len()], &target) >= T::zero())
+}
+
+fn default_gravity<T: RealField>() -> Vector3<T> {
+    Vector3::new(T::zero(), T::zero(), na::convert(-9.81))
+}
+
+impl<T> Clone for Chain<T>
+where
+    T: RealField + SubsetOf<f64>,
+{
+    fn clone(&self) -> Self {
+        // first node must be root
+        if self.nodes.is_empty() {
+            return Self {
+                nodes: vec![],
+                movable_nodes: vec![],
+                dof: 0,
+                gravity: self.gravity.clone(),
+                payloads: self.payloads.clone(),
+                collision_ignore_pairs: self.collision_ignore_pairs.clone(),
+                collision_shapes: self.collision_shapes.clone(),
+                home_positions: self.home_positions.clone(),
+                telemetry: self.telemetry.clone(),
+                position_reports_muted: AtomicUsize::new(0),
+                frames: self.frames.clone(),
+                limit_mode: self.limit_mode,
+            };
+        }
+        assert!(self.nodes[0].is_root());
+        // Clone everything
+        let mut new_nodes = self
+            .nodes
+            .iter()
+            .map(|n| {
+                let node = Node::new(n.joint().clone());
+                node.set_link(n.link().clone());
+                node
+            })
+            .collect::<Vec<_>>();
+        // Connect to new nodes
+        for i in 0..new_nodes.len() {
+            if let Some(p) = self.nodes[i].parent() {
+                // get index of p
+                let parent_index = self.nodes.iter().position(|x| *x == p).unwrap();
+                new_nodes[i].set_parent(&new_nodes[parent_index]);
+            }
+            if let Some(m) = self.nodes[i].mimic_parent() {
+                let parent_index = self.nodes.iter().position(|x| *x == m).unwrap();
+                new_nodes[i].set_mimic_mapping(
+                    &new_nodes[parent_index],
+                    self.nodes[i].mimic_mapping().unwrap(),
+                );
+            }
+        }
+        //
+        // first node must be root
+        assert!(new_nodes[0].is_root());
+        let mut chain = Chain::from_root(new_nodes[0].clone());
+        chain.gravity = self.gravity.clone();
+        chain.payloads = self.payloads.clone();
+        chain.collision_ignore_pairs = self.collision_ignore_pairs.clone();
+        chain.collision_shapes = self.collision_shapes.clone();
+        chain.home_positions = self.home_positions.clone();
+        chain.telemetry = self.telemetry.clone();
+        chain.frames = self.frames.clone();
+        chain.limit_mode = self.limit_mode;
+        // keep the order given by `reorder_joints()`
+        chain.movable_nodes = self
+            .movable_nodes
+            .iter()
+            .map(|node| {
+                let index = self
+                    .nodes
+                    .iter()
+                    .position(|n| n == node)
+                    .expect("movable nodes are in the nodes");
+                new_nodes[index].clone()
+            })
+            .collect();
+        chain
+    }
+}
+
+#[derive(Debug)]
+/// Kinematic chain without any branch.
+///
+/// All joints are connected sequentially.
+pub struct SerialChain<T: RealField> {
+    inner: Chain<T>,
+    end_transform_cache: Option<Mutex<TransformCache<T>>>,
+    tool_transform: Isometry3<T>,
+}
+
+impl<T> SerialChain<T>
+where
+    T: RealField + SubsetOf<f64>,
+{
+    /// Convert Chain into SerialChain without any check
+    ///
+    /// If the input Chain has any branches it causes serious bugs.
+    ///
+    pub fn new_unchecked(inner: Chain<T>) -> Self {
+        Self {
+            inner,
+            end_transform_cache: None,
+            tool_transform: Isometry3::identity(),
+        }
+    }
+    /// Convert Chain into SerialChain
+    ///
+    /// If the input Chain has any branches it fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let node = k::NodeBuilder::<f32>::new().into_node();
+    /// let chain = k::Chain::from_root(node);
+    /// assert!(k::SerialChain::try_new(chain).is_some());
+    /// ```
+    ///
+    /// ```
+    /// let node0 = k::NodeBuilder::<f32>::new().into_node();
+    /// let node1 = k::NodeBuilder::new().into_node();
+    /// let node2 = k::NodeBuilder::new().into_node();
+    /// node1.set_parent(&node0);
+    /// node2.set_parent(&node0);
+    /// let chain = k::Chain::from_root(node0);
+    /// assert!(k::SerialChain::try_new(chain).is_none());
+    /// ```
+    pub fn try_new(inner: Chain<T>) -> Option<Self> {
+        {
+            let num = inner.iter().count();
+            for node in inner.iter().take(num - 1) {
+                if node.children().len() != 1 {
+                    return None;
+                }
+            }
+        }
+        Some(Self::new_unchecked(inner))
+    }
+    /// Create SerialChain from the end `Node`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let node = k::NodeBuilder::<f32>::new().into_node();
+    /// let s_chain = k::SerialChain::from_end(&node);
+    /// ```
+    pub fn from_end(end_joint: &Node<T>) -> SerialChain<T> {
+        SerialChain::new_unchecked(Chain::from_end(end_joint))
+    }
+
+    /// Create SerialChain from the joints in order from the root
+    ///
+    /// The nodes are created and connected in sequence, so it is useful to generate
+    /// chains procedurally. The links can be set by `find(name)` and `set_link` later.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let mut j1 = Joint::new("j1", JointType::Rotational { axis: Vector3::y_axis() });
+    /// j1.set_origin(Isometry3::translation(0.0, 0.0, 0.5));
+    /// let arm = SerialChain::<f64>::from_joints(vec![
+    ///     Joint::new("j0", JointType::Rotational { axis: Vector3::z_axis() }),
+    ///     j1,
+    /// ]);
+    /// assert_eq!(arm.dof(), 2);
+    /// assert!(arm.find("j1").is_some());
+    /// ```
+    pub fn from_joints(joints: Vec<Joint<T>>) -> SerialChain<T> {
+        let nodes = joints.into_iter().map(Node::new).collect::<Vec<_>>();
+        for i in 1..nodes.len() {
+            nodes[i].set_parent(&nodes[i - 1]);
+        }
+        SerialChain::new_unchecked(Chain::from_nodes(nodes))
+    }
+
+    /// Create SerialChain from the end `Node` and root `Node`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let node0 = k::NodeBuilder::<f32>::new().into_node();
+    /// let node1 = k::NodeBuilder::<f32>::new().into_node();
+    /// let node2 = k::NodeBuilder::<f32>::new().into_node();
+    /// let node3 = k::NodeBuilder::<f32>::new().into_node();
+    /// use k::connect;
+    /// connect![node0 => node1 => node2 => node3];
+    /// let s_chain = k::SerialChain::from_end_to_root(&node2, &node1);
+    /// assert_eq!(s_chain.iter().count(), 2);
+    /// ```
+    pub fn from_end_to_root(end_joint: &Node<T>, root_joint: &Node<T>) -> SerialChain<T> {
+        SerialChain::new_unchecked(Chain::from_end_to_root(end_joint, root_joint))
+    }
+
+    /// Mount a copy of this chain on the end of a copy of `base` and returns the composed chain
+    ///
+    /// The nodes of both chains are copied by `to_tree()` with their current positions,
+    /// so neither this chain nor `base` (and their trees) is changed. The joints of the
+    /// returned chain are the joints of `base` followed by the joints of this chain, and its
+    /// `end_transform()` accounts for the pose of the base joints. To follow the motion of
+    /// `base`, set its positions to the first `base.dof()` joints of the returned chain.
+    /// The tool transform of this chain is kept.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let platform = NodeBuilder::new()
+    ///     .name("platform_x")
+    ///     .joint_type(JointType::Linear { axis: Vector3::x_axis() })
+    ///     .into_node();
+    /// let base = SerialChain::<f64>::from_end(&platform);
+    /// let shoulder = NodeBuilder::new()
+    ///     .name("shoulder")
+    ///     .joint_type(JointType::Rotational { axis: Vector3::z_axis() })
+    ///     .into_node();
+    /// let hand = NodeBuilder::new()
+    ///     .name("hand")
+    ///     .translation(Translation3::new(0.5, 0.0, 0.0))
+    ///     .into_node();
+    /// hand.set_parent(&shoulder);
+    /// let arm = SerialChain::from_end(&hand);
+    ///
+    /// let mobile_arm = arm.mounted_on(&base);
+    /// assert_eq!(mobile_arm.dof(), 2);
+    /// base.set_joint_positions(&[1.0]).unwrap();
+    /// let arm_positions = arm.joint_positions();
+    /// mobile_arm
+    ///     .set_joint_positions(&[base.joint_positions(), arm_positions].concat())
+    ///     .unwrap();
+    /// assert!((mobile_arm.end_transform().translation.vector.x - 1.5).abs() < 1e-10);
+    /// // the base and the arm themselves are not changed
+    /// assert!(platform.children().is_empty());
+    /// assert!(shoulder.is_root());
+    /// assert!((arm.end_transform().translation.vector.x - 0.5).abs() < 1e-10);
+    /// ```
+    pub fn mounted_on(&self, base: &SerialChain<T>) -> SerialChain<T> {
+        let base_copy = base.to_tree();
+        let copy = self.to_tree();
+        let root = copy.iter().next().expect("SerialChain must have a node");
+        // the base pose is for the root of the whole tree, not for the mounted chain
+        root.lock().joint.set_base_pose(Isometry3::identity());
+        root.set_parent(
+            base_copy
+                .iter()
+                .last()
+                .expect("SerialChain must have a node"),
+        );
+        let mut mounted =
+            SerialChain::from_end(copy.iter().last().expect("SerialChain must have a node"));
+        mounted.tool_transform = self.tool_transform.clone();
+        mounted
+    }
+
+    /// Copy the nodes of this chain into a new standalone `Chain`
+    ///
+    /// The joints and the links are cloned, so the returned chain does not share any
+    /// nodes with this chain. The root of the new chain is placed at the world origin,
+    /// so its forward kinematics is the same with `end_transform()` without the
+    /// tool transform. Mimic relations inside this chain are kept.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let chain = Chain::<f64>::from_urdf_file("urdf/sample.urdf").unwrap();
+    /// let arm = SerialChain::from_end(chain.find("l_wrist_pitch").unwrap());
+    /// let tree = arm.to_tree();
+    /// assert_eq!(tree.dof(), arm.dof());
+    /// tree.set_joint_positions(&[0.1; 6]).unwrap();
+    /// assert_eq!(arm.joint_positions(), vec![0.0; 6]);
+    /// ```
+    pub fn to_tree(&self) -> Chain<T> {
+        let new_nodes = self
+            .iter()
+            .map(|n| {
+                let node = Node::new(n.joint().clone());
+                node.set_link(n.link().clone());
+                node
+            })
+            .collect::<Vec<_>>();
+        for (i, node) in self.iter().enumerate() {
+            if i > 0 {
+                new_nodes[i].set_parent(&new_nodes[i - 1]);
+            }
+            if let Some(m) = node.mimic_parent() {
+                if let Some(parent_index) = self.iter().position(|x| *x == m) {
+                    let mimic = node.mimic_mapping().unwrap();
+                    new_nodes[i].set_mimic_mapping(&new_nodes[parent_index], mimic);
+                }
+            }
+        }
+        let mut chain = Chain::from_root(new_nodes[0].clone());
+        chain.gravity = self.gravity.clone();
+        chain
+    }
+
+    /// Safely unwrap and returns inner `Chain` instance
+    pub fn unwrap(self) -> Chain<T> {
+        self.inner
+    }
+    /// Calculate transform of the end joint
+    ///
+    /// The tool transform which is set by `set_tool_transform` is applied, so it is
+    /// the transform of the tool tip if it is set.
+    /// If the cache is enabled by `set_end_transform_cache`, the cached value is
+    /// returned for the same (quantized) joint positions. The IK solvers and `jacobian()`
+    /// do not use the cache.
+    pub fn end_transform(&self) -> Isometry3<T> {
+        let Some(cache) = &self.end_transform_cache else {
+            return self.calc_end_transform();
+        };
+        // the sum increases whenever any origin or base pose in this chain is changed
+        let revision = self.iter().fold(0usize, |sum, node| {
+            sum.wrapping_add(node.joint().revision())
+        });
+        let positions = self.joint_positions();
+        let mut cache = cache.lock().unwrap();
+        let key = cache.key(&positions, revision);
+        if let Some(trans) = cache.get(&key) {
+            return trans;
+        }
+        let trans = self.calc_end_transform();
+        cache.insert(key, trans.clone());
+        trans
+    }
+
+    /// `end_transform()` without the cache
+    pub(crate) fn calc_end_transform(&self) -> Isometry3<T> {
+        self.iter().fold(Isometry3::identity(), |trans, joint| {
+            trans * joint.joint().local_transform()
+        }) * self.tool_transform.clone()
+    }
+
+    /// Set the rigid transform of the tool (TCP) from the end joint
+    ///
+    /// `end_transform()`, `jacobian()` and the IK solvers use the tool tip instead of
+    /// the end joint, without adding a fixed joint for the tool.
+    /// `update_transforms()` is not affected. The default is the identity.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let l0 = NodeBuilder::new()
+    ///     .joint_type(JointType::Rotational { axis: Vector3::z_axis() })
+    ///     .into_node();
+    /// let mut arm = SerialChain::<f64>::from_end(&l0);
+    /// arm.set_tool_transform(Isometry3::translation(0.5, 0.0, 0.0));
+    /// arm.set_joint_positions(&[std::f64::consts::FRAC_PI_2]).unwrap();
+    /// assert!((arm.end_transform().translation.vector.y - 0.5).abs() < 1e-10);
+    /// ```
+    pub fn set_tool_transform(&mut self, tool: Isometry3<T>) {
+        self.tool_transform = tool;
+        if let Some(cache) = &mut self.end_transform_cache {
+            let cache = cache.get_mut().unwrap();
+            *cache = cache.cleared();
+        }
+    }
+
+    /// The transform of the tool which is set by `set_tool_transform`
+    pub fn tool_transform(&self) -> &Isometry3<T> {
+        &self.tool_transform
+    }
+
+    /// Set the telemetry of the inner `Chain`
+    ///
+    /// See `Chain::set_telemetry`.
+    pub fn set_telemetry(&mut self, telemetry: Arc<dyn Telemetry<T>>) {
+        self.inner.set_telemetry(telemetry);
+    }
+
+    /// Set the limit mode of the inner `Chain`
+    ///
+    /// See `Chain::set_limit_mode`.
+    pub fn set_limit_mode(&mut self, mode: LimitMode) {
+        self.inner.set_limit_mode(mode);
+    }
+
+    /// Enable LRU cache of `end_transform()`
+    ///
+    /// The cache is keyed by the joint positions quantized by `resolution`, and it holds
+    /// `capacity` entries at most. `capacity = 0` disables the cache (default).
+    /// The cached values are not used after the origins of the joints
+    /// (`Node::set_origin`), the base pose (`Chain::set_base_pose`) or the tool transform
+    /// (`set_tool_transform`) are changed. Only the joint positions and these changes are
+    /// tracked, so enable the cache again after changing the chain in other ways.
+    /// The cached pose may differ from the exact one by the change of `resolution`,
+    /// so the IK solvers and `jacobian()` always calculate the exact one.
+    /// It fails if `resolution` is not positive.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let l0 = NodeBuilder::new()
+    ///     .joint_type(JointType::Rotational { axis: Vector3::z_axis() })
+    ///     .into_node();
+    /// let l1 = NodeBuilder::new()
+    ///     .translation(Translation3::new(1.0, 0.0, 0.0))
+    ///     .into_node();
+    /// l1.set_parent(&l0);
+    /// let mut arm = SerialChain::<f64>::from_end(&l1);
+    /// arm.set_end_transform_cache(16, 1.0e-6).unwrap();
+    /// arm.end_transform();
+    /// arm.end_transform();
+    /// assert_eq!(arm.end_transform_cache_hits(), 1);
+    /// assert_eq!(arm.end_transform_cache_misses(), 1);
+    /// ```
+    pub fn set_end_transform_cache(&mut self, capacity: usize, resolution: T) -> Result<(), Error> {
+        self.end_transform_cache = if capacity == 0 {
+            None
+        } else {
+            Some(Mutex::new(TransformCache::new(capacity, resolution)?))
+        };
+        Ok(())
+    }
+
+    /// Number of the cache hits of `end_transform()`
+    pub fn end_transform_cache_hits(&self) -> usize {
+        self.end_transform_cache
+            .as_ref()
+            .map_or(0, |cache| cache.lock().unwrap().hits())
+    }
+
+    /// Number of the cache misses of `end_transform()`
+    pub fn end_transform_cache_misses(&self) -> usize {
+        self.end_transform_cache
+            .as_ref()
+            .map_or(0, |cache| cache.lock().unwrap().misses())
+    }
+
+    /// Calculate the twist (linear and angular velocity) of the end joint
+    ///
+    /// It is `jacobian() * joint_velocities()`. The first three elements are
+    /// the linear velocity and the last three are the angular velocity in the world coordinate.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let l0 = NodeBuilder::new()
+    ///     .joint_type(JointType::Rotational { axis: Vector3::z_axis() })
+    ///     .into_node();
+    /// let l1 = NodeBuilder::new()
+    ///     .translation(Translation3::new(1.0, 0.0, 0.0))
+    ///     .into_node();
+    /// l1.set_parent(&l0);
+    /// let arm = SerialChain::<f64>::from_end(&l1);
+    /// arm.set_joint_velocities(&[2.0]).unwrap();
+    /// let twist = arm.end_twist();
+    /// assert!((twist[1] - 2.0).abs() < 1e-10);
+    /// assert!((twist[5] - 2.0).abs() < 1e-10);
+    /// ```
+    pub fn end_twist(&self) -> Vector6<T> {
+        let twist = jacobian(self) * DVector::from_vec(self.joint_velocities());
+        Vector6::from_iterator(twist.iter().cloned())
+    }
+
+    /// Calculate the jacobian numerically by the central differences of `end_transform()`
+    ///
+    /// Each joint is moved by `±epsilon` without checking the limits, and the positions
+    /// are restored after the calculation. The rows are the same as `jacobian()`: the linear
+    /// velocity and the angular velocity (the difference of the rotations in the world coordinate).
+    /// It is useful to validate the analytic jacobian.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let l0 = NodeBuilder::new()
+    ///     .joint_type(JointType::Rotational { axis: Vector3::z_axis() })
+    ///     .into_node();
+    /// let l1 = NodeBuilder::new()
+    ///     .translation(Translation3::new(1.0, 0.0, 0.0))
+    ///     .into_node();
+    /// l1.set_parent(&l0);
+    /// let arm = SerialChain::<f64>::from_end(&l1);
+    /// let numerical = arm.jacobian_numerical(1.0e-6);
+    /// assert!((numerical - jacobian(&arm)).norm() < 1e-6);
+    /// ```
+    pub fn jacobian_numerical(&self, epsilon: T) -> DMatrix<T> {
+        let original = self.joint_positions();
+        let two_epsilon = epsilon.clone() + epsilon.clone();
+        let mut jacobi = DMatrix::zeros(6, self.dof());
+        for i in 0..self.dof() {
+            let mut positions = original.clone();
+            positions[i] = original[i].clone() + epsilon.clone();
+            self.set_joint_positions_unchecked(&positions);
+            let plus = self.calc_end_transform();
+            positions[i] = original[i].clone() - epsilon.clone();
+            self.set_joint_positions_unchecked(&positions);
+            let minus = self.calc_end_transform();
+            let linear = (plus.translation.vector - minus.translation.vector) / two_epsilon.clone();
+            let angular =
+                (plus.rotation * minus.rotation.inverse()).scaled_axis() / two_epsilon.clone();
+            for r in 0..3 {
+                jacobi[(r, i)] = linear[r].clone();
+                jacobi[(r + 3, i)] = angular[r].clone();
+            }
+        }
+        self.set_joint_positions_unchecked(&original);
+        jacobi
+    }
+
+    /// Calculate the analytic jacobian with the Euler angles of `convention`
+    ///
+    /// The first three rows are the linear velocity of the end as `jacobian()`, and the last
+    /// three rows are the rates of the Euler angles of `end_transform()` instead of
+    /// the angular velocity. It returns `None` at the representation singularity
+    /// (gimbal lock) of the Euler angles, for example `pitch = ±pi/2` of roll-pitch-yaw.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let l0 = NodeBuilder::new()
+    ///     .joint_type(JointType::Rotational { axis: Vector3::z_axis() })
+    ///     .into_node();
+    /// let arm = SerialChain::<f64>::from_end(&l0);
+    /// let jacobi = arm.analytic_jacobian(EulerConvention::RollPitchYaw).unwrap();
+    /// // the joint changes only the yaw
+    /// assert_eq!(jacobi[(5, 0)], 1.0);
+    /// assert_eq!(jacobi[(3, 0)], 0.0);
+    /// ```
+    pub fn analytic_jacobian(&self, convention: EulerConvention) -> Option<DMatrix<T>> {
+        let angles = convention.angles(&self.calc_end_transform().rotation);
+        let rate_inverse = convention.rate_matrix(&angles)?.try_inverse()?;
+        let mut jacobi = jacobian(self);
+        let angular = &rate_inverse * jacobi.rows(3, 3);
+        jacobi.rows_mut(3, 3).copy_from(&angular);
+        Some(jacobi)
+    }
+
+    /// Calculate the damped pseudo-inverse of the jacobian
+    ///
+    /// It is the right inverse `J^T (J J^T + damping^2 I)^-1` if `dof() >= 6`, otherwise
+    /// the left inverse `(J^T J + damping^2 I)^-1 J^T`. If the matrix cannot be inverted
+    /// (at a singular pose without damping), the pseudo-inverse by SVD is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let l0 = NodeBuilder::new()
+    ///     .joint_type(JointType::Rotational { axis: Vector3::z_axis() })
+    ///     .into_node();
+    /// let l1 = NodeBuilder::new()
+    ///     .translation(Translation3::new(1.0, 0.0, 0.0))
+    ///     .into_node();
+    /// l1.set_parent(&l0);
+    /// let arm = SerialChain::<f64>::from_end(&l1);
+    /// let pinv = arm.jacobian_pinv(0.0);
+    /// assert_eq!(pinv.shape(), (1, 6));
+    /// ```
+    pub fn jacobian_pinv(&self, damping: T) -> DMatrix<T> {
+        const EPS: f64 = 1.0e-10;
+        let jacobi = jacobian(self);
+        let damping_squared = damping.clone() * damping;
+        let jacobi_t = jacobi.transpose();
+        let inverse = if self.dof() >= jacobi.nrows() {
+            let size = jacobi.nrows();
+            (&jacobi * &jacobi_t + DMatrix::identity(size, size) * damping_squared)
+                .try_inverse()
+                .map(|inv| &jacobi_t * inv)
+        } else {
+            let size = jacobi.ncols();
+            (&jacobi_t * &jacobi + DMatrix::identity(size, size) * damping_squared)
+                .try_inverse()
+                .map(|inv| inv * &jacobi_t)
+        };
+        inverse.unwrap_or_else(|| {
+            jacobi
+                .pseudo_inverse(na::convert(EPS))
+                .expect("pseudo_inverse of the jacobian must be calculated")
+        })
+    }
+
+    /// Calculate the condition number of the jacobian
+    ///
+    /// It is the ratio of the largest singular value to the smallest one (of `min(6, dof())`
+    /// singular values). It becomes large near singular poses.
+    /// It returns `None` if `dof()` is zero or the jacobian is singular (the smallest singular
+    /// value is not larger than the largest one times the machine epsilon).
+    /// Note that the jacobian has both translation and rotation rows, so the value depends
+    /// on the unit of the length.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let l0 = NodeBuilder::new()
+    ///     .joint_type(JointType::Rotational { axis: Vector3::z_axis() })
+    ///     .into_node();
+    /// let l1 = NodeBuilder::new()
+    ///     .translation(Translation3::new(1.0, 0.0, 0.0))
+    ///     .into_node();
+    /// l1.set_parent(&l0);
+    /// let arm = SerialChain::<f64>::from_end(&l1);
+    /// assert!((arm.jacobian_condition_number().unwrap() - 1.0).abs() < 1e-10);
+    /// ```
+    pub fn jacobian_condition_number(&self) -> Option<T> {
+        if self.dof() == 0 {
+            return None;
+        }
+        let singular_values = jacobian(self).singular_values();
+        let max = singular_values.max();
+        let min = singular_values.min();
+        if min <= max.clone() * T::default_epsilon() {
+            return None;
+        }
+        Some(max / min)
+    }
+
+    /// Calculate how fast the end moves by each joint
+    ///
+    /// It is the norm of the translation part of each column of the jacobian,
+    /// that is the speed of the end in m/s when the joint moves at 1 rad/s (or 1 m/s).
+    /// The order is the same as `joint_positions()`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let l0 = NodeBuilder::new()
+    ///     .joint_type(JointType::Rotational { axis: Vector3::z_axis() })
+    ///     .into_node();
+    /// let l1 = NodeBuilder::new()
+    ///     .translation(Translation3::new(2.0, 0.0, 0.0))
+    ///     .into_node();
+    /// l1.set_parent(&l0);
+    /// let arm = SerialChain::<f64>::from_end(&l1);
+    /// assert!((arm.joint_sensitivities()[0] - 2.0).abs() < 1e-10);
+    /// ```
+    pub fn joint_sensitivities(&self) -> Vec<T> {
+        let jacobi = jacobian(self);
+        jacobi
+            .column_iter()
+            .map(|column| column.fixed_rows::<3>(0).norm())
+            .collect()
+    }
+
+    /// Calculate the Cartesian stiffness of the end from the stiffness of the joints
+    ///
+    /// It is `J+^T K J+` where `K` is the diagonal matrix of `joint_stiffness` and `J+` is
+    /// the pseudo-inverse of the jacobian (`jacobian_pinv(0)`), at the current positions.
+    /// The rows and columns are translation (x, y, z) then rotation, like `jacobian()`.
+    /// If `dof() < 6`, it is not full rank.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let chain = Chain::<f64>::from_urdf_file("urdf/sample.urdf").unwrap();
+    /// let arm = SerialChain::from_end(chain.find("l_wrist_pitch").unwrap());
+    /// arm.set_joint_positions(&[0.1, 0.2, 0.3, -0.5, 0.4, -0.3]).unwrap();
+    /// let stiffness = arm.cartesian_stiffness(&[100.0; 6]).unwrap();
+    /// assert!((stiffness - stiffness.transpose()).norm() < 1e-6);
+    /// assert!(arm.cartesian_stiffness(&[100.0; 5]).is_err());
+    /// ```
+    pub fn cartesian_stiffness(&self, joint_stiffness: &[T]) -> Result<Matrix6<T>, Error> {
+        if joint_stiffness.len() != self.dof() {
+            return Err(Error::SizeMismatchError {
+                input: joint_stiffness.len(),
+                required: self.dof(),
+            });
+        }
+        let pinv = self.jacobian_pinv(T::zero());
+        let joint_stiffness = DMatrix::from_diagonal(&DVector::from_column_slice(joint_stiffness));
+        let stiffness = pinv.transpose() * joint_stiffness * pinv;
+        Ok(Matrix6::from_fn(|r, c| stiffness[(r, c)].clone()))
+    }
+
+    /// Calculate the length of the fully stretched chain, the upper bound of the reach
+    ///
+    /// It is the sum of the lengths of the translations of the origins from the first joint
+    /// to the end and the tool transform. The stroke (the larger absolute value of the limits)
+    /// of the linear joints is added too, and the linear joints without limits are counted
+    /// at the current positions. The end can not be farther than this from the first joint.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let l0 = NodeBuilder::new()
+    ///     .joint_type(JointType::Rotational { axis: Vector3::z_axis() })
+    ///     .into_node();
+    /// let l1 = NodeBuilder::new()
+    ///     .translation(Translation3::new(0.3, 0.4, 0.0))
+    ///     .into_node();
+    /// l1.set_parent(&l0);
+    /// let arm = SerialChain::<f64>::from_end(&l1);
+    /// assert!((arm.max_reach() - 0.5).abs() < 1e-10);
+    /// ```
+    pub fn max_reach(&self) -> T {
+        let links = self.iter().enumerate().fold(T::zero(), |sum, (i, node)| {
+            let joint = node.joint();
+            let origin = if i == 0 {
+                T::zero()
+            } else {
+                joint.origin().translation.vector.norm()
+            };
+            let stroke = match (&joint.joint_type, &joint.limits) {
+                (JointType::Linear { .. }, Some(range)) => {
+                    range.min.clone().abs().max(range.max.clone().abs())
+                }
+                (JointType::Linear { .. }, None) => {
+                    joint.joint_position().unwrap_or_else(T::zero).abs()
+                }
+                _ => T::zero(),
+            };
+            sum + origin + stroke
+        });
+        links + self.tool_transform.translation.vector.norm()
+    }
+
+    /// Estimate the volume of the workspace of the end by Monte Carlo sampling
+    ///
+    /// The end positions of `samples` random joint positions (within the limits) are
+    /// collected into a voxel grid whose size is a tenth of `max_reach()`, and the volume of
+    /// the occupied voxels is returned. It is a rough value, but it is comparable across
+    /// arms with the same `samples`. The joint positions are restored after the sampling,
+    /// and the random seed is fixed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let l0 = NodeBuilder::new()
+    ///     .joint_type(JointType::Rotational { axis: Vector3::z_axis() })
+    ///     .into_node();
+    /// let l1 = NodeBuilder::new()
+    ///     .translation(Translation3::new(1.0, 0.0, 0.0))
+    ///     .into_node();
+    /// l1.set_parent(&l0);
+    /// let arm = SerialChain::<f64>::from_end(&l1);
+    /// // a circle has no volume, but the voxels have
+    /// assert!(arm.workspace_volume_estimate(1000) > 0.0);
+    /// ```
+    pub fn workspace_volume_estimate(&self, samples: usize) -> T {
+        const SEED: u64 = 0;
+        const VOXELS_PER_REACH: f64 = 10.0;
+        let reach = self.max_reach();
+        if reach <= T::zero() || samples == 0 {
+            return T::zero();
+        }
+        let voxel = reach / na::convert(VOXELS_PER_REACH);
+        let orig_positions = self.joint_positions();
+        let mut rng = SplitMix64::new(SEED);
+        let mut occupied = HashSet::new();
+        for _ in 0..samples {
+            self.set_joint_positions_unchecked(&sample_joint_positions(self, &mut rng));
+            let position = self.calc_end_transform().translation.vector;
+            let index = position.map(|x| {
+                na::try_convert::<T, f64>((x / voxel.clone()).floor()).unwrap_or_default() as i64
+            });
+            occupied.insert((index.x, index.y, index.z));
+        }
+        self.set_joint_positions_unchecked(&orig_positions);
+        voxel.clone() * voxel.clone() * voxel * na::convert(occupied.len() as f64)
+    }
+
+    /// Calculate the joint torques (forces for linear joints) to exert `wrench` at the end
+    ///
+    /// It is `J^T wrench` at the current positions, where `wrench` is (force, torque)
+    /// in the world frame, like the rows of `jacobian()`. Gravity is not included,
+    /// add `gravity_torques()` to hold the arm too.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let l0 = NodeBuilder::new()
+    ///     .joint_type(JointType::Rotational { axis: Vector3::z_axis() })
+    ///     .into_node();
+    /// let l1 = NodeBuilder::new()
+    ///     .translation(Translation3::new(2.0, 0.0, 0.0))
+    ///     .into_node();
+    /// l1.set_parent(&l0);
+    /// let arm = SerialChain::<f64>::from_end(&l1);
+    /// let wrench = nalgebra::Vector6::new(0.0, 1.0, 0.0, 0.0, 0.0, 0.0);
+    /// assert!((arm.torques_for_wrench(&wrench)[0] - 2.0).abs() < 1e-10);
+    /// ```
+    pub fn torques_for_wrench(&self, wrench: &Vector6<T>) -> Vec<T> {
+        let torques = jacobian(self).transpose() * wrench;
+        torques.iter().cloned().collect()
+    }
+
+    /// Calculate the principal axes of the manipulability ellipsoid of the translation
+    ///
+    /// Returns the eigenvectors (unit vectors in the world frame) and the eigenvalues of
+    /// `J Jᵀ` of the translational rows of the jacobian, in descending order of the
+    /// eigenvalues. The radii of the ellipsoid are the square roots of the eigenvalues,
+    /// so the end moves most easily along the first axis.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let l0 = NodeBuilder::new()
+    ///     .joint_type(JointType::Rotational { axis: Vector3::z_axis() })
+    ///     .into_node();
+    /// let l1 = NodeBuilder::new()
+    ///     .translation(Translation3::new(2.0, 0.0, 0.0))
+    ///     .into_node();
+    /// l1.set_parent(&l0);
+    /// let arm = SerialChain::<f64>::from_end(&l1);
+    /// let (axes, values) = arm.manipulability_ellipsoid();
+    /// assert!((axes[0].y.abs() - 1.0).abs() < 1e-10);
+    /// assert!((values[0] - 4.0).abs() < 1e-10);
+    /// assert!(values[1].abs() < 1e-10);
+    /// ```
+    pub fn manipulability_ellipsoid(&self) -> (Vec<Vector3<T>>, Vec<T>) {
+        let jacobi = jacobian(self).rows(0, 3).into_owned();
+        let jjt = &jacobi * jacobi.transpose();
+        let eigen = Matrix3::from_fn(|r, c| jjt[(r, c)].clone()).symmetric_eigen();
+        let mut pairs = eigen
+            .eigenvalues
+            .iter()
+            .cloned()
+            .zip(eigen.eigenvectors.column_iter().map(|v| v.normalize()))
+            .collect::<Vec<_>>();
+        pairs.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+        let (values, axes): (Vec<_>, Vec<_>) = pairs.into_iter().unzip();
+        (axes, values)
+    }
+
+    /// Calculate the operational space (task space) inertia matrix of the end
+    ///
+    /// It is `(J M^-1 J^T)^-1`, where `J` is `jacobian()` and `M` is the joint space inertia
+    /// matrix of the links (and the payloads) of this chain at the current positions.
+    /// It fails with `InverseMatrixError` if the matrices cannot be inverted, for example
+    /// at singular poses, with less than 6 joints or with joints which move no inertia.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let l0 = NodeBuilder::new()
+    ///     .joint_type(JointType::Rotational { axis: Vector3::z_axis() })
+    ///     .into_node();
+    /// let arm = SerialChain::<f64>::from_end(&l0);
+    /// // one joint cannot move the end in 6 dof
+    /// assert!(arm.operational_space_inertia().is_err());
+    /// ```
+    pub fn operational_space_inertia(&self) -> Result<Matrix6<T>, Error> {
+        let mass_inverse = self
+            .joint_space_inertia()
+            .try_inverse()
+            .ok_or(Error::InverseMatrixError)?;
+        let jacobi = jacobian(self);
+        let inverse = &jacobi * mass_inverse * jacobi.transpose();
+        let inertia = Matrix6::from_fn(|r, c| inverse[(r, c)].clone())
+            .try_inverse()
+            .ok_or(Error::InverseMatrixError)?;
+        // remove the asymmetry by the rounding errors
+        Ok((&inertia + inertia.transpose()) * na::convert::<f64, T>(0.5))
+    }
+
+    /// Calculate the error between the end and `target` as the magnitudes of translation and rotation
+    ///
+    /// Returns (the distance in meters, the rotation angle in radians).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let l0 = NodeBuilder::new()
+    ///     .joint_type(JointType::Rotational { axis: Vector3::z_axis() })
+    ///     .into_node();
+    /// let l1 = NodeBuilder::new()
+    ///     .translation(Translation3::new(1.0, 0.0, 0.0))
+    ///     .into_node();
+    /// l1.set_parent(&l0);
+    /// let arm = SerialChain::<f64>::from_end(&l1);
+    /// let end = arm.end_transform();
+    /// assert_eq!(arm.pose_error_magnitudes(&end), (0.0, 0.0));
+    ///
+    /// let target = Isometry3::new(Vector3::new(1.0, 0.3, 0.4), Vector3::new(0.0, 0.0, 0.5));
+    /// let (translation, rotation) = arm.pose_error_magnitudes(&target);
+    /// assert!((translation - 0.5).abs() < 1e-10);
+    /// assert!((rotation - 0.5).abs() < 1e-10);
+    /// ```
+    pub fn pose_error_magnitudes(&self, target: &Isometry3<T>) -> (T, T) {
+        let end = self.calc_end_transform();
+        let translation = (&target.translation.vector - &end.translation.vector).norm();
+        let rotation = end.rotation.rotation_to(&target.rotation).angle();
+        (translation, rotation)
+    }
+
+    /// Express the end transform as a screw motion (the log map of SE(3))
+    ///
+    /// Returns (the unit direction of the screw axis, the point on the axis which is
+    /// the closest to the origin, the rotation angle about the axis, the translation along the axis).
+    /// For a pure translation, the axis is the direction of the translation and
+    /// the point is the origin. For the identity, the axis is zero.
+    /// It is calculated from the current positions, without the cache of `end_transform()`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let l0 = NodeBuilder::new()
+    ///     .translation(Translation3::new(0.0, 0.0, 0.5))
+    ///     .joint_type(JointType::Linear { axis: Vector3::z_axis() })
+    ///     .into_node();
+    /// let arm = SerialChain::<f64>::from_end(&l0);
+    /// let (axis, _point, angle, translation) = arm.end_transform_as_screw();
+    /// assert_eq!(axis, Vector3::z());
+    /// assert_eq!(angle, 0.0);
+    /// assert!((translation - 0.5).abs() < 1e-10);
+    /// ```
+    pub fn end_transform_as_screw(&self) -> (Vector3<T>, Vector3<T>, T, T) {
+        let end = self.calc_end_transform();
+        let t = end.translation.vector;
+        match end.rotation.axis_angle() {
+            Some((axis, angle)) if angle > T::default_epsilon() => {
+                let axis = axis.into_inner();
+                let translation = axis.dot(&t);
+                let perpendicular = &t - &axis * translation.clone();
+                // (I - R) q = t_perp for q perpendicular to the axis
+                let half: T = na::convert(0.5);
+                let cot = T::one() / (angle.clone() * half.clone()).tan();
+                let point = (&perpendicular + axis.cross(&perpendicular) * cot) * half;
+                (axis, point, angle, translation)
+            }
+            _ => {
+                let translation = t.norm();
+                let axis = if translation > T::default_epsilon() {
+                    &t / translation.clone()
+                } else {
+                    Vector3::zeros()
+                };
+                (axis, Vector3::zeros(), T::zero(), translation)
+            }
+        }
+    }
+}
+
+impl<T> Clone for SerialChain<T>
+where
+    T: RealField + SubsetOf<f64>,
+{
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            end_transform_cache: self
+                .end_transform_cache
+                .as_ref()
+                .map(|cache| Mutex::new(cache.lock().unwrap().cleared())),
+            tool_transform: self.tool_transform.clone(),
+        }
+    }
+}
+
+impl<T> Display for SerialChain<T>
+where
+    T: RealField + SubsetOf<f64>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.inner.fmt(f)
+    }
+}
+
+impl<T> Deref for SerialChain<T>
+where
+    T: RealField,
+{
+    type Target = Chain<T>;
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::urdf::sample_chain;
+    #[cfg(target_family = "wasm")]
+    use wasm_bindgen_test::wasm_bindgen_test as test;
+
+    #[test]
+    fn test_chain0() {
+        let joint0 = NodeBuilder::new()
+            .name("j0")
+            .translation(na::Translation3::new(0.0, 0.1, 0.0))
+            .joint_type(JointType::Rotational {
+                axis: na::Vector3::y_axis(),
+            })
+            .into_node();
+        let joint1 = NodeBuilder::new()
+            .translation(na::Translation3::new(0.0, 0.1, 0.1))
+            .name("j1")
+            .joint_type(JointType::Rotational {
+                axis: na::Vector3::y_axis(),
+            })
+            .into_node();
+        let joint2 = NodeBuilder::new()
+            .name("j2")
+            .translation(na::Translation3::new(0.0, 0.1, 0.1))
+            .joint_type(JointType::Rotational {
+                axis: na::Vector3::y_axis(),
+            })
+            .into_node();
+        let joint3 = NodeBuilder::new()
+            .name("j3")
+            .translation(na::Translation3::new(0.0, 0.1, 0.2))
+            .joint_type(JointType::Rotational {
+                axis: na::Vector3::y_axis(),
+            })
+            .into_node();
+        let joint4 = NodeBuilder::new()
+            .name("j4")
+            .translation(na::Translation3::new(0.0, 0.1, 0.1))
+            .joint_type(JointType::Rotational {
+                axis: na::Vector3::y_axis(),
+            })
+            .into_node();
+        let joint5 = NodeBuilder::new()
+            .name("j5")
+            .translation(na::Translation3::new(0.0, 0.1, 0.1))
+            .joint_type(JointType::Rotational {
+                axis: na::Vector3::y_axis(),
+            })
+            .into_node();
+        joint1.set_parent(&joint0);
+        joint2.set_parent(&joint1);
+        joint3.set_parent(&joint2);
+        joint4.set_parent(&joint0);
+        joint5.set_parent(&joint4);
+
+        let names = joint0
+            .iter_descendants()
+            .map(|joint| joint.joint().name.clone())
+            .collect::<Vec<_>>();
+        println!("{joint0}");
+        assert_eq!(names.len(), 6);
+        println!("names = {names:?}");
+        let positions = joint0
+            .iter_descendants()
+            .map(|node| node.joint().joint_position())
+            .collect::<Vec<_>>();
+        println!("positions = {positions:?}");
+
+        fn get_z(joint: &Node<f32>) -> f32 {
+            match joint.parent_world_transform() {
+                Some(iso) => iso.translation.vector.z,
+                None => 0.0f32,
+            }
+        }
+
+        let poses = joint0
+            .iter_descendants()
+            .map(|joint| get_z(&joint))
+            .collect::<Vec<_>>();
+        println!("poses = {poses:?}");
+
+        let _ = joint0
+            .iter_ancestors()
+            .map(|joint| joint.set_joint_position(-0.5))
+            .collect::<Vec<_>>();
+        let positions = joint0
+            .iter_descendants()
+            .map(|node| node.joint().joint_position())
+            .collect::<Vec<_>>();
+        println!("positions = {positions:?}");
+
+        let poses = joint0
+            .iter_descendants()
+            .map(|joint| get_z(&joint))
+            .collect::<Vec<_>>();
+        println!("poses = {poses:?}");
+
+        let arm = Chain::from_end(&joint3);
+        assert_eq!(arm.joint_positions().len(), 4);
+        println!("{:?}", arm.joint_positions());
+    }
+
+    #[test]
+    fn test_mimic() {
+        let joint0 = NodeBuilder::new()
+            .name("j0")
+            .translation(na::Translation3::new(0.0, 0.1, 0.0))
+            .joint_type(JointType::Rotational {
+                axis: na::Vector3::y_axis(),
+            })
+            .into_node();
+        let joint1 = NodeBuilder::new()
+            .name("joint1")
+            .translation(na::Translation3::new(0.0, 0.1, 0.1))
+            .joint_type(JointType::Rotational {
+                axis: na::Vector3::y_axis(),
+            })
+            .into_node();
+        let joint2 = NodeBuilder::new()
+            .name("joint2")
+            .translation(na::Translation3::new(0.0, 0.1, 0.1))
+            .joint_type(JointType::Rotational {
+                axis: na::Vector3::y_axis(),
+            })
+            .into_node();
+        joint1.set_parent(&joint0);
+        joint2.set_parent(&joint1);
+        joint2.set_mimic_parent(&joint1, Mimic::new(2.0, 0.5));
+
+        // the linear mimic is kept in `NodeImpl::mimic`
+        assert_eq!(joint2.lock().mimic.as_ref().unwrap().multiplier, 2.0);
+        assert!(matches!(
+            joint2.mimic_mapping(),
+            Some(MimicMapping::Linear(_))
+        ));
+
+        let arm = Chain::from_root(joint0);
+
+        assert_eq!(arm.joint_positions().len(), 3);
+        println!("{:?}", arm.joint_positions());
+        let positions = vec![0.1, 0.2, 0.3];
+        arm.set_joint_positions(&positions).unwrap();
+        let positions = arm.joint_positions();
+        assert!((positions[0] - 0.1f64).abs() < f64::EPSILON);
+        assert!((positions[1] - 0.2f64).abs() < f64::EPSILON);
+        assert!((positions[2] - 0.9f64).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_nonlinear_mimic() {
+        let joint0 = NodeBuilder::new()
+            .name("j0")
+            .joint_type(JointType::Rotational {
+                axis: na::Vector3::z_axis(),
+            })
+            .into_node();
+        let joint1 = NodeBuilder::new()
+            .name("j1")
+            .translation(na::Translation3::new(0.1, 0.0, 0.0))
+            .joint_type(JointType::Rotational {
+                axis: na::Vector3::z_axis(),
+            })
+            .into_node();
+        joint1.set_parent(&joint0);
+        joint1.set_mimic_parent_fn(&joint0, |x: f64| x * x);
+        assert!(joint1.lock().mimic.is_none());
+        assert_eq!(joint1.mimic_mapping().unwrap().mimic_position(0.5), 0.25);
+        joint1.set_link(Some(
+            crate::link::LinkBuilder::new().name("link1").finalize(),
+        ));
+        let arm = Chain::from_root(joint0);
+        for source in [-0.8, 0.3, 1.2] {
+            arm.set_joint_positions(&[source, 0.0]).unwrap();
+            let positions = arm.joint_positions();
+            assert_eq!(positions[0], source);
+            assert!((positions[1] - source * source).abs() < 1e-12);
+        }
+        // the compiled FK uses the same relation
+        let fk = arm.compile_fk("link1").unwrap();
+        arm.update_transforms();
+        let expected = arm.find("j1").unwrap().world_transform().unwrap();
+        assert!((fk(&[1.2, 0.0]).to_homogeneous() - expected.to_homogeneous()).norm() < 1e-12);
+    }
+
+    #[test]
+    fn test_gravity_torques() {
+        use crate::link::*;
+        let joint0 = NodeBuilder::new()
+            .name("j0")
+            .joint_type(JointType::Rotational {
+                axis: na::Vector3::y_axis(),
+            })
+            .into_node();
+        let joint1 = NodeBuilder::new()
+            .name("j1")
+            .translation(na::Translation3::new(1.0, 0.0, 0.0))
+            .into_node();
+        joint1.set_link(Some(
+            LinkBuilder::new()
+                .name("l1")
+                .inertial(Inertial::from_mass(2.0))
+                .finalize(),
+        ));
+        joint1.set_parent(&joint0);
+        let mut chain = Chain::<f64>::from_root(joint0);
+        let torques = chain.gravity_torques();
+        assert_eq!(torques.len(), 1);
+        // 2kg at 1m from the y axis: -(y x x) . (0, 0, -9.81 * 2) = -19.62
+        assert!((torques[0] + 19.62).abs() < 1e-10);
+
+        chain.set_gravity(na::Vector3::new(0.0, 0.0, -1.0));
+        let torques = chain.gravity_torques();
+        assert!((torques[0] + 2.0).abs() < 1e-10);
+
+        let torques = chain.gravity_torques_with(&na::Vector3::zeros());
+        assert!(torques[0].abs() < 1e-10);
+
+        // 0.5kg at 1.5m
+        chain
+            .set_payload("l1", 0.5, na::Vector3::new(0.5, 0.0, 0.0))
+            .unwrap();
+        let torques = chain.gravity_torques();
+        assert!((torques[0] + 2.75).abs() < 1e-10);
+        chain.clear_payload("l1");
+        let torques = chain.gravity_torques();
+        assert!((torques[0] + 2.0).abs() < 1e-10);
+    }
+
+    #[test]
+    fn test_end_twist() {
+        let joint0 = NodeBuilder::new()
+            .name("j0")
+            .joint_type(JointType::Rotational {
+                axis: na::Vector3::y_axis(),
+            })
+            .into_node();
+        let joint1 = NodeBuilder::new()
+            .name("j1")
+            .translation(na::Translation3::new(0.0, 0.0, 0.5))
+            .into_node();
+        joint1.set_parent(&joint0);
+        let arm = SerialChain::<f64>::from_end(&joint1);
+        assert_eq!(arm.end_twist(), Vector6::zeros());
+
+        arm.set_joint_velocities(&[1.0]).unwrap();
+        assert_eq!(arm.joint_velocities(), vec![1.0]);
+        // y x (0, 0, 0.5) = (0.5, 0, 0)
+        let twist = arm.end_twist();
+        assert!((twist - Vector6::new(0.5, 0.0, 0.0, 0.0, 1.0, 0.0)).norm() < 1e-10);
+
+        arm.set_joint_positions(&[std::f64::consts::FRAC_PI_2])
+            .unwrap();
+        let twist = arm.end_twist();
+        assert!((twist - Vector6::new(0.0, 0.0, -0.5, 0.0, 1.0, 0.0)).norm() < 1e-10);
+        assert!(arm.set_joint_velocities(&[1.0, 2.0]).is_err());
+    }
+
+    #[test]
+    fn test_reorder_joints() {
+        let chain = sample_chain();
+        let mut chain = SerialChain::from_end(chain.find("l_wrist_pitch").unwrap()).unwrap();
+        chain
+            .set_joint_positions(&[0.1, 0.2, 0.3, 0.4, 0.5, 0.6])
+            .unwrap();
+        chain.update_transforms();
+        let end = chain.find("l_wrist_pitch").unwrap().world_transform();
+        let mut order = chain
+            .iter_joints()
+            .map(|j| j.name.clone())
+            .collect::<Vec<_>>();
+        order.reverse();
+        chain.reorder_joints(&order).unwrap();
+        assert_eq!(chain.joint_positions(), vec![0.6, 0.5, 0.4, 0.3, 0.2, 0.1]);
+        let names = chain
+            .iter_joints()
+            .map(|j| j.name.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(names, order);
+        chain
+            .set_joint_positions(&[0.6, 0.5, 0.4, 0.3, 0.2, 0.1])
+            .unwrap();
+        chain.update_transforms();
+        assert_eq!(chain.find("l_wrist_pitch").unwrap().world_transform(), end);
+        assert_eq!(chain.iter().next().unwrap().joint().name, "root");
+
+        assert!(matches!(
+            chain.reorder_joints(&order[1..]),
+            Err(Error::SizeMismatchError { .. })
+        ));
+        let mut invalid = order.clone();
+        invalid[0] = "no_exist".to_owned();
+        assert!(matches!(
+            chain.reorder_joints(&invalid),
+            Err(Error::InvalidJointNameError { .. })
+        ));
+        invalid[0] = order[1].clone();
+        assert!(matches!(
+            chain.reorder_joints(&invalid),
+            Err(Error::DuplicateJointNameError { .. })
+        ));
+        assert_eq!(chain.joint_positions(), vec![0.6, 0.5, 0.4, 0.3, 0.2, 0.1]);
+        assert_eq!(
+            chain.clone().joint_positions(),
+            vec![0.6, 0.5, 0.4, 0.3, 0.2, 0.1]
+        );
+    }
+
+    #[test]
+    fn test_end_transform_cache() {
+        let joint0 = NodeBuilder::new()
+            .name("j0")
+            .joint_type(JointType::Rotational {
+                axis: na::Vector3::z_axis(),
+            })
+            .into_node();
+        let joint1 = NodeBuilder::new()
+            .name("j1")
+            .translation(na::Translation3::new(1.0, 0.0, 0.0))
+            .joint_type(JointType::Rotational {
+                axis: na::Vector3::z_axis(),
+            })
+            .into_node();
+        joint1.set_parent(&joint0);
+        let mut arm = SerialChain::<f64>::from_end(&joint1);
+        arm.end_transform();
+        assert_eq!(arm.end_transform_cache_misses(), 0);
+
+        arm.set_end_transform_cache(2, 1.0e-3).unwrap();
+        let t0 = arm.end_transform();
+        assert_eq!(arm.end_transform_cache_misses(), 1);
+        assert_eq!(arm.end_transform(), t0);
+        assert_eq!(arm.end_transform(), t0);
+        assert_eq!(arm.end_transform_cache_hits(), 2);
+
+        // within the resolution
+        arm.set_joint_positions(&[1.0e-5, 0.0]).unwrap();
+        assert_eq!(arm.end_transform(), t0);
+        assert_eq!(arm.end_transform_cache_hits(), 3);
+
+        arm.set_joint_positions(&[0.5, 0.0]).unwrap();
+        let t1 = arm.end_transform();
+        assert_ne!(t1, t0);
+        assert_eq!(arm.end_transform_cache_misses(), 2);
+
+        // evict the least recently used entry (t0)
+        arm.set_joint_positions(&[0.5, 0.5]).unwrap();
+        arm.end_transform();
+        arm.set_joint_positions(&[0.0, 0.0]).unwrap();
+        assert_eq!(arm.end_transform(), t0);
+        assert_eq!(arm.end_transform_cache_misses(), 4);
+
+        let arm2 = arm.clone();
+        assert_eq!(arm2.end_transform_cache_hits(), 0);
+        arm.set_end_transform_cache(0, 1.0e-3).unwrap();
+        assert_eq!(arm.end_transform_cache_hits(), 0);
+        for resolution in [0.0, -1.0, f64::NAN] {
+            assert!(matches!(
+                arm.set_end_transform_cache(2, resolution),
+                Err(Error::InvalidParameterError { .. })
+            ));
+        }
+
+        // the jacobian and IK use the exact pose
+        arm.set_end_transform_cache(2, 0.1).unwrap();
+        arm.set_joint_positions(&[0.3, 0.4]).unwrap();
+        let coarse = arm.end_transform();
+        arm.set_joint_positions(&[0.32, 0.4]).unwrap();
+        assert_eq!(arm.end_transform(), coarse);
+        let mut exact_arm = arm.clone();
+        exact_arm.set_end_transform_cache(0, 0.1).unwrap();
+        assert_ne!(exact_arm.end_transform(), coarse);
+        assert_eq!(jacobian(&arm), jacobian(&exact_arm));
+        let (translation_error, _) = arm.pose_error_magnitudes(&exact_arm.end_transform());
+        assert!(translation_error < 1.0e-12);
+
+        // the changes of the origins and the base pose are not hidden by the cache
+        arm.set_end_transform_cache(2, 1.0e-3).unwrap();
+        let t0 = arm.end_transform();
+        joint1.set_origin(na::Isometry3::translation(2.0, 0.0, 0.0));
+        let t1 = arm.end_transform();
+        assert_ne!(t1, t0);
+        assert_eq!(t1, arm.calc_end_transform());
+        arm.set_base_pose(na::Isometry3::translation(0.0, 0.0, 1.0));
+        let t2 = arm.end_transform();
+        assert_ne!(t2, t1);
+        assert_eq!(t2, arm.calc_end_transform());
+        assert_eq!(arm.end_transform_cache_hits(), 0);
+        assert_eq!(arm.end_transform(), t2);
+        assert_eq!(arm.end_transform_cache_hits(), 1);
+    }
+
+    #[test]
+    fn test_joint_names_order() {
+        let chain = sample_chain();
+        let names = chain.joint_names();
+        assert_eq!(names, sample_chain().joint_names());
+        assert_eq!(names, chain.configuration().names());
+        // depth-first: the left arm, then the right arm
+        assert_eq!(
+            names,
+            [
+                "l_shoulder_yaw",
+                "l_shoulder_pitch",
+                "l_shoulder_roll",
+                "l_elbow_pitch",
+                "l_wrist_yaw",
+                "l_wrist_pitch",
+                "r_shoulder_yaw",
+                "r_shoulder_pitch",
+                "r_shoulder_roll",
+                "r_elbow_pitch",
+                "r_wrist_yaw",
+                "r_wrist_pitch"
+            ]
+        );
+        let positions = (0..12).map(|i| i as f64 * 0.1 - 0.5).collect::<Vec<_>>();
+        chain.set_joint_positions(&positions).unwrap();
+        for (name, position) in names.iter().zip(positions.iter()) {
+            assert_eq!(
+                chain.find(name).unwrap().joint_position().unwrap(),
+                *position
+            );
+        }
+        // the same with the chain which is created by the cloned nodes
+        assert_eq!(chain.clone().joint_names(), names);
+    }
+
+    #[test]
+    fn test_move_joint_positions() {
+        let mut chain = sample_chain();
+        let mut deltas = vec![0.0; 12];
+        deltas[3] = -0.4;
+        deltas[5] = 0.1;
+        chain.move_joint_positions(&deltas).unwrap();
+        chain.move_joint_positions(&deltas).unwrap();
+        let positions = chain.joint_positions();
+        assert!((positions[3] + 0.8).abs() < 1e-10);
+        assert!((positions[5] - 0.2).abs() < 1e-10);
+        assert_eq!(positions[0], 0.0);
+
+        // l_elbow_pitch is limited to [-2, 3]
+        chain.move_joint_position("l_elbow_pitch", -1.0).unwrap();
+        assert!((chain.joint_positions()[3] + 1.8).abs() < 1e-10);
+        assert!(matches!(
+            chain.move_joint_position("l_elbow_pitch", -0.5),
+            Err(Error::OutOfLimitError { .. })
+        ));
+        chain.move_joint_positions_clamped(&deltas).unwrap();
+        assert_eq!(chain.joint_positions()[3], -2.0);
+        assert!(chain.move_joint_positions(&deltas).is_err());
+
+        assert!(chain.move_joint_positions(&[0.1]).is_err());
+        assert!(chain.move_joint_positions_clamped(&[0.1]).is_err());
+        assert!(chain.move_joint_position("root", 0.1).is_err());
+
+        // the single joint follows the limit mode
+        chain.set_limit_mode(LimitMode::Clamp);
+        chain.move_joint_position("l_elbow_pitch", 10.0).unwrap();
+        assert_eq!(chain.joint_positions()[3], 3.0);
+        chain.set_limit_mode(LimitMode::Ignore);
+        chain.move_joint_position("l_elbow_pitch", 10.0).unwrap();
+        assert_eq!(chain.joint_positions()[3], 13.0);
+    }
+
+    #[test]
+    fn test_iter_movable_nodes() {
+        let chain = sample_chain();
+        let arm = SerialChain::from_end(chain.find("l_wrist_pitch").unwrap());
+        assert_eq!(arm.iter_movable_nodes().count(), arm.dof());
+        let names = arm
+            .iter_movable_nodes()
+            .map(|node| node.joint().name.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(names, arm.configuration().names());
+        assert_eq!(chain.iter_movable_nodes().count(), chain.dof());
+    }
+
+    #[test]
+    fn test_world_transform_matrices() {
+        let chain = sample_chain();
+        chain
+            .set_joint_positions(&[0.1, 0.2, 0.3, -0.4, 0.5, 0.6, 0.7, 0.8, 0.9, -1.0, 0.1, 0.2])
+            .unwrap();
+        let matrices = chain.world_transform_matrices();
+        let transforms = chain.update_transforms();
+        assert_eq!(matrices.len(), chain.iter().count());
+        for (matrix, trans) in matrices.iter().zip(transforms.iter()) {
+            let rotation =
+                na::Rotation3::from_matrix(&matrix.fixed_view::<3, 3>(0, 0).into_owned());
+            let restored = Isometry3::from_parts(
+                na::Translation3::from(matrix.fixed_view::<3, 1>(0, 3).into_owned()),
+                na::UnitQuaternion::from_rotation_matrix(&rotation),
+            );
+            assert!((restored.translation.vector - trans.translation.vector).norm() < 1e-10);
+            assert!(restored.rotation.angle_to(&trans.rotation) < 1e-7);
+            assert_eq!(matrix.row(3), na::RowVector4::new(0.0, 0.0, 0.0, 1.0));
+        }
+    }
+
+    #[test]
+    fn test_path_is_collision_free() {
+        let j0 = NodeBuilder::new()
+            .name("j0")
+            .joint_type(JointType::Rotational {
+                axis: Vector3::z_axis(),
+            })
+            .limits(Some((-1.0..=1.0).into()))
+            .into_node();
+        let j1 = NodeBuilder::new()
+            .name("j1")
+            .translation(na::Translation3::new(1.0, 0.0, 0.0))
+            .joint_type(JointType::Rotational {
+                axis: Vector3::z_axis(),
+            })
+            .into_node();
+        let end = NodeBuilder::new()
+            .name("end")
+            .translation(na::Translation3::new(1.0, 0.0, 0.0))
+            .into_node();
+        j1.set_parent(&j0);
+        end.set_parent(&j1);
+        let chain = Chain::from_root(j0);
+        chain.set_joint_positions(&[0.3, 0.2]).unwrap();
+        // the end collides with the base when the arm is folded
+        let is_free = |chain: &Chain<f64>| {
+            chain.update_transforms();
+            let end = chain.find("end").unwrap().world_transform().unwrap();
+            end.translation.vector.norm() > 0.5
+        };
+        assert!(chain
+            .path_is_collision_free(&[-0.5, -2.0], &[0.5, 2.0], 20, is_free)
+            .unwrap());
+        // both ends are free, but the arm is folded at j1 = pi
+        let mut checked = 0;
+        assert!(!chain
+            .path_is_collision_free(&[0.0, 2.0], &[0.0, 4.2], 20, |chain| {
+                checked += 1;
+                is_free(chain)
+            })
+            .unwrap());
+        assert!(checked > 1 && checked < 21);
+        assert_eq!(chain.joint_positions(), vec![0.3, 0.2]);
+
+        assert!(chain
+            .path_is_collision_free(&[0.0], &[1.0], 20, is_free)
+            .is_err());
+        // out of the limits
+        assert!(chain
+            .path_is_collision_free(&[0.0, 0.0], &[1.5, 0.0], 20, is_free)
+            .is_err());
+        assert_eq!(chain.joint_positions(), vec![0.3, 0.2]);
+    }
+
+    #[test]
+    fn test_compile_fk() {
+        let chain = sample_chain();
+        let fk = chain.compile_fk("r_wrist2").unwrap();
+        let mut rng = SplitMix64::new(0);
+        for _ in 0..10 {
+            let positions = sample_joint_positions(&chain, &mut rng);
+            chain.set_joint_positions(&positions).unwrap();
+            chain.update_transforms();
+            let expected = chain
+                .find("r_wrist_pitch")
+                .unwrap()
+                .world_transform()
+                .unwrap();
+            let trans = fk(&positions);
+            assert!((trans.translation.vector - expected.translation.vector).norm() < 1e-10);
+            assert!(trans.rotation.angle_to(&expected.rotation) < 1e-10);
+        }
+        // it takes the name of the link, not the joint
+        assert!(chain.compile_fk("r_wrist_pitch").is_err());
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    #[test]
+    #[should_panic(expected = "the length of the positions must be the dof")]
+    fn test_compile_fk_short_positions() {
+        let chain = sample_chain();
+        let fk = chain.compile_fk("r_wrist2").unwrap();
+        fk(&[0.0; 3]);
+    }
+
+    #[test]
+    fn test_end_transforms_into() {
+        let chain = sample_chain();
+        let positions_list = (0..10)
+            .map(|i| {
+                (0..12)
+                    .map(|j| ((i * j) as f64 * 0.1).sin())
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let mut out = Vec::with_capacity(16);
+        let capacity = out.capacity();
+        for _ in 0..3 {
+            chain
+                .end_transforms_into(&positions_list, "l_wrist_pitch", &mut out)
+                .unwrap();
+            assert_eq!(out.len(), positions_list.len());
+            assert_eq!(out.capacity(), capacity);
+        }
+        let end = chain.find("l_wrist_pitch").unwrap();
+        for (positions, trans) in positions_list.iter().zip(out.iter()) {
+            chain.set_joint_positions(positions).unwrap();
+            chain.update_transforms();
+            let expected = end.world_transform().unwrap();
+            assert!((trans.to_homogeneous() - expected.to_homogeneous()).norm() < 1e-10);
+        }
+        assert!(chain
+            .end_transforms_into(&[vec![0.0; 3]], "l_wrist_pitch", &mut out)
+            .is_err());
+        assert!(chain
+            .end_transforms_into(&positions_list, "no_exist", &mut out)
+            .is_err());
+    }
+
+    #[test]
+    fn test_jacobian_condition_number() {
+        // shoulder (yaw, pitch, roll), elbow (pitch) and wrist (yaw, pitch)
+        let joints = [
+            (na::Vector3::z_axis(), 0.0),
+            (na::Vector3::y_axis(), 0.0),
+            (na::Vector3::x_axis(), 0.0),
+            (na::Vector3::y_axis(), -0.3),
+            (na::Vector3::z_axis(), -0.3),
+            (na::Vector3::y_axis(), 0.0),
+        ];
+        let nodes = joints
+            .iter()
+            .map(|(axis, z)| {
+                NodeBuilder::new()
+                    .translation(na::Translation3::new(0.0, 0.0, *z))
+                    .joint_type(JointType::Rotational { axis: *axis })
+                    .into_node()
+            })
+            .collect::<Vec<_>>();
+        for i in 1..nodes.len() {
+            nodes[i].set_parent(&nodes[i - 1]);
+        }
+        let end = NodeBuilder::new()
+            .translation(na::Translation3::new(0.0, 0.0, -0.1))
+            .into_node();
+        end.set_parent(&nodes[5]);
+        let arm = SerialChain::<f64>::from_end(&end);
+        // the elbow approaches the full extension
+        let numbers = [-1.0, -0.1, -0.01, -0.001]
+            .iter()
+            .map(|elbow| {
+                arm.set_joint_positions(&[0.1, 0.2, 0.3, *elbow, 0.4, -0.3])
+                    .unwrap();
+                arm.jacobian_condition_number().unwrap()
+            })
+            .collect::<Vec<_>>();
+        assert!(numbers[0] < 50.0);
+        assert!(numbers.windows(2).all(|w| w[0] < w[1]));
+        assert!(numbers[3] > 1000.0);
+        // singular at the full extension
+        arm.set_joint_positions(&[0.1, 0.2, 0.3, 0.0, 0.4, -0.3])
+            .unwrap();
+        assert_eq!(arm.jacobian_condition_number(), None);
+        // no joints
+        let fixed = SerialChain::<f64>::from_end(&NodeBuilder::new().into_node());
+        assert_eq!(fixed.dof(), 0);
+        assert_eq!(fixed.jacobian_condition_number(), None);
+    }
+
+    #[test]
+    fn test_joint_sensitivities() {
+        let chain = sample_chain();
+        let arm = SerialChain::from_end(chain.find("l_wrist_pitch").unwrap());
+        arm.set_joint_positions(&[0.1, 0.2, 0.3, -0.5, 0.4, -0.3])
+            .unwrap();
+        let sensitivities = arm.joint_sensitivities();
+        assert_eq!(sensitivities.len(), 6);
+        // the shoulder moves the end more than the wrist
+        assert!(sensitivities[1] > sensitivities[4]);
+        assert!(sensitivities[1] > 0.3);
+        // the end is at the origin of the last joint
+        assert!(sensitivities[5] < 1e-10);
+        let jacobi = jacobian(&arm);
+        for (i, sensitivity) in sensitivities.iter().enumerate() {
+            let v = Vector3::new(jacobi[(0, i)], jacobi[(1, i)], jacobi[(2, i)]);
+            assert!((v.norm() - sensitivity).abs() < 1e-10);
+        }
+    }
+
+    #[test]
+    fn test_link_jacobian() {
+        let chain = sample_chain();
+        let arm = SerialChain::from_end(chain.find("l_wrist_pitch").unwrap());
+        arm.set_joint_positions(&[0.1, 0.2, 0.3, -0.5, 0.4, -0.3])
+            .unwrap();
+        let expected = jacobian(&arm);
+        assert!((arm.link_jacobian("l_wrist2").unwrap() - &expected).norm() < 1e-10);
+
+        let j = chain.link_jacobian("l_wrist2").unwrap();
+        assert_eq!(j.ncols(), chain.dof());
+        let arm_names = arm.joint_names();
+        let mut arm_index = 0;
+        for (i, name) in chain.joint_names().iter().enumerate() {
+            if arm_names.contains(name) {
+                assert!((j.column(i) - expected.column(arm_index)).norm() < 1e-10);
+                arm_index += 1;
+            } else {
+                assert_eq!(j.column(i).norm(), 0.0);
+            }
+        }
+        assert_eq!(arm_index, arm.dof());
+        // the elbow does not depend on the wrist
+        let j = chain.link_jacobian("l_elbow1").unwrap();
+        let index = |name: &str| chain.joint_names().iter().position(|n| n == name).unwrap();
+        assert!(j.column(index("l_shoulder_pitch")).norm() > 0.0);
+        assert_eq!(j.column(index("l_wrist_yaw")).norm(), 0.0);
+        assert!(chain.link_jacobian("no_exist").is_none());
+    }
+
+    #[test]
+    fn test_cartesian_stiffness() {
+        let chain = sample_chain();
+        let arm = SerialChain::from_end(chain.find("l_wrist_pitch").unwrap());
+        arm.set_joint_positions(&[0.1, 0.2, 0.3, -0.5, 0.4, -0.3])
+            .unwrap();
+        let stiffness = arm.cartesian_stiffness(&[100.0; 6]).unwrap();
+        assert!((stiffness - stiffness.transpose()).norm() < 1e-6 * stiffness.norm());
+        let eigenvalues = stiffness.symmetric_eigenvalues();
+        assert!(eigenvalues.iter().all(|e| *e > 0.0));
+        // J^T K_cart J = K_joint for the square jacobian
+        let j = jacobian(&arm);
+        let joint_stiffness = j.transpose() * stiffness * &j;
+        assert!((joint_stiffness - DMatrix::identity(6, 6) * 100.0).norm() < 1e-6);
+        // doubled joint stiffness doubles the Cartesian stiffness
+        let stiffness2 = arm.cartesian_stiffness(&[200.0; 6]).unwrap();
+        assert!((stiffness2 - stiffness * 2.0).norm() < 1e-6 * stiffness2.norm());
+    }
+
+    #[test]
+    fn test_limit_mode() {
+        let mut chain = sample_chain();
+        let mut positions = vec![0.0; chain.dof()];
+        positions[0] = 100.0;
+        let range = chain.iter_joints().next().unwrap().limits.unwrap();
+        assert_eq!(chain.limit_mode(), LimitMode::Error);
+        assert!(matches!(
+            chain.set_joint_positions(&positions),
+            Err(Error::OutOfLimitError { .. })
+        ));
+
+        chain.set_limit_mode(LimitMode::Clamp);
+        chain.set_joint_positions(&positions).unwrap();
+        assert_eq!(chain.joint_positions()[0], range.max);
+
+        chain.set_limit_mode(LimitMode::Ignore);
+        chain.set_joint_positions(&positions).unwrap();
+        assert_eq!(chain.joint_positions()[0], 100.0);
+        assert_eq!(chain.clone().limit_mode(), LimitMode::Ignore);
+
+        // the methods built on set_joint_positions() follow the mode
+        chain.set_limit_mode(LimitMode::Clamp);
+        let clamped = chain.with_positions(&positions, |c| c.joint_positions()[0]);
+        assert_eq!(clamped.unwrap(), range.max);
+        assert!(chain.mass_matrix(&positions).is_ok());
+        chain.set_limit_mode(LimitMode::Error);
+        assert!(matches!(
+            chain.with_positions(&positions, |_| ()),
+            Err(Error::OutOfLimitError { .. })
+        ));
+        assert!(chain.mass_matrix(&positions).is_err());
+        // the joints ignore the mode
+        chain.set_limit_mode(LimitMode::Ignore);
+        let name = chain.iter_joints().next().unwrap().name.clone();
+        let joint = chain.find(&name).unwrap();
+        assert!(joint.set_joint_position(100.0).is_err());
+
+        // the size is checked in any mode
+        assert!(matches!(
+            chain.set_joint_positions(&[0.0]),
+            Err(Error::SizeMismatchError { .. })
+        ));
+    }
+
+    #[test]
+    fn test_jacobian_numerical() {
+        let chain = sample_chain();
+        let mut arm = SerialChain::from_end(chain.find("l_wrist_pitch").unwrap());
+        let positions = vec![0.1, 0.2, 0.3, -0.5, 0.4, -0.3];
+        arm.set_joint_positions(&positions).unwrap();
+        arm.set_tool_transform(Isometry3::translation(0.0, 0.0, -0.1));
+        let numerical = arm.jacobian_numerical(1.0e-6);
+        let analytic = jacobian(&arm);
+        assert_eq!(numerical.shape(), (6, 6));
+        assert!((numerical - analytic).norm() < 1.0e-6);
+        assert_eq!(arm.joint_positions(), positions);
+    }
+
+    #[test]
+    fn test_joint_residual() {
+        let l0 = NodeBuilder::new()
+            .joint_type(JointType::Rotational {
+                axis: Vector3::z_axis(),
+            })
+            .into_node();
+        let l1 = NodeBuilder::new()
+            .joint_type(JointType::Rotational {
+                axis: Vector3::y_axis(),
+            })
+            .limits(Some((-4.0..=4.0).into()))
+            .into_node();
+        l1.set_parent(&l0);
+        let chain = Chain::<f64>::from_root(l0);
+        let two_pi = std::f64::consts::TAU;
+        chain.set_joint_positions(&[two_pi - 0.1, 3.0]).unwrap();
+        let residual = chain.joint_residual(&[0.1, -3.0]).unwrap();
+        // the continuous joint is wrapped, but the limited one is not
+        assert!((residual[0] - 0.2).abs() < 1e-10);
+        assert!((residual[1] + 6.0).abs() < 1e-10);
+        assert!(chain.joint_residual(&[0.1]).is_err());
+    }
+
+    #[test]
+    fn test_serial_chain_from_joints() {
+        let joints = (0..3)
+            .map(|i| {
+                let mut joint = Joint::new(
+                    &format!("j{i}"),
+                    JointType::Rotational {
+                        axis: Vector3::z_axis(),
+                    },
+                );
+                if i > 0 {
+                    joint.set_origin(Isometry3::translation(1.0, 0.0, 0.0));
+                }
+                joint
+            })
+            .chain(std::iter::once({
+                let mut tip = Joint::new("tip", JointType::Fixed);
+                tip.set_origin(Isometry3::translation(1.0, 0.0, 0.0));
+                tip
+            }))
+            .collect::<Vec<_>>();
+        let arm = SerialChain::<f64>::from_joints(joints);
+        assert_eq!(arm.dof(), 3);
+        assert_eq!(
+            arm.iter()
+                .map(|node| node.joint().name.clone())
+                .collect::<Vec<_>>(),
+            vec!["j0", "j1", "j2", "tip"]
+        );
+        assert!(
+            (arm.end_transform().translation.vector - Vector3::new(3.0, 0.0, 0.0)).norm() < 1e-10
+        );
+        let half_pi = std::f64::consts::FRAC_PI_2;
+        arm.set_joint_positions(&[half_pi, 0.0, -half_pi]).unwrap();
+        let transforms = arm.update_transforms();
+        assert!((transforms[3].translation.vector - Vector3::new(1.0, 2.0, 0.0)).norm() < 1e-10);
+    }
+
+    #[test]
+    fn test_end_transforms_soa() {
+        let chain = sample_chain();
+        let configs = (0..5)
+            .map(|i| {
+                (0..chain.dof())
+                    .map(|j| 0.1 * ((i + j) % 5) as f64 - 0.2)
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let poses = chain.end_transforms_soa(&configs, "l_wrist_pitch").unwrap();
+        assert_eq!(poses.len(), 5);
+        assert_eq!(poses.qz.len(), 5);
+        let wrist = chain.find("l_wrist_pitch").unwrap();
+        for (i, positions) in configs.iter().enumerate() {
+            chain.set_joint_positions(positions).unwrap();
+            chain.update_transforms();
+            let expected = wrist.world_transform().unwrap();
+            let pose = poses.get(i).unwrap();
+            assert!((pose.translation.vector - expected.translation.vector).norm() < 1e-10);
+            assert!(pose.rotation.angle_to(&expected.rotation) < 1e-7);
+        }
+        assert!(poses.get(5).is_none());
+        assert!(chain.end_transforms_soa(&configs, "no_exist").is_err());
+        assert!(chain
+            .end_transforms_soa(&[vec![0.0]], "l_wrist_pitch")
+            .is_err());
+        assert!(chain
+            .end_transforms_soa(&[], "l_wrist_pitch")
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_subtree_joints() {
+        let chain = sample_chain();
+        let names = |link_name: &str| {
+            chain.subtree_joints(link_name).map(|nodes| {
+                nodes
+                    .iter()
+                    .map(|node| node.joint().name.clone())
+                    .collect::<Vec<_>>()
+            })
+        };
+        let left = names("l_shoulder1").unwrap();
+        assert_eq!(
+            left,
+            vec![
+                "l_shoulder_pitch",
+                "l_shoulder_roll",
+                "l_elbow_pitch",
+                "l_wrist_yaw",
+                "l_wrist_pitch"
+            ]
+        );
+        assert!(left.iter().all(|name| !name.starts_with("r_")));
+        assert_eq!(names("root_body").unwrap().len(), chain.dof());
+        assert_eq!(names("l_wrist2").unwrap(), Vec::<String>::new());
+        assert!(names("no_exist").is_none());
+    }
+
+    #[test]
+    fn test_analytic_jacobian() {
+        let chain = sample_chain();
+        let arm = SerialChain::from_end(chain.find("l_wrist_pitch").unwrap());
+        let positions = vec![0.1, 0.2, 0.3, -0.5, 0.4, -0.3];
+        arm.set_joint_positions(&positions).unwrap();
+        let epsilon = 1.0e-6;
+        for convention in [EulerConvention::RollPitchYaw, EulerConvention::Zyz] {
+            let jacobi = arm.analytic_jacobian(convention).unwrap();
+            for i in 0..arm.dof() {
+                let mut moved = positions.clone();
+                moved[i] += epsilon;
+                arm.set_joint_positions(&moved).unwrap();
+                let plus = arm.end_transform();
+                moved[i] -= 2.0 * epsilon;
+                arm.set_joint_positions(&moved).unwrap();
+                let minus = arm.end_transform();
+                let linear = (plus.translation.vector - minus.translation.vector) / (2.0 * epsilon);
+                let rates = (convention.angles(&plus.rotation)
+                    - convention.angles(&minus.rotation))
+                    / (2.0 * epsilon);
+                for r in 0..3 {
+                    assert!((jacobi[(r, i)] - linear[r]).abs() < 1.0e-6);
+                    assert!((jacobi[(r + 3, i)] - rates[r]).abs() < 1.0e-6);
+                }
+            }
+            arm.set_joint_positions(&positions).unwrap();
+        }
+
+        // gimbal lock of roll-pitch-yaw
+        let l0 = NodeBuilder::new()
+            .joint_type(JointType::Rotational {
+                axis: Vector3::y_axis(),
+            })
+            .into_node();
+        let arm = SerialChain::<f64>::from_end(&l0);
+        arm.set_joint_positions(&[std::f64::consts::FRAC_PI_2])
+            .unwrap();
+        assert!(arm
+            .analytic_jacobian(EulerConvention::RollPitchYaw)
+            .is_none());
+        assert!(arm.analytic_jacobian(EulerConvention::Zyz).is_some());
+    }
+
+    #[test]
+    fn test_renormalize_transforms() {
+        let nodes = (0..300)
+            .map(|i| {
+                let axis = match i % 3 {
+                    0 => Vector3::x_axis(),
+                    1 => Vector3::y_axis(),
+                    _ => Vector3::z_axis(),
+                };
+                NodeBuilder::<f32>::new()
+                    .translation(na::Translation3::new(0.0, 0.0, 0.01))
+                    .joint_type(JointType::Rotational { axis })
+                    .into_node()
+            })
+            .collect::<Vec<_>>();
+        for i in 1..nodes.len() {
+            nodes[i].set_parent(&nodes[i - 1]);
+        }
+        let chain = Chain::from_root(nodes[0].clone());
+        let mut rng = SplitMix64::new(0);
+        let mut max_error = 0.0f32;
+        for _ in 0..20 {
+            let positions = sample_joint_positions(&chain, &mut rng);
+            chain.set_joint_positions(&positions).unwrap();
+            chain.update_transforms();
+            max_error = max_error.max(chain.max_rotation_nonorthogonality());
+            chain.renormalize_transforms();
+            assert!(chain.max_rotation_nonorthogonality() < 1.0e-6);
         }
+        // the errors are accumulated without the renormalization
+        assert!(max_error > 0.0);
     }
 
-    /// Safely unwrap and returns inner `Chain` instance
-    pub fn unwrap(self) -> Chain<T> {
-        self.inner
+    #[test]
+    fn test_contributing_joints() {
+        let chain = sample_chain();
+        let names = chain.contributing_joints("l_wrist2").unwrap();
+        assert_eq!(
+            names,
+            vec![
+                "l_shoulder_yaw",
+                "l_shoulder_pitch",
+                "l_shoulder_roll",
+                "l_elbow_pitch",
+                "l_wrist_yaw",
+                "l_wrist_pitch"
+            ]
+        );
+        // the joints of the other branch are excluded
+        assert!(names.iter().all(|name| !name.starts_with("r_")));
+        let right = chain.contributing_joints("r_elbow1").unwrap();
+        assert_eq!(right.len(), 4);
+        assert!(right.iter().all(|name| name.starts_with("r_")));
+        assert_eq!(
+            chain.contributing_joints("root_body").unwrap(),
+            Vec::<String>::new()
+        );
+        assert!(chain.contributing_joints("no_exist").is_none());
+
+        // the non-zero columns of the jacobian
+        chain.set_joint_positions(&[0.1; 12]).unwrap();
+        let jacobi = chain.link_jacobian("r_elbow1").unwrap();
+        for (i, name) in chain.joint_names().iter().enumerate() {
+            assert_eq!(jacobi.column(i).norm() > 0.0, right.contains(name));
+        }
     }
-    /// Calculate transform of the end joint
-    pub fn end_transform(&self) -> Isometry3<T> {
-        self.iter().fold(Isometry3::identity(), |trans, joint| {
-            trans * joint.joint().local_transform()
-        })
+
+    #[test]
+    fn test_workspace_volume_estimate() {
+        let create_arm = |length: f64| {
+            let nodes = (0..4)
+                .map(|i| {
+                    let builder = NodeBuilder::new().translation(na::Translation3::new(
+                        0.0,
+                        0.0,
+                        if i == 0 { 0.0 } else { length },
+                    ));
+                    let axis = if i % 2 == 0 {
+                        Vector3::z_axis()
+                    } else {
+                        Vector3::y_axis()
+                    };
+                    if i < 3 {
+                        builder
+                            .joint_type(JointType::Rotational { axis })
+                            .limits(Some((-2.0..=2.0).into()))
+                            .into_node()
+                    } else {
+                        builder.into_node()
+                    }
+                })
+                .collect::<Vec<_>>();
+            for i in 1..nodes.len() {
+                nodes[i].set_parent(&nodes[i - 1]);
+            }
+            SerialChain::<f64>::from_end(&nodes[3])
+        };
+        let short = create_arm(0.2);
+        let long = create_arm(0.4);
+        long.set_joint_positions(&[0.1, 0.2, 0.3]).unwrap();
+        let short_volume = short.workspace_volume_estimate(2000);
+        let long_volume = long.workspace_volume_estimate(2000);
+        assert!(short_volume > 0.0);
+        assert!(long_volume > short_volume * 4.0);
+        // the bound of the ball of the max reach
+        let reach = long.max_reach();
+        assert!(long_volume < 4.0 / 3.0 * std::f64::consts::PI * (reach * 1.2).powi(3));
+        assert_eq!(long.joint_positions(), vec![0.1, 0.2, 0.3]);
+        assert_eq!(long.workspace_volume_estimate(0), 0.0);
     }
-}
 
-impl<T> Clone for SerialChain<T>
-where
-    T: RealField + SubsetOf<f64>,
-{
-    fn clone(&self) -> Self {
-        Self {
-            inner: self.inner.clone(),
+    /// 6 dof arm whose links have `mass` and the inertia `mass * 0.01 I`
+    fn create_arm6_with_mass(mass: f64) -> SerialChain<f64> {
+        use crate::link::*;
+
+        let axes = [
+            Vector3::z_axis(),
+            Vector3::y_axis(),
+            Vector3::y_axis(),
+            Vector3::x_axis(),
+            Vector3::y_axis(),
+            Vector3::x_axis(),
+        ];
+        let nodes = axes
+            .iter()
+            .enumerate()
+            .map(|(i, axis)| {
+                let node = NodeBuilder::new()
+                    .translation(na::Translation3::new(
+                        0.0,
+                        0.0,
+                        if i == 0 { 0.0 } else { 0.2 },
+                    ))
+                    .joint_type(JointType::Rotational { axis: *axis })
+                    .into_node();
+                let inertial = Inertial::new(
+                    Isometry3::translation(0.0, 0.0, 0.1),
+                    mass,
+                    na::Matrix3::identity() * mass * 0.01,
+                );
+                node.set_link(Some(LinkBuilder::new().inertial(inertial).finalize()));
+                node
+            })
+            .collect::<Vec<_>>();
+        for i in 1..nodes.len() {
+            nodes[i].set_parent(&nodes[i - 1]);
         }
+        let arm = SerialChain::from_end(&nodes[5]);
+        arm.set_joint_positions(&[0.1, 0.5, -0.8, 0.3, 0.6, -0.2])
+            .unwrap();
+        arm
     }
-}
 
-impl<T> Display for SerialChain<T>
-where
-    T: RealField + SubsetOf<f64>,
-{
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.inner.fmt(f)
+    #[test]
+    fn test_operational_space_inertia() {
+        let arm = create_arm6_with_mass(1.0);
+        let inertia = arm.operational_space_inertia().unwrap();
+        assert!((inertia - inertia.transpose()).norm() < 1e-10);
+        assert!(inertia.symmetric_eigenvalues().iter().all(|e| *e > 0.0));
+        assert!(inertia.cholesky().is_some());
+
+        // the kinetic energy of the end twist
+        let qdot = vec![0.3, -0.2, 0.5, 0.1, -0.4, 0.2];
+        let twist = jacobian(&arm) * na::DVector::from_vec(qdot.clone());
+        let twist = Vector6::from_iterator(twist.iter().cloned());
+        let energy = 0.5 * twist.dot(&(inertia * twist));
+        assert!((energy - arm.kinetic_energy(&qdot).unwrap()).abs() < 1e-8);
+
+        // proportional to the masses
+        let heavy = create_arm6_with_mass(3.0);
+        let heavy_inertia = heavy.operational_space_inertia().unwrap();
+        assert!((heavy_inertia - inertia * 3.0).norm() < 1e-6 * inertia.norm());
     }
-}
 
-impl<T> Deref for SerialChain<T>
-where
-    T: RealField,
-{
-    type Target = Chain<T>;
-    fn deref(&self) -> &Self::Target {
-        &self.inner
+    #[test]
+    fn test_mass_matrix() {
+        use crate::link::*;
+
+        // single link: the inertia about the joint axis
+        let node = NodeBuilder::new()
+            .joint_type(JointType::Rotational {
+                axis: Vector3::y_axis(),
+            })
+            .into_node();
+        let inertia = na::Matrix3::new(0.1, 0.0, 0.0, 0.0, 0.2, 0.0, 0.0, 0.0, 0.3);
+        let inertial = Inertial::new(Isometry3::translation(0.4, 0.0, 0.3), 2.0, inertia);
+        node.set_link(Some(LinkBuilder::new().inertial(inertial).finalize()));
+        let single = Chain::from_root(node);
+        for q in [0.0, 0.7, -1.2] {
+            let m = single.mass_matrix(&[q]).unwrap();
+            assert_eq!(m.shape(), (1, 1));
+            assert!((m[(0, 0)] - (0.2 + 2.0 * (0.4 * 0.4 + 0.3 * 0.3_f64))).abs() < 1e-10);
+        }
+
+        // 3 dof arm with a linear joint and a payload
+        let nodes = [
+            JointType::Rotational {
+                axis: Vector3::z_axis(),
+            },
+            JointType::Linear {
+                axis: Vector3::x_axis(),
+            },
+            JointType::Rotational {
+                axis: Vector3::y_axis(),
+            },
+        ]
+        .into_iter()
+        .enumerate()
+        .map(|(i, joint_type)| {
+            let node = NodeBuilder::new()
+                .name(&format!("j{i}"))
+                .translation(na::Translation3::new(0.0, 0.1, 0.3))
+                .joint_type(joint_type)
+                .into_node();
+            let inertial = Inertial::new(
+                Isometry3::new(Vector3::new(0.1, 0.0, 0.2), Vector3::new(0.3, 0.2, 0.0)),
+                1.0 + i as f64,
+                na::Matrix3::new(0.1, 0.01, 0.0, 0.01, 0.2, 0.02, 0.0, 0.02, 0.3),
+            );
+            let link = LinkBuilder::new()
+                .name(&format!("l{i}"))
+                .inertial(inertial)
+                .finalize();
+            node.set_link(Some(link));
+            node
+        })
+        .collect::<Vec<_>>();
+        for i in 1..nodes.len() {
+            nodes[i].set_parent(&nodes[i - 1]);
+        }
+        let mut arm = Chain::from_root(nodes[0].clone());
+        arm.set_payload("l2", 0.5, Vector3::new(0.2, 0.0, 0.0))
+            .unwrap();
+        arm.set_joint_positions(&[0.1, 0.2, 0.3]).unwrap();
+        let q = [0.4, -0.3, 1.1];
+        let m = arm.mass_matrix(&q).unwrap();
+        assert_eq!(m.shape(), (3, 3));
+        assert!((&m - m.transpose()).norm() < 1e-12);
+        assert!(m.clone().cholesky().is_some());
+        // the positions are restored
+        assert_eq!(arm.joint_positions(), vec![0.1, 0.2, 0.3]);
+
+        // 1/2 qdot^T M qdot is the kinetic energy
+        let qdot = [0.3, -0.5, 0.8];
+        arm.set_joint_positions(&q).unwrap();
+        let v = na::DVector::from_row_slice(&qdot);
+        let energy = 0.5 * v.dot(&(&m * &v));
+        assert!((energy - arm.kinetic_energy(&qdot).unwrap()).abs() < 1e-10);
+
+        // the descendants before the ancestors
+        arm.reorder_joints(&["j2".to_owned(), "j0".to_owned(), "j1".to_owned()])
+            .unwrap();
+        let q = [1.1, 0.4, -0.3];
+        let m = arm.mass_matrix(&q).unwrap();
+        assert!((&m - m.transpose()).norm() < 1e-12);
+        assert!(m[(0, 1)].abs() > 1e-6);
+        arm.set_joint_positions(&q).unwrap();
+        let qdot = [0.8, 0.3, -0.5];
+        let v = na::DVector::from_row_slice(&qdot);
+        let energy = 0.5 * v.dot(&(&m * &v));
+        assert!((energy - arm.kinetic_energy(&qdot).unwrap()).abs() < 1e-10);
+
+        assert!(matches!(
+            arm.mass_matrix(&[0.0; 2]),
+            Err(Error::SizeMismatchError {
+                input: 2,
+                required: 3
+            })
+        ));
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    #[cfg(target_family = "wasm")]
-    use wasm_bindgen_test::wasm_bindgen_test as test;
+    #[test]
+    fn test_frames() {
+        let mut chain = sample_chain();
+        let offset = Isometry3::new(Vector3::new(0.0, 0.02, 0.1), Vector3::new(0.3, 0.0, 0.0));
+        chain.add_frame("camera", "l_wrist2", offset).unwrap();
+        chain.set_joint_positions(&[0.2; 12]).unwrap();
+        let camera = chain.frame_transform("camera").unwrap();
+        let link_transform = chain
+            .find_link("l_wrist2")
+            .unwrap()
+            .world_transform()
+            .unwrap();
+        assert!(
+            ((link_transform * offset).to_homogeneous() - camera.to_homogeneous()).norm() < 1e-10
+        );
+        // the frame follows the link
+        chain.set_joint_positions(&[-0.1; 12]).unwrap();
+        assert!(chain.frame_transform("camera").unwrap() != camera);
+        // clones have the frames
+        assert!(chain.clone().frame_transform("camera").is_some());
+        chain.remove_frame("camera");
+        assert!(chain.frame_transform("camera").is_none());
+    }
 
     #[test]
-    fn test_chain0() {
-        let joint0 = NodeBuilder::new()
-            .name("j0")
-            .translation(na::Translation3::new(0.0, 0.1, 0.0))
-            .joint_type(JointType::Rotational {
-                axis: na::Vector3::y_axis(),
+    fn test_end_transform_as_screw() {
+        let l0 = NodeBuilder::new()
+            .translation(na::Translation3::new(0.3, -0.4, 0.0))
+            .joint_type(JointType::Linear {
+                axis: Vector3::x_axis(),
             })
             .into_node();
-        let joint1 = NodeBuilder::new()
-            .translation(na::Translation3::new(0.0, 0.1, 0.1))
-            .name("j1")
+        let arm = SerialChain::<f64>::from_end(&l0);
+        let (axis, point, angle, translation) = arm.end_transform_as_screw();
+        assert!((axis - Vector3::new(0.6, -0.8, 0.0)).norm() < 1e-10);
+        assert_eq!(point, Vector3::zeros());
+        assert_eq!(angle, 0.0);
+        assert!((translation - 0.5).abs() < 1e-10);
+
+        // rotation about the axis parallel to z through (1, 2, 0) with pitch
+        let pitch = 0.2;
+        let center = Vector3::new(1.0, 2.0, 0.0);
+        let rotation = na::UnitQuaternion::from_axis_angle(&Vector3::z_axis(), 0.7);
+        let origin = Isometry3::from_parts(
+            na::Translation3::from(center - rotation * center + Vector3::z() * pitch),
+            rotation,
+        );
+        l0.set_origin(origin);
+        arm.set_joint_positions(&[0.0]).unwrap();
+        let (axis, point, angle, translation) = arm.end_transform_as_screw();
+        assert!((axis - Vector3::z()).norm() < 1e-10);
+        assert!((point - center).norm() < 1e-10);
+        assert!((angle - 0.7).abs() < 1e-10);
+        assert!((translation - pitch).abs() < 1e-10);
+    }
+
+    #[test]
+    fn test_max_reach() {
+        let nodes = (0..4)
+            .map(|i| {
+                let builder = NodeBuilder::new().translation(na::Translation3::new(
+                    0.0,
+                    0.0,
+                    if i == 0 { 1.0 } else { 0.1 },
+                ));
+                if i < 3 {
+                    builder
+                        .joint_type(JointType::Rotational {
+                            axis: Vector3::y_axis(),
+                        })
+                        .into_node()
+                } else {
+                    builder.into_node()
+                }
+            })
+            .collect::<Vec<_>>();
+        for i in 1..nodes.len() {
+            nodes[i].set_parent(&nodes[i - 1]);
+        }
+        let mut arm = SerialChain::<f64>::from_end(&nodes[3]);
+        // the height of the base is not included
+        assert!((arm.max_reach() - 0.3).abs() < 1e-10);
+        arm.set_joint_positions(&[0.3, -0.5, 0.2]).unwrap();
+        let transforms = arm.update_transforms();
+        let distance = (transforms[3].translation.vector - transforms[0].translation.vector).norm();
+        assert!(distance <= arm.max_reach());
+        arm.set_tool_transform(Isometry3::translation(0.0, 0.05, 0.0));
+        assert!((arm.max_reach() - 0.35).abs() < 1e-10);
+    }
+
+    #[test]
+    fn test_torques_for_wrench() {
+        // horizontal 2-link arm along x, which rotates in the XZ plane
+        let j0 = NodeBuilder::new()
             .joint_type(JointType::Rotational {
-                axis: na::Vector3::y_axis(),
+                axis: Vector3::y_axis(),
             })
             .into_node();
-        let joint2 = NodeBuilder::new()
-            .name("j2")
-            .translation(na::Translation3::new(0.0, 0.1, 0.1))
+        let j1 = NodeBuilder::new()
+            .translation(na::Translation3::new(1.0, 0.0, 0.0))
             .joint_type(JointType::Rotational {
-                axis: na::Vector3::y_axis(),
+                axis: Vector3::y_axis(),
             })
             .into_node();
-        let joint3 = NodeBuilder::new()
-            .name("j3")
-            .translation(na::Translation3::new(0.0, 0.1, 0.2))
+        let tip = NodeBuilder::new()
+            .translation(na::Translation3::new(1.0, 0.0, 0.0))
+            .into_node();
+        j1.set_parent(&j0);
+        tip.set_parent(&j1);
+        let arm = SerialChain::<f64>::from_end(&tip);
+        // 10 N downward at the tip: the moment arms are 2 m and 1 m
+        let wrench = Vector6::new(0.0, 0.0, -10.0, 0.0, 0.0, 0.0);
+        let torques = arm.torques_for_wrench(&wrench);
+        assert!((torques[0] - 20.0).abs() < 1e-10);
+        assert!((torques[1] - 10.0).abs() < 1e-10);
+        // a pure torque around the axis is the same for all joints
+        let wrench = Vector6::new(0.0, 0.0, 0.0, 0.0, 3.0, 0.0);
+        assert_eq!(arm.torques_for_wrench(&wrench), vec![3.0, 3.0]);
+    }
+
+    #[test]
+    fn test_manipulability_ellipsoid() {
+        let chain = sample_chain();
+        let arm = SerialChain::from_end(chain.find("l_wrist_pitch").unwrap());
+        arm.set_joint_positions(&[0.1, 0.2, 0.3, -0.5, 0.4, -0.3])
+            .unwrap();
+        let (axes, values) = arm.manipulability_ellipsoid();
+        assert_eq!(axes.len(), 3);
+        assert!(values[0] >= values[1] && values[1] >= values[2]);
+        assert!(axes[0].dot(&axes[1]).abs() < 1e-10);
+        let j = jacobian(&arm).rows(0, 3).into_owned();
+        let mobility = |direction: &Vector3<f64>| (j.transpose() * direction).norm_squared();
+        assert!((mobility(&axes[0]) - values[0]).abs() < 1e-10);
+        // the first axis is the most mobile direction
+        for i in 0..100 {
+            let t = i as f64 * 0.1;
+            let direction =
+                Vector3::new(t.cos() * (t * 0.3).sin(), t.sin(), (t * 0.7).cos()).normalize();
+            assert!(mobility(&direction) <= values[0] + 1e-10);
+        }
+    }
+
+    #[test]
+    fn test_joint_index() {
+        let chain = sample_chain();
+        for i in 0..chain.dof() {
+            let name = chain.joint_name_at(i).unwrap();
+            assert_eq!(chain.joint_index(&name), Some(i));
+        }
+        assert_eq!(chain.joint_name_at(chain.dof()), None);
+        assert_eq!(chain.joint_index("no_exist"), None);
+        let arm = SerialChain::from_end(chain.find("r_wrist_pitch").unwrap());
+        assert_eq!(arm.joint_index("r_shoulder_yaw"), Some(0));
+        assert_eq!(arm.joint_index("l_shoulder_yaw"), None);
+    }
+
+    #[test]
+    fn test_grid_sample_configs() {
+        let l0 = NodeBuilder::new()
             .joint_type(JointType::Rotational {
-                axis: na::Vector3::y_axis(),
+                axis: Vector3::z_axis(),
             })
+            .limits(Some((-1.0..=1.0).into()))
             .into_node();
-        let joint4 = NodeBuilder::new()
-            .name("j4")
-            .translation(na::Translation3::new(0.0, 0.1, 0.1))
-            .joint_type(JointType::Rotational {
-                axis: na::Vector3::y_axis(),
+        let l1 = NodeBuilder::new()
+            .joint_type(JointType::Linear {
+                axis: Vector3::x_axis(),
             })
+            .limits(Some((0.0..=0.4).into()))
             .into_node();
-        let joint5 = NodeBuilder::new()
-            .name("j5")
-            .translation(na::Translation3::new(0.0, 0.1, 0.1))
+        l1.set_parent(&l0);
+        let chain = Chain::<f64>::from_root(l0);
+        let configs = chain.grid_sample_configs(3).collect::<Vec<_>>();
+        assert_eq!(configs.len(), 9);
+        for corner in [[-1.0, 0.0], [-1.0, 0.4], [1.0, 0.0], [1.0, 0.4], [0.0, 0.2]] {
+            assert!(configs.contains(&corner.to_vec()));
+        }
+        assert_eq!(configs[1], vec![-1.0, 0.2]);
+        assert_eq!(chain.grid_sample_configs(0).count(), 0);
+        // lazy
+        let chain = sample_chain();
+        assert_eq!(chain.grid_sample_configs(10).nth(5).unwrap().len(), 12);
+    }
+
+    #[test]
+    fn test_apply_limit_margin() {
+        let chain = sample_chain();
+        let (lower, upper) = chain.joint_limit_vectors();
+        chain.apply_limit_margin(0.9);
+        let (new_lower, new_upper) = chain.joint_limit_vectors();
+        for i in 0..chain.dof() {
+            let range = upper[i] - lower[i];
+            assert!((new_upper[i] - new_lower[i] - range * 0.9).abs() < 1e-10);
+            assert!((new_upper[i] + new_lower[i] - upper[i] - lower[i]).abs() < 1e-10);
+        }
+        // the outer 5% is rejected
+        let mut positions = vec![0.0; chain.dof()];
+        positions[0] = upper[0] - (upper[0] - lower[0]) * 0.04;
+        assert!(chain.set_joint_positions(&positions).is_err());
+        positions[0] = lower[0] + (upper[0] - lower[0]) * 0.04;
+        assert!(chain.set_joint_positions(&positions).is_err());
+        positions[0] = upper[0] - (upper[0] - lower[0]) * 0.06;
+        assert!(chain.set_joint_positions(&positions).is_ok());
+
+        // the mimic children follow their clamped parents
+        let j0 = NodeBuilder::new()
+            .joint_type(JointType::Linear {
+                axis: Vector3::z_axis(),
+            })
+            .limits(Some((-1.0..=1.0).into()))
+            .into_node();
+        let j1 = NodeBuilder::new()
+            .joint_type(JointType::Linear {
+                axis: Vector3::z_axis(),
+            })
+            .limits(Some((-3.0..=3.0).into()))
+            .into_node();
+        j1.set_parent(&j0);
+        j1.set_mimic_parent(&j0, crate::joint::Mimic::new(2.0, 0.0));
+        let chain = Chain::<f64>::from_root(j0);
+        chain.set_joint_positions(&[1.0, 0.0]).unwrap();
+        assert_eq!(chain.joint_positions(), vec![1.0, 2.0]);
+        chain.apply_limit_margin(0.5);
+        assert_eq!(chain.joint_positions(), vec![0.5, 1.0]);
+        // the margin is not compounded
+        chain.apply_limit_margin(0.5);
+        assert_eq!(
+            chain.joint_limit_vectors(),
+            (vec![-0.5, -1.5], vec![0.5, 1.5])
+        );
+        chain.apply_limit_margin(1.0);
+        assert_eq!(
+            chain.joint_limit_vectors(),
+            (vec![-1.0, -3.0], vec![1.0, 3.0])
+        );
+        assert_eq!(chain.joint_positions(), vec![0.5, 1.0]);
+
+        // the limits edited after the margin are the new original
+        chain.apply_limit_margin(0.5);
+        chain.movable_nodes[0].lock().joint.limits = Some((-2.0..=4.0).into());
+        chain.apply_limit_margin(0.5);
+        assert_eq!(
+            chain.joint_limit_vectors(),
+            (vec![-0.5, -1.5], vec![2.5, 1.5])
+        );
+    }
+
+    #[test]
+    fn test_find_minimum_energy_pose() {
+        use crate::link::*;
+        // pendulum which starts horizontally
+        let pendulum = |limits: Option<Range<f64>>| {
+            let j0 = NodeBuilder::new()
+                .joint_type(JointType::Rotational {
+                    axis: Vector3::y_axis(),
+                })
+                .limits(limits)
+                .into_node();
+            let bob = NodeBuilder::new()
+                .name("bob")
+                .translation(na::Translation3::new(0.5, 0.0, 0.0))
+                .into_node();
+            bob.set_link(Some(
+                LinkBuilder::new()
+                    .inertial(Inertial::from_mass(2.0))
+                    .finalize(),
+            ));
+            bob.set_parent(&j0);
+            Chain::<f64>::from_root(j0)
+        };
+        let chain = pendulum(None);
+        chain.find_minimum_energy_pose(Vector3::new(0.0, 0.0, -9.81), 200);
+        // hanging straight down
+        assert!((chain.joint_positions()[0] - std::f64::consts::FRAC_PI_2).abs() < 1e-4);
+        chain.update_transforms();
+        let bob = chain.find("bob").unwrap();
+        let position = bob.world_transform().unwrap().translation.vector;
+        assert!((position - Vector3::new(0.0, 0.0, -0.5)).norm() < 1e-4);
+        assert!(chain.gravity_torques()[0].abs() < 1e-3);
+
+        // stops at the limit
+        let chain = pendulum(Some((-1.0..=1.0).into()));
+        chain.find_minimum_energy_pose(Vector3::new(0.0, 0.0, -9.81), 200);
+        assert!((chain.joint_positions()[0] - 1.0).abs() < 1e-10);
+    }
+
+    #[test]
+    fn test_kinetic_energy() {
+        use crate::link::*;
+        let inertia = Matrix3::from_diagonal(&Vector3::new(0.1, 0.2, 0.3));
+        let l0 = NodeBuilder::new()
             .joint_type(JointType::Rotational {
-                axis: na::Vector3::y_axis(),
+                axis: Vector3::z_axis(),
             })
             .into_node();
-        joint1.set_parent(&joint0);
-        joint2.set_parent(&joint1);
-        joint3.set_parent(&joint2);
-        joint4.set_parent(&joint0);
-        joint5.set_parent(&joint4);
+        l0.set_link(Some(
+            LinkBuilder::new()
+                .name("link")
+                .inertial(Inertial::new(Isometry3::identity(), 2.0, inertia))
+                .finalize(),
+        ));
+        let mut chain = Chain::<f64>::from_root(l0);
+        // 1/2 I w^2
+        assert!((chain.kinetic_energy(&[3.0]).unwrap() - 0.5 * 0.3 * 9.0).abs() < 1e-10);
+        chain.set_joint_positions(&[0.7]).unwrap();
+        assert!((chain.kinetic_energy(&[3.0]).unwrap() - 0.5 * 0.3 * 9.0).abs() < 1e-10);
+        // the payload at r adds 1/2 m (r w)^2
+        chain
+            .set_payload("link", 0.5, Vector3::new(0.4, 0.0, 0.0))
+            .unwrap();
+        let expected = 0.5 * 0.3 * 9.0 + 0.5 * 0.5 * (0.4f64 * 3.0).powi(2);
+        assert!((chain.kinetic_energy(&[3.0]).unwrap() - expected).abs() < 1e-10);
+        assert_eq!(chain.kinetic_energy(&[0.0]).unwrap(), 0.0);
+    }
 
-        let names = joint0
-            .iter_descendants()
-            .map(|joint| joint.joint().name.clone())
-            .collect::<Vec<_>>();
-        println!("{joint0}");
-        assert_eq!(names.len(), 6);
-        println!("names = {names:?}");
-        let positions = joint0
-            .iter_descendants()
-            .map(|node| node.joint().joint_position())
+    #[test]
+    fn test_joint_positions_deg() {
+        let chain = sample_chain();
+        let mut degrees = vec![0.0; chain.dof()];
+        degrees[0] = 90.0;
+        degrees[1] = -45.0;
+        chain.set_joint_positions_deg(&degrees).unwrap();
+        let positions = chain.joint_positions();
+        assert!((positions[0] - std::f64::consts::FRAC_PI_2).abs() < 1e-10);
+        assert!((positions[1] + std::f64::consts::FRAC_PI_4).abs() < 1e-10);
+        for (read, written) in chain.joint_positions_deg().iter().zip(degrees.iter()) {
+            assert!((read - written).abs() < 1e-10);
+        }
+        // the limits are checked in radians
+        degrees[0] = 180.0;
+        assert!(chain.set_joint_positions_deg(&degrees).is_err());
+        assert!(chain.set_joint_positions_deg(&[90.0]).is_err());
+    }
+
+    #[test]
+    fn test_point_within_support_polygon() {
+        let normal = Vector3::z();
+        // unordered with an inner point
+        let contacts = [
+            Point3::new(1.0, 1.0, 0.0),
+            Point3::new(-1.0, -1.0, 0.0),
+            Point3::new(0.2, 0.1, 0.0),
+            Point3::new(-1.0, 1.0, 0.0),
+            Point3::new(1.0, -1.0, 0.0),
+        ];
+        let centroid = Point3::new(0.0, 0.0, 0.5);
+        assert!(point_within_support_polygon(&centroid, &contacts, &normal));
+        let far = Point3::new(3.0, 0.0, 0.0);
+        assert!(!point_within_support_polygon(&far, &contacts, &normal));
+        assert!(!point_within_support_polygon(
+            &centroid,
+            &contacts[..2],
+            &normal
+        ));
+        // tilted ground
+        let normal = Vector3::new(1.0, 0.0, 1.0).normalize();
+        let tilted = [
+            Point3::new(1.0, 1.0, -1.0),
+            Point3::new(1.0, -1.0, -1.0),
+            Point3::new(-1.0, 1.0, 1.0),
+            Point3::new(-1.0, -1.0, 1.0),
+        ];
+        assert!(point_within_support_polygon(
+            &Point3::origin(),
+            &tilted,
+            &normal
+        ));
+        assert!(!point_within_support_polygon(
+            &Point3::new(0.0, 2.0, 0.0),
+            &tilted,
+            &normal
+        ));
+    }
+
+    #[test]
+    fn test_com_within_polygon() {
+        let chain = sample_chain();
+        let com = chain.com_ground_projection(Vector3::z());
+        assert!((com.coords - center_of_mass(&chain).xy().push(0.0)).norm() < 1e-10);
+        let square = |x: f64, y: f64| {
+            [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)]
+                .iter()
+                .map(|(dx, dy)| Point3::new(x + dx * 0.1, y + dy * 0.1, 0.0))
+                .collect::<Vec<_>>()
+        };
+        assert!(chain.com_within_polygon(&square(com.x, com.y)));
+        assert!(!chain.com_within_polygon(&square(com.x + 1.0, com.y)));
+    }
+
+    #[test]
+    fn test_base_pose() {
+        let chain = sample_chain();
+        chain.set_joint_positions(&vec![0.2; chain.dof()]).unwrap();
+        let origin = Isometry3::translation(0.0, 0.0, 0.2);
+        chain.set_origin(origin);
+        let before = chain.update_transforms();
+        let base = Isometry3::new(Vector3::new(1.0, -0.5, 0.3), Vector3::new(0.0, 0.0, 0.4));
+        chain.set_base_pose(base);
+        assert_eq!(chain.base_pose(), base);
+        // the origin is kept
+        assert_eq!(chain.origin(), origin);
+        let after = chain.update_transforms();
+        for (b, a) in before.iter().zip(after.iter()) {
+            assert!(((base * b).to_homogeneous() - a.to_homogeneous()).norm() < 1e-10);
+        }
+        // the clone and the serial chain from the root see the base
+        assert_eq!(chain.clone().base_pose(), base);
+        let arm = SerialChain::from_end(chain.find("l_wrist_pitch").unwrap());
+        let end = arm.end_transform();
+        assert!((end.to_homogeneous() - after[6].to_homogeneous()).norm() < 1e-10);
+        // the FK which does not use the nodes see the base
+        let positions = chain.joint_positions();
+        let mut out = Vec::new();
+        chain
+            .end_transforms_into(&[positions.clone()], "l_wrist_pitch", &mut out)
+            .unwrap();
+        assert!((out[0].to_homogeneous() - after[6].to_homogeneous()).norm() < 1e-10);
+        let frozen = chain.freeze().transforms(&positions).unwrap();
+        for (f, a) in frozen.iter().zip(after.iter()) {
+            assert!((f.to_homogeneous() - a.to_homogeneous()).norm() < 1e-10);
+        }
+        // the base pose of a sub chain is set to the root of the tree
+        let sub = Chain::from_end_to_root(
+            chain.find("l_wrist_pitch").unwrap(),
+            chain.find("l_elbow_pitch").unwrap(),
+        );
+        assert_eq!(sub.base_pose(), base);
+        let moved = Isometry3::translation(0.0, 2.0, 0.0);
+        sub.set_base_pose(moved);
+        assert_eq!(chain.base_pose(), moved);
+        assert_eq!(chain.origin(), origin);
+        let moved_transforms = chain.update_transforms();
+        for (b, m) in before.iter().zip(moved_transforms.iter()) {
+            assert!(((moved * b).to_homogeneous() - m.to_homogeneous()).norm() < 1e-10);
+        }
+    }
+
+    #[test]
+    fn test_compute_transforms() {
+        let chain = sample_chain();
+        let positions = (0..chain.dof())
+            .map(|i| 0.1 * i as f64 - 0.5)
             .collect::<Vec<_>>();
-        println!("positions = {positions:?}");
+        chain.set_joint_positions(&positions).unwrap();
+        let transforms = chain.compute_transforms();
+        assert!(chain.iter().all(|node| node.world_transform().is_none()));
+        assert_eq!(transforms, chain.update_transforms());
 
-        fn get_z(joint: &Node<f32>) -> f32 {
-            match joint.parent_world_transform() {
-                Some(iso) => iso.translation.vector.z,
-                None => 0.0f32,
+        // the caches are neither read nor written
+        let sentinel = Isometry3::translation(100.0, 0.0, 0.0);
+        for node in chain.iter() {
+            node.joint().set_world_transform(sentinel);
+        }
+        assert_eq!(chain.compute_transforms(), transforms);
+        assert!(chain
+            .iter()
+            .all(|node| node.world_transform() == Some(sentinel)));
+
+        // a sub tree includes the transforms of the ancestors
+        let sub = Chain::from_root(chain.find("l_elbow_pitch").unwrap().clone());
+        let index = chain
+            .iter()
+            .position(|node| node.joint().name == "l_elbow_pitch")
+            .unwrap();
+        assert!(
+            (sub.compute_transforms()[0].translation.vector - transforms[index].translation.vector)
+                .norm()
+                < 1e-10
+        );
+    }
+
+    #[test]
+    fn test_limit_hit_telemetry() {
+        #[derive(Default)]
+        struct LimitHits(Mutex<Vec<String>>);
+        impl Telemetry<f64> for LimitHits {
+            fn on_limit_hit(&self, joint_name: &str, _position: f64) {
+                self.0.lock().unwrap().push(joint_name.to_owned());
             }
         }
 
-        let poses = joint0
-            .iter_descendants()
-            .map(|joint| get_z(&joint))
-            .collect::<Vec<_>>();
-        println!("poses = {poses:?}");
+        let mut chain = sample_chain();
+        let hits = Arc::new(LimitHits::default());
+        chain.set_telemetry(hits.clone());
+        let mut positions = vec![0.0; chain.dof()];
+        positions[3] = 100.0;
+        chain.set_joint_positions_clamped(&positions);
+        assert!(chain.set_joint_positions(&positions).is_err());
+        chain.set_limit_mode(LimitMode::Clamp);
+        chain.set_joint_positions(&positions).unwrap();
+        assert_eq!(*hits.0.lock().unwrap(), vec!["l_elbow_pitch"; 3]);
+        // the internal calculations do not report the limit hits
+        chain.without_position_reports(|| chain.set_joint_positions_clamped(&positions));
+        let mut arm = SerialChain::from_end(chain.find("l_wrist_pitch").unwrap());
+        arm.set_telemetry(hits.clone());
+        let mut target = arm.end_transform();
+        target.translation.vector.x += 10.0;
+        assert!(!arm.is_reachable(&target, 2));
+        assert_eq!(hits.0.lock().unwrap().len(), 3);
+    }
 
-        let _ = joint0
-            .iter_ancestors()
-            .map(|joint| joint.set_joint_position(-0.5))
-            .collect::<Vec<_>>();
-        let positions = joint0
-            .iter_descendants()
-            .map(|node| node.joint().joint_position())
-            .collect::<Vec<_>>();
-        println!("positions = {positions:?}");
+    #[test]
+    fn test_update_transforms_from() {
+        #[derive(Default)]
+        struct FkCounter(Mutex<usize>);
+        impl Telemetry<f64> for FkCounter {
+            fn on_fk(&self, _transforms: &[Isometry3<f64>]) {
+                *self.0.lock().unwrap() += 1;
+            }
+        }
 
-        let poses = joint0
-            .iter_descendants()
-            .map(|joint| get_z(&joint))
+        let mut chain = sample_chain();
+        chain.update_velocities();
+        let counter = Arc::new(FkCounter::default());
+        chain.set_telemetry(counter.clone());
+        let sentinel = Isometry3::translation(100.0, 0.0, 0.0);
+
+        // changing a leaf joint updates only the leaf
+        let leaf = chain.find("l_wrist_pitch").unwrap();
+        let parent_name = leaf.parent().unwrap().joint().name.clone();
+        for node in chain.iter() {
+            if node.joint().name != parent_name {
+                node.joint().set_world_transform(sentinel);
+            }
+        }
+        leaf.set_joint_position(0.3).unwrap();
+        chain.update_transforms_from("l_wrist_pitch").unwrap();
+        let expected = leaf.parent_world_transform().unwrap() * leaf.joint().local_transform();
+        assert_eq!(leaf.world_transform().unwrap(), expected);
+        // the velocity of the updated joint is cleared, and the telemetry is called
+        assert!(leaf.world_velocity().is_none());
+        assert!(leaf.parent().unwrap().world_velocity().is_some());
+        assert_eq!(*counter.0.lock().unwrap(), 1);
+        let num_untouched = chain
+            .iter()
+            .filter(|node| node.world_transform() == Some(sentinel))
+            .count();
+        assert_eq!(num_untouched, chain.iter().count() - 2);
+
+        // changing the root joint updates all
+        for node in chain.iter() {
+            node.joint().set_world_transform(sentinel);
+        }
+        let root_name = chain.iter().next().unwrap().joint().name.clone();
+        chain.update_transforms_from(&root_name).unwrap();
+        let transforms = chain
+            .iter()
+            .map(|node| node.world_transform().unwrap())
             .collect::<Vec<_>>();
-        println!("poses = {poses:?}");
+        for node in chain.iter() {
+            node.joint().clear_caches();
+        }
+        assert_eq!(transforms, chain.update_transforms());
+        assert!(chain.update_transforms_from("no_exist").is_err());
+    }
 
-        let arm = Chain::from_end(&joint3);
-        assert_eq!(arm.joint_positions().len(), 4);
-        println!("{:?}", arm.joint_positions());
+    #[test]
+    fn test_to_tree() {
+        let chain = sample_chain();
+        let arm = SerialChain::from_end(chain.find("l_wrist_pitch").unwrap());
+        arm.set_joint_positions(&[0.1, 0.2, 0.3, -0.5, 0.4, -0.3])
+            .unwrap();
+        let tree = arm.to_tree();
+        assert_eq!(tree.dof(), arm.dof());
+        assert_eq!(tree.joint_positions(), arm.joint_positions());
+        assert_eq!(tree.iter().count(), arm.iter().count());
+        tree.update_transforms();
+        let end = tree
+            .find("l_wrist_pitch")
+            .unwrap()
+            .world_transform()
+            .unwrap();
+        let expected = arm.end_transform();
+        assert!((end.to_homogeneous() - expected.to_homogeneous()).norm() < 1e-10);
+        // not shared
+        tree.set_joint_positions(&[0.0; 6]).unwrap();
+        assert_eq!(arm.joint_positions()[0], 0.1);
     }
 
     #[test]
-    fn test_mimic() {
-        let joint0 = NodeBuilder::new()
-            .name("j0")
-            .translation(na::Translation3::new(0.0, 0.1, 0.0))
-            .joint_type(JointType::Rotational {
-                axis: na::Vector3::y_axis(),
+    fn test_mounted_on() {
+        let platform_x = NodeBuilder::new()
+            .name("platform_x")
+            .joint_type(JointType::Linear {
+                axis: Vector3::x_axis(),
             })
             .into_node();
-        let joint1 = NodeBuilder::new()
-            .name("joint1")
-            .translation(na::Translation3::new(0.0, 0.1, 0.1))
+        let platform_yaw = NodeBuilder::new()
+            .name("platform_yaw")
             .joint_type(JointType::Rotational {
-                axis: na::Vector3::y_axis(),
+                axis: Vector3::z_axis(),
             })
             .into_node();
-        let joint2 = NodeBuilder::new()
-            .name("joint2")
-            .translation(na::Translation3::new(0.0, 0.1, 0.1))
+        platform_yaw.set_parent(&platform_x);
+        let base = SerialChain::<f64>::from_end(&platform_yaw);
+
+        let chain = sample_chain();
+        let arm = SerialChain::from_end_to_root(
+            chain.find("l_wrist_pitch").unwrap(),
+            chain.find("l_shoulder_yaw").unwrap(),
+        );
+        arm.set_joint_positions(&[0.1, 0.2, 0.3, -0.5, 0.4, -0.3])
+            .unwrap();
+        let arm_end = arm.end_transform();
+
+        let shoulder = chain.find("l_shoulder_yaw").unwrap();
+        let shoulder_parent = shoulder.parent();
+        let tree_transforms = chain.update_transforms();
+        let full_arm = SerialChain::from_end(chain.find("l_wrist_pitch").unwrap());
+        let full_arm_end = full_arm.end_transform();
+
+        let mobile_arm = arm.mounted_on(&base);
+        let mobile_arm2 = arm.mounted_on(&base);
+        // the base is not changed
+        assert!(platform_yaw.children().is_empty());
+        assert!(platform_x.parent().is_none());
+        assert_eq!(base.iter().count(), 2);
+        // the source tree of the arm is not changed
+        assert!(shoulder_parent.is_some());
+        assert_eq!(shoulder.parent(), shoulder_parent);
+        mobile_arm2.set_joint_positions(&[0.0; 8]).unwrap();
+        assert_eq!(base.joint_positions(), vec![0.0; 2]);
+        assert_eq!(arm.joint_positions(), vec![0.1, 0.2, 0.3, -0.5, 0.4, -0.3]);
+        assert_eq!(full_arm.end_transform(), full_arm_end);
+        assert_eq!(arm.end_transform(), arm_end);
+        assert_eq!(chain.update_transforms(), tree_transforms);
+        assert_eq!(mobile_arm.dof(), 8);
+        assert_eq!(mobile_arm2.dof(), 8);
+        assert_eq!(
+            mobile_arm.joint_names()[..2],
+            ["platform_x".to_string(), "platform_yaw".to_string()]
+        );
+        // the current positions are copied
+        assert_eq!(mobile_arm.joint_positions()[2..], arm.joint_positions()[..]);
+
+        for base_positions in [[0.0, 0.0], [1.0, 0.0], [0.5, 1.2]] {
+            base.set_joint_positions(&base_positions).unwrap();
+            mobile_arm
+                .set_joint_positions(&[base.joint_positions(), arm.joint_positions()].concat())
+                .unwrap();
+            let expected = base.end_transform() * arm_end;
+            let end = mobile_arm.end_transform();
+            assert!((end.translation.vector - expected.translation.vector).norm() < 1e-10);
+            assert!(end.rotation.angle_to(&expected.rotation) < 1e-10);
+            let world = mobile_arm.update_transforms();
+            assert!(
+                (world.last().unwrap().translation.vector - expected.translation.vector).norm()
+                    < 1e-10
+            );
+        }
+        assert!(platform_yaw.children().is_empty());
+    }
+
+    #[test]
+    fn test_jacobian_pinv() {
+        let chain = sample_chain();
+        let arm = SerialChain::from_end(chain.find("r_wrist_pitch").unwrap());
+        arm.set_joint_positions(&[0.1, 0.2, 0.3, -0.5, 0.4, -0.3])
+            .unwrap();
+        let j = jacobian(&arm);
+        let pinv = arm.jacobian_pinv(0.0);
+        assert!((&j * &pinv * &j - &j).norm() < 1e-8);
+        assert!((&pinv * &j - DMatrix::identity(6, 6)).norm() < 1e-8);
+
+        // redundant: right inverse
+        let wrist_roll = NodeBuilder::new()
+            .translation(na::Translation3::new(0.0, 0.0, -0.1))
             .joint_type(JointType::Rotational {
-                axis: na::Vector3::y_axis(),
+                axis: na::Vector3::x_axis(),
             })
             .into_node();
-        joint1.set_parent(&joint0);
-        joint2.set_parent(&joint1);
-        joint2.set_mimic_parent(&joint1, Mimic::new(2.0, 0.5));
-
-        let arm = Chain::from_root(joint0);
-
-        assert_eq!(arm.joint_positions().len(), 3);
-        println!("{:?}", arm.joint_positions());
-        let positions = vec![0.1, 0.2, 0.3];
-        arm.set_joint_positions(&positions).unwrap();
-        let positions = arm.joint_positions();
-        assert!((positions[0] - 0.1f64).abs() < f64::EPSILON);
-        assert!((positions[1] - 0.2f64).abs() < f64::EPSILON);
-        assert!((positions[2] - 0.9f64).abs() < f64::EPSILON);
+        wrist_roll.set_parent(chain.find("r_wrist_pitch").unwrap());
+        let redundant_arm = SerialChain::from_end(&wrist_roll);
+        redundant_arm
+            .set_joint_positions(&[0.1, 0.2, 0.3, -0.5, 0.4, -0.3, 0.2])
+            .unwrap();
+        let j = jacobian(&redundant_arm);
+        let pinv = redundant_arm.jacobian_pinv(0.0);
+        assert_eq!(pinv.shape(), (7, 6));
+        assert!((&j * &pinv - DMatrix::identity(6, 6)).norm() < 1e-8);
+        // damped
+        let damped = redundant_arm.jacobian_pinv(0.1);
+        assert!((&j * &damped - DMatrix::identity(6, 6)).norm() > 1e-4);
     }
 }
@@ -0,0 +1,405 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+//! Load the skeleton (node hierarchy) of [COLLADA](https://www.khronos.org/collada/) format
+//! and create `k::Chain`
+//!
+//! Only the first `<visual_scene>` in `<library_visual_scenes>` is used.
+//! Each `<node>` becomes a `Node`, and its `<matrix>`, `<translate>` and `<rotate>` elements
+//! are used as the origin transform. Each `<rotate>` of the nodes with `type="JOINT"` becomes
+//! a rotational joint around its axis, and the angle is used as the initial position.
+//! Geometries, controllers and animations are ignored.
+//!
+//! The nodes without `name` and `id` are named `joint_0`, `joint_1`, ... in the order of
+//! appearance. `<scale>` (other than `1 1 1`), `<skew>`, `<lookat>` and `<instance_node>`
+//! are not supported, and `ColladaError::Unsupported` is returned for them.
+
+use super::chain::*;
+use super::joint::*;
+use super::link::*;
+use super::node::*;
+use super::urdf::ROOT_JOINT_NAME;
+use na::{Isometry3, Matrix3, Matrix4, RealField, Rotation3, Translation3, UnitQuaternion};
+use nalgebra as na;
+use quick_xml::events::{BytesStart, Event};
+use simba::scalar::SubsetOf;
+use std::path::Path;
+use thiserror::Error;
+
+/// The reason of the COLLADA loading error
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum ColladaError {
+    #[error("failed to read file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("failed to parse xml: {0}")]
+    Xml(#[from] quick_xml::Error),
+    #[error("invalid COLLADA: {0}")]
+    Invalid(String),
+    #[error("unsupported COLLADA: {0}")]
+    Unsupported(String),
+}
+
+/// Minimal DOM of the xml
+#[derive(Debug, Default)]
+struct Element {
+    name: String,
+    attributes: Vec<(String, String)>,
+    text: String,
+    children: Vec<Element>,
+}
+
+impl Element {
+    fn from_start(start: &BytesStart<'_>) -> Result<Self, ColladaError> {
+        let mut attributes = Vec::new();
+        for attr in start.attributes() {
+            let attr = attr.map_err(quick_xml::Error::from)?;
+            attributes.push((
+                String::from_utf8_lossy(attr.key.local_name().as_ref()).into_owned(),
+                attr.unescape_value()?.into_owned(),
+            ));
+        }
+        Ok(Self {
+            name: String::from_utf8_lossy(start.local_name().as_ref()).into_owned(),
+            attributes,
+            ..Default::default()
+        })
+    }
+
+    fn attribute(&self, key: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|c| c.name == name)
+    }
+
+    fn floats(&self) -> Result<Vec<f64>, ColladaError> {
+        self.text
+            .split_whitespace()
+            .map(|v| {
+                v.parse::<f64>()
+                    .map_err(|e| ColladaError::Invalid(format!("<{}>: {e}", self.name)))
+            })
+            .collect()
+    }
+
+    fn floats_n(&self, n: usize) -> Result<Vec<f64>, ColladaError> {
+        let values = self.floats()?;
+        if values.len() != n {
+            return Err(ColladaError::Invalid(format!(
+                "<{}> must have {n} values, but has {}",
+                self.name,
+                values.len()
+            )));
+        }
+        Ok(values)
+    }
+}
+
+fn parse_xml(xml: &str) -> Result<Element, ColladaError> {
+    let mut reader = quick_xml::Reader::from_str(xml);
+    let mut stack = vec![Element::default()];
+    loop {
+        match reader.read_event()? {
+            Event::Start(start) => stack.push(Element::from_start(&start)?),
+            Event::Empty(start) => {
+                let element = Element::from_start(&start)?;
+                stack.last_mut().unwrap().children.push(element);
+            }
+            Event::Text(text) => {
+                let text = text.decode().map_err(quick_xml::Error::from)?;
+                stack.last_mut().unwrap().text.push_str(&text);
+            }
+            Event::End(_) => {
+                let element = stack.pop().unwrap();
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(element),
+                    None => return Err(ColladaError::Invalid("unbalanced tags".to_owned())),
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    if stack.len() != 1 {
+        return Err(ColladaError::Invalid("unclosed tags".to_owned()));
+    }
+    stack
+        .pop()
+        .unwrap()
+        .children
+        .into_iter()
+        .find(|e| e.name == "COLLADA")
+        .ok_or_else(|| ColladaError::Invalid("<COLLADA> is not found".to_owned()))
+}
+
+fn isometry_from_matrix<T: RealField>(values: &[f64]) -> Isometry3<T> {
+    // COLLADA matrices are row major
+    let m = Matrix4::from_row_slice(values);
+    let rotation = Rotation3::from_matrix(&Matrix3::from(m.fixed_view::<3, 3>(0, 0)));
+    na::convert(Isometry3::from_parts(
+        Translation3::new(m[(0, 3)], m[(1, 3)], m[(2, 3)]),
+        UnitQuaternion::from_rotation_matrix(&rotation),
+    ))
+}
+
+fn rotational_joint_from<T>(name: &str, origin: Isometry3<T>, values: &[f64]) -> Joint<T>
+where
+    T: RealField + SubsetOf<f64>,
+{
+    let axis = na::Unit::new_normalize(na::Vector3::new(
+        na::convert(values[0]),
+        na::convert(values[1]),
+        na::convert(values[2]),
+    ));
+    let mut joint = NodeBuilder::new()
+        .name(name)
+        .joint_type(JointType::Rotational { axis })
+        .origin(origin)
+        .finalize();
+    joint.set_joint_position_unchecked(na::convert(values[3].to_radians()));
+    joint
+}
+
+/// Create nodes for `<node>` element and its descendants, and returns the first node
+///
+/// `num_unnamed` is the number of the nodes without names so far, to name them uniquely.
+fn nodes_from_element<T>(
+    element: &Element,
+    num_unnamed: &mut usize,
+) -> Result<Node<T>, ColladaError>
+where
+    T: RealField + SubsetOf<f64>,
+{
+    let name = match element
+        .attribute("name")
+        .or_else(|| element.attribute("id"))
+    {
+        Some(name) => name.to_owned(),
+        None => {
+            *num_unnamed += 1;
+            format!("joint_{}", *num_unnamed - 1)
+        }
+    };
+    let name = name.as_str();
+    let is_joint = element.attribute("type") == Some("JOINT");
+    let mut nodes: Vec<Node<T>> = Vec::new();
+    let mut trans = Isometry3::identity();
+    for e in &element.children {
+        match e.name.as_str() {
+            "matrix" => trans *= isometry_from_matrix(&e.floats_n(16)?),
+            "translate" => {
+                let v = e.floats_n(3)?;
+                trans *= na::convert::<_, Translation3<T>>(Translation3::new(v[0], v[1], v[2]));
+            }
+            "rotate" if is_joint => {
+                let joint_name = if nodes.is_empty() {
+                    name.to_owned()
+                } else {
+                    match e.attribute("sid") {
+                        Some(sid) => format!("{name}_{sid}"),
+                        None => format!("{name}_{}", nodes.len()),
+                    }
+                };
+                let node = Node::new(rotational_joint_from(&joint_name, trans, &e.floats_n(4)?));
+                if let Some(parent) = nodes.last() {
+                    node.set_parent(parent);
+                }
+                nodes.push(node);
+                trans = Isometry3::identity();
+            }
+            "rotate" => {
+                let v = e.floats_n(4)?;
+                let axis = na::Unit::new_normalize(na::Vector3::new(v[0], v[1], v[2]));
+                trans *= na::convert::<_, UnitQuaternion<T>>(UnitQuaternion::from_axis_angle(
+                    &axis,
+                    v[3].to_radians(),
+                ));
+            }
+            "scale" => {
+                let v = e.floats_n(3)?;
+                if v.iter().any(|s| (s - 1.0).abs() > 1.0e-6) {
+                    return Err(ColladaError::Unsupported(format!(
+                        "<scale> of {name} is not 1 1 1"
+                    )));
+                }
+            }
+            "skew" | "lookat" | "instance_node" => {
+                return Err(ColladaError::Unsupported(format!("<{}> in {name}", e.name)));
+            }
+            _ => {}
+        }
+    }
+    if nodes.is_empty() {
+        let node = NodeBuilder::new().name(name).origin(trans).into_node();
+        nodes.push(node);
+        trans = Isometry3::identity();
+    }
+    let last = nodes.last().unwrap().clone();
+    last.set_link(Some(LinkBuilder::new().name(name).finalize()));
+    for c in element.children.iter().filter(|c| c.name == "node") {
+        let child = nodes_from_element::<T>(c, num_unnamed)?;
+        // apply the transforms after the last rotation
+        child.set_origin(trans.clone() * child.origin());
+        child.set_parent(&last);
+    }
+    Ok(nodes[0].clone())
+}
+
+impl<T> Chain<T>
+where
+    T: RealField + SubsetOf<f64>,
+{
+    /// Create `Chain` from the node hierarchy of COLLADA string
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let dae = r#"<?xml version="1.0"?>
+    /// <COLLADA xmlns="http://www.collada.org/2005/11/COLLADASchema" version="1.4.1">
+    ///   <library_visual_scenes>
+    ///     <visual_scene id="Scene">
+    ///       <node id="Bone" name="Bone" type="JOINT">
+    ///         <translate>0 0 1</translate>
+    ///         <rotate sid="rotateZ">0 0 1 0</rotate>
+    ///       </node>
+    ///     </visual_scene>
+    ///   </library_visual_scenes>
+    /// </COLLADA>"#;
+    /// let chain = k::Chain::<f64>::from_collada_str(dae).unwrap();
+    /// assert_eq!(chain.dof(), 1);
+    /// ```
+    pub fn from_collada_str(xml: &str) -> Result<Self, ColladaError> {
+        let collada = parse_xml(xml)?;
+        let scene = collada
+            .child("library_visual_scenes")
+            .and_then(|lib| lib.child("visual_scene"))
+            .ok_or_else(|| ColladaError::Invalid("<visual_scene> is not found".to_owned()))?;
+        let root_node = NodeBuilder::<T>::new().name(ROOT_JOINT_NAME).into_node();
+        let mut num_unnamed = 0;
+        for e in scene.children.iter().filter(|c| c.name == "node") {
+            let node = nodes_from_element::<T>(e, &mut num_unnamed)?;
+            node.set_parent(&root_node);
+        }
+        Ok(Chain::from_root(root_node))
+    }
+
+    /// Create `Chain` from the node hierarchy of COLLADA (.dae) file
+    pub fn from_collada_file<P>(path: P) -> Result<Self, ColladaError>
+    where
+        P: AsRef<Path>,
+    {
+        Self::from_collada_str(&std::fs::read_to_string(path)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(target_family = "wasm")]
+    use wasm_bindgen_test::wasm_bindgen_test as test;
+
+    const TWO_BONES: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<COLLADA xmlns="http://www.collada.org/2005/11/COLLADASchema" version="1.4.1">
+  <asset><up_axis>Z_UP</up_axis></asset>
+  <library_visual_scenes>
+    <visual_scene id="Scene" name="Scene">
+      <node id="Armature" name="Armature" type="NODE">
+        <matrix sid="transform">1 0 0 0 0 1 0 0 0 0 1 0.5 0 0 0 1</matrix>
+        <node id="Armature_Bone0" name="Bone0" sid="Bone0" type="JOINT">
+          <translate sid="location">0 0 0</translate>
+          <rotate sid="rotationY">0 1 0 0</rotate>
+          <node id="Armature_Bone1" name="Bone1" sid="Bone1" type="JOINT">
+            <matrix sid="transform">1 0 0 0 0 1 0 0 0 0 1 1 0 0 0 1</matrix>
+            <rotate sid="rotationY">0 1 0 90</rotate>
+            <node id="Armature_Tip" name="Tip" type="NODE">
+              <translate>0 0 1</translate>
+            </node>
+          </node>
+        </node>
+      </node>
+    </visual_scene>
+  </library_visual_scenes>
+</COLLADA>"#;
+
+    #[test]
+    fn test_two_bones() {
+        let chain = Chain::<f64>::from_collada_str(TWO_BONES).unwrap();
+        let names = chain
+            .iter()
+            .map(|node| node.joint().name.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["root", "Armature", "Bone0", "Bone1", "Tip"]);
+        assert_eq!(chain.dof(), 2);
+        let positions = chain.joint_positions();
+        assert!(positions[0].abs() < 1e-10);
+        assert!((positions[1] - std::f64::consts::FRAC_PI_2).abs() < 1e-10);
+
+        let transforms = chain.update_transforms();
+        let tip = transforms[4].translation.vector;
+        assert!((tip - na::Vector3::new(1.0, 0.0, 1.5)).norm() < 1e-10);
+        assert!(chain.find_link("Bone1").is_some());
+
+        chain.set_joint_positions(&[0.0, 0.0]).unwrap();
+        let tip = chain.update_transforms()[4].translation.vector;
+        assert!((tip - na::Vector3::new(0.0, 0.0, 2.5)).norm() < 1e-10);
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(Chain::<f64>::from_collada_str("<COLLADA></COLLADA>").is_err());
+        assert!(Chain::<f64>::from_collada_str("<robot/>").is_err());
+    }
+
+    fn scene(nodes: &str) -> String {
+        format!(
+            r#"<COLLADA><library_visual_scenes><visual_scene>{nodes}</visual_scene></library_visual_scenes></COLLADA>"#
+        )
+    }
+
+    #[test]
+    fn test_unnamed_nodes() {
+        let chain = Chain::<f64>::from_collada_str(&scene(
+            r#"<node type="JOINT">
+                 <rotate>0 0 1 0</rotate>
+                 <rotate>0 1 0 0</rotate>
+                 <node type="JOINT"><rotate>0 0 1 0</rotate></node>
+               </node>"#,
+        ))
+        .unwrap();
+        assert_eq!(chain.joint_names(), ["joint_0", "joint_0_1", "joint_1"]);
+    }
+
+    #[test]
+    fn test_unsupported() {
+        let unsupported = |node: &str| {
+            matches!(
+                Chain::<f64>::from_collada_str(&scene(node)),
+                Err(ColladaError::Unsupported(_))
+            )
+        };
+        assert!(unsupported(r#"<node name="a"><scale>2 1 1</scale></node>"#));
+        assert!(unsupported(
+            r#"<node name="a"><instance_node url="b"/></node>"#
+        ));
+        assert!(!unsupported(
+            r#"<node name="a"><scale>1 1 1</scale></node>"#
+        ));
+    }
+}
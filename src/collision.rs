@@ -0,0 +1,1143 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+use super::chain::*;
+use super::errors::*;
+use super::link::*;
+use na::{DMatrix, DVector, Isometry3, Point3, RealField, Unit, Vector3};
+use nalgebra as na;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use simba::scalar::SubsetOf;
+
+fn sorted_pair(link_name1: &str, link_name2: &str) -> (String, String) {
+    if link_name1 <= link_name2 {
+        (link_name1.to_owned(), link_name2.to_owned())
+    } else {
+        (link_name2.to_owned(), link_name1.to_owned())
+    }
+}
+
+/// Primitive shape for the self collision checking
+///
+/// The shapes are centered at the origins of their frames. They are checked by
+/// `Chain::min_self_distance()` with the shapes of the collisions of the links, which are
+/// converted by `from_geometry()`, and the ones added by `Chain::add_collision_shape()`.
+/// Meshes are not supported, so use a collision checking library for them with
+/// `Chain::collision_check_pairs()`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[non_exhaustive]
+pub enum CollisionShape<T: RealField> {
+    Sphere {
+        radius: T,
+    },
+    /// The segment from `-half_height * axis` to `half_height * axis` swept by a sphere
+    Capsule {
+        radius: T,
+        half_height: T,
+        axis: Unit<Vector3<T>>,
+    },
+    Box {
+        half_extents: Vector3<T>,
+    },
+    /// The cylinder from `-half_height * axis` to `half_height * axis`
+    Cylinder {
+        radius: T,
+        half_height: T,
+        axis: Unit<Vector3<T>>,
+    },
+}
+
+impl<T> CollisionShape<T>
+where
+    T: RealField,
+{
+    /// Convert the geometry of a link
+    ///
+    /// The axes of capsules and cylinders are Z, like URDF. It returns `None` for meshes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::link::Geometry;
+    /// use k::{CollisionShape, Vector3};
+    ///
+    /// let capsule = Geometry::<f64>::Capsule { radius: 0.1, length: 1.0 };
+    /// assert_eq!(
+    ///     CollisionShape::from_geometry(&capsule),
+    ///     Some(CollisionShape::Capsule {
+    ///         radius: 0.1,
+    ///         half_height: 0.5,
+    ///         axis: Vector3::z_axis(),
+    ///     })
+    /// );
+    /// ```
+    pub fn from_geometry(geometry: &Geometry<T>) -> Option<Self> {
+        let half = |v: &T| v.clone() * na::convert(0.5);
+        Some(match geometry {
+            Geometry::Sphere { radius } => CollisionShape::Sphere {
+                radius: radius.clone(),
+            },
+            Geometry::Capsule { radius, length } => CollisionShape::Capsule {
+                radius: radius.clone(),
+                half_height: half(length),
+                axis: Vector3::z_axis(),
+            },
+            Geometry::Box {
+                depth,
+                width,
+                height,
+            } => CollisionShape::Box {
+                half_extents: Vector3::new(half(depth), half(width), half(height)),
+            },
+            Geometry::Cylinder { radius, length } => CollisionShape::Cylinder {
+                radius: radius.clone(),
+                half_height: half(length),
+                axis: Vector3::z_axis(),
+            },
+            Geometry::Mesh { .. } => return None,
+        })
+    }
+
+    /// Radius of the sphere which encloses the shape around its origin
+    pub fn bounding_radius(&self) -> T {
+        match self {
+            CollisionShape::Sphere { radius } => radius.clone(),
+            CollisionShape::Capsule {
+                radius,
+                half_height,
+                ..
+            } => radius.clone() + half_height.clone(),
+            CollisionShape::Box { half_extents } => half_extents.norm(),
+            CollisionShape::Cylinder {
+                radius,
+                half_height,
+                ..
+            } => {
+                (radius.clone() * radius.clone() + half_height.clone() * half_height.clone()).sqrt()
+            }
+        }
+    }
+
+    /// Calculate the signed distance between two shapes placed at `pose` and `other_pose`
+    ///
+    /// The distance is negative if the shapes are overlapping, and its magnitude is
+    /// the penetration depth. The distances of spheres and capsules are exact,
+    /// and the ones of boxes and cylinders are calculated by GJK and EPA algorithms.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::{CollisionShape, Isometry3, Vector3};
+    ///
+    /// let capsule = CollisionShape::<f64>::Capsule {
+    ///     radius: 0.1,
+    ///     half_height: 0.5,
+    ///     axis: Vector3::x_axis(),
+    /// };
+    /// // parallel capsules
+    /// let d = capsule.distance(
+    ///     &Isometry3::identity(),
+    ///     &capsule,
+    ///     &Isometry3::translation(0.2, 0.5, 0.0),
+    /// );
+    /// assert!((d - 0.3).abs() < 1e-6);
+    /// // overlapping capsules
+    /// let d = capsule.distance(
+    ///     &Isometry3::identity(),
+    ///     &capsule,
+    ///     &Isometry3::translation(0.0, 0.15, 0.0),
+    /// );
+    /// assert!((d + 0.05).abs() < 1e-6);
+    /// ```
+    pub fn distance(
+        &self,
+        pose: &Isometry3<T>,
+        other: &CollisionShape<T>,
+        other_pose: &Isometry3<T>,
+    ) -> T {
+        let (core1, radius1) = Core::from_shape(self);
+        let (core2, radius2) = Core::from_shape(other);
+        core_distance(&core1, pose, &core2, other_pose) - radius1 - radius2
+    }
+}
+
+impl<T> Chain<T>
+where
+    T: RealField + SubsetOf<f64>,
+{
+    /// Set the pairs of the links which are not checked for collision
+    ///
+    /// This is the same as `disable_collisions` of SRDF. The order in a pair does not matter.
+    /// The previous pairs are replaced.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut chain = k::Chain::<f64>::from_urdf_file("urdf/sample.urdf").unwrap();
+    /// chain.set_collision_ignore_pairs(&[("l_shoulder1".to_owned(), "l_shoulder2".to_owned())]);
+    /// assert!(chain.is_collision_ignored("l_shoulder2", "l_shoulder1"));
+    /// assert!(!chain.is_collision_ignored("l_shoulder1", "l_shoulder3"));
+    /// ```
+    pub fn set_collision_ignore_pairs(&mut self, pairs: &[(String, String)]) {
+        self.collision_ignore_pairs = pairs
+            .iter()
+            .map(|(link_name1, link_name2)| sorted_pair(link_name1, link_name2))
+            .collect();
+    }
+
+    /// Check if the collision between the two links is ignored
+    pub fn is_collision_ignored(&self, link_name1: &str, link_name2: &str) -> bool {
+        self.collision_ignore_pairs
+            .contains(&sorted_pair(link_name1, link_name2))
+    }
+
+    /// Add a collision shape to the link
+    ///
+    /// The shape is placed at `origin` in the same frame as the collisions of the link,
+    /// and it is checked by `min_self_distance()` with them. Use this for the shapes which
+    /// can not be written in URDF, like capsules along the links.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let mut chain = Chain::<f64>::from_urdf_file("urdf/sample.urdf").unwrap();
+    /// let capsule = CollisionShape::Capsule {
+    ///     radius: 0.05,
+    ///     half_height: 0.1,
+    ///     axis: Vector3::y_axis(),
+    /// };
+    /// chain
+    ///     .add_collision_shape("l_shoulder1", Isometry3::identity(), capsule.clone())
+    ///     .unwrap();
+    /// assert!(chain
+    ///     .add_collision_shape("no_exist", Isometry3::identity(), capsule)
+    ///     .is_err());
+    /// chain.clear_collision_shapes("l_shoulder1");
+    /// ```
+    pub fn add_collision_shape(
+        &mut self,
+        link_name: &str,
+        origin: Isometry3<T>,
+        shape: CollisionShape<T>,
+    ) -> Result<(), Error> {
+        if self.find_link(link_name).is_none() {
+            return Err(Error::InvalidLinkNameError {
+                link_name: link_name.to_owned(),
+            });
+        }
+        self.collision_shapes
+            .push((link_name.to_owned(), origin, shape));
+        Ok(())
+    }
+
+    /// Remove the collision shapes of the link which are added by `add_collision_shape`
+    pub fn clear_collision_shapes(&mut self, link_name: &str) {
+        self.collision_shapes
+            .retain(|(name, _, _)| name != link_name);
+    }
+
+    /// Get the pairs of the links which have collision shapes and should be checked
+    ///
+    /// The links which have collisions or the shapes added by `add_collision_shape` are used.
+    /// The pairs which are set by `set_collision_ignore_pairs` are excluded.
+    /// The adjacent links (the links of a joint and its parent joint) are excluded too,
+    /// because their shapes usually overlap around the joint.
+    /// The links are in the order of `iter_links()`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::link::*;
+    ///
+    /// let mut chain = k::Chain::<f64>::from_urdf_file("urdf/sample.urdf").unwrap();
+    /// for name in ["l_shoulder1", "l_shoulder2", "l_shoulder3"] {
+    ///     let node = chain.find_link(name).unwrap();
+    ///     let mut link = node.link().clone().unwrap();
+    ///     link.collisions.push(Collision::new(
+    ///         name.to_owned(),
+    ///         k::Isometry3::identity(),
+    ///         Geometry::Sphere { radius: 0.1 },
+    ///     ));
+    ///     node.set_link(Some(link));
+    /// }
+    /// assert_eq!(
+    ///     chain.collision_check_pairs(),
+    ///     vec![
+    ///         ("root_body".to_owned(), "l_shoulder2".to_owned()),
+    ///         ("root_body".to_owned(), "l_shoulder3".to_owned()),
+    ///         ("l_shoulder1".to_owned(), "l_shoulder3".to_owned()),
+    ///     ]
+    /// );
+    /// ```
+    pub fn collision_check_pairs(&self) -> Vec<(String, String)> {
+        let names = self
+            .iter_links()
+            .filter(|link| {
+                !link.collisions.is_empty()
+                    || self
+                        .collision_shapes
+                        .iter()
+                        .any(|(name, _, _)| *name == link.name)
+            })
+            .map(|link| link.name.clone())
+            .collect::<Vec<_>>();
+        let adjacent_pairs = self
+            .iter()
+            .filter_map(|node| {
+                let parent = node.parent()?;
+                let parent_link = parent.link();
+                let link = node.link();
+                Some(sorted_pair(
+                    &parent_link.as_ref()?.name,
+                    &link.as_ref()?.name,
+                ))
+            })
+            .collect::<Vec<_>>();
+        let mut pairs = Vec::new();
+        for (i, link_name1) in names.iter().enumerate() {
+            for link_name2 in &names[i + 1..] {
+                if !self.is_collision_ignored(link_name1, link_name2)
+                    && !adjacent_pairs.contains(&sorted_pair(link_name1, link_name2))
+                {
+                    pairs.push((link_name1.clone(), link_name2.clone()));
+                }
+            }
+        }
+        pairs
+    }
+
+    /// Calculate the minimum distance between the collision shapes of the links
+    ///
+    /// The pairs of `collision_check_pairs()` are checked by `CollisionShape::distance`
+    /// with the current joint positions. The collisions of the links are converted by
+    /// `CollisionShape::from_geometry` (meshes are skipped), and the shapes added by
+    /// `add_collision_shape` are used too. It returns the distance and the names of the links,
+    /// or `None` if there are no pairs of supported shapes. The distance is negative
+    /// if the shapes are overlapping.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    /// use k::link::*;
+    ///
+    /// let sphere = |name: &str| {
+    ///     let node = NodeBuilder::<f64>::new()
+    ///         .name(name)
+    ///         .translation(Translation3::new(1.0, 0.0, 0.0))
+    ///         .into_node();
+    ///     let collision = Collision::new(
+    ///         name.to_owned(),
+    ///         Isometry3::identity(),
+    ///         Geometry::Sphere { radius: 0.2 },
+    ///     );
+    ///     node.set_link(Some(LinkBuilder::new().name(name).add_collision(collision).finalize()));
+    ///     node
+    /// };
+    /// let l0 = sphere("a");
+    /// let l1 = NodeBuilder::new().name("no_shape").into_node();
+    /// l1.set_link(Some(LinkBuilder::new().name("no_shape").finalize()));
+    /// let l2 = sphere("b");
+    /// l1.set_parent(&l0);
+    /// l2.set_parent(&l1);
+    /// let chain = Chain::from_root(l0);
+    /// // "a" is placed at the origin and "b" is placed at the frame of "no_shape"
+    /// let (distance, _, _) = chain.min_self_distance().unwrap();
+    /// assert!((distance - 0.6).abs() < 1e-6);
+    /// ```
+    pub fn min_self_distance(&self) -> Option<(T, String, String)> {
+        let shapes = self.world_collision_shapes();
+        let find = |name: &str| {
+            shapes
+                .iter()
+                .find(|(link_name, _)| link_name == name)
+                .map(|(_, shapes)| shapes)
+        };
+        let mut min: Option<(T, String, String)> = None;
+        for (link_name1, link_name2) in self.collision_check_pairs() {
+            let (Some(shapes1), Some(shapes2)) = (find(&link_name1), find(&link_name2)) else {
+                continue;
+            };
+            for (shape1, trans1) in shapes1 {
+                for (shape2, trans2) in shapes2 {
+                    let distance = shape1.distance(trans1, shape2, trans2);
+                    if !matches!(&min, Some((d, _, _)) if *d <= distance) {
+                        min = Some((distance, link_name1.clone(), link_name2.clone()));
+                    }
+                }
+            }
+        }
+        min
+    }
+
+    /// The supported collision shapes of each link and their world transforms
+    fn world_collision_shapes(&self) -> Vec<(String, Vec<(CollisionShape<T>, Isometry3<T>)>)> {
+        self.update_link_transforms();
+        self.iter()
+            .filter_map(|node| {
+                let parent_transform = node.parent_world_transform()?;
+                let link = node.link();
+                let link = link.as_ref()?;
+                let mut shapes = link
+                    .collisions
+                    .iter()
+                    .filter_map(|c| {
+                        Some((
+                            CollisionShape::from_geometry(&c.geometry)?,
+                            c.world_transform().clone()?,
+                        ))
+                    })
+                    .collect::<Vec<_>>();
+                shapes.extend(
+                    self.collision_shapes
+                        .iter()
+                        .filter(|(name, _, _)| *name == link.name)
+                        .map(|(_, origin, shape)| {
+                            (shape.clone(), parent_transform.clone() * origin)
+                        }),
+                );
+                Some((link.name.clone(), shapes))
+            })
+            .collect()
+    }
+
+    /// Calculate a sphere which encloses the origins of all joints and the collision shapes
+    ///
+    /// It returns the center and the radius in the world coordinate at the current joint positions.
+    /// The center is the center of the bounding box, so it is not the minimal sphere,
+    /// but it is enough for broad-phase tests. Meshes are handled as points because
+    /// their sizes are unknown.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let chain = k::Chain::<f64>::from_urdf_file("urdf/sample.urdf").unwrap();
+    /// let (center, radius) = chain.bounding_sphere();
+    /// for trans in chain.update_transforms() {
+    ///     let origin = nalgebra::Point3::from(trans.translation.vector);
+    ///     assert!((origin - center).norm() <= radius + 1e-10);
+    /// }
+    /// ```
+    pub fn bounding_sphere(&self) -> (Point3<T>, T) {
+        let mut spheres = self
+            .update_transforms()
+            .into_iter()
+            .map(|trans| (trans.translation.vector, T::zero()))
+            .collect::<Vec<_>>();
+        self.update_link_transforms();
+        for link in self.iter_links() {
+            for collision in &link.collisions {
+                if let Some(trans) = collision.world_transform() {
+                    let radius = collision.geometry.bounding_radius().unwrap_or_else(T::zero);
+                    spheres.push((trans.translation.vector.clone(), radius));
+                }
+            }
+        }
+        for (link_name, origin, shape) in &self.collision_shapes {
+            if let Some(trans) = self
+                .find_link(link_name)
+                .and_then(|node| node.parent_world_transform())
+            {
+                spheres.push(((trans * origin).translation.vector, shape.bounding_radius()));
+            }
+        }
+        let Some((first, _)) = spheres.first() else {
+            return (Point3::origin(), T::zero());
+        };
+        let mut min = first.clone();
+        let mut max = first.clone();
+        for (center, radius) in &spheres {
+            for i in 0..3 {
+                min[i] = min[i].clone().min(center[i].clone() - radius.clone());
+                max[i] = max[i].clone().max(center[i].clone() + radius.clone());
+            }
+        }
+        let center = (min + max) / na::convert::<f64, T>(2.0);
+        let radius = spheres
+            .iter()
+            .map(|(c, r)| (c - &center).norm() + r.clone())
+            .fold(T::zero(), |a, b| a.max(b));
+        (Point3::from(center), radius)
+    }
+}
+
+/// Convex core of a primitive shape and the radius around it
+///
+/// Rounded shapes (spheres and capsules) are handled as a point or a segment with a radius,
+/// which makes their distances exact.
+enum Core<T: RealField> {
+    Point,
+    Segment {
+        half_length: T,
+        axis: Unit<Vector3<T>>,
+    },
+    Box {
+        half_extents: Vector3<T>,
+    },
+    Cylinder {
+        radius: T,
+        half_length: T,
+        axis: Unit<Vector3<T>>,
+    },
+}
+
+impl<T: RealField> Core<T> {
+    fn from_shape(shape: &CollisionShape<T>) -> (Self, T) {
+        match shape {
+            CollisionShape::Sphere { radius } => (Core::Point, radius.clone()),
+            CollisionShape::Capsule {
+                radius,
+                half_height,
+                axis,
+            } => (
+                Core::Segment {
+                    half_length: half_height.clone(),
+                    axis: axis.clone(),
+                },
+                radius.clone(),
+            ),
+            CollisionShape::Box { half_extents } => (
+                Core::Box {
+                    half_extents: half_extents.clone(),
+                },
+                T::zero(),
+            ),
+            CollisionShape::Cylinder {
+                radius,
+                half_height,
+                axis,
+            } => (
+                Core::Cylinder {
+                    radius: radius.clone(),
+                    half_length: half_height.clone(),
+                    axis: axis.clone(),
+                },
+                T::zero(),
+            ),
+        }
+    }
+
+    /// The farthest point in `direction` in the local frame
+    fn local_support(&self, direction: &Vector3<T>) -> Vector3<T> {
+        let signed = |v: &T, d: &T| {
+            if *d >= T::zero() {
+                v.clone()
+            } else {
+                -v.clone()
+            }
+        };
+        match self {
+            Core::Point => Vector3::zeros(),
+            Core::Segment { half_length, axis } => {
+                axis.clone().into_inner() * signed(half_length, &axis.dot(direction))
+            }
+            Core::Box { half_extents } => Vector3::new(
+                signed(&half_extents.x, &direction.x),
+                signed(&half_extents.y, &direction.y),
+                signed(&half_extents.z, &direction.z),
+            ),
+            Core::Cylinder {
+                radius,
+                half_length,
+                axis,
+            } => {
+                let axial = axis.dot(direction);
+                let radial = direction - axis.clone().into_inner() * axial.clone();
+                let radial_norm = radial.norm();
+                let radial = if radial_norm > T::zero() {
+                    radial * (radius.clone() / radial_norm)
+                } else {
+                    Vector3::zeros()
+                };
+                radial + axis.clone().into_inner() * signed(half_length, &axial)
+            }
+        }
+    }
+
+    fn support(&self, pose: &Isometry3<T>, direction: &Vector3<T>) -> Vector3<T> {
+        let local_direction = pose.rotation.inverse_transform_vector(direction);
+        pose.transform_point(&self.local_support(&local_direction).into())
+            .coords
+    }
+}
+
+/// The closest point to the origin in the convex hull of `simplex` (at most 4 points)
+///
+/// The points which are not used to express the closest point are removed from `simplex`.
+fn closest_point_on_simplex<T: RealField>(simplex: &mut Vec<Vector3<T>>) -> Vector3<T> {
+    let n = simplex.len();
+    let mut best: Option<(T, Vector3<T>, usize)> = None;
+    for mask in 1usize..(1 << n) {
+        let points = (0..n)
+            .filter(|i| mask & (1 << i) != 0)
+            .map(|i| simplex[i].clone())
+            .collect::<Vec<_>>();
+        let Some(point) = closest_point_on_affine_hull(&points) else {
+            continue;
+        };
+        let norm = point.norm();
+        if !matches!(&best, Some((d, _, _)) if *d <= norm) {
+            best = Some((norm, point, mask));
+        }
+    }
+    let (_, point, mask) = best.expect("the vertices are always valid");
+    let mut i = 0;
+    simplex.retain(|_| {
+        let keep = mask & (1 << i) != 0;
+        i += 1;
+        keep
+    });
+    point
+}
+
+/// The closest point to the origin in the affine hull of `points`, if it is inside the simplex
+fn closest_point_on_affine_hull<T: RealField>(points: &[Vector3<T>]) -> Option<Vector3<T>> {
+    let base = &points[0];
+    let m = points.len() - 1;
+    if m == 0 {
+        return Some(base.clone());
+    }
+    let edges = DMatrix::from_fn(3, m, |r, c| points[c + 1][r].clone() - base[r].clone());
+    let rhs = -edges.transpose() * DVector::from_column_slice(base.as_slice());
+    let coefficients = (edges.transpose() * &edges).lu().solve(&rhs)?;
+    let sum = coefficients.iter().fold(T::zero(), |s, c| s + c.clone());
+    if coefficients.iter().any(|c| *c < T::zero()) || sum > T::one() {
+        return None;
+    }
+    Some(base + Vector3::from_iterator((edges * coefficients).iter().cloned()))
+}
+
+/// Signed distance between the convex cores by GJK algorithm
+///
+/// It is negative if they are intersecting, and its magnitude is the penetration depth
+/// calculated by `penetration_depth()`.
+fn core_distance<T: RealField>(
+    core1: &Core<T>,
+    pose1: &Isometry3<T>,
+    core2: &Core<T>,
+    pose2: &Isometry3<T>,
+) -> T {
+    const NUM_MAX_ITERATIONS: usize = 64;
+    let eps: T = na::convert(1.0e-10);
+    let support = |direction: &Vector3<T>| {
+        core1.support(pose1, direction) - core2.support(pose2, &-direction)
+    };
+    let mut direction = &pose1.translation.vector - &pose2.translation.vector;
+    if direction.norm() <= eps {
+        direction = Vector3::x();
+    }
+    let mut simplex = vec![support(&-direction)];
+    let mut closest = simplex[0].clone();
+    for _ in 0..NUM_MAX_ITERATIONS {
+        let distance_squared = closest.norm_squared();
+        if distance_squared <= eps.clone() * eps.clone() || simplex.len() == 4 {
+            return -penetration_depth(&support, simplex);
+        }
+        let w = support(&-closest.clone());
+        // no more progress toward the origin
+        if distance_squared.clone() - closest.dot(&w) <= eps.clone() * distance_squared.clone()
+            || simplex.iter().any(|p| (p - &w).norm() <= eps)
+        {
+            break;
+        }
+        simplex.push(w);
+        closest = closest_point_on_simplex(&mut simplex);
+    }
+    closest.norm()
+}
+
+/// Distance from the origin to the boundary of the Minkowski difference by EPA algorithm
+///
+/// `simplex` is the simplex of GJK which contains the origin. It is expanded to
+/// a tetrahedron first, and zero is returned if it can not be, because the Minkowski
+/// difference is flat (for example, two crossing segments) and the origin is on its boundary.
+fn penetration_depth<T: RealField>(
+    support: &impl Fn(&Vector3<T>) -> Vector3<T>,
+    mut simplex: Vec<Vector3<T>>,
+) -> T {
+    const NUM_MAX_ITERATIONS: usize = 64;
+    let eps: T = na::convert(1.0e-10);
+    let axes = [Vector3::x(), Vector3::y(), Vector3::z()];
+    while simplex.len() < 4 {
+        // the directions in which a new point is out of the affine hull of the simplex
+        let directions = match simplex.len() {
+            1 => axes.to_vec(),
+            2 => {
+                let edge = &simplex[1] - &simplex[0];
+                axes.iter().map(|axis| edge.cross(axis)).collect()
+            }
+            _ => vec![(&simplex[1] - &simplex[0]).cross(&(&simplex[2] - &simplex[0]))],
+        };
+        let offset = |p: &Vector3<T>| {
+            let v = p - &simplex[0];
+            match simplex.len() {
+                1 => v.norm(),
+                2 => v.cross(&(&simplex[1] - &simplex[0]).normalize()).norm(),
+                _ => v
+                    .dot(
+                        &(&simplex[1] - &simplex[0])
+                            .cross(&(&simplex[2] - &simplex[0]))
+                            .normalize(),
+                    )
+                    .abs(),
+            }
+        };
+        let point = directions
+            .iter()
+            .filter(|d| d.norm() > eps)
+            .flat_map(|d| [support(d), support(&-d)])
+            .find(|p| offset(p) > eps);
+        let Some(point) = point else {
+            return T::zero();
+        };
+        simplex.push(point);
+    }
+
+    // a point inside the polytope to orient the normals of the faces
+    let center = simplex.iter().fold(Vector3::zeros(), |c, p| c + p) / na::convert::<f64, T>(4.0);
+    let make_face = |points: &[Vector3<T>], indices: [usize; 3]| {
+        let [i, j, k] = indices;
+        let normal = (&points[j] - &points[i]).cross(&(&points[k] - &points[i]));
+        let norm = normal.norm();
+        if norm <= eps {
+            return None;
+        }
+        let mut normal = normal / norm;
+        if normal.dot(&(&points[i] - &center)) < T::zero() {
+            normal = -normal;
+        }
+        let distance = normal.dot(&points[i]);
+        Some((indices, normal, distance))
+    };
+    let mut points = simplex;
+    let mut faces = [[0, 1, 2], [0, 1, 3], [0, 2, 3], [1, 2, 3]]
+        .into_iter()
+        .filter_map(|indices| make_face(&points, indices))
+        .collect::<Vec<_>>();
+    let mut depth = T::zero();
+    for _ in 0..NUM_MAX_ITERATIONS {
+        let Some((_, normal, distance)) = faces
+            .iter()
+            .min_by(|a, b| a.2.partial_cmp(&b.2).unwrap_or(std::cmp::Ordering::Equal))
+            .cloned()
+        else {
+            break;
+        };
+        depth = distance.clone();
+        let w = support(&normal);
+        if normal.dot(&w) - distance <= eps {
+            break;
+        }
+        // remove the faces which are visible from `w`, and keep the edges of the hole
+        let mut horizon: Vec<(usize, usize)> = Vec::new();
+        faces.retain(|([i, j, k], normal, distance)| {
+            if normal.dot(&w) <= *distance {
+                return true;
+            }
+            for (a, b) in [(*i, *j), (*j, *k), (*k, *i)] {
+                if let Some(index) = horizon.iter().position(|e| *e == (a, b) || *e == (b, a)) {
+                    horizon.remove(index);
+                } else {
+                    horizon.push((a, b));
+                }
+            }
+            false
+        });
+        points.push(w);
+        let new_index = points.len() - 1;
+        faces.extend(
+            horizon
+                .into_iter()
+                .filter_map(|(a, b)| make_face(&points, [a, b, new_index])),
+        );
+    }
+    depth.max(T::zero())
+}
+
+impl<T> Geometry<T>
+where
+    T: RealField,
+{
+    /// Radius of the sphere which encloses the shape around its origin
+    ///
+    /// It returns `None` for meshes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::link::Geometry;
+    ///
+    /// let capsule = Geometry::<f64>::Capsule { radius: 0.1, length: 1.0 };
+    /// assert!((capsule.bounding_radius().unwrap() - 0.6).abs() < 1e-10);
+    /// ```
+    pub fn bounding_radius(&self) -> Option<T> {
+        CollisionShape::from_geometry(self).map(|shape| shape.bounding_radius())
+    }
+
+    /// Calculate the signed distance between two shapes placed at `pose` and `other_pose`
+    ///
+    /// The geometries are converted by `CollisionShape::from_geometry` and checked by
+    /// `CollisionShape::distance`, so the distance is negative if the shapes are overlapping.
+    /// It returns `None` if any of them is a mesh.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::link::Geometry;
+    /// use k::{Isometry3, Vector3};
+    ///
+    /// let capsule = Geometry::<f64>::Capsule { radius: 0.1, length: 1.0 };
+    /// let d = capsule.distance(
+    ///     &Isometry3::identity(),
+    ///     &capsule,
+    ///     &Isometry3::translation(0.5, 0.0, 0.0),
+    /// );
+    /// assert!((d.unwrap() - 0.3).abs() < 1e-6);
+    /// ```
+    pub fn distance(
+        &self,
+        pose: &Isometry3<T>,
+        other: &Geometry<T>,
+        other_pose: &Isometry3<T>,
+    ) -> Option<T> {
+        let shape = CollisionShape::from_geometry(self)?;
+        let other_shape = CollisionShape::from_geometry(other)?;
+        Some(shape.distance(pose, &other_shape, other_pose))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::joint::*;
+    use crate::node::*;
+    #[cfg(target_family = "wasm")]
+    use wasm_bindgen_test::wasm_bindgen_test as test;
+
+    /// Serial chain of the links "a", "b" and "c", placed at every 0.3 along X
+    ///
+    /// Each link has a collision of `geometry` at its origin if it is given.
+    fn create_abc_chain(geometry: Option<Geometry<f64>>) -> Chain<f64> {
+        let nodes = ["a", "b", "c"]
+            .iter()
+            .map(|name| {
+                let node = NodeBuilder::<f64>::new()
+                    .name(name)
+                    .translation(na::Translation3::new(0.3, 0.0, 0.0))
+                    .into_node();
+                let mut link = LinkBuilder::new().name(name);
+                if let Some(geometry) = &geometry {
+                    link = link.add_collision(Collision::new(
+                        name.to_string(),
+                        Isometry3::identity(),
+                        geometry.clone(),
+                    ));
+                }
+                node.set_link(Some(link.finalize()));
+                node
+            })
+            .collect::<Vec<_>>();
+        nodes[1].set_parent(&nodes[0]);
+        nodes[2].set_parent(&nodes[1]);
+        Chain::from_root(nodes[0].clone())
+    }
+
+    #[test]
+    fn test_collision_ignore_pairs() {
+        let mut chain = create_abc_chain(Some(Geometry::Sphere { radius: 0.1 }));
+        let pair = |a: &str, b: &str| (a.to_owned(), b.to_owned());
+        // the adjacent links are excluded
+        assert_eq!(chain.collision_check_pairs(), vec![pair("a", "c")]);
+        chain.set_collision_ignore_pairs(&[pair("c", "a")]);
+        assert!(chain.collision_check_pairs().is_empty());
+        assert!(chain.clone().is_collision_ignored("a", "c"));
+        chain.set_collision_ignore_pairs(&[]);
+        assert_eq!(chain.collision_check_pairs().len(), 1);
+    }
+
+    #[test]
+    fn test_capsule_distance() {
+        let capsule = Geometry::<f64>::Capsule {
+            radius: 0.1,
+            length: 1.0,
+        };
+        // parallel, overlapping along the axis
+        let d = capsule
+            .distance(
+                &Isometry3::identity(),
+                &capsule,
+                &Isometry3::translation(0.6, 0.0, 0.3),
+            )
+            .unwrap();
+        assert!((d - 0.4).abs() < 1e-6);
+        // parallel, apart along the axis: distance between the end points
+        let d = capsule
+            .distance(
+                &Isometry3::identity(),
+                &capsule,
+                &Isometry3::translation(0.3, 0.0, 1.4),
+            )
+            .unwrap();
+        assert!((d - (0.5 - 0.2)).abs() < 1e-6);
+        // crossing at right angles
+        let d = capsule
+            .distance(
+                &Isometry3::identity(),
+                &capsule,
+                &Isometry3::new(
+                    Vector3::new(0.5, 0.0, 0.2),
+                    Vector3::x() * std::f64::consts::FRAC_PI_2,
+                ),
+            )
+            .unwrap();
+        assert!((d - 0.3).abs() < 1e-6);
+        // overlapping
+        let d = capsule
+            .distance(
+                &Isometry3::identity(),
+                &capsule,
+                &Isometry3::translation(0.15, 0.0, 0.0),
+            )
+            .unwrap();
+        assert!((d + 0.05).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_box_and_cylinder_distance() {
+        let cube = Geometry::<f64>::Box {
+            depth: 1.0,
+            width: 1.0,
+            height: 1.0,
+        };
+        let sphere = Geometry::Sphere { radius: 0.1 };
+        let d = cube
+            .distance(
+                &Isometry3::identity(),
+                &sphere,
+                &Isometry3::translation(1.0, 0.0, 0.0),
+            )
+            .unwrap();
+        assert!((d - 0.4).abs() < 1e-6);
+        // nearest to the corner
+        let d = cube
+            .distance(
+                &Isometry3::identity(),
+                &sphere,
+                &Isometry3::translation(1.5, 1.5, 1.5),
+            )
+            .unwrap();
+        assert!((d - (3.0f64.sqrt() - 0.1)).abs() < 1e-6);
+        // the edge of the rotated cube to the face of the other cube
+        let rotated = Isometry3::new(
+            Vector3::new(2.0, 0.0, 0.0),
+            Vector3::z() * std::f64::consts::FRAC_PI_4,
+        );
+        let d = cube
+            .distance(&Isometry3::identity(), &cube, &rotated)
+            .unwrap();
+        assert!((d - (1.5 - 0.5f64.sqrt())).abs() < 1e-6);
+        // intersecting, the penetration depth is along X or Y
+        let d = cube
+            .distance(
+                &Isometry3::identity(),
+                &cube,
+                &Isometry3::translation(0.5, 0.5, 0.0),
+            )
+            .unwrap();
+        assert!((d + 0.5).abs() < 1e-6);
+
+        let cylinder = Geometry::Cylinder {
+            radius: 0.2,
+            length: 1.0,
+        };
+        let d = cylinder
+            .distance(
+                &Isometry3::identity(),
+                &sphere,
+                &Isometry3::translation(0.0, 0.6, 0.3),
+            )
+            .unwrap();
+        assert!((d - 0.3).abs() < 1e-4);
+
+        let mesh = Geometry::Mesh {
+            filename: "a.stl".to_owned(),
+            scale: Vector3::new(1.0, 1.0, 1.0),
+        };
+        assert!(mesh
+            .distance(&Isometry3::identity(), &sphere, &Isometry3::identity())
+            .is_none());
+    }
+
+    #[test]
+    fn test_collision_shape_distance() {
+        let axis = Unit::new_normalize(Vector3::new(1.0, 1.0, 0.0));
+        let capsule = CollisionShape::<f64>::Capsule {
+            radius: 0.1,
+            half_height: 0.5,
+            axis,
+        };
+        // parallel, apart by 0.5 perpendicularly to the axis
+        let offset = Vector3::new(-1.0, 1.0, 0.0).normalize() * 0.5 + axis.into_inner() * 0.3;
+        let d = capsule.distance(
+            &Isometry3::identity(),
+            &capsule,
+            &Isometry3::new(offset, Vector3::zeros()),
+        );
+        assert!((d - 0.3).abs() < 1e-6);
+        // parallel, apart along the axis: distance between the end points
+        let d = capsule.distance(
+            &Isometry3::identity(),
+            &capsule,
+            &Isometry3::new(axis.into_inner() * 1.4, Vector3::zeros()),
+        );
+        assert!((d - 0.2).abs() < 1e-6);
+        // the same capsule as the one along Z rotated to the axis
+        let z_capsule = CollisionShape::Capsule {
+            radius: 0.1,
+            half_height: 0.5,
+            axis: Vector3::z_axis(),
+        };
+        let rotation = na::UnitQuaternion::rotation_between(&Vector3::z(), &axis).unwrap();
+        let d = z_capsule.distance(
+            &Isometry3::from_parts(na::Translation3::identity(), rotation),
+            &capsule,
+            &Isometry3::new(offset, Vector3::zeros()),
+        );
+        assert!((d - 0.3).abs() < 1e-6);
+
+        // crossing segments: the depth is the sum of the radii
+        let x_capsule = CollisionShape::Capsule {
+            radius: 0.1,
+            half_height: 0.5,
+            axis: Vector3::x_axis(),
+        };
+        let d = x_capsule.distance(&Isometry3::identity(), &z_capsule, &Isometry3::identity());
+        assert!((d + 0.2).abs() < 1e-6);
+        // the same centers
+        let sphere = CollisionShape::Sphere { radius: 0.1 };
+        let d = sphere.distance(&Isometry3::identity(), &sphere, &Isometry3::identity());
+        assert!((d + 0.2).abs() < 1e-6);
+        // the sphere inside the box is nearest to the face of +X
+        let cube = CollisionShape::Box {
+            half_extents: Vector3::new(0.5, 0.5, 0.5),
+        };
+        let d = cube.distance(
+            &Isometry3::identity(),
+            &sphere,
+            &Isometry3::translation(0.3, 0.1, 0.0),
+        );
+        assert!((d + 0.3).abs() < 1e-6);
+        assert!((cube.bounding_radius() - 0.75f64.sqrt()).abs() < 1e-10);
+        assert!((capsule.bounding_radius() - 0.6).abs() < 1e-10);
+        assert!(CollisionShape::from_geometry(&Geometry::Mesh {
+            filename: "a.stl".to_owned(),
+            scale: Vector3::new(1.0, 1.0, 1.0),
+        })
+        .is_none());
+    }
+
+    #[test]
+    fn test_add_collision_shape() {
+        let mut chain = create_abc_chain(None);
+        assert!(chain.min_self_distance().is_none());
+        // capsules along the links
+        let capsule = CollisionShape::Capsule {
+            radius: 0.05,
+            half_height: 0.1,
+            axis: Vector3::x_axis(),
+        };
+        for name in ["a", "b", "c"] {
+            chain
+                .add_collision_shape(name, Isometry3::identity(), capsule.clone())
+                .unwrap();
+        }
+        assert!(chain
+            .add_collision_shape("d", Isometry3::identity(), capsule.clone())
+            .is_err());
+        // "a" is at 0.0 and "c" is at 0.6 along X
+        let (d, link_name1, link_name2) = chain.min_self_distance().unwrap();
+        assert!((d - 0.3).abs() < 1e-6);
+        assert_eq!((link_name1.as_str(), link_name2.as_str()), ("a", "c"));
+        let (center, radius) = chain.bounding_sphere();
+        // from the end of "a" at -0.15 to the origin of "c" at 0.9
+        assert!((center - Point3::new(0.375, 0.0, 0.0)).norm() < 1e-10);
+        assert!((radius - 0.525).abs() < 1e-10);
+
+        // the shapes are kept by clone
+        let cloned = chain.clone();
+        chain.clear_collision_shapes("c");
+        assert!(chain.min_self_distance().is_none());
+        assert!(cloned.min_self_distance().is_some());
+    }
+
+    #[test]
+    fn test_min_self_distance() {
+        let mut chain = create_abc_chain(Some(Geometry::Capsule {
+            radius: 0.05,
+            length: 0.4,
+        }));
+        // the overlapping adjacent links are not checked
+        let (d, link_name1, link_name2) = chain.min_self_distance().unwrap();
+        assert!((d - 0.5).abs() < 1e-6);
+        assert_eq!((link_name1.as_str(), link_name2.as_str()), ("a", "c"));
+        chain.set_collision_ignore_pairs(&[("a".to_owned(), "c".to_owned())]);
+        assert!(chain.min_self_distance().is_none());
+    }
+
+    #[test]
+    fn test_bounding_sphere() {
+        let l0 = NodeBuilder::<f64>::new()
+            .name("l0")
+            .joint_type(JointType::Rotational {
+                axis: Vector3::z_axis(),
+            })
+            .into_node();
+        let l1 = NodeBuilder::new()
+            .name("l1")
+            .translation(na::Translation3::new(1.0, 0.0, 0.0))
+            .into_node();
+        l1.set_link(Some(
+            LinkBuilder::new()
+                .name("l1")
+                .add_collision(Collision::new(
+                    "l1".to_owned(),
+                    na::Isometry3::identity(),
+                    Geometry::Sphere { radius: 0.5 },
+                ))
+                .finalize(),
+        ));
+        l1.set_parent(&l0);
+        let chain = Chain::from_root(l0);
+        let (center, radius) = chain.bounding_sphere();
+        // the collision of l1 is placed at the frame of its parent joint (the origin),
+        // so the box is from the far side of the sphere to the origin of l1
+        assert!((center - Point3::new(0.25, 0.0, 0.0)).norm() < 1e-10);
+        assert!((radius - 0.75).abs() < 1e-10);
+
+        chain
+            .set_joint_positions(&[std::f64::consts::FRAC_PI_2])
+            .unwrap();
+        let (center, radius) = chain.bounding_sphere();
+        assert!((center - Point3::new(0.0, 0.25, 0.0)).norm() < 1e-10);
+        for trans in chain.update_transforms() {
+            let origin = Point3::from(trans.translation.vector);
+            assert!((origin - center).norm() <= radius + 1e-10);
+        }
+    }
+}
@@ -0,0 +1,304 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+use super::chain::*;
+use super::errors::*;
+use super::telemetry::*;
+use na::{Isometry3, RealField};
+use nalgebra as na;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use simba::scalar::SubsetOf;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+/// Joint positions which are set to `Chain`
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct JointCommand<T> {
+    /// Seconds from the start of the recording
+    pub time: f64,
+    pub positions: Vec<T>,
+}
+
+/// Sequence of the joint commands, in the order of the calls
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CommandLog<T> {
+    pub commands: Vec<JointCommand<T>>,
+}
+
+impl<T> CommandLog<T> {
+    /// Create an empty log
+    pub fn new() -> Self {
+        Self {
+            commands: Vec::new(),
+        }
+    }
+
+    /// Append the command
+    pub fn push(&mut self, time: f64, positions: Vec<T>) {
+        self.commands.push(JointCommand { time, positions });
+    }
+
+    /// Number of the commands
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    /// Returns true if there are no commands
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+}
+
+/// Telemetry which records the changes of the joint positions into `CommandLog`
+///
+/// Set it by `Chain::set_telemetry`, then get the log by `log()`. The positions are
+/// recorded by `Telemetry::on_set_joint_positions`, so only the successful changes are
+/// recorded, with the resulting positions. Use `with_telemetry()` to keep another
+/// telemetry of the chain.
+///
+/// # Examples
+///
+/// ```
+/// use k::*;
+/// use std::sync::Arc;
+///
+/// let mut chain = Chain::<f64>::from_urdf_file("urdf/sample.urdf").unwrap();
+/// let recorder = Arc::new(CommandRecorder::new());
+/// chain.set_telemetry(recorder.clone());
+/// chain.set_joint_positions(&[0.1; 12]).unwrap();
+/// // out of the limits
+/// assert!(chain.set_joint_positions(&[10.0; 12]).is_err());
+/// chain.clear_telemetry();
+///
+/// let log = recorder.log();
+/// assert_eq!(log.len(), 1);
+/// chain.set_joint_positions(&[0.0; 12]).unwrap();
+/// chain.replay(&log).unwrap();
+/// assert_eq!(chain.joint_positions(), vec![0.1; 12]);
+/// ```
+pub struct CommandRecorder<T: RealField> {
+    clock: Box<dyn Fn() -> f64 + Send + Sync>,
+    log: Mutex<CommandLog<T>>,
+    telemetry: Option<Arc<dyn Telemetry<T>>>,
+}
+
+impl<T: RealField> CommandRecorder<T> {
+    /// Start recording, with the seconds from now as the times
+    ///
+    /// `std::time::Instant` is not available on `wasm32-unknown-unknown`,
+    /// so use `with_clock()` there.
+    #[cfg(not(target_family = "wasm"))]
+    pub fn new() -> Self {
+        let start = std::time::Instant::now();
+        Self::with_clock(move || start.elapsed().as_secs_f64())
+    }
+
+    /// Start recording, with the times in seconds which are returned by `clock`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    /// use std::sync::Arc;
+    ///
+    /// let l0 = NodeBuilder::new()
+    ///     .joint_type(JointType::Rotational { axis: Vector3::z_axis() })
+    ///     .into_node();
+    /// let mut chain = Chain::<f64>::from_root(l0);
+    /// // the number of the calls as the time
+    /// let count = AtomicUsize::new(0);
+    /// let recorder = Arc::new(CommandRecorder::with_clock(move || {
+    ///     count.fetch_add(1, Ordering::SeqCst) as f64
+    /// }));
+    /// chain.set_telemetry(recorder.clone());
+    /// chain.set_joint_positions(&[0.1]).unwrap();
+    /// chain.set_joint_positions(&[0.2]).unwrap();
+    /// assert_eq!(recorder.log().commands[1].time, 1.0);
+    /// ```
+    pub fn with_clock<F>(clock: F) -> Self
+    where
+        F: Fn() -> f64 + Send + Sync + 'static,
+    {
+        Self {
+            clock: Box::new(clock),
+            log: Mutex::new(CommandLog::new()),
+            telemetry: None,
+        }
+    }
+
+    /// Forward all the events to `telemetry` too
+    ///
+    /// `Chain` has only one telemetry, so use this to record the commands with another one.
+    pub fn with_telemetry(mut self, telemetry: Arc<dyn Telemetry<T>>) -> Self {
+        self.telemetry = Some(telemetry);
+        self
+    }
+
+    /// Copy of the recorded commands
+    pub fn log(&self) -> CommandLog<T> {
+        self.log.lock().unwrap().clone()
+    }
+
+    /// Clear the recorded commands
+    pub fn clear(&self) {
+        self.log.lock().unwrap().commands.clear();
+    }
+}
+
+#[cfg(not(target_family = "wasm"))]
+impl<T: RealField> Default for CommandRecorder<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: RealField> fmt::Debug for CommandRecorder<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CommandRecorder")
+            .field("log", &self.log)
+            .field("telemetry", &self.telemetry.is_some())
+            .finish()
+    }
+}
+
+impl<T: RealField> Telemetry<T> for CommandRecorder<T> {
+    fn on_fk(&self, transforms: &[Isometry3<T>]) {
+        if let Some(telemetry) = &self.telemetry {
+            telemetry.on_fk(transforms);
+        }
+    }
+
+    fn on_ik_iteration(&self, iteration: usize, position_error: T, rotation_error: T) {
+        if let Some(telemetry) = &self.telemetry {
+            telemetry.on_ik_iteration(iteration, position_error, rotation_error);
+        }
+    }
+
+    fn on_limit_hit(&self, joint_name: &str, position: T) {
+        if let Some(telemetry) = &self.telemetry {
+            telemetry.on_limit_hit(joint_name, position);
+        }
+    }
+
+    fn on_set_joint_positions(&self, positions: &[T]) {
+        let time = (self.clock)();
+        self.log.lock().unwrap().push(time, positions.to_vec());
+        if let Some(telemetry) = &self.telemetry {
+            telemetry.on_set_joint_positions(positions);
+        }
+    }
+}
+
+impl<T> Chain<T>
+where
+    T: RealField + SubsetOf<f64>,
+{
+    /// Apply the commands of `log` by `set_joint_positions()` in order
+    ///
+    /// The times of the commands are not waited. It stops at the first error.
+    /// Only the successful changes are recorded by `CommandRecorder`, so the log
+    /// of this chain reproduces its final positions.
+    pub fn replay(&self, log: &CommandLog<T>) -> Result<(), Error> {
+        for command in &log.commands {
+            self.set_joint_positions(&command.positions)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::urdf::sample_chain;
+    use std::sync::Arc;
+    #[cfg(target_family = "wasm")]
+    use wasm_bindgen_test::wasm_bindgen_test as test;
+
+    #[test]
+    fn test_record_and_replay() {
+        let mut chain = sample_chain();
+        let recorder = Arc::new(CommandRecorder::with_clock(|| 0.0));
+        chain.set_telemetry(recorder.clone());
+        let positions = (0..chain.dof())
+            .map(|i| 0.05 * i as f64 - 0.2)
+            .collect::<Vec<_>>();
+        chain.set_joint_positions(&positions).unwrap();
+        // the failed commands are not recorded
+        assert!(chain
+            .set_joint_positions(&vec![100.0; chain.dof()])
+            .is_err());
+        // the temporary changes are not recorded
+        chain
+            .with_positions(&vec![0.3; chain.dof()], |c| {
+                c.set_joint_positions(&vec![0.4; c.dof()]).unwrap();
+            })
+            .unwrap();
+        chain.mass_matrix(&vec![0.3; chain.dof()]).unwrap();
+        let mut moved = positions.clone();
+        moved[3] = -0.5;
+        chain.set_joint_positions(&moved).unwrap();
+        // the clamped positions
+        chain.set_joint_positions_clamped(&vec![100.0; chain.dof()]);
+        chain.clear_telemetry();
+        let expected = chain.joint_positions();
+
+        let log = recorder.log();
+        assert_eq!(log.len(), 3);
+        assert_eq!(log.commands[2].positions, expected);
+        chain.set_joint_positions(&vec![0.0; chain.dof()]).unwrap();
+        chain.replay(&log).unwrap();
+        assert_eq!(chain.joint_positions(), expected);
+        assert_eq!(recorder.log().len(), 3);
+
+        recorder.clear();
+        assert!(recorder.log().is_empty());
+    }
+
+    #[test]
+    fn test_record_ik() {
+        use crate::ik::*;
+
+        #[derive(Default)]
+        struct Counter(Mutex<usize>);
+        impl Telemetry<f64> for Counter {
+            fn on_ik_iteration(&self, _iteration: usize, _position: f64, _rotation: f64) {
+                *self.0.lock().unwrap() += 1;
+            }
+        }
+
+        let chain = sample_chain();
+        let mut arm = SerialChain::from_end(chain.find("l_wrist_pitch").unwrap());
+        let counter = Arc::new(Counter::default());
+        let recorder =
+            Arc::new(CommandRecorder::with_clock(|| 0.0).with_telemetry(counter.clone()));
+        arm.set_telemetry(recorder.clone());
+        arm.set_joint_positions(&[0.1, 0.2, 0.3, -0.5, 0.4, -0.3])
+            .unwrap();
+        let mut target = arm.end_transform();
+        target.translation.vector.z += 0.05;
+        JacobianIkSolver::default().solve(&arm, &target).unwrap();
+        assert!(arm.is_reachable(&target, 2));
+        // the solution, not the iterations
+        let log = recorder.log();
+        assert_eq!(log.len(), 2);
+        assert_eq!(log.commands[1].positions, arm.joint_positions());
+        // forwarded
+        assert!(*counter.0.lock().unwrap() > 0);
+    }
+}
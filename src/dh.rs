@@ -0,0 +1,324 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+use super::chain::*;
+use super::errors::*;
+use super::funcs::*;
+use super::joint::*;
+use na::{Isometry3, Point3, RealField, Translation3, UnitQuaternion, Vector3};
+use nalgebra as na;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use simba::scalar::SubsetOf;
+
+/// One row of the standard Denavit-Hartenberg parameters
+///
+/// The transform of the row is `Rot_z(theta) * Trans_z(d) * Trans_x(a) * Rot_x(alpha)`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DhRow<T: RealField> {
+    pub a: T,
+    pub alpha: T,
+    pub d: T,
+    pub theta: T,
+}
+
+impl<T> DhRow<T>
+where
+    T: RealField,
+{
+    /// Transform from the previous frame to the frame of this row
+    pub fn transform(&self) -> Isometry3<T> {
+        Isometry3::from_parts(
+            Translation3::new(T::zero(), T::zero(), self.d.clone()),
+            UnitQuaternion::from_axis_angle(&Vector3::z_axis(), self.theta.clone()),
+        ) * Isometry3::from_parts(
+            Translation3::new(self.a.clone(), T::zero(), T::zero()),
+            UnitQuaternion::from_axis_angle(&Vector3::x_axis(), self.alpha.clone()),
+        )
+    }
+}
+
+/// Frame of the DH convention: the origin, x axis and z axis
+struct DhFrame<T: RealField> {
+    origin: Point3<T>,
+    x: Vector3<T>,
+    z: Vector3<T>,
+}
+
+impl<T> DhFrame<T>
+where
+    T: RealField,
+{
+    fn row_to(&self, next: &DhFrame<T>) -> DhRow<T> {
+        let diff = &next.origin - &self.origin;
+        DhRow {
+            a: diff.dot(&next.x),
+            alpha: self
+                .z
+                .cross(&next.z)
+                .dot(&next.x)
+                .atan2(self.z.dot(&next.z)),
+            d: diff.dot(&self.z),
+            theta: self
+                .x
+                .cross(&next.x)
+                .dot(&self.z)
+                .atan2(self.x.dot(&next.x)),
+        }
+    }
+
+    /// Create the next frame whose z axis is the line (`point`, `axis`)
+    fn next_frame(&self, point: &Point3<T>, axis: &Vector3<T>, eps: &T) -> DhFrame<T> {
+        let normal = self.z.cross(axis);
+        let (origin, x) = if normal.norm() < *eps {
+            // parallel: use the common normal which passes through the current origin
+            let foot = point + axis * (&self.origin - point).dot(axis);
+            let x = (&foot - &self.origin)
+                .try_normalize(eps.clone())
+                .unwrap_or_else(|| self.x.clone());
+            (foot, x)
+        } else {
+            let (on_z, on_axis) = closest_points(&self.origin, &self.z, point, axis);
+            let x = (&on_axis - &on_z)
+                .try_normalize(eps.clone())
+                .unwrap_or_else(|| normal.normalize());
+            (on_axis, x)
+        };
+        DhFrame {
+            origin,
+            x,
+            z: axis.clone(),
+        }
+    }
+}
+
+impl<T> Chain<T>
+where
+    T: RealField + SubsetOf<f64>,
+{
+    /// Get the standard Denavit-Hartenberg parameters from the root to `end_joint_name`
+    ///
+    /// The parameters are derived from the current world transforms of the movable joints,
+    /// so `theta` (and `d` of the linear joints) includes the current positions.
+    /// The product of `DhRow::transform()` of the rows is the world transform of the end joint.
+    ///
+    /// The frame 0 of DH is the world frame, so the axis of the first movable joint must be
+    /// the Z axis of the world. The X axis of the end joint must be perpendicular to the axis
+    /// of the last movable joint and intersect it. Otherwise `UnsupportedKinematicsError` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let l0 = NodeBuilder::new()
+    ///     .name("j0")
+    ///     .translation(Translation3::new(0.0, 0.0, 0.5))
+    ///     .joint_type(JointType::Rotational { axis: Vector3::z_axis() })
+    ///     .into_node();
+    /// let l1 = NodeBuilder::new()
+    ///     .name("end")
+    ///     .translation(Translation3::new(1.0, 0.0, 0.0))
+    ///     .into_node();
+    /// l1.set_parent(&l0);
+    /// let chain = Chain::<f64>::from_root(l0);
+    /// chain.set_joint_positions(&[0.3]).unwrap();
+    /// let rows = chain.to_dh_parameters("end").unwrap();
+    /// assert_eq!(rows.len(), 1);
+    /// assert!((rows[0].a - 1.0).abs() < 1e-10);
+    /// assert!((rows[0].d - 0.5).abs() < 1e-10);
+    /// assert!((rows[0].theta - 0.3).abs() < 1e-10);
+    /// ```
+    pub fn to_dh_parameters(&self, end_joint_name: &str) -> Result<Vec<DhRow<T>>, Error> {
+        let end = self
+            .find(end_joint_name)
+            .ok_or_else(|| Error::InvalidJointNameError {
+                joint_name: end_joint_name.to_owned(),
+            })?;
+        self.update_transforms();
+        let mut axes = end
+            .iter_ancestors()
+            .filter(|node| node.joint().is_movable())
+            .map(|node| {
+                let joint = node.joint();
+                let trans = joint.world_transform().unwrap();
+                let axis = match &joint.joint_type {
+                    JointType::Rotational { axis } | JointType::Linear { axis } => {
+                        trans.rotation * axis.clone().into_inner()
+                    }
+                    JointType::Fixed => unreachable!(),
+                };
+                (
+                    joint.name.clone(),
+                    Point3::from(trans.translation.vector),
+                    axis,
+                )
+            })
+            .collect::<Vec<_>>();
+        axes.reverse();
+        let unsupported = |reason: String| Error::UnsupportedKinematicsError { reason };
+        let eps = na::convert::<f64, T>(1.0e-6);
+        let (first_name, first_point, first_axis) = axes
+            .first()
+            .ok_or_else(|| unsupported("no movable joints".to_owned()))?;
+        if (first_axis - Vector3::z()).norm() > eps
+            || first_point.x.clone().hypot(first_point.y.clone()) > eps
+        {
+            return Err(unsupported(format!(
+                "axis of {first_name} is not the Z axis of the world"
+            )));
+        }
+        let mut frame = DhFrame {
+            origin: Point3::origin(),
+            x: Vector3::x(),
+            z: Vector3::z(),
+        };
+        let mut rows = Vec::with_capacity(axes.len());
+        for (_, point, axis) in axes.iter().skip(1) {
+            let next = frame.next_frame(point, axis, &eps);
+            rows.push(frame.row_to(&next));
+            frame = next;
+        }
+        let end_trans = end.world_transform().unwrap();
+        let end_frame = DhFrame {
+            origin: Point3::from(end_trans.translation.vector),
+            x: &end_trans.rotation * Vector3::x(),
+            z: end_trans.rotation * Vector3::z(),
+        };
+        let to_end = &end_frame.origin - &frame.origin;
+        let off_axis = &to_end - &frame.z * to_end.dot(&frame.z);
+        if end_frame.x.dot(&frame.z).abs() > eps
+            || (&off_axis - &end_frame.x * off_axis.dot(&end_frame.x)).norm() > eps
+        {
+            return Err(unsupported(format!(
+                "X axis of {end_joint_name} does not intersect the last joint axis perpendicularly"
+            )));
+        }
+        rows.push(frame.row_to(&end_frame));
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::*;
+    #[cfg(target_family = "wasm")]
+    use wasm_bindgen_test::wasm_bindgen_test as test;
+
+    /// Build a chain from (a, alpha, d) rows of a DH-compatible arm
+    fn create_dh_chain(rows: &[(f64, f64, f64)]) -> Chain<f64> {
+        let mut nodes: Vec<Node<f64>> = Vec::new();
+        let mut prev: Option<(f64, f64)> = None;
+        for (i, &(a, alpha, d)) in rows.iter().enumerate() {
+            let origin = match prev {
+                Some((a, alpha)) => Isometry3::from_parts(
+                    Translation3::new(a, 0.0, 0.0),
+                    UnitQuaternion::from_axis_angle(&Vector3::x_axis(), alpha),
+                ),
+                None => Isometry3::identity(),
+            } * Translation3::new(0.0, 0.0, d);
+            let node = NodeBuilder::new()
+                .name(&format!("j{i}"))
+                .origin(origin)
+                .joint_type(JointType::Rotational {
+                    axis: Vector3::z_axis(),
+                })
+                .into_node();
+            if let Some(parent) = nodes.last() {
+                node.set_parent(parent);
+            }
+            nodes.push(node);
+            prev = Some((a, alpha));
+        }
+        let (a, alpha) = prev.unwrap();
+        let end = NodeBuilder::new()
+            .name("end")
+            .origin(Isometry3::from_parts(
+                Translation3::new(a, 0.0, 0.0),
+                UnitQuaternion::from_axis_angle(&Vector3::x_axis(), alpha),
+            ))
+            .into_node();
+        end.set_parent(nodes.last().unwrap());
+        Chain::from_root(nodes[0].clone())
+    }
+
+    #[test]
+    fn test_to_dh_parameters() {
+        // PUMA 560 like
+        let params = [
+            (0.0, std::f64::consts::FRAC_PI_2, 0.6),
+            (0.43, 0.0, 0.0),
+            (0.02, -std::f64::consts::FRAC_PI_2, 0.15),
+            (0.0, std::f64::consts::FRAC_PI_2, 0.43),
+            (0.0, -std::f64::consts::FRAC_PI_2, 0.0),
+            (0.0, 0.0, 0.1),
+        ];
+        let chain = create_dh_chain(&params);
+        for positions in [
+            [0.0; 6],
+            [0.1, -0.5, 0.8, 1.2, -0.3, 2.0],
+            [-2.0, 1.0, -1.5, 0.2, 0.9, -0.7],
+        ] {
+            chain.set_joint_positions(&positions).unwrap();
+            let rows = chain.to_dh_parameters("end").unwrap();
+            assert_eq!(rows.len(), 6);
+            let fk = rows
+                .iter()
+                .fold(Isometry3::identity(), |trans, row| trans * row.transform());
+            let end = chain.find("end").unwrap().world_transform().unwrap();
+            assert!((fk.to_homogeneous() - end.to_homogeneous()).norm() < 1e-9);
+            for (row, (a, _, d)) in rows.iter().zip(params.iter()) {
+                assert!((row.a.abs() - a).abs() < 1e-9);
+                assert!((row.d.abs() - d).abs() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn test_to_dh_parameters_unsupported() {
+        let chain = create_dh_chain(&[(0.5, 0.0, 0.0), (0.5, 0.0, 0.0)]);
+        assert!(chain.to_dh_parameters("end").is_ok());
+        assert!(matches!(
+            chain.to_dh_parameters("no_exist"),
+            Err(Error::InvalidJointNameError { .. })
+        ));
+        // the first axis is not the Z axis
+        let root = NodeBuilder::new()
+            .joint_type(JointType::Rotational {
+                axis: Vector3::x_axis(),
+            })
+            .into_node();
+        chain.find("j0").unwrap().set_parent(&root);
+        let chain = Chain::from_root(root);
+        assert!(matches!(
+            chain.to_dh_parameters("end"),
+            Err(Error::UnsupportedKinematicsError { .. })
+        ));
+        // the end frame is offset along Y
+        let chain = create_dh_chain(&[(0.5, 0.0, 0.0)]);
+        let tool = NodeBuilder::new()
+            .name("tool")
+            .translation(Translation3::new(0.0, 0.2, 0.0))
+            .into_node();
+        tool.set_parent(chain.find("end").unwrap());
+        let chain = Chain::from_root(chain.find("j0").unwrap().clone());
+        assert!(matches!(
+            chain.to_dh_parameters("tool"),
+            Err(Error::UnsupportedKinematicsError { .. })
+        ));
+    }
+}
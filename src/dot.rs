@@ -0,0 +1,129 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+
+use super::chain::*;
+use super::joint::*;
+use na::RealField;
+use nalgebra as na;
+use simba::scalar::SubsetOf;
+use std::fmt::Write;
+
+/// Escape `"` and `\` for a quoted string of DOT
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn joint_type_name<T: RealField>(joint_type: &JointType<T>) -> &'static str {
+    match joint_type {
+        JointType::Fixed => "fixed",
+        JointType::Rotational { .. } => "rotational",
+        JointType::Linear { .. } => "linear",
+    }
+}
+
+impl<T> Chain<T>
+where
+    T: RealField + SubsetOf<f64>,
+{
+    /// Export the structure of the chain as [Graphviz](https://graphviz.org/) DOT
+    ///
+    /// Each node of the chain is a node of the graph, labeled with the name of the link
+    /// (the name of the joint if it has no link) and the type of the joint.
+    /// Each node which has a parent in the chain has an edge from the parent,
+    /// labeled with the name of the joint.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let chain = k::Chain::<f64>::from_urdf_file("urdf/sample.urdf").unwrap();
+    /// let dot = chain.to_dot();
+    /// assert!(dot.starts_with("digraph"));
+    /// assert!(dot.contains("label=\"l_shoulder_yaw\""));
+    /// // an edge for each node except the root
+    /// assert_eq!(dot.matches(" -> ").count(), chain.iter().count() - 1);
+    /// assert!(dot.ends_with("}\n"));
+    /// ```
+    pub fn to_dot(&self) -> String {
+        let nodes = self.iter().collect::<Vec<_>>();
+        let mut dot = String::from("digraph {\n");
+        for (i, node) in nodes.iter().enumerate() {
+            let link_name = node.link().as_ref().map(|link| link.name.clone());
+            let joint = node.joint();
+            let name = link_name.unwrap_or_else(|| joint.name.clone());
+            writeln!(
+                dot,
+                "  n{i} [label=\"{}\\n({})\"];",
+                escape(&name),
+                joint_type_name(&joint.joint_type)
+            )
+            .unwrap();
+        }
+        for (i, node) in nodes.iter().enumerate() {
+            let Some(parent) = node.parent() else {
+                continue;
+            };
+            let Some(parent_index) = nodes.iter().position(|n| **n == parent) else {
+                continue;
+            };
+            writeln!(
+                dot,
+                "  n{parent_index} -> n{i} [label=\"{}\"];",
+                escape(&node.joint().name)
+            )
+            .unwrap();
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::link::*;
+    use crate::node::*;
+    use crate::urdf::sample_chain;
+    #[cfg(target_family = "wasm")]
+    use wasm_bindgen_test::wasm_bindgen_test as test;
+
+    #[test]
+    fn test_to_dot() {
+        let chain = sample_chain();
+        let dot = chain.to_dot();
+        let num_links = chain.iter_links().count();
+        assert_eq!(num_links, 13);
+        assert_eq!(dot.matches("[label=\"").count(), 13 + 12);
+        assert_eq!(dot.matches(" -> ").count(), num_links - 1);
+        assert!(dot.contains("label=\"l_wrist2\\n(rotational)\""));
+        assert!(dot.contains("label=\"l_wrist_pitch\""));
+        assert!(dot.trim_end().ends_with('}'));
+
+        // a sub chain does not have the edge to the outside
+        let arm = Chain::from_end(chain.find("l_wrist_pitch").unwrap());
+        let sub_dot = Chain::from_end_to_root(
+            chain.find("l_wrist_pitch").unwrap(),
+            chain.find("l_elbow_pitch").unwrap(),
+        )
+        .to_dot();
+        assert_eq!(sub_dot.matches(" -> ").count(), 2);
+        assert_eq!(arm.to_dot().matches(" -> ").count(), 6);
+
+        let node = NodeBuilder::<f64>::new().name("j").into_node();
+        node.set_link(Some(LinkBuilder::new().name("a\"b").finalize()));
+        let dot = Chain::from_root(node).to_dot();
+        assert!(dot.contains("n0 [label=\"a\\\"b\\n(fixed)\"];"));
+    }
+}
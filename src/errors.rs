@@ -80,4 +80,18 @@ pub enum Error {
     PreconditionError { dof: usize, necessary_dof: usize },
     #[error("There is no valid joint named {}", joint_name)]
     InvalidJointNameError { joint_name: String },
+    #[error("There is no link named {}", link_name)]
+    InvalidLinkNameError { link_name: String },
+    #[error("joint {} is specified more than once", joint_name)]
+    DuplicateJointNameError { joint_name: String },
+    #[error("joint {} is not a rotational joint", joint_name)]
+    NotRotationalJointError { joint_name: String },
+    #[error("invalid tree structure: {}", reason)]
+    InvalidTreeError { reason: String },
+    #[error("unsupported kinematics: {}", reason)]
+    UnsupportedKinematicsError { reason: String },
+    #[error("waypoint {} is not reachable: {}", index, source)]
+    UnreachableWaypointError { index: usize, source: Box<Error> },
+    #[error("invalid parameter {}: {}", name, reason)]
+    InvalidParameterError { name: String, reason: String },
 }
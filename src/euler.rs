@@ -0,0 +1,154 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+use na::{Matrix3, RealField, UnitQuaternion, Vector3};
+use nalgebra as na;
+
+/// Convention of the Euler angles
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EulerConvention {
+    /// Roll, pitch and yaw: `R = Rz(yaw) * Ry(pitch) * Rx(roll)`, the same as
+    /// `UnitQuaternion::euler_angles()`. The angles are `(roll, pitch, yaw)`.
+    RollPitchYaw,
+    /// `R = Rz(a) * Ry(b) * Rz(c)`. The angles are `(a, b, c)` and `b` is in `[0, pi]`.
+    Zyz,
+}
+
+impl EulerConvention {
+    /// Extract the Euler angles of `rotation`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let rotation = UnitQuaternion::from_euler_angles(0.1, 0.2, 0.3);
+    /// let angles = EulerConvention::RollPitchYaw.angles(&rotation);
+    /// assert!((angles - Vector3::new(0.1, 0.2, 0.3)).norm() < 1e-10);
+    /// ```
+    pub fn angles<T: RealField>(&self, rotation: &UnitQuaternion<T>) -> Vector3<T> {
+        match self {
+            EulerConvention::RollPitchYaw => {
+                let (roll, pitch, yaw) = rotation.euler_angles();
+                Vector3::new(roll, pitch, yaw)
+            }
+            EulerConvention::Zyz => {
+                let r = rotation.clone().to_rotation_matrix().into_inner();
+                let b = (r[(0, 2)].clone() * r[(0, 2)].clone()
+                    + r[(1, 2)].clone() * r[(1, 2)].clone())
+                .sqrt()
+                .atan2(r[(2, 2)].clone());
+                if b.clone().sin().abs() < T::default_epsilon() {
+                    // gimbal lock: only a + c (or a - c) is determined
+                    let a = r[(1, 0)].clone().atan2(r[(0, 0)].clone());
+                    return Vector3::new(a, b, T::zero());
+                }
+                let a = r[(1, 2)].clone().atan2(r[(0, 2)].clone());
+                let c = r[(2, 1)].clone().atan2(-r[(2, 0)].clone());
+                Vector3::new(a, b, c)
+            }
+        }
+    }
+
+    /// Matrix `E` which maps the rates of the Euler `angles` to the angular velocity
+    ///
+    /// The angular velocity in the world coordinate is `E * d(angles)/dt`.
+    /// It returns `None` at the representation singularity (gimbal lock),
+    /// where `E` cannot be inverted.
+    pub fn rate_matrix<T: RealField>(&self, angles: &Vector3<T>) -> Option<Matrix3<T>> {
+        let matrix = match self {
+            EulerConvention::RollPitchYaw => {
+                let (sp, cp) = angles[1].clone().sin_cos();
+                let (sy, cy) = angles[2].clone().sin_cos();
+                Matrix3::new(
+                    cp.clone() * cy.clone(),
+                    -sy.clone(),
+                    T::zero(),
+                    cp * sy,
+                    cy,
+                    T::zero(),
+                    -sp,
+                    T::zero(),
+                    T::one(),
+                )
+            }
+            EulerConvention::Zyz => {
+                let (sa, ca) = angles[0].clone().sin_cos();
+                let (sb, cb) = angles[1].clone().sin_cos();
+                Matrix3::new(
+                    T::zero(),
+                    -sa.clone(),
+                    ca.clone() * sb.clone(),
+                    T::zero(),
+                    ca,
+                    sa * sb,
+                    T::one(),
+                    T::zero(),
+                    cb,
+                )
+            }
+        };
+        if matrix.determinant().abs() < T::default_epsilon().sqrt() {
+            None
+        } else {
+            Some(matrix)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(target_family = "wasm")]
+    use wasm_bindgen_test::wasm_bindgen_test as test;
+
+    #[test]
+    fn test_euler_angles() {
+        let a = 0.3;
+        let b = 1.1;
+        let c = -0.7;
+        let rotation = UnitQuaternion::from_axis_angle(&Vector3::z_axis(), a)
+            * UnitQuaternion::from_axis_angle(&Vector3::y_axis(), b)
+            * UnitQuaternion::from_axis_angle(&Vector3::z_axis(), c);
+        let angles = EulerConvention::Zyz.angles(&rotation);
+        assert!((angles - Vector3::new(a, b, c)).norm() < 1e-10);
+
+        // the angular velocity by the finite difference
+        for convention in [EulerConvention::RollPitchYaw, EulerConvention::Zyz] {
+            let angles = convention.angles(&rotation);
+            let rates = Vector3::new(0.2, -0.5, 0.3);
+            let dt = 1.0e-6;
+            let from_angles = |angles: &Vector3<f64>| match convention {
+                EulerConvention::RollPitchYaw => {
+                    UnitQuaternion::from_euler_angles(angles[0], angles[1], angles[2])
+                }
+                EulerConvention::Zyz => {
+                    UnitQuaternion::from_axis_angle(&Vector3::z_axis(), angles[0])
+                        * UnitQuaternion::from_axis_angle(&Vector3::y_axis(), angles[1])
+                        * UnitQuaternion::from_axis_angle(&Vector3::z_axis(), angles[2])
+                }
+            };
+            let next = from_angles(&(angles + rates * dt));
+            let omega = (next * from_angles(&angles).inverse()).scaled_axis() / dt;
+            let matrix = convention.rate_matrix(&angles).unwrap();
+            assert!((matrix * rates - omega).norm() < 1e-5);
+        }
+        let locked = Vector3::new(0.1, std::f64::consts::FRAC_PI_2, 0.2);
+        assert!(EulerConvention::RollPitchYaw.rate_matrix(&locked).is_none());
+        assert!(EulerConvention::Zyz
+            .rate_matrix(&Vector3::new(0.1, 0.0, 0.2))
+            .is_none());
+    }
+}
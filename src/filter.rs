@@ -0,0 +1,220 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+
+use super::chain::*;
+use super::errors::*;
+use super::funcs::*;
+use super::joint::*;
+use na::RealField;
+use nalgebra as na;
+use simba::scalar::SubsetOf;
+
+/// Low pass filter of the joint positions (exponential moving average)
+///
+/// It smooths successive joint positions, for example the outputs of IK.
+/// The output is `y += alpha * (x - y)` for each input `x`, so `alpha = 1` means no filtering
+/// and smaller `alpha` makes the output smoother and slower. `alpha` should be in `(0, 1]`.
+/// For the wrapped (continuous) joints, the difference is taken as the shortest angle
+/// and the output is wrapped into `(-pi, pi]`, so the output does not jump at `±pi`.
+///
+/// # Examples
+///
+/// ```
+/// let mut filter = k::JointFilter::new(0.5, 2);
+/// assert_eq!(filter.filter(&[1.0, 2.0]).unwrap(), vec![1.0, 2.0]);
+/// assert_eq!(filter.filter(&[3.0, 2.0]).unwrap(), vec![2.0, 2.0]);
+/// filter.reset();
+/// assert_eq!(filter.filter(&[3.0, 2.0]).unwrap(), vec![3.0, 2.0]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct JointFilter<T: RealField> {
+    alpha: T,
+    wrapped: Vec<bool>,
+    state: Option<Vec<T>>,
+}
+
+impl<T> JointFilter<T>
+where
+    T: RealField + SubsetOf<f64>,
+{
+    /// Create a filter for `dof` joints without wrapped joints
+    pub fn new(alpha: T, dof: usize) -> Self {
+        Self {
+            alpha,
+            wrapped: vec![false; dof],
+            state: None,
+        }
+    }
+
+    /// Create a filter for the movable joints of `chain`
+    ///
+    /// The rotational joints without limits (continuous joints of URDF) are wrapped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let l0 = NodeBuilder::new()
+    ///     .joint_type(JointType::Rotational { axis: Vector3::z_axis() })
+    ///     .into_node();
+    /// let chain = Chain::<f64>::from_root(l0);
+    /// let mut filter = JointFilter::from_chain(&chain, 0.5);
+    /// filter.filter(&[3.0]).unwrap();
+    /// // -3.0 is close to 3.0 across pi
+    /// let filtered = filter.filter(&[-3.0]).unwrap();
+    /// assert!(filtered[0].abs() > 3.0);
+    /// ```
+    pub fn from_chain(chain: &Chain<T>, alpha: T) -> Self {
+        let wrapped = chain
+            .iter_joints()
+            .map(|joint| {
+                matches!(joint.joint_type, JointType::Rotational { .. }) && joint.limits.is_none()
+            })
+            .collect();
+        Self {
+            alpha,
+            wrapped,
+            state: None,
+        }
+    }
+
+    /// Set which joints are wrapped at `±pi`
+    ///
+    /// The length must be the same as the number of the joints.
+    pub fn set_wrapped_joints(&mut self, wrapped: &[bool]) -> Result<(), Error> {
+        if wrapped.len() != self.wrapped.len() {
+            return Err(Error::SizeMismatchError {
+                input: wrapped.len(),
+                required: self.wrapped.len(),
+            });
+        }
+        self.wrapped = wrapped.to_vec();
+        Ok(())
+    }
+
+    /// Smoothing factor
+    pub fn alpha(&self) -> T {
+        self.alpha.clone()
+    }
+
+    /// Set the smoothing factor
+    pub fn set_alpha(&mut self, alpha: T) {
+        self.alpha = alpha;
+    }
+
+    /// Filter the next `positions` and returns the smoothed positions
+    ///
+    /// The first input after `new()` or `reset()` is returned as it is.
+    pub fn filter(&mut self, positions: &[T]) -> Result<Vec<T>, Error> {
+        if positions.len() != self.wrapped.len() {
+            return Err(Error::SizeMismatchError {
+                input: positions.len(),
+                required: self.wrapped.len(),
+            });
+        }
+        let output = match &self.state {
+            None => positions
+                .iter()
+                .zip(self.wrapped.iter())
+                .map(|(position, wrapped)| {
+                    if *wrapped {
+                        wrap_angle(position.clone())
+                    } else {
+                        position.clone()
+                    }
+                })
+                .collect::<Vec<_>>(),
+            Some(state) => state
+                .iter()
+                .zip(positions.iter())
+                .zip(self.wrapped.iter())
+                .map(|((prev, position), wrapped)| {
+                    if *wrapped {
+                        let diff = wrap_angle(position.clone() - prev.clone());
+                        wrap_angle(prev.clone() + self.alpha.clone() * diff)
+                    } else {
+                        prev.clone() + self.alpha.clone() * (position.clone() - prev.clone())
+                    }
+                })
+                .collect(),
+        };
+        self.state = Some(output.clone());
+        Ok(output)
+    }
+
+    /// Clear the state, then the next input is not smoothed
+    pub fn reset(&mut self) {
+        self.state = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(target_family = "wasm")]
+    use wasm_bindgen_test::wasm_bindgen_test as test;
+
+    fn total_variation(values: &[f64]) -> f64 {
+        values.windows(2).map(|w| (w[1] - w[0]).abs()).sum()
+    }
+
+    #[test]
+    fn test_joint_filter_smooths() {
+        let mut filter = JointFilter::new(0.2, 1);
+        let noisy = (0..100)
+            .map(|i| 0.5 + 0.001 * i as f64 + if i % 2 == 0 { 0.05 } else { -0.05 })
+            .collect::<Vec<_>>();
+        let filtered = noisy
+            .iter()
+            .map(|q| filter.filter(&[*q]).unwrap()[0])
+            .collect::<Vec<_>>();
+        assert!(total_variation(&filtered) < total_variation(&noisy) * 0.5);
+        // it follows the trend
+        assert!((filtered.last().unwrap() - noisy.last().unwrap()).abs() < 0.1);
+        assert!(filter.filter(&[0.0, 0.0]).is_err());
+    }
+
+    #[test]
+    fn test_joint_filter_wrapping() {
+        let pi = std::f64::consts::PI;
+        let mut filter = JointFilter::new(0.5, 2);
+        filter.set_wrapped_joints(&[true, false]).unwrap();
+        assert!(filter.set_wrapped_joints(&[true]).is_err());
+        let inputs = [
+            [pi - 0.05, 1.0],
+            [-pi + 0.05, 1.0],
+            [pi - 0.03, 1.0],
+            [-pi + 0.02, 1.0],
+        ];
+        let mut prev: Option<Vec<f64>> = None;
+        for input in inputs.iter() {
+            let output = filter.filter(input).unwrap();
+            assert!(output[0] > -pi && output[0] <= pi);
+            // near ±pi, not near 0
+            assert!(output[0].abs() > pi - 0.06);
+            if let Some(prev) = prev {
+                assert!(wrap_angle(output[0] - prev[0]).abs() < 0.1);
+            }
+            prev = Some(output);
+        }
+
+        // without wrapping, it goes through 0
+        let mut filter = JointFilter::new(0.5, 1);
+        filter.filter(&[pi - 0.05]).unwrap();
+        assert!(filter.filter(&[-pi + 0.05]).unwrap()[0].abs() < 0.1);
+    }
+}
@@ -0,0 +1,115 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+use crate::errors::*;
+use na::{Isometry3, RealField};
+use nalgebra as na;
+use simba::scalar::SubsetOf;
+use std::collections::VecDeque;
+
+/// LRU cache of forward kinematics keyed by quantized joint positions
+///
+/// The key also has the revision of the origins, see `key()`.
+#[derive(Debug, Clone)]
+pub(crate) struct TransformCache<T: RealField> {
+    capacity: usize,
+    resolution: f64,
+    /// the most recently used entry is at the front
+    entries: VecDeque<(Vec<i64>, Isometry3<T>)>,
+    hits: usize,
+    misses: usize,
+}
+
+impl<T> TransformCache<T>
+where
+    T: RealField + SubsetOf<f64>,
+{
+    pub(crate) fn new(capacity: usize, resolution: T) -> Result<Self, Error> {
+        let resolution: f64 = na::try_convert(resolution).unwrap_or_default();
+        // NaN is rejected too
+        if !(resolution > 0.0 && resolution.is_finite()) {
+            return Err(Error::InvalidParameterError {
+                name: "resolution".to_owned(),
+                reason: format!("must be positive, but {resolution}"),
+            });
+        }
+        Ok(Self {
+            capacity,
+            resolution,
+            entries: VecDeque::with_capacity(capacity),
+            hits: 0,
+            misses: 0,
+        })
+    }
+
+    /// Create an empty cache with the same settings
+    pub(crate) fn cleared(&self) -> Self {
+        Self {
+            entries: VecDeque::with_capacity(self.capacity),
+            hits: 0,
+            misses: 0,
+            ..self.clone()
+        }
+    }
+
+    /// The key of the quantized positions and `revision`
+    ///
+    /// `revision` must be changed when the transform is changed by anything other than
+    /// the positions, like the origins of the joints.
+    pub(crate) fn key(&self, positions: &[T], revision: usize) -> Vec<i64> {
+        positions
+            .iter()
+            .map(|p| {
+                let p: f64 = na::try_convert(p.clone()).unwrap_or_default();
+                (p / self.resolution).round() as i64
+            })
+            .chain([revision as i64])
+            .collect()
+    }
+
+    pub(crate) fn get(&mut self, key: &[i64]) -> Option<Isometry3<T>> {
+        match self.entries.iter().position(|(k, _)| k == key) {
+            Some(index) => {
+                self.hits += 1;
+                let entry = self.entries.remove(index).unwrap();
+                let trans = entry.1.clone();
+                self.entries.push_front(entry);
+                Some(trans)
+            }
+            None => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    pub(crate) fn insert(&mut self, key: Vec<i64>, trans: Isometry3<T>) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() >= self.capacity {
+            self.entries.pop_back();
+        }
+        self.entries.push_front((key, trans));
+    }
+
+    pub(crate) fn hits(&self) -> usize {
+        self.hits
+    }
+
+    pub(crate) fn misses(&self) -> usize {
+        self.misses
+    }
+}
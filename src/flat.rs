@@ -0,0 +1,494 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+use super::chain::*;
+use super::errors::*;
+use super::joint::*;
+use na::{Isometry3, RealField};
+use nalgebra as na;
+use simba::scalar::SubsetOf;
+use std::sync::Arc;
+
+/// Index based kinematic tree for fast forward kinematics
+///
+/// All joints are stored in a `Vec` in the same order as `Chain::iter()`,
+/// with the indices of their parents. Parents always come before their children.
+/// The topology is fixed, but the joint positions can be changed.
+///
+/// # Examples
+///
+/// ```
+/// use k::*;
+///
+/// let l0 = NodeBuilder::new()
+///     .translation(Translation3::new(0.0, 0.0, 0.1))
+///     .joint_type(JointType::Rotational { axis: Vector3::y_axis() })
+///     .into_node();
+/// let l1 = NodeBuilder::new()
+///     .translation(Translation3::new(0.0, 0.0, 0.5))
+///     .joint_type(JointType::Linear { axis: Vector3::z_axis() })
+///     .into_node();
+/// l1.set_parent(&l0);
+/// let chain = Chain::<f64>::from_root(l0);
+/// let mut flat = chain.to_flat();
+/// flat.set_joint_positions(&[0.0, 0.2]).unwrap();
+/// let transforms = flat.update_transforms();
+/// assert!((transforms[1].translation.vector.z - 0.8).abs() < 1e-10);
+/// ```
+#[derive(Debug, Clone)]
+pub struct FlatChain<T: RealField> {
+    joints: Vec<Joint<T>>,
+    parents: Vec<Option<usize>>,
+    mimics: Vec<Option<(usize, MimicMapping<T>)>>,
+    movable_indices: Vec<usize>,
+}
+
+impl<T> FlatChain<T>
+where
+    T: RealField + SubsetOf<f64>,
+{
+    /// Iterate for all joints
+    ///
+    /// The order is the same as `Chain::iter()`.
+    pub fn iter(&self) -> impl Iterator<Item = &Joint<T>> {
+        self.joints.iter()
+    }
+
+    /// Iterate for movable joints
+    pub fn iter_joints(&self) -> impl Iterator<Item = &Joint<T>> {
+        self.movable_indices.iter().map(move |i| &self.joints[*i])
+    }
+
+    /// Indices of the parents of the joints, `None` for the root
+    pub fn parents(&self) -> &[Option<usize>] {
+        &self.parents
+    }
+
+    /// Calculate the degree of freedom
+    pub fn dof(&self) -> usize {
+        self.movable_indices.len()
+    }
+
+    /// Find the index of the joint by name
+    pub fn find(&self, joint_name: &str) -> Option<usize> {
+        self.joints
+            .iter()
+            .position(|joint| joint.name == joint_name)
+    }
+
+    /// Get the positions of the joints
+    ///
+    /// `FixedJoint` is ignored. the length is the same with `dof()`
+    pub fn joint_positions(&self) -> Vec<T> {
+        self.iter_joints()
+            .map(|joint| {
+                joint
+                    .joint_position()
+                    .expect("Must be a bug: movable joint must have position")
+            })
+            .collect()
+    }
+
+    /// Set the positions of the joints
+    ///
+    /// `FixedJoints` are ignored. the input number must be equal with `dof()`.
+    /// The positions for the mimic joints are ignored as `Chain::set_joint_positions`.
+    pub fn set_joint_positions(&mut self, positions_vec: &[T]) -> Result<(), Error> {
+        if positions_vec.len() != self.dof() {
+            return Err(Error::SizeMismatchError {
+                input: positions_vec.len(),
+                required: self.dof(),
+            });
+        }
+        for (i, position) in self.movable_indices.iter().zip(positions_vec.iter()) {
+            if self.mimics[*i].is_none() {
+                self.joints[*i].set_joint_position(position.clone())?;
+            }
+        }
+        self.update_mimics()
+    }
+
+    /// Fast, but without check, dangerous `set_joint_positions`
+    ///
+    /// The mimic joints are updated as `set_joint_positions`, without the limits.
+    pub fn set_joint_positions_unchecked(&mut self, positions_vec: &[T]) {
+        for (i, position) in self.movable_indices.iter().zip(positions_vec.iter()) {
+            if self.mimics[*i].is_none() {
+                self.joints[*i].set_joint_position_unchecked(position.clone());
+            }
+        }
+        for i in 0..self.joints.len() {
+            if let Some(position) = self.mimic_position(i) {
+                self.joints[i].set_joint_position_unchecked(position);
+            }
+        }
+    }
+
+    fn update_mimics(&mut self) -> Result<(), Error> {
+        for i in 0..self.joints.len() {
+            if let Some(position) = self.mimic_position(i) {
+                self.joints[i].set_joint_position(position)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The position of the `i`-th joint from its mimic parent, if it is a mimic joint
+    fn mimic_position(&self, i: usize) -> Option<T> {
+        let (parent, mimic) = self.mimics[i].as_ref()?;
+        let from = self.joints[*parent]
+            .joint_position()
+            .expect("mimic parent must be movable");
+        Some(mimic.mimic_position(from))
+    }
+
+    /// Calculate the world transforms of all joints
+    ///
+    /// The order is the same as `iter()`.
+    pub fn update_transforms(&self) -> Vec<Isometry3<T>> {
+        let mut transforms: Vec<Isometry3<T>> = Vec::with_capacity(self.joints.len());
+        for (joint, parent) in self.joints.iter().zip(self.parents.iter()) {
+            let trans = match parent {
+                Some(p) => transforms[*p].clone() * joint.local_transform(),
+                None => joint.local_transform(),
+            };
+            transforms.push(trans);
+        }
+        transforms
+    }
+}
+
+impl<T> Chain<T>
+where
+    T: RealField + SubsetOf<f64>,
+{
+    /// Create `FlatChain` which has the same joints and positions with this `Chain`
+    ///
+    /// The links are not copied.
+    /// Parents which are not included in this `Chain` are treated as the world.
+    pub fn to_flat(&self) -> FlatChain<T> {
+        let nodes = self.iter().collect::<Vec<_>>();
+        let index_of = |node| nodes.iter().position(|n| **n == node);
+        let parents = nodes
+            .iter()
+            .map(|node| node.parent().and_then(index_of))
+            .collect();
+        let mimics = nodes
+            .iter()
+            .map(|node| {
+                let parent = node.mimic_parent().and_then(index_of)?;
+                let mimic = node.mimic_mapping()?;
+                Some((parent, mimic))
+            })
+            .collect();
+        let joints = nodes
+            .iter()
+            .map(|node| node.joint().clone())
+            .collect::<Vec<_>>();
+        // in the order of `iter_joints()`, which may be changed by `reorder_joints()`
+        let movable_indices = self
+            .iter_movable_nodes()
+            .map(|node| index_of(node.clone()).expect("movable nodes are in this chain"))
+            .collect();
+        FlatChain {
+            joints,
+            parents,
+            mimics,
+            movable_indices,
+        }
+    }
+
+    /// Create `FrozenChain`, a read only copy of this `Chain` for the forward kinematics
+    ///
+    /// The positions of this `Chain` are not copied. See `to_flat` for the parents.
+    pub fn freeze(&self) -> FrozenChain<T> {
+        let flat = self.to_flat();
+        FrozenChain {
+            inner: Arc::new(FrozenChainData {
+                names: flat.joints.iter().map(|joint| joint.name.clone()).collect(),
+                joint_types: flat
+                    .joints
+                    .iter()
+                    .map(|joint| joint.joint_type.clone())
+                    .collect(),
+                // the base pose is applied before the origin as `Joint::local_transform`
+                origins: flat
+                    .joints
+                    .iter()
+                    .map(|joint| joint.base_pose() * joint.origin().clone())
+                    .collect(),
+                limits: flat
+                    .joints
+                    .iter()
+                    .map(|joint| joint.limits.clone())
+                    .collect(),
+                parents: flat.parents,
+                mimics: flat.mimics,
+                movable_indices: flat.movable_indices,
+            }),
+        }
+    }
+}
+
+/// Read only kinematic tree which can be shared between threads
+///
+/// It keeps only the origins, the types, the limits and the topology of the joints
+/// in plain `Vec`s, without any caches. It does not have the joint positions, and
+/// the forward kinematics is calculated from the given positions. It is `Send` and `Sync`,
+/// and `clone()` is cheap because the data is shared by `Arc`.
+///
+/// # Examples
+///
+/// ```
+/// use k::*;
+///
+/// let chain = Chain::<f64>::from_urdf_file("urdf/sample.urdf").unwrap();
+/// let frozen = chain.freeze();
+/// let handles = (0..4)
+///     .map(|i| {
+///         let frozen = frozen.clone();
+///         std::thread::spawn(move || {
+///             let positions = vec![0.1 * i as f64; frozen.dof()];
+///             frozen.transforms(&positions).unwrap()
+///         })
+///     })
+///     .collect::<Vec<_>>();
+/// for handle in handles {
+///     assert_eq!(handle.join().unwrap().len(), chain.iter().count());
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct FrozenChain<T: RealField> {
+    inner: Arc<FrozenChainData<T>>,
+}
+
+#[derive(Debug)]
+struct FrozenChainData<T: RealField> {
+    names: Vec<String>,
+    joint_types: Vec<JointType<T>>,
+    origins: Vec<Isometry3<T>>,
+    limits: Vec<Option<Range<T>>>,
+    parents: Vec<Option<usize>>,
+    mimics: Vec<Option<(usize, MimicMapping<T>)>>,
+    movable_indices: Vec<usize>,
+}
+
+impl<T> FrozenChain<T>
+where
+    T: RealField + SubsetOf<f64>,
+{
+    /// Calculate the degree of freedom
+    pub fn dof(&self) -> usize {
+        self.inner.movable_indices.len()
+    }
+
+    /// Names of all joints
+    ///
+    /// The order is the same as `Chain::iter()`, and it is the order of `transforms()`.
+    pub fn names(&self) -> &[String] {
+        &self.inner.names
+    }
+
+    /// Names of the movable joints, the order of the input positions
+    pub fn joint_names(&self) -> Vec<String> {
+        self.inner
+            .movable_indices
+            .iter()
+            .map(|i| self.inner.names[*i].clone())
+            .collect()
+    }
+
+    /// Limits of the movable joints
+    pub fn joint_limits(&self) -> Vec<Option<Range<T>>> {
+        self.inner
+            .movable_indices
+            .iter()
+            .map(|i| self.inner.limits[*i].clone())
+            .collect()
+    }
+
+    /// Indices of the parents of the joints, `None` for the root
+    pub fn parents(&self) -> &[Option<usize>] {
+        &self.inner.parents
+    }
+
+    /// Find the index of the joint by name
+    pub fn find(&self, joint_name: &str) -> Option<usize> {
+        self.inner.names.iter().position(|name| name == joint_name)
+    }
+
+    /// Calculate the world transforms of all joints for `positions`
+    ///
+    /// `positions` are the positions of the movable joints, in the same order as
+    /// `Chain::set_joint_positions`, and the length must be `dof()`. The limits are not checked.
+    /// The positions for the mimic joints are ignored as `Chain::set_joint_positions`.
+    pub fn transforms(&self, positions: &[T]) -> Result<Vec<Isometry3<T>>, Error> {
+        if positions.len() != self.dof() {
+            return Err(Error::SizeMismatchError {
+                input: positions.len(),
+                required: self.dof(),
+            });
+        }
+        let data = &self.inner;
+        let mut joint_positions = vec![T::zero(); data.names.len()];
+        for (i, position) in data.movable_indices.iter().zip(positions.iter()) {
+            joint_positions[*i] = position.clone();
+        }
+        for (i, mimic) in data.mimics.iter().enumerate() {
+            if let Some((parent, mimic)) = mimic {
+                joint_positions[i] = mimic.mimic_position(joint_positions[*parent].clone());
+            }
+        }
+        let mut transforms: Vec<Isometry3<T>> = Vec::with_capacity(data.names.len());
+        for (((origin, joint_type), position), parent) in data
+            .origins
+            .iter()
+            .zip(data.joint_types.iter())
+            .zip(joint_positions)
+            .zip(data.parents.iter())
+        {
+            let local = origin.clone() * joint_type.transform(position);
+            let trans = match parent {
+                Some(p) => transforms[*p].clone() * local,
+                None => local,
+            };
+            transforms.push(trans);
+        }
+        Ok(transforms)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::urdf::sample_chain;
+    #[cfg(target_family = "wasm")]
+    use wasm_bindgen_test::wasm_bindgen_test as test;
+
+    #[test]
+    fn test_flat_chain() {
+        let chain = sample_chain();
+        let positions = vec![0.0, 0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8, 0.9, -0.1, -0.2];
+        chain.set_joint_positions(&positions).unwrap();
+        let mut flat = chain.to_flat();
+        assert_eq!(flat.dof(), 12);
+        assert_eq!(flat.joint_positions(), positions);
+        assert_eq!(flat.update_transforms(), chain.update_transforms());
+
+        let positions = vec![
+            0.5, -0.1, 0.2, -0.3, 0.4, 0.0, 0.6, 0.7, -0.8, 0.9, 0.1, 0.2,
+        ];
+        flat.set_joint_positions(&positions).unwrap();
+        chain.set_joint_positions(&positions).unwrap();
+        let flat_transforms = flat.update_transforms();
+        for (f, c) in flat_transforms.iter().zip(chain.update_transforms().iter()) {
+            assert!((f.translation.vector - c.translation.vector).norm() < 1e-10);
+            assert!(f.rotation.angle_to(&c.rotation) < 1e-10);
+        }
+        assert_eq!(flat.parents()[0], None);
+        assert_eq!(
+            flat.parents()[flat.find("l_shoulder_pitch").unwrap()],
+            Some(1)
+        );
+        assert!(flat.set_joint_positions(&[0.0]).is_err());
+    }
+
+    #[test]
+    fn test_flat_chain_reordered() {
+        let mut chain = sample_chain();
+        let mut order = chain.joint_names();
+        order.reverse();
+        chain.reorder_joints(&order).unwrap();
+        let positions = vec![
+            0.5, -0.1, 0.2, -0.3, 0.4, 0.0, 0.6, 0.7, -0.8, 0.9, 0.1, 0.2,
+        ];
+        chain.set_joint_positions_clamped(&positions);
+        let positions = chain.joint_positions();
+        let transforms = chain.update_transforms();
+
+        let mut flat = chain.to_flat();
+        assert_eq!(flat.joint_positions(), positions);
+        flat.set_joint_positions(&chain.joint_positions()).unwrap();
+        let frozen = chain.freeze();
+        assert_eq!(frozen.joint_names(), chain.joint_names());
+        let frozen_transforms = frozen.transforms(&positions).unwrap();
+        for ((f, z), c) in flat
+            .update_transforms()
+            .iter()
+            .zip(frozen_transforms.iter())
+            .zip(transforms.iter())
+        {
+            assert!((f.translation.vector - c.translation.vector).norm() < 1e-10);
+            assert!(f.rotation.angle_to(&c.rotation) < 1e-10);
+            assert!((z.translation.vector - c.translation.vector).norm() < 1e-10);
+            assert!(z.rotation.angle_to(&c.rotation) < 1e-10);
+        }
+        // the order is kept by clone
+        assert_eq!(chain.clone().joint_names(), order);
+    }
+
+    #[test]
+    fn test_flat_chain_mimic() {
+        use crate::NodeBuilder;
+        let j0 = NodeBuilder::new()
+            .joint_type(JointType::Linear {
+                axis: na::Vector3::z_axis(),
+            })
+            .limits(Some((-1.0..=1.0).into()))
+            .into_node();
+        let j1 = NodeBuilder::new()
+            .joint_type(JointType::Linear {
+                axis: na::Vector3::z_axis(),
+            })
+            .limits(Some((-1.0..=1.0).into()))
+            .into_node();
+        j1.set_parent(&j0);
+        j1.set_mimic_parent(&j0, Mimic::new(2.0, 0.1));
+        let mut flat = Chain::<f64>::from_root(j0).to_flat();
+        flat.set_joint_positions(&[0.2, 0.0]).unwrap();
+        assert_eq!(flat.joint_positions(), vec![0.2, 0.5]);
+        // the limits are not checked
+        flat.set_joint_positions_unchecked(&[0.7, 0.0]);
+        assert_eq!(flat.joint_positions(), vec![0.7, 1.5]);
+    }
+
+    #[test]
+    fn test_frozen_chain() {
+        fn assert_send_sync_clone<S: Send + Sync + Clone>(_: &S) {}
+
+        let chain = sample_chain();
+        let frozen = chain.freeze();
+        assert_send_sync_clone(&frozen);
+        assert_eq!(frozen.dof(), chain.dof());
+        assert_eq!(frozen.joint_names(), chain.joint_names());
+        let positions = vec![
+            0.5, -0.1, 0.2, -0.3, 0.4, 0.0, 0.6, 0.7, -0.8, 0.9, 0.1, 0.2,
+        ];
+        chain.set_joint_positions(&positions).unwrap();
+        let frozen_transforms = frozen.clone().transforms(&positions).unwrap();
+        let transforms = chain.update_transforms();
+        assert_eq!(frozen_transforms.len(), transforms.len());
+        for (f, c) in frozen_transforms.iter().zip(transforms.iter()) {
+            assert!((f.translation.vector - c.translation.vector).norm() < 1e-10);
+            assert!(f.rotation.angle_to(&c.rotation) < 1e-10);
+        }
+        assert!(frozen.transforms(&[0.0]).is_err());
+        assert_eq!(frozen.parents(), chain.to_flat().parents());
+        assert_eq!(
+            frozen.find("l_shoulder_pitch"),
+            chain.to_flat().find("l_shoulder_pitch")
+        );
+    }
+}
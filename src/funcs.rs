@@ -1,50 +1,88 @@
 use super::chain::*;
 use super::joint::*;
-use na::{DMatrix, RealField, Vector3};
+use na::{DMatrix, Point3, RealField, Vector3};
 use nalgebra as na;
 use simba::scalar::SubsetOf;
 
+/// Wrap the angle into `(-pi, pi]`
+pub(crate) fn wrap_angle<T: RealField>(angle: T) -> T {
+    let two_pi = T::two_pi();
+    let wrapped = angle.clone() - two_pi.clone() * ((angle + T::pi()) / two_pi.clone()).floor();
+    if wrapped <= -T::pi() {
+        wrapped + two_pi
+    } else {
+        wrapped
+    }
+}
+
+/// Closest points of two lines `p + t * d` which are not parallel
+///
+/// The directions `d1` and `d2` must be unit vectors.
+pub(crate) fn closest_points<T: RealField>(
+    p1: &Point3<T>,
+    d1: &Vector3<T>,
+    p2: &Point3<T>,
+    d2: &Vector3<T>,
+) -> (Point3<T>, Point3<T>) {
+    let r = p1 - p2;
+    let b = d1.dot(d2);
+    let c = d1.dot(&r);
+    let f = d2.dot(&r);
+    let denom = T::one() - b.clone() * b.clone();
+    let s = (b.clone() * f.clone() - c.clone()) / denom.clone();
+    let t = (f - b * c) / denom;
+    (p1 + d1 * s, p2 + d2 * t)
+}
+
+/// Column of the jacobian for `joint` (its world transform must be updated)
+///
+/// `p_n` is the position of the point whose velocity is calculated.
+pub(crate) fn jacobian_column<T>(joint: &Joint<T>, p_n: &Vector3<T>) -> [T; 6]
+where
+    T: RealField + SubsetOf<f64>,
+{
+    let t_i = joint.world_transform().unwrap();
+    match &joint.joint_type {
+        JointType::Linear { axis } => {
+            let p_i = t_i.rotation * axis;
+            [
+                p_i[0].clone(),
+                p_i[1].clone(),
+                p_i[2].clone(),
+                na::zero(),
+                na::zero(),
+                na::zero(),
+            ]
+        }
+        JointType::Rotational { axis } => {
+            let p_i = t_i.translation;
+            let a_i = t_i.rotation * axis;
+            let dp_i = a_i.cross(&(p_n - p_i.vector));
+            [
+                dp_i[0].clone(),
+                dp_i[1].clone(),
+                dp_i[2].clone(),
+                a_i[0].clone(),
+                a_i[1].clone(),
+                a_i[2].clone(),
+            ]
+        }
+        JointType::Fixed => panic!("impossible, bug of jacobian"),
+    }
+}
+
 /// Calculate Jacobian of the serial chain (manipulator).
 pub fn jacobian<T>(arm: &SerialChain<T>) -> DMatrix<T>
 where
     T: RealField + SubsetOf<f64>,
 {
     let dof = arm.dof();
-    let t_n = arm.end_transform();
+    let t_n = arm.calc_end_transform();
     arm.update_transforms();
     let p_n = t_n.translation;
     let jacobi_vec = arm
         .iter_joints()
-        .map(|joint| {
-            let t_i = joint.world_transform().unwrap();
-            match &joint.joint_type {
-                JointType::Linear { axis } => {
-                    let p_i = t_i.rotation * axis;
-                    [
-                        p_i[0].clone(),
-                        p_i[1].clone(),
-                        p_i[2].clone(),
-                        na::zero(),
-                        na::zero(),
-                        na::zero(),
-                    ]
-                }
-                JointType::Rotational { axis } => {
-                    let p_i = t_i.translation;
-                    let a_i = t_i.rotation * axis;
-                    let dp_i = a_i.cross(&(p_n.clone().vector - p_i.vector));
-                    [
-                        dp_i[0].clone(),
-                        dp_i[1].clone(),
-                        dp_i[2].clone(),
-                        a_i[0].clone(),
-                        a_i[1].clone(),
-                        a_i[2].clone(),
-                    ]
-                }
-                JointType::Fixed => panic!("impossible, bug of jacobian"),
-            }
-        })
+        .map(|joint| jacobian_column(&joint, &p_n.vector))
         .collect::<Vec<_>>();
     // Pi: a_i x (p_n - Pi)
     // wi: a_i
@@ -53,6 +91,8 @@ where
 
 /// Calculate the center of mass of the chain
 ///
+/// The payloads which are set by `Chain::set_payload` are included.
+///
 /// ```
 /// use k::*;
 /// use k::link::*;
@@ -77,18 +117,99 @@ where
     let mut com = Vector3::zeros();
 
     chain.update_transforms();
-    chain.iter().for_each(|node| {
-        if let Some(trans) = node.world_transform() {
-            if let Some(ref link) = *node.link() {
-                let inertia_trans = trans * link.inertial.origin().translation.clone();
-                com += inertia_trans.translation.vector * link.inertial.mass.clone();
-                total_mass += link.inertial.mass.clone();
-            }
-        }
-    });
+    for (_, mass, position) in chain.point_masses() {
+        com += position * mass.clone();
+        total_mass += mass;
+    }
     com / total_mass
 }
 
+/// Generate random joint positions within the limits
+///
+/// Rotational joints without limits use `[-pi, pi]`.
+/// Linear joints without limits keep their current positions.
+///
+/// ```
+/// use k::*;
+/// use rand::SeedableRng;
+///
+/// let l0 = NodeBuilder::new()
+///     .joint_type(JointType::Rotational { axis: Vector3::y_axis() })
+///     .limits(Some((-0.5..=0.5).into()))
+///     .into_node();
+/// let chain = Chain::<f64>::from_root(l0);
+/// let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+/// let positions = random_joint_positions(&chain, &mut rng);
+/// assert!(positions[0].abs() <= 0.5);
+/// ```
+#[cfg(feature = "rand")]
+pub fn random_joint_positions<T, R>(chain: &Chain<T>, rng: &mut R) -> Vec<T>
+where
+    T: RealField + SubsetOf<f64>,
+    R: rand::RngCore + ?Sized,
+{
+    use rand::Rng;
+    joint_positions_by_ratios(chain, || rng.gen::<f64>())
+}
+
+/// `random_joint_positions()` with the generator of this crate, for the seeded samplings
+pub(crate) fn sample_joint_positions<T>(chain: &Chain<T>, rng: &mut SplitMix64) -> Vec<T>
+where
+    T: RealField + SubsetOf<f64>,
+{
+    joint_positions_by_ratios(chain, || rng.next_f64())
+}
+
+/// Joint positions at the ratios in `[0, 1)` of the ranges, see `random_joint_positions()`
+fn joint_positions_by_ratios<T, F>(chain: &Chain<T>, mut ratio: F) -> Vec<T>
+where
+    T: RealField + SubsetOf<f64>,
+    F: FnMut() -> f64,
+{
+    chain
+        .iter_joints()
+        .map(|joint| {
+            let (min, max) = match (&joint.limits, &joint.joint_type) {
+                (Some(range), _) => (range.min.clone(), range.max.clone()),
+                (None, JointType::Rotational { .. }) => (-T::pi(), T::pi()),
+                _ => {
+                    return joint
+                        .joint_position()
+                        .expect("Must be a bug: movable joint must have position")
+                }
+            };
+            let ratio: T = na::convert(ratio());
+            min.clone() + (max - min) * ratio
+        })
+        .collect()
+}
+
+/// Small deterministic random number generator (SplitMix64)
+///
+/// It is used for the seeded samplings in this crate, like the restarts of IK,
+/// so `rand` is not needed by them.
+#[derive(Debug, Clone)]
+pub(crate) struct SplitMix64(u64);
+
+impl SplitMix64 {
+    pub(crate) fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform random number in `[0, 1)`
+    pub(crate) fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -14,9 +14,10 @@
   limitations under the License.
 */
 
+use std::collections::HashSet;
 use std::fmt;
 
-use na::{DVector, Isometry3, RealField, Vector3, Vector6};
+use na::{DMatrix, DVector, Isometry3, Point3, RealField, UnitQuaternion, Vector3, Vector6};
 use nalgebra as na;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
@@ -25,6 +26,7 @@ use simba::scalar::SubsetOf;
 use super::chain::*;
 use super::errors::*;
 use super::funcs::*;
+use super::joint::*;
 
 /// From 'Humanoid Robot (Kajita)' P.64
 fn calc_pose_diff<T>(a: &Isometry3<T>, b: &Isometry3<T>) -> Vector6<T>
@@ -82,6 +84,7 @@ pub struct Constraints {
     pub rotation_y: bool,
     #[cfg_attr(feature = "serde", serde(default = "default_true"))]
     pub rotation_z: bool,
+    /// The joints which are not moved. Their columns of the jacobian are removed.
     #[cfg_attr(feature = "serde", serde(default))]
     pub ignored_joint_names: Vec<String>,
 }
@@ -116,7 +119,7 @@ impl Default for Constraints {
     }
 }
 
-fn define_operational_space(constraints: &Constraints) -> [bool; 6] {
+pub(crate) fn define_operational_space(constraints: &Constraints) -> [bool; 6] {
     let mut arr = [true; 6];
     arr[0] = constraints.position_x;
     arr[1] = constraints.position_y;
@@ -143,18 +146,85 @@ where
         target_pose: &Isometry3<T>,
         constraints: &Constraints,
     ) -> Result<(), Error>;
+    /// Move the end transform of the `arm` to `target_pose` without moving the `locked` joints
+    ///
+    /// This is the same as `solve_with_constraints` with `ignored_joint_names`.
+    fn solve_with_locked_joints(
+        &self,
+        arm: &SerialChain<T>,
+        target_pose: &Isometry3<T>,
+        locked: &HashSet<String>,
+    ) -> Result<(), Error> {
+        let constraints = Constraints {
+            ignored_joint_names: locked.iter().cloned().collect(),
+            ..Default::default()
+        };
+        self.solve_with_constraints(arm, target_pose, &constraints)
+    }
 }
 
 /// Inverse Kinematics Solver using Jacobian matrix
 pub struct JacobianIkSolver<T: RealField> {
-    /// If the distance is smaller than this value, it is reached.
-    pub allowable_target_distance: T,
-    /// If the angle distance is smaller than this value, it is reached.
-    pub allowable_target_angle: T,
+    /// If the distance is smaller than this value, it is reached (meters)
+    pub position_tolerance: T,
+    /// If the angle distance is smaller than this value, it is reached (radians)
+    ///
+    /// It is converged only when both magnitudes of `SerialChain::pose_error_magnitudes`
+    /// are smaller than the tolerances. With `Constraints`, only the constrained axes are
+    /// counted.
+    pub orientation_tolerance: T,
     /// multiplier for jacobian
     pub jacobian_multiplier: T,
     /// How many times the joints are tried to be moved
     pub num_max_try: usize,
+    /// How many times the solver retries from random joint positions if it is not converged
+    ///
+    /// If all of them fail, the joint positions are restored to the ones at the start of
+    /// the solve, and the error of the attempt which was closest to the target is returned.
+    pub restarts: usize,
+    /// Seed of the random joint positions of `restarts`
+    ///
+    /// The same seed gives the same sequence of the initial positions, so the result is
+    /// reproducible.
+    pub restart_seed: u64,
+    /// Manipulability below which the damping is applied
+    pub manipulability_threshold: T,
+    /// Damping factor at the singular pose (manipulability is zero). `0` disables the damping
+    ///
+    /// The damping factor increases from zero at `manipulability_threshold`
+    /// to `max_damping` at the singular pose.
+    pub max_damping: T,
+    /// If true, redundant joints are pulled back toward the positions at the start of the solve
+    ///
+    /// Among the solutions which reach the target, the one which minimizes
+    /// `||q - q_start||` is preferred. It is applied in the null space, and it is
+    /// added to the output of the nullspace function if both are set.
+    pub least_norm: bool,
+    /// In the least norm mode, it is converged when the joints move less than this value
+    /// in one iteration (the norm of the change of the joint positions)
+    ///
+    /// It is in the joint space, independent of `position_tolerance`.
+    pub least_norm_step_tolerance: T,
+    /// If it is not zero, the jacobian is calculated once in this number of iterations,
+    /// and it is estimated by Broyden's rank-one update in the other iterations
+    ///
+    /// It reduces the cost of each iteration for the chains with many joints, but it may
+    /// need more iterations. `0` (default) or `1` calculates the jacobian in every iteration.
+    pub broyden_interval: usize,
+    /// Weights of the joints for the weighted pseudo-inverse `W^-1 J^T (J W^-1 J^T)^-1`
+    ///
+    /// The joints with larger weights move less. The weights must be positive and finite,
+    /// and the length must be `dof()` of the arm, otherwise the solvers return `Err`.
+    /// Empty (default) means that all weights are one.
+    pub joint_weights: Vec<T>,
+    /// If it is set, the solution must be apart from self collisions by this distance
+    ///
+    /// `Chain::min_self_distance()` of the arm is checked in each iteration, and if it is
+    /// smaller than this margin, the joints are pushed apart in the null space.
+    /// The adjacent links are not checked (see `Chain::collision_check_pairs()`).
+    /// It is converged only when the target is reached and the distance is not smaller
+    /// than the margin. It needs a redundant arm (or constraints) to move in the null space.
+    pub self_collision_margin: Option<T>,
     /// Nullspace function for a redundant system
     #[allow(clippy::type_complexity)]
     nullspace_function: Option<Box<dyn Fn(&[T]) -> Vec<T> + Send + Sync>>,
@@ -174,16 +244,25 @@ where
     /// let solver = k::JacobianIkSolver::new(0.01, 0.01, 0.5, 100);
     /// ```
     pub fn new(
-        allowable_target_distance: T,
-        allowable_target_angle: T,
+        position_tolerance: T,
+        orientation_tolerance: T,
         jacobian_multiplier: T,
         num_max_try: usize,
     ) -> JacobianIkSolver<T> {
         JacobianIkSolver {
-            allowable_target_distance,
-            allowable_target_angle,
+            position_tolerance,
+            orientation_tolerance,
             jacobian_multiplier,
             num_max_try,
+            restarts: 0,
+            restart_seed: 0,
+            manipulability_threshold: na::convert(0.01),
+            max_damping: T::zero(),
+            least_norm: false,
+            least_norm_step_tolerance: na::convert(1.0e-4),
+            broyden_interval: 0,
+            joint_weights: Vec::new(),
+            self_collision_margin: None,
             nullspace_function: None,
         }
     }
@@ -210,6 +289,158 @@ where
         self.nullspace_function = None;
     }
 
+    /// Set the position tolerance (meters) and the orientation tolerance (radians)
+    ///
+    /// They are compared with the magnitudes of the error, like `SerialChain::pose_error_magnitudes`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut solver = k::JacobianIkSolver::<f64>::default();
+    /// // 1mm and 1 degree
+    /// solver.set_tolerances(0.001, 1.0f64.to_radians());
+    /// assert_eq!(solver.position_tolerance, 0.001);
+    /// ```
+    pub fn set_tolerances(&mut self, position_tolerance: T, orientation_tolerance: T) {
+        self.position_tolerance = position_tolerance;
+        self.orientation_tolerance = orientation_tolerance;
+    }
+
+    /// Enable the least norm mode with the tolerance of the change of the joint positions
+    ///
+    /// See `least_norm` and `least_norm_step_tolerance`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut solver = k::JacobianIkSolver::<f64>::default();
+    /// solver.set_least_norm(1.0e-5);
+    /// assert!(solver.least_norm);
+    /// ```
+    pub fn set_least_norm(&mut self, step_tolerance: T) {
+        self.least_norm = true;
+        self.least_norm_step_tolerance = step_tolerance;
+    }
+
+    /// Set the parameters of the variable damping to avoid singularities
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut solver = k::JacobianIkSolver::<f64>::default();
+    /// solver.set_variable_damping(0.01, 0.1);
+    /// ```
+    pub fn set_variable_damping(&mut self, manipulability_threshold: T, max_damping: T) {
+        self.manipulability_threshold = manipulability_threshold;
+        self.max_damping = max_damping;
+    }
+
+    /// Square of the damping factor for `jacobi`
+    ///
+    /// λ² = λmax² (1 - (w / w0)²) if w < w0, otherwise 0,
+    /// where w is the manipulability sqrt(det(J J^T)).
+    fn damping_factor_squared(&self, jacobi: &DMatrix<T>) -> T {
+        if self.max_damping <= T::zero() || self.manipulability_threshold <= T::zero() {
+            return T::zero();
+        }
+        let det = (jacobi * jacobi.transpose()).determinant();
+        let manipulability = det.max(T::zero()).sqrt();
+        if manipulability >= self.manipulability_threshold {
+            return T::zero();
+        }
+        let ratio = manipulability / self.manipulability_threshold.clone();
+        self.max_damping.clone() * self.max_damping.clone() * (T::one() - ratio.clone() * ratio)
+    }
+
+    /// Secondary task projected to the null space, or `None` if there is no secondary task
+    fn subtask(
+        &self,
+        arm: &SerialChain<T>,
+        positions: &[T],
+        start_positions: &[T],
+        ignored_joint_indices: &[usize],
+    ) -> Option<DVector<T>> {
+        let repulsion = self.self_collision_repulsion(arm, positions);
+        let mut subtask = match self.nullspace_function {
+            Some(ref f) => DVector::from_vec(f(positions)),
+            None if self.least_norm || repulsion.is_some() => DVector::zeros(positions.len()),
+            None => return None,
+        };
+        if self.least_norm {
+            for (i, (q, q_start)) in positions.iter().zip(start_positions.iter()).enumerate() {
+                subtask[i] += q_start.clone() - q.clone();
+            }
+        }
+        if let Some(repulsion) = repulsion {
+            subtask += repulsion;
+        }
+        for (i, joint_index) in ignored_joint_indices.iter().enumerate() {
+            subtask = subtask.remove_row(*joint_index - i);
+        }
+        Some(subtask)
+    }
+
+    /// Motion of the joints which increases the self distance over `self_collision_margin`
+    ///
+    /// The gradient of `min_self_distance()` is calculated numerically, and the step is
+    /// the Newton step of the linearized distance. It aims a little (`position_tolerance`)
+    /// beyond the margin to cross it in finite iterations. `None` if the distance is enough.
+    fn self_collision_repulsion(
+        &self,
+        arm: &SerialChain<T>,
+        positions: &[T],
+    ) -> Option<DVector<T>> {
+        let margin = self.self_collision_margin.clone()? + self.position_tolerance.clone();
+        let (distance, _, _) = arm.min_self_distance()?;
+        if distance >= margin {
+            return None;
+        }
+        let epsilon = na::convert::<f64, T>(1.0e-4);
+        let mut gradient = DVector::zeros(positions.len());
+        let mut moved = positions.to_vec();
+        for i in 0..positions.len() {
+            moved[i] = positions[i].clone() + epsilon.clone();
+            arm.set_joint_positions_unchecked(&moved);
+            let plus = arm.min_self_distance().map(|(d, _, _)| d);
+            moved[i] = positions[i].clone() - epsilon.clone();
+            arm.set_joint_positions_unchecked(&moved);
+            let minus = arm.min_self_distance().map(|(d, _, _)| d);
+            moved[i] = positions[i].clone();
+            if let (Some(plus), Some(minus)) = (plus, minus) {
+                gradient[i] = (plus - minus) / (epsilon.clone() + epsilon.clone());
+            }
+        }
+        arm.set_joint_positions_unchecked(positions);
+        let norm_squared = gradient.norm_squared();
+        if norm_squared <= T::default_epsilon() {
+            return None;
+        }
+        Some(gradient * ((margin - distance) / norm_squared))
+    }
+
+    /// Check if the arm is apart from self collisions by `self_collision_margin`
+    fn is_collision_free(&self, arm: &SerialChain<T>) -> bool {
+        match (&self.self_collision_margin, arm.min_self_distance()) {
+            (Some(margin), Some((distance, _, _))) => distance >= *margin,
+            _ => true,
+        }
+    }
+
+    /// `W^(-1/2)` of the joints which are not ignored
+    ///
+    /// The columns of the jacobian are scaled by it, then the weighted pseudo-inverse is
+    /// `W^(-1/2) (J W^(-1/2))^+`.
+    fn joint_scales(&self, dof: usize, ignored_joint_indices: &[usize]) -> DVector<T> {
+        let scales = (0..dof)
+            .filter(|i| !ignored_joint_indices.contains(i))
+            .map(|i| match self.joint_weights.get(i) {
+                Some(weight) => T::one() / weight.clone().sqrt(),
+                None => T::one(),
+            })
+            .collect::<Vec<_>>();
+        DVector::from_vec(scales)
+    }
+
     fn add_positions_with_multiplier(&self, input: &[T], add_values: &[T]) -> Vec<T> {
         input
             .iter()
@@ -218,20 +449,68 @@ where
             .collect()
     }
 
+    /// Full (6 x dof) jacobian, calculated or estimated by Broyden's update
+    fn jacobian_with_estimate(
+        &self,
+        arm: &SerialChain<T>,
+        positions: &[T],
+        pose_diff: Vector6<T>,
+        estimate: &mut JacobianEstimate<T>,
+    ) -> DMatrix<T> {
+        let jacobi = match estimate.jacobian.take() {
+            Some(mut jacobi) if estimate.age + 1 < self.broyden_interval => {
+                let d_q = DVector::from_iterator(
+                    positions.len(),
+                    positions
+                        .iter()
+                        .zip(estimate.positions.iter())
+                        .map(|(q, prev)| q.clone() - prev.clone()),
+                );
+                let d_q_norm_squared = d_q.norm_squared();
+                if d_q_norm_squared > T::default_epsilon() {
+                    // the pose diff is (target - current), so the motion is the negative change
+                    let d_x = &estimate.pose_diff - &pose_diff;
+                    let correction = (DVector::from_column_slice(d_x.as_slice()) - &jacobi * &d_q)
+                        / d_q_norm_squared;
+                    jacobi += correction * d_q.transpose();
+                }
+                estimate.age += 1;
+                jacobi
+            }
+            _ => {
+                estimate.age = 0;
+                jacobian(arm)
+            }
+        };
+        if self.broyden_interval > 0 {
+            estimate.jacobian = Some(jacobi.clone());
+            estimate.positions = positions.to_vec();
+            estimate.pose_diff = pose_diff;
+        }
+        jacobi
+    }
+
     fn solve_one_loop_with_constraints(
         &self,
         arm: &SerialChain<T>,
         target_pose: &Isometry3<T>,
         operational_space: &[bool; 6],
         ignored_joint_indices: &[usize],
+        start_positions: &[T],
+        estimate: &mut JacobianEstimate<T>,
     ) -> Result<DVector<T>, Error> {
         let required_dof = operational_space.iter().filter(|x| **x).count();
         let orig_positions = arm.joint_positions();
         let available_dof = arm.dof() - ignored_joint_indices.len();
 
-        let t_n = arm.end_transform();
+        let t_n = arm.calc_end_transform();
         let err = calc_pose_diff_with_constraints(target_pose, &t_n, *operational_space);
-        let mut jacobi = jacobian(arm);
+        let mut jacobi = self.jacobian_with_estimate(
+            arm,
+            &orig_positions,
+            calc_pose_diff(target_pose, &t_n),
+            estimate,
+        );
         let mut num_removed_rows = 0;
         for (i, use_i) in operational_space.iter().enumerate() {
             if !use_i {
@@ -243,22 +522,47 @@ where
         for (i, joint_index) in ignored_joint_indices.iter().enumerate() {
             jacobi = jacobi.remove_column(*joint_index - i);
         }
+        let scales = self.joint_scales(arm.dof(), ignored_joint_indices);
+        for (mut column, scale) in jacobi.column_iter_mut().zip(scales.iter()) {
+            column *= scale.clone();
+        }
+        // the secondary task in the scaled joint space
+        let subtask = self
+            .subtask(arm, &orig_positions, start_positions, ignored_joint_indices)
+            .map(|subtask| subtask.component_div(&scales));
 
-        let positions_vec = if available_dof > required_dof {
+        let damping_squared = self.damping_factor_squared(&jacobi);
+        let positions_vec = if damping_squared > T::zero() {
+            // damped least squares: J^T (J J^T + λ² I)^-1
+            let jacobi_inv = jacobi.transpose()
+                * (&jacobi * jacobi.transpose()
+                    + DMatrix::identity(required_dof, required_dof) * damping_squared)
+                    .try_inverse()
+                    .ok_or(Error::InverseMatrixError)?;
+            let mut d_q = &jacobi_inv * err;
+            if available_dof > required_dof {
+                if let Some(subtask) = subtask {
+                    d_q += (DMatrix::identity(available_dof, available_dof) - jacobi_inv * jacobi)
+                        * subtask;
+                }
+            }
+            d_q.component_mul_assign(&scales);
+            for joint_index in ignored_joint_indices {
+                d_q = d_q.insert_row(*joint_index, T::zero());
+            }
+            self.add_positions_with_multiplier(&orig_positions, d_q.as_slice())
+        } else if available_dof > required_dof {
             const EPS: f64 = 0.0001;
             // redundant: pseudo inverse
-            match self.nullspace_function {
-                Some(ref f) => {
+            match subtask {
+                Some(subtask) => {
                     let jacobi_inv = jacobi.clone().pseudo_inverse(na::convert(EPS)).unwrap();
 
-                    let mut subtask = na::DVector::from_vec(f(&orig_positions));
-                    for (i, joint_index) in ignored_joint_indices.iter().enumerate() {
-                        subtask = subtask.remove_row(*joint_index - i);
-                    }
                     let mut d_q = jacobi_inv.clone() * err
                         + (na::DMatrix::identity(available_dof, available_dof)
                             - jacobi_inv * jacobi)
                             * subtask;
+                    d_q.component_mul_assign(&scales);
                     for joint_index in ignored_joint_indices {
                         d_q = d_q.insert_row(*joint_index, T::zero());
                     }
@@ -269,6 +573,7 @@ where
                         .svd(true, true)
                         .solve(&err, na::convert(EPS))
                         .unwrap();
+                    d_q.component_mul_assign(&scales);
                     for joint_index in ignored_joint_indices {
                         d_q = d_q.insert_row(*joint_index, T::zero());
                     }
@@ -277,19 +582,17 @@ where
             }
         } else {
             // normal inverse matrix
-            self.add_positions_with_multiplier(
-                &orig_positions,
-                jacobi
-                    .lu()
-                    .solve(&err)
-                    .ok_or(Error::InverseMatrixError)?
-                    .as_slice(),
-            )
+            let mut d_q = jacobi.lu().solve(&err).ok_or(Error::InverseMatrixError)?;
+            d_q.component_mul_assign(&scales);
+            for joint_index in ignored_joint_indices {
+                d_q = d_q.insert_row(*joint_index, T::zero());
+            }
+            self.add_positions_with_multiplier(&orig_positions, d_q.as_slice())
         };
         arm.set_joint_positions_clamped(&positions_vec);
         Ok(calc_pose_diff_with_constraints(
             target_pose,
-            &arm.end_transform(),
+            &arm.calc_end_transform(),
             *operational_space,
         ))
     }
@@ -299,11 +602,28 @@ where
         arm: &SerialChain<T>,
         target_pose: &Isometry3<T>,
         constraints: &Constraints,
+        start_positions: &[T],
     ) -> Result<(), Error> {
         let operational_space = define_operational_space(constraints);
         let required_dof = operational_space.iter().filter(|x| **x).count();
         let orig_positions = arm.joint_positions();
         let available_dof = arm.dof() - constraints.ignored_joint_names.len();
+        if !self.joint_weights.is_empty() && self.joint_weights.len() != arm.dof() {
+            return Err(Error::SizeMismatchError {
+                input: self.joint_weights.len(),
+                required: arm.dof(),
+            });
+        }
+        if let Some(weight) = self
+            .joint_weights
+            .iter()
+            .find(|weight| !(**weight > T::zero() && weight.is_finite()))
+        {
+            return Err(Error::InvalidParameterError {
+                name: "joint_weights".to_owned(),
+                reason: format!("must be positive, but {weight}"),
+            });
+        }
         if available_dof < required_dof {
             return Err(Error::PreconditionError {
                 dof: available_dof,
@@ -326,23 +646,48 @@ where
         }
         ignored_joint_indices.sort_unstable();
         let mut last_target_distance = None;
-        for _ in 0..self.num_max_try {
+        let mut last_reached_positions = None;
+        let mut estimate = JacobianEstimate::new();
+        for iteration in 0..self.num_max_try {
+            let prev_positions = arm.joint_positions();
             let target_diff = self.solve_one_loop_with_constraints(
                 arm,
                 target_pose,
                 &operational_space,
                 &ignored_joint_indices,
+                start_positions,
+                &mut estimate,
             )?;
             let (len_diff, rot_diff) = target_diff_to_len_rot_diff(&target_diff, operational_space);
-            if len_diff.norm() < self.allowable_target_distance
-                && rot_diff.norm() < self.allowable_target_angle
+            let (distance, angle) = if operational_space == [true; 6] {
+                arm.pose_error_magnitudes(target_pose)
+            } else {
+                (len_diff.norm(), rot_diff.norm())
+            };
+            if let Some(telemetry) = arm.telemetry() {
+                telemetry.on_ik_iteration(iteration, distance.clone(), angle.clone());
+            }
+            if distance < self.position_tolerance
+                && angle < self.orientation_tolerance
+                && self.is_collision_free(arm)
             {
                 let non_checked_positions = arm.joint_positions();
-                arm.set_joint_positions_clamped(&non_checked_positions);
-                return Ok(());
+                // in the least norm mode, keep moving in the null space until it settles
+                if !self.least_norm
+                    || positions_distance(&prev_positions, &non_checked_positions)
+                        < self.least_norm_step_tolerance
+                {
+                    arm.set_joint_positions_clamped(&non_checked_positions);
+                    return Ok(());
+                }
+                last_reached_positions = Some(non_checked_positions);
             }
             last_target_distance = Some((len_diff, rot_diff));
         }
+        if let Some(positions) = last_reached_positions {
+            arm.set_joint_positions_clamped(&positions);
+            return Ok(());
+        }
         arm.set_joint_positions(&orig_positions)?;
         Err(Error::NotConvergedError {
             num_tried: self.num_max_try,
@@ -354,13 +699,113 @@ where
     }
 }
 
+impl<T> JacobianIkSolver<T>
+where
+    T: RealField + SubsetOf<f64>,
+{
+    /// Retry `restarts` times from random joint positions, and returns the best result
+    ///
+    /// The positions of the ignored joints are not changed. Each failed attempt restores
+    /// the positions at the start, so they are kept if all of them fail.
+    fn solve_with_restarts(
+        &self,
+        arm: &SerialChain<T>,
+        target_pose: &Isometry3<T>,
+        constraints: &Constraints,
+        first_result: Result<(), Error>,
+    ) -> Result<(), Error> {
+        let mut rng = SplitMix64::new(self.restart_seed);
+        let seed_positions = arm.joint_positions();
+        let ignored = arm
+            .iter_joints()
+            .map(|joint| constraints.ignored_joint_names.contains(&joint.name))
+            .collect::<Vec<_>>();
+        let mut best = first_result;
+        for _ in 0..self.restarts {
+            let positions = sample_joint_positions(arm, &mut rng)
+                .into_iter()
+                .zip(seed_positions.iter().zip(ignored.iter()))
+                .map(|(random, (seed, is_ignored))| if *is_ignored { seed.clone() } else { random })
+                .collect::<Vec<_>>();
+            arm.set_joint_positions_clamped(&positions);
+            let re = self.solve_with_constraints_internal(
+                arm,
+                target_pose,
+                constraints,
+                &seed_positions,
+            );
+            if re.is_ok() {
+                return re;
+            }
+            if not_converged_distance(&re) < not_converged_distance(&best) {
+                best = re;
+            }
+        }
+        best
+    }
+}
+
+/// Jacobian which is updated by Broyden's method, kept between the iterations of IK
+struct JacobianEstimate<T: RealField> {
+    jacobian: Option<DMatrix<T>>,
+    /// positions and pose diff when `jacobian` was calculated
+    positions: Vec<T>,
+    pose_diff: Vector6<T>,
+    /// number of the updates since `jacobian` was calculated analytically
+    age: usize,
+}
+
+impl<T: RealField> JacobianEstimate<T> {
+    fn new() -> Self {
+        Self {
+            jacobian: None,
+            positions: Vec::new(),
+            pose_diff: Vector6::zeros(),
+            age: 0,
+        }
+    }
+}
+
+/// Euclidean distance between two joint position vectors
+fn positions_distance<T: RealField>(a: &[T], b: &[T]) -> T {
+    a.iter()
+        .zip(b.iter())
+        .fold(T::zero(), |sum, (a, b)| {
+            let d = a.clone() - b.clone();
+            sum + d.clone() * d
+        })
+        .sqrt()
+}
+
+/// Sum of the position and rotation diff of `NotConvergedError`, used to compare failures
+fn not_converged_distance(result: &Result<(), Error>) -> f64 {
+    match result {
+        Err(Error::NotConvergedError {
+            position_diff,
+            rotation_diff,
+            ..
+        }) => position_diff.norm() + rotation_diff.norm(),
+        Ok(_) => 0.0,
+        Err(_) => f64::INFINITY,
+    }
+}
+
 impl<T: RealField + fmt::Debug> fmt::Debug for JacobianIkSolver<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("JacobianIkSolver")
-            .field("allowable_target_distance", &self.allowable_target_distance)
-            .field("allowable_target_angle", &self.allowable_target_angle)
+            .field("position_tolerance", &self.position_tolerance)
+            .field("orientation_tolerance", &self.orientation_tolerance)
             .field("jacobian_multiplier", &self.jacobian_multiplier)
             .field("num_max_try", &self.num_max_try)
+            .field("restarts", &self.restarts)
+            .field("restart_seed", &self.restart_seed)
+            .field("manipulability_threshold", &self.manipulability_threshold)
+            .field("max_damping", &self.max_damping)
+            .field("least_norm", &self.least_norm)
+            .field("least_norm_step_tolerance", &self.least_norm_step_tolerance)
+            .field("broyden_interval", &self.broyden_interval)
+            .field("joint_weights", &self.joint_weights)
+            .field("self_collision_margin", &self.self_collision_margin)
             .field("has_nullspace_function", &self.nullspace_function.is_some())
             .finish()
     }
@@ -469,10 +914,24 @@ where
         constraints: &Constraints,
     ) -> Result<(), Error> {
         let orig_positions = arm.joint_positions();
-        let re = self.solve_with_constraints_internal(arm, target_pose, constraints);
-        if re.is_err() {
-            arm.set_joint_positions(&orig_positions)?;
-        };
+        let re = arm.without_position_reports(|| {
+            let mut re = self.solve_with_constraints_internal(
+                arm,
+                target_pose,
+                constraints,
+                &orig_positions,
+            );
+            if re.is_err() && self.restarts > 0 {
+                re = self.solve_with_restarts(arm, target_pose, constraints, re);
+            }
+            if re.is_err() {
+                arm.set_joint_positions(&orig_positions)?;
+            };
+            re
+        });
+        if re.is_ok() {
+            arm.report_joint_positions();
+        }
         re
     }
 }
@@ -486,6 +945,368 @@ where
     }
 }
 
+impl<T> SerialChain<T>
+where
+    T: RealField + SubsetOf<f64>,
+{
+    /// Find the nearest reachable position of the end to `target`
+    ///
+    /// It runs position-only damped least squares IK (joint limits are respected)
+    /// and returns where the end actually converged. If `target` is reachable,
+    /// the result is almost the same as `target`. If not, it is the closest point
+    /// on the boundary of the workspace that was found.
+    ///
+    /// The joint positions are restored after the calculation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let l0 = NodeBuilder::new()
+    ///     .joint_type(JointType::Rotational { axis: Vector3::y_axis() })
+    ///     .into_node();
+    /// let l1 = NodeBuilder::new()
+    ///     .translation(Translation3::new(1.0, 0.0, 0.0))
+    ///     .into_node();
+    /// l1.set_parent(&l0);
+    /// let arm = SerialChain::<f64>::from_end(&l1);
+    /// let p = arm.project_onto_workspace(&nalgebra::Point3::new(0.0, 0.0, 2.0));
+    /// assert!((p.z - 1.0).abs() < 0.01);
+    /// ```
+    pub fn project_onto_workspace(&self, target: &Point3<T>) -> Point3<T> {
+        const NUM_MAX_TRY: usize = 1000;
+        let damping: T = na::convert(0.01);
+        let allowable_distance: T = na::convert(1.0e-6);
+        let orig_positions = self.joint_positions();
+
+        let mut best = Point3::from(self.calc_end_transform().translation.vector);
+        let mut best_distance = (target - &best).norm();
+        for _ in 0..NUM_MAX_TRY {
+            if best_distance < allowable_distance {
+                break;
+            }
+            let current = self.calc_end_transform().translation.vector;
+            let err = &target.coords - current;
+            let jacobi = jacobian(self).rows(0, 3).into_owned();
+            let jjt = &jacobi * jacobi.transpose()
+                + DMatrix::identity(3, 3) * (damping.clone() * damping.clone());
+            let d_q = match jjt.lu().solve(&err) {
+                Some(v) => jacobi.transpose() * v,
+                None => break,
+            };
+            let positions = self
+                .joint_positions()
+                .iter()
+                .zip(d_q.iter())
+                .map(|(q, dq)| q.clone() + dq.clone())
+                .collect::<Vec<_>>();
+            self.without_position_reports(|| self.set_joint_positions_clamped(&positions));
+            let reached = Point3::from(self.calc_end_transform().translation.vector);
+            let distance = (target - &reached).norm();
+            if distance < best_distance {
+                best_distance = distance;
+                best = reached;
+            }
+        }
+        self.set_joint_positions_unchecked(&orig_positions);
+        best
+    }
+
+    /// Estimate the range of Z which the end can reach at the column (`x`, `y`)
+    ///
+    /// `samples` heights between the lowest and the highest possible Z (the position of
+    /// the first joint -/+ the total length of the arm) are checked by
+    /// `project_onto_workspace`. Returns `None` if no height is reachable.
+    /// The result is as accurate as the interval of the samples.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let l0 = NodeBuilder::new()
+    ///     .joint_type(JointType::Rotational { axis: Vector3::y_axis() })
+    ///     .into_node();
+    /// let l1 = NodeBuilder::new()
+    ///     .translation(Translation3::new(1.0, 0.0, 0.0))
+    ///     .into_node();
+    /// l1.set_parent(&l0);
+    /// let arm = SerialChain::<f64>::from_end(&l1);
+    /// // circle of radius 1 in XZ plane
+    /// let (min, max) = arm.reachable_z_range(0.0, 0.0, 21).unwrap();
+    /// assert!((min + 1.0).abs() < 0.11 && (max - 1.0).abs() < 0.11);
+    /// assert!(arm.reachable_z_range(2.0, 0.0, 21).is_none());
+    /// ```
+    pub fn reachable_z_range(&self, x: T, y: T, samples: usize) -> Option<(T, T)> {
+        let allowable_distance: T = na::convert(1.0e-3);
+        let transforms = self.update_transforms();
+        let base = transforms.first()?.translation.vector.clone();
+        let length = transforms.windows(2).fold(T::zero(), |sum, t| {
+            sum + (&t[1].translation.vector - &t[0].translation.vector).norm()
+        });
+        let min_z = base.z.clone() - length.clone();
+        let step = if samples > 1 {
+            length * na::convert(2.0) / na::convert((samples - 1) as f64)
+        } else {
+            T::zero()
+        };
+        let mut range: Option<(T, T)> = None;
+        for i in 0..samples {
+            let z = min_z.clone() + step.clone() * na::convert(i as f64);
+            let target = Point3::new(x.clone(), y.clone(), z.clone());
+            if (self.project_onto_workspace(&target) - target).norm() > allowable_distance {
+                continue;
+            }
+            range = Some(match range {
+                Some((min, max)) => (min.min(z.clone()), max.max(z)),
+                None => (z.clone(), z),
+            });
+        }
+        range
+    }
+
+    /// Check if the end can reach `target` within the joint limits
+    ///
+    /// The current joint positions and `samples` random joint positions within the limits
+    /// are used as the seeds of short IK attempts (`JacobianIkSolver::default()` with
+    /// a small number of iterations). It returns `true` as soon as one of them reaches
+    /// `target`, so it is faster than a full solve with many restarts, but `false`
+    /// does not prove that `target` is unreachable.
+    ///
+    /// The joint positions are restored after the calculation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let chain = Chain::<f64>::from_urdf_file("urdf/sample.urdf").unwrap();
+    /// let arm = SerialChain::from_end(chain.find("l_wrist_pitch").unwrap());
+    /// arm.set_joint_positions(&[0.1, 0.2, 0.3, -0.5, 0.4, -0.3]).unwrap();
+    /// let mut target = arm.end_transform();
+    /// target.translation.vector.z += 0.05;
+    /// assert!(arm.is_reachable(&target, 10));
+    /// target.translation.vector.z += 10.0;
+    /// assert!(!arm.is_reachable(&target, 10));
+    /// ```
+    pub fn is_reachable(&self, target: &Isometry3<T>, samples: usize) -> bool {
+        const SEED: u64 = 0;
+        const NUM_MAX_TRY: usize = 30;
+        let solver = JacobianIkSolver {
+            num_max_try: NUM_MAX_TRY,
+            ..Default::default()
+        };
+        let orig_positions = self.joint_positions();
+        let mut rng = SplitMix64::new(SEED);
+        let mut reachable = false;
+        for i in 0..=samples {
+            if i > 0 {
+                self.without_position_reports(|| {
+                    self.set_joint_positions_clamped(&sample_joint_positions(self, &mut rng))
+                });
+            }
+            if self
+                .without_position_reports(|| solver.solve(self, target))
+                .is_ok()
+            {
+                reachable = true;
+                break;
+            }
+        }
+        self.set_joint_positions_unchecked(&orig_positions);
+        reachable
+    }
+
+    /// Solve IK for each of `waypoints` in order, and returns the joint trajectory
+    ///
+    /// Each waypoint is solved from the solution of the previous one (the first one is
+    /// solved from the current positions), so the trajectory is continuous as long as
+    /// the waypoints are close. Use `trajectory::cartesian_interpolate` to create them.
+    /// The arm is left at the solution of the last waypoint. If any waypoint is not
+    /// reached, the joint positions are restored and it returns
+    /// `Error::UnreachableWaypointError` with the index of the waypoint.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let chain = Chain::<f64>::from_urdf_file("urdf/sample.urdf").unwrap();
+    /// let arm = SerialChain::from_end(chain.find("l_wrist_pitch").unwrap());
+    /// arm.set_joint_positions(&[0.1, 0.2, 0.3, -0.5, 0.4, -0.3]).unwrap();
+    /// let start = arm.end_transform();
+    /// let mut end = start.clone();
+    /// end.translation.vector.z += 0.05;
+    /// let waypoints = trajectory::cartesian_interpolate(&start, &end, 5);
+    /// let solver = JacobianIkSolver::default();
+    /// let trajectory = arm.follow_cartesian_path(&waypoints, &solver).unwrap();
+    /// assert_eq!(trajectory.len(), 6);
+    /// ```
+    pub fn follow_cartesian_path(
+        &self,
+        waypoints: &[Isometry3<T>],
+        solver: &dyn InverseKinematicsSolver<T>,
+    ) -> Result<Vec<Vec<T>>, Error> {
+        let orig_positions = self.joint_positions();
+        let mut trajectory = Vec::with_capacity(waypoints.len());
+        for (index, waypoint) in waypoints.iter().enumerate() {
+            if let Err(err) = solver.solve(self, waypoint) {
+                self.set_joint_positions_unchecked(&orig_positions);
+                return Err(Error::UnreachableWaypointError {
+                    index,
+                    source: Box::new(err),
+                });
+            }
+            trajectory.push(self.joint_positions());
+        }
+        Ok(trajectory)
+    }
+
+    /// Sample the orientations of the end which are achievable at `position`
+    ///
+    /// Position-only IK to `position` is solved from `samples` random joint positions
+    /// within the limits, and the rotations of the end of the converged ones are returned.
+    /// A wider spread of the orientations means higher dexterity at the position.
+    /// The result is empty if `position` is not reached.
+    ///
+    /// The joint positions are restored after the calculation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let chain = Chain::<f64>::from_urdf_file("urdf/sample.urdf").unwrap();
+    /// let arm = SerialChain::from_end(chain.find("l_wrist_pitch").unwrap());
+    /// arm.set_joint_positions(&[0.1, 0.2, 0.3, -0.5, 0.4, -0.3]).unwrap();
+    /// let position = nalgebra::Point3::from(arm.end_transform().translation.vector);
+    /// let orientations = arm.orientation_workspace(&position, 10);
+    /// assert!(!orientations.is_empty());
+    /// ```
+    pub fn orientation_workspace(
+        &self,
+        position: &Point3<T>,
+        samples: usize,
+    ) -> Vec<UnitQuaternion<T>> {
+        const SEED: u64 = 0;
+        const NUM_MAX_TRY: usize = 100;
+        let solver = JacobianIkSolver {
+            num_max_try: NUM_MAX_TRY,
+            ..Default::default()
+        };
+        let constraints = Constraints {
+            rotation_x: false,
+            rotation_y: false,
+            rotation_z: false,
+            ..Default::default()
+        };
+        let target =
+            Isometry3::from_parts(position.coords.clone().into(), UnitQuaternion::identity());
+        let orig_positions = self.joint_positions();
+        let mut rng = SplitMix64::new(SEED);
+        let mut orientations = Vec::new();
+        for _ in 0..samples {
+            self.without_position_reports(|| {
+                self.set_joint_positions_clamped(&sample_joint_positions(self, &mut rng))
+            });
+            if self
+                .without_position_reports(|| {
+                    solver.solve_with_constraints(self, &target, &constraints)
+                })
+                .is_ok()
+            {
+                orientations.push(self.calc_end_transform().rotation);
+            }
+        }
+        self.set_joint_positions_unchecked(&orig_positions);
+        orientations
+    }
+
+    /// Find multiple distinct IK solutions for `target`, like elbow up / down
+    ///
+    /// See `solve_all_with_constraints` for the details.
+    pub fn solve_all(&self, target: &Isometry3<T>) -> Vec<Vec<T>> {
+        self.solve_all_with_constraints(target, &Constraints::default())
+    }
+
+    /// Find multiple distinct IK solutions for `target` with constraints
+    ///
+    /// The IK is solved from the current joint positions and from random joint positions
+    /// within the limits, and the converged positions are collected. Rotational joints
+    /// without limits are wrapped into `[-pi, pi]`, and the solutions which are close to
+    /// the already found ones in joint space are removed. The solution from the current
+    /// positions comes first if it exists. Since the seeds are random, it is not
+    /// guaranteed that all the branches are found.
+    ///
+    /// The joint positions are restored after the calculation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let chain = Chain::<f64>::from_urdf_file("urdf/sample.urdf").unwrap();
+    /// let arm = SerialChain::from_end(chain.find("l_wrist_pitch").unwrap());
+    /// arm.set_joint_positions(&[0.1, 0.2, 0.3, -0.5, 0.4, -0.3]).unwrap();
+    /// let target = arm.end_transform();
+    /// let solutions = arm.solve_all(&target);
+    /// assert!(!solutions.is_empty());
+    /// ```
+    pub fn solve_all_with_constraints(
+        &self,
+        target: &Isometry3<T>,
+        constraints: &Constraints,
+    ) -> Vec<Vec<T>> {
+        const SEED: u64 = 0;
+        const NUM_SEEDS: usize = 30;
+        const NUM_MAX_TRY: usize = 100;
+        let allowable_distance: T = na::convert(0.01);
+        let solver = JacobianIkSolver {
+            num_max_try: NUM_MAX_TRY,
+            ..Default::default()
+        };
+        let wrapped = self
+            .iter_joints()
+            .map(|joint| {
+                matches!(joint.joint_type, JointType::Rotational { .. }) && joint.limits.is_none()
+            })
+            .collect::<Vec<_>>();
+        let orig_positions = self.joint_positions();
+        let mut rng = SplitMix64::new(SEED);
+        let mut solutions: Vec<Vec<T>> = Vec::new();
+        for i in 0..=NUM_SEEDS {
+            if i > 0 {
+                self.without_position_reports(|| {
+                    self.set_joint_positions_clamped(&sample_joint_positions(self, &mut rng))
+                });
+            }
+            if self
+                .without_position_reports(|| {
+                    solver.solve_with_constraints(self, target, constraints)
+                })
+                .is_err()
+            {
+                continue;
+            }
+            let solution = self
+                .joint_positions()
+                .into_iter()
+                .zip(wrapped.iter())
+                .map(|(q, &wrap)| if wrap { wrap_angle(q) } else { q })
+                .collect::<Vec<_>>();
+            if solutions
+                .iter()
+                .all(|s| positions_distance(s, &solution) > allowable_distance)
+            {
+                solutions.push(solution);
+            }
+        }
+        self.set_joint_positions_unchecked(&orig_positions);
+        solutions
+    }
+}
+
 /// Utility function to create nullspace function using reference joint positions.
 /// This is just an example to use nullspace.
 ///
@@ -510,9 +1331,91 @@ pub fn create_reference_positions_nullspace_function<T: RealField>(
     }
 }
 
+/// Create nullspace function which moves the joints away from the limits
+///
+/// It returns `-weight * gradient` of `Chain::joint_limit_avoidance_gradient`
+/// for the limits of the movable joints of `arm`, because the output of the nullspace
+/// function is added to the positions.
+///
+/// # Examples
+///
+/// ```
+/// use k::*;
+///
+/// let chain = Chain::<f64>::from_urdf_file("urdf/sample.urdf").unwrap();
+/// let arm = SerialChain::from_end(chain.find("l_wrist_pitch").unwrap());
+/// let mut solver = JacobianIkSolver::default();
+/// solver.set_nullspace_function(Box::new(
+///     create_joint_limit_avoidance_nullspace_function(&arm, 0.1),
+/// ));
+/// ```
+pub fn create_joint_limit_avoidance_nullspace_function<T>(
+    arm: &SerialChain<T>,
+    weight: T,
+) -> impl Fn(&[T]) -> Vec<T>
+where
+    T: RealField + SubsetOf<f64>,
+{
+    let limits = arm
+        .iter_joints()
+        .map(|joint| joint.limits.clone())
+        .collect::<Vec<_>>();
+    move |positions| {
+        joint_limit_avoidance_gradient(&limits, positions)
+            .into_iter()
+            .map(|g| -weight.clone() * g)
+            .collect()
+    }
+}
+
+/// Solve IK of the serial chain from the root of `chain` to `end_joint_name` without changing `chain`
+///
+/// The solver works on a clone of `chain`, and the positions of the movable joints
+/// of the serial chain (from the root to the end) are returned.
+///
+/// # Examples
+///
+/// ```
+/// use k::*;
+///
+/// let chain = Chain::<f64>::from_urdf_file("urdf/sample.urdf").unwrap();
+/// let arm = SerialChain::from_end(chain.find("l_wrist_pitch").unwrap());
+/// arm.set_joint_positions(&[0.1, 0.2, 0.0, -0.5, 0.0, -0.3]).unwrap();
+/// let target = arm.end_transform();
+/// let initial = vec![0.0, 0.1, 0.0, -0.4, 0.0, -0.2];
+/// arm.set_joint_positions(&initial).unwrap();
+///
+/// let solver = JacobianIkSolver::default();
+/// let positions = solve_ik(&chain, "l_wrist_pitch", &target, &solver).unwrap();
+/// assert_eq!(positions.len(), 6);
+/// // chain is not changed
+/// assert_eq!(arm.joint_positions(), initial);
+/// ```
+pub fn solve_ik<T, S>(
+    chain: &Chain<T>,
+    end_joint_name: &str,
+    target_pose: &Isometry3<T>,
+    solver: &S,
+) -> Result<Vec<T>, Error>
+where
+    T: RealField + SubsetOf<f64>,
+    S: InverseKinematicsSolver<T> + ?Sized,
+{
+    let chain = chain.clone();
+    let end = chain
+        .find(end_joint_name)
+        .ok_or_else(|| Error::InvalidJointNameError {
+            joint_name: end_joint_name.to_owned(),
+        })?;
+    let arm = SerialChain::from_end(end);
+    solver.solve(&arm, target_pose)?;
+    Ok(arm.joint_positions())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::urdf::sample_chain;
     #[cfg(target_family = "wasm")]
     use wasm_bindgen_test::wasm_bindgen_test as test;
 
@@ -525,4 +1428,186 @@ mod tests {
         assert!((values[0] - 0.25f64).abs() < f64::EPSILON);
         assert!((values[1] - (-0.05f64)).abs() < f64::EPSILON);
     }
+
+    fn create_planar_arm() -> SerialChain<f64> {
+        use crate::joint::*;
+        use crate::node::*;
+        let l0 = NodeBuilder::new()
+            .joint_type(JointType::Rotational {
+                axis: Vector3::y_axis(),
+            })
+            .into_node();
+        let l1 = NodeBuilder::new()
+            .translation(na::Translation3::new(1.0, 0.0, 0.0))
+            .joint_type(JointType::Rotational {
+                axis: Vector3::y_axis(),
+            })
+            .into_node();
+        let l2 = NodeBuilder::new()
+            .translation(na::Translation3::new(1.0, 0.0, 0.0))
+            .into_node();
+        l1.set_parent(&l0);
+        l2.set_parent(&l1);
+        SerialChain::from_end(&l2)
+    }
+
+    #[test]
+    fn test_variable_damping_near_singularity() {
+        const PLANAR: [bool; 6] = [true, false, true, false, false, false];
+        let arm = create_planar_arm();
+        let target = Isometry3::translation(1.9, 0.0, 0.0);
+        let mut solver = JacobianIkSolver::new(0.001, 0.001, 1.0, 100);
+
+        // almost stretched
+        arm.set_joint_positions(&[0.0, 1.0e-4]).unwrap();
+        solver
+            .solve_one_loop_with_constraints(
+                &arm,
+                &target,
+                &PLANAR,
+                &[],
+                &[0.0, 1.0e-4],
+                &mut JacobianEstimate::new(),
+            )
+            .unwrap();
+        let undamped_step = arm.joint_positions()[1] - 1.0e-4;
+        assert!(undamped_step.abs() > 10.0);
+
+        solver.set_variable_damping(0.1, 0.1);
+        arm.set_joint_positions(&[0.0, 1.0e-4]).unwrap();
+        solver
+            .solve_one_loop_with_constraints(
+                &arm,
+                &target,
+                &PLANAR,
+                &[],
+                &[0.0, 1.0e-4],
+                &mut JacobianEstimate::new(),
+            )
+            .unwrap();
+        let damped_step = arm.joint_positions()[1] - 1.0e-4;
+        assert!(damped_step.abs() < 1.0);
+    }
+
+    #[test]
+    fn test_broyden_jacobian_evaluations() {
+        const PLANAR: [bool; 6] = [true, false, true, false, false, false];
+        let target = Isometry3::translation(1.2, 0.0, -0.9);
+        let solve = |broyden_interval: usize| {
+            let arm = create_planar_arm();
+            arm.set_joint_positions(&[0.3, 0.5]).unwrap();
+            let mut solver = JacobianIkSolver::new(1.0e-6, 1.0e-6, 0.5, 100);
+            solver.broyden_interval = broyden_interval;
+            let mut estimate = JacobianEstimate::new();
+            let mut evaluations = 0;
+            for _ in 0..40 {
+                solver
+                    .solve_one_loop_with_constraints(
+                        &arm,
+                        &target,
+                        &PLANAR,
+                        &[],
+                        &[0.3, 0.5],
+                        &mut estimate,
+                    )
+                    .unwrap();
+                // the age is reset only when the jacobian is calculated, not estimated
+                if estimate.age == 0 {
+                    evaluations += 1;
+                }
+            }
+            (arm.end_transform(), evaluations)
+        };
+        let (full, full_evaluations) = solve(0);
+        let (broyden, broyden_evaluations) = solve(5);
+        assert_eq!(full_evaluations, 40);
+        assert_eq!(broyden_evaluations, 8);
+        assert!((full.translation.vector - target.translation.vector).norm() < 1.0e-6);
+        assert!((broyden.translation.vector - target.translation.vector).norm() < 1.0e-6);
+    }
+
+    #[test]
+    fn test_variable_damping_far_from_singularity() {
+        let arm = create_planar_arm();
+        let constraints = Constraints {
+            position_y: false,
+            rotation_x: false,
+            rotation_y: false,
+            rotation_z: false,
+            ..Default::default()
+        };
+        let target = Isometry3::translation(1.2, 0.0, -0.8);
+        let solver = JacobianIkSolver::new(0.0001, 0.001, 0.5, 100);
+        arm.set_joint_positions(&[0.3, 1.0]).unwrap();
+        solver
+            .solve_with_constraints(&arm, &target, &constraints)
+            .unwrap();
+        let undamped = arm.joint_positions();
+
+        let mut solver = solver;
+        solver.set_variable_damping(0.1, 0.1);
+        arm.set_joint_positions(&[0.3, 1.0]).unwrap();
+        solver
+            .solve_with_constraints(&arm, &target, &constraints)
+            .unwrap();
+        assert_eq!(arm.joint_positions(), undamped);
+        let reached = arm.end_transform().translation.vector;
+        assert!((reached - target.translation.vector).norm() < 0.0001);
+    }
+
+    #[test]
+    fn test_project_onto_workspace() {
+        let arm = create_planar_arm();
+        arm.set_joint_positions(&[0.1, 0.3]).unwrap();
+
+        let target = Point3::new(3.0, 0.0, -3.0);
+        let projected = arm.project_onto_workspace(&target);
+        // on the boundary (fully stretched)
+        assert!((projected.coords.norm() - 2.0).abs() < 0.01);
+        // along the target direction
+        let dir = target.coords.normalize();
+        assert!((projected.coords.normalize() - dir).norm() < 0.01);
+        // positions are restored
+        assert_eq!(arm.joint_positions(), vec![0.1, 0.3]);
+    }
+
+    #[test]
+    fn test_reachable_z_range() {
+        use crate::joint::*;
+        use crate::node::*;
+        // sphere of radius 2
+        let yaw = NodeBuilder::new()
+            .joint_type(JointType::Rotational {
+                axis: Vector3::z_axis(),
+            })
+            .into_node();
+        let arm = create_planar_arm();
+        arm.iter().next().unwrap().set_parent(&yaw);
+        let arm = SerialChain::from_end(arm.iter().last().unwrap());
+        arm.set_joint_positions(&[0.0, 0.0, 0.5]).unwrap();
+        let (min, max) = arm.reachable_z_range(1.0, 0.0, 21).unwrap();
+        let expected = 3.0f64.sqrt();
+        assert!((min + expected).abs() < 0.2);
+        assert!((max - expected).abs() < 0.2);
+        assert!(arm.reachable_z_range(2.5, 0.0, 5).is_none());
+        assert_eq!(arm.joint_positions(), vec![0.0, 0.0, 0.5]);
+    }
+
+    #[test]
+    fn test_is_reachable() {
+        let chain = sample_chain();
+        let arm = SerialChain::from_end(chain.find("l_wrist_pitch").unwrap());
+        arm.set_joint_positions(&[0.8, -0.5, 1.0, -1.2, 0.5, 0.3])
+            .unwrap();
+        let in_range = arm.end_transform();
+        let start = vec![0.1, 0.2, 0.3, -0.5, 0.4, -0.3];
+        arm.set_joint_positions(&start).unwrap();
+        assert!(arm.is_reachable(&in_range, 20));
+        assert_eq!(arm.joint_positions(), start);
+
+        let mut out_of_range = in_range;
+        out_of_range.translation.vector.x += 2.0;
+        assert!(!arm.is_reachable(&out_of_range, 20));
+        assert_eq!(arm.joint_positions(), start);
+    }
 }
@@ -17,7 +17,7 @@ use super::joint_type::*;
 use super::range::*;
 use super::velocity::*;
 use crate::errors::*;
-use na::{Isometry3, RealField, Translation3, UnitQuaternion};
+use na::{Isometry3, RealField, UnitQuaternion};
 use nalgebra as na;
 use simba::scalar::SubsetOf;
 use std::cell::RefCell;
@@ -38,6 +38,12 @@ pub struct Joint<T: RealField> {
     pub limits: Option<Range<T>>,
     /// local origin transform of joint
     origin: Isometry3<T>,
+    /// pose of the floating base, which is applied before the origin (only for the root)
+    base_pose: Option<Isometry3<T>>,
+    /// limits before `Chain::apply_limit_margin` is called first, and the limits it set
+    limits_without_margin: Option<(Range<T>, Range<T>)>,
+    /// incremented when the origin or the base pose is changed
+    revision: usize,
     /// cache of world transform
     world_transform_cache: RefCell<Option<Isometry3<T>>>,
     /// cache of world velocity
@@ -72,6 +78,9 @@ where
             velocity: T::zero(),
             limits: None,
             origin: Isometry3::identity(),
+            base_pose: None,
+            limits_without_margin: None,
+            revision: 0,
             world_transform_cache: RefCell::new(None),
             world_velocity_cache: RefCell::new(None),
         }
@@ -102,6 +111,14 @@ where
     /// ```
     ///
     pub fn set_joint_position(&mut self, position: T) -> Result<(), Error> {
+        self.check_joint_position(&position)?;
+        self.position = position;
+        self.clear_caches();
+        Ok(())
+    }
+
+    /// Check that `position` can be set by `set_joint_position` without setting it
+    pub(crate) fn check_joint_position(&self, position: &T) -> Result<(), Error> {
         if !self.is_movable() {
             return Err(Error::SetToFixedError {
                 joint_name: self.name.to_string(),
@@ -111,14 +128,12 @@ where
             if !range.is_valid(position.clone()) {
                 return Err(Error::OutOfLimitError {
                     joint_name: self.name.to_string(),
-                    position: na::try_convert(position).unwrap_or_default(),
+                    position: na::try_convert(position.clone()).unwrap_or_default(),
                     max_limit: na::try_convert(range.max.clone()).unwrap_or_default(),
                     min_limit: na::try_convert(range.min.clone()).unwrap_or_default(),
                 });
             }
         }
-        self.position = position;
-        self.clear_caches();
         Ok(())
     }
     /// Set the clamped position of the joint
@@ -155,6 +170,69 @@ where
         };
         self.set_joint_position_unchecked(position_clamped);
     }
+    /// Set the position from the rotation of the joint
+    ///
+    /// `rotation` is the rotation of this joint relative to the origin.
+    /// It is projected onto the axis, and the signed angle around the axis is used as the position.
+    /// Returns the residual rotation which can not be expressed by this joint
+    /// (`rotation = rotation(position) * residual`).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use nalgebra as na;
+    ///
+    /// let mut rot = k::Joint::<f64>::new("r0", k::JointType::Rotational { axis: na::Vector3::y_axis() });
+    /// let q = na::UnitQuaternion::from_axis_angle(&na::Vector3::y_axis(), 0.5);
+    /// let residual = rot.set_joint_position_from_rotation(&q).unwrap();
+    /// assert!((rot.joint_position().unwrap() - 0.5).abs() < 1e-10);
+    /// assert!(residual.angle() < 1e-10);
+    ///
+    /// // the rotation around other axes remains as the residual
+    /// let swing = na::UnitQuaternion::from_axis_angle(&na::Vector3::x_axis(), 0.2);
+    /// let q = na::UnitQuaternion::from_axis_angle(&na::Vector3::y_axis(), -2.0) * swing;
+    /// let residual = rot.set_joint_position_from_rotation(&q).unwrap();
+    /// assert!((rot.joint_position().unwrap() + 2.0).abs() < 0.05);
+    /// assert!((residual.angle() - 0.2).abs() < 0.05);
+    ///
+    /// // linear joint can not be set by rotation
+    /// let mut lin = k::Joint::<f64>::new("l0", k::JointType::Linear { axis: na::Vector3::x_axis() });
+    /// assert!(lin.set_joint_position_from_rotation(&q).is_err());
+    /// ```
+    pub fn set_joint_position_from_rotation(
+        &mut self,
+        rotation: &UnitQuaternion<T>,
+    ) -> Result<UnitQuaternion<T>, Error> {
+        let (position, residual) = self.decompose_rotation(rotation)?;
+        self.set_joint_position(position)?;
+        Ok(residual)
+    }
+
+    /// Decompose `rotation` into the position around the axis and the residual rotation
+    pub(crate) fn decompose_rotation(
+        &self,
+        rotation: &UnitQuaternion<T>,
+    ) -> Result<(T, UnitQuaternion<T>), Error> {
+        let axis = match &self.joint_type {
+            JointType::Rotational { axis } => axis,
+            _ => {
+                return Err(Error::NotRotationalJointError {
+                    joint_name: self.name.to_string(),
+                })
+            }
+        };
+        let q = rotation.quaternion();
+        // use the same hemisphere to get the angle in [-pi, pi]
+        let (w, v) = if q.w < T::zero() {
+            (-q.w.clone(), -q.imag())
+        } else {
+            (q.w.clone(), q.imag())
+        };
+        let position = v.dot(axis).atan2(w) * na::convert(2.0);
+        let twist = UnitQuaternion::from_axis_angle(axis, position.clone());
+        let residual = twist.inverse() * rotation;
+        Ok((position, residual))
+    }
     pub fn set_joint_position_unchecked(&mut self, position: T) {
         self.position = position;
         self.clear_caches();
@@ -176,6 +254,7 @@ where
     #[inline]
     pub fn set_origin(&mut self, origin: Isometry3<T>) {
         self.origin = origin;
+        self.revision = self.revision.wrapping_add(1);
         self.clear_caches();
     }
 
@@ -214,18 +293,75 @@ where
     /// ```
     ///
     pub fn local_transform(&self) -> Isometry3<T> {
-        let joint_transform = match &self.joint_type {
-            JointType::Fixed => Isometry3::identity(),
-            JointType::Rotational { axis } => Isometry3::from_parts(
-                Translation3::new(T::zero(), T::zero(), T::zero()),
-                UnitQuaternion::from_axis_angle(axis, self.position.clone()),
-            ),
-            JointType::Linear { axis } => Isometry3::from_parts(
-                Translation3::from(axis.clone().into_inner() * self.position.clone()),
-                UnitQuaternion::identity(),
-            ),
+        let transform = self.origin.clone() * self.joint_type.transform(self.position.clone());
+        match &self.base_pose {
+            Some(base_pose) => base_pose.clone() * transform,
+            None => transform,
+        }
+    }
+
+    /// Pose of the floating base, see `Chain::set_base_pose`
+    pub(crate) fn base_pose(&self) -> Isometry3<T> {
+        self.base_pose.clone().unwrap_or_else(Isometry3::identity)
+    }
+
+    pub(crate) fn set_base_pose(&mut self, base_pose: Isometry3<T>) {
+        self.base_pose = Some(base_pose);
+        self.revision = self.revision.wrapping_add(1);
+        self.clear_caches();
+    }
+
+    /// The limits before the margin, if the limits are not changed since it is applied
+    ///
+    /// `limits` is a public field, so the limits edited by the user are the new original.
+    fn limits_without_margin(&self) -> Option<&Range<T>> {
+        let (original, applied) = self.limits_without_margin.as_ref()?;
+        match &self.limits {
+            Some(limits) if limits.min == applied.min && limits.max == applied.max => {
+                Some(original)
+            }
+            _ => None,
+        }
+    }
+
+    /// Shrink the limits toward their middle to `fraction` times the original range
+    ///
+    /// The original range is saved at the first call, so the next call replaces the margin.
+    /// If `limits` is changed after that, the changed limits are used as the original.
+    /// It does nothing if the joint has no limits.
+    pub(crate) fn set_limit_margin(&mut self, fraction: T) {
+        let Some(range) = self
+            .limits_without_margin()
+            .or(self.limits.as_ref())
+            .cloned()
+        else {
+            self.limits_without_margin = None;
+            return;
         };
-        self.origin.clone() * joint_transform
+        let two: T = na::convert(2.0);
+        let mid = (range.min.clone() + range.max.clone()) / two.clone();
+        let half = (range.max.clone() - range.min.clone()) * fraction / two;
+        let limits = Range::new(mid.clone() - half.clone(), mid + half);
+        self.limits_without_margin = Some((range, limits.clone()));
+        self.limits = Some(limits);
+    }
+
+    /// Ratio of the current range of the limits to the original one, if a margin is applied
+    pub(crate) fn limit_margin(&self) -> Option<T> {
+        let original = self.limits_without_margin()?;
+        let limits = self.limits.as_ref()?;
+        let width = original.max.clone() - original.min.clone();
+        if width <= T::zero() {
+            return Some(T::one());
+        }
+        Some((limits.max.clone() - limits.min.clone()) / width)
+    }
+
+    /// Counter of the changes of the origin and the base pose
+    ///
+    /// It is used to invalidate the cache of `SerialChain::end_transform`.
+    pub(crate) fn revision(&self) -> usize {
+        self.revision
     }
 
     #[inline]
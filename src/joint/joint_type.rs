@@ -13,10 +13,12 @@
   See the License for the specific language governing permissions and
   limitations under the License.
 */
-use nalgebra::{RealField, Unit, Vector3};
+use nalgebra::{Isometry3, RealField, Translation3, Unit, UnitQuaternion, Vector3};
 use std::fmt::{self, Display};
 
 /// Type of Joint, `Fixed`, `Rotational`, `Linear` is supported now
+///
+/// A floating base is not a joint type, see `Chain::set_base_pose`.
 #[derive(Copy, Debug, Clone)]
 pub enum JointType<T: RealField> {
     /// Fixed joint. It has no `joint_position` and axis.
@@ -33,6 +35,23 @@ pub enum JointType<T: RealField> {
     },
 }
 
+impl<T: RealField> JointType<T> {
+    /// Transform by the motion of the joint at `position` (without the origin)
+    pub(crate) fn transform(&self, position: T) -> Isometry3<T> {
+        match self {
+            JointType::Fixed => Isometry3::identity(),
+            JointType::Rotational { axis } => Isometry3::from_parts(
+                Translation3::new(T::zero(), T::zero(), T::zero()),
+                UnitQuaternion::from_axis_angle(axis, position),
+            ),
+            JointType::Linear { axis } => Isometry3::from_parts(
+                Translation3::from(axis.clone().into_inner() * position),
+                UnitQuaternion::identity(),
+            ),
+        }
+    }
+}
+
 fn axis_to_string<T: RealField>(axis: &Unit<Vector3<T>>) -> &str {
     if *axis == Vector3::x_axis() {
         "+X"
@@ -14,6 +14,8 @@
   limitations under the License.
 */
 use nalgebra::RealField;
+use std::fmt;
+use std::sync::Arc;
 
 /// Information for copying joint state of other joint
 ///
@@ -26,6 +28,8 @@ use nalgebra::RealField;
 ///
 /// output position (mimic_position() is calculated by `joint positions = joint[name] * multiplier + origin`
 ///
+/// Nonlinear couplings are set by `Node::set_mimic_parent_fn`, see `MimicMapping`.
+///
 #[derive(Debug, Clone)]
 pub struct Mimic<T: RealField> {
     pub multiplier: T,
@@ -63,3 +67,66 @@ where
         from_position * self.multiplier.clone() + self.origin.clone()
     }
 }
+
+/// Mapping from the position of the source joint to the position of the mimic joint
+///
+/// `Linear` is set by `Node::set_mimic_parent`, and `Nonlinear` is set by
+/// `Node::set_mimic_parent_fn` for the couplings like gear profiles.
+#[derive(Clone)]
+#[non_exhaustive]
+pub enum MimicMapping<T: RealField> {
+    /// `multiplier * position + origin`
+    Linear(Mimic<T>),
+    /// any function of the position
+    Nonlinear(Arc<dyn Fn(T) -> T + Send + Sync>),
+}
+
+impl<T> MimicMapping<T>
+where
+    T: RealField,
+{
+    /// Calculate the mimic joint position
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    /// use k::joint::*;
+    ///
+    /// let m = MimicMapping::Linear(Mimic::<f64>::new(1.0, 0.5));
+    /// assert_eq!(m.mimic_position(0.2), 0.7);
+    /// let m = MimicMapping::<f64>::Nonlinear(Arc::new(|x| x * x));
+    /// assert_eq!(m.mimic_position(0.5), 0.25);
+    /// ```
+    pub fn mimic_position(&self, from_position: T) -> T {
+        match self {
+            MimicMapping::Linear(mimic) => mimic.mimic_position(from_position),
+            MimicMapping::Nonlinear(function) => function(from_position),
+        }
+    }
+}
+
+impl<T: RealField> From<Mimic<T>> for MimicMapping<T> {
+    fn from(mimic: Mimic<T>) -> Self {
+        MimicMapping::Linear(mimic)
+    }
+}
+
+/// The function of a nonlinear mimic joint, which is kept by `NodeImpl`
+#[derive(Clone)]
+pub(crate) struct MimicFn<T>(pub(crate) Arc<dyn Fn(T) -> T + Send + Sync>);
+
+impl<T> fmt::Debug for MimicFn<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Fn(T) -> T")
+    }
+}
+
+impl<T: RealField> fmt::Debug for MimicMapping<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MimicMapping::Linear(mimic) => f.debug_tuple("Linear").field(mimic).finish(),
+            MimicMapping::Nonlinear(_) => f.debug_tuple("Nonlinear").field(&"Fn(T) -> T").finish(),
+        }
+    }
+}
@@ -17,26 +17,56 @@
 #![doc = include_str!("../README.md")]
 #![allow(clippy::needless_doctest_main)]
 
+mod analytic_ik;
+mod builder;
 mod chain;
+mod collision;
+mod command_log;
+mod dh;
+mod dot;
 mod errors;
+mod euler;
+mod filter;
+mod fk_cache;
+mod flat;
 mod funcs;
 mod ik;
+mod pid;
+mod planar;
+mod state;
+mod telemetry;
+mod validate;
 
+pub mod collada;
 pub mod iterator;
 pub mod joint;
 pub mod link;
 pub mod node;
 pub mod prelude;
+pub mod trajectory;
 pub mod urdf;
 
 pub use crate::{
+    analytic_ik::*,
+    builder::*,
     chain::*,
+    collision::*,
+    command_log::*,
+    dh::*,
     errors::*,
+    euler::*,
+    filter::*,
+    flat::*,
     funcs::*,
     ik::*,
     joint::{Joint, JointType},
     link::Link,
     node::{Node, NodeBuilder},
+    pid::*,
+    planar::*,
+    state::*,
+    telemetry::*,
+    validate::*,
 };
 
 // re-export from nalgebra
@@ -41,6 +41,21 @@ where
     pub mimic_children: Vec<Node<T>>,
     pub mimic: Option<Mimic<T>>,
     pub link: Option<Link<T>>,
+    /// the function which is set by `Node::set_mimic_parent_fn`, used instead of `mimic`
+    mimic_fn: Option<MimicFn<T>>,
+}
+
+impl<T> NodeImpl<T>
+where
+    T: RealField,
+{
+    /// The mapping from the position of the mimic parent, if this is a mimic joint
+    pub(crate) fn mimic_mapping(&self) -> Option<MimicMapping<T>> {
+        match &self.mimic_fn {
+            Some(function) => Some(MimicMapping::Nonlinear(function.0.clone())),
+            None => self.mimic.clone().map(MimicMapping::Linear),
+        }
+    }
 }
 
 /// Parts of `Chain`
@@ -66,6 +81,7 @@ where
             mimic_children: Vec::new(),
             mimic: None,
             link: None,
+            mimic_fn: None,
         })))
     }
 
@@ -98,6 +114,9 @@ where
         Ancestors::new(Some(self.clone()))
     }
     /// iter to the end, it contains `nodes[id]` itself
+    ///
+    /// The order is depth-first (pre-order), and the children are visited in the order
+    /// in which they were added by `set_parent`, so it is the same for the same tree.
     #[inline]
     pub fn iter_descendants(&self) -> Descendants<T> {
         Descendants::new(vec![self.clone()])
@@ -208,7 +227,7 @@ where
         node.joint.set_joint_position(position.clone())?;
         for child in &node.mimic_children {
             let mut child_node = child.lock();
-            let mimic = child_node.mimic.clone();
+            let mimic = child_node.mimic_mapping();
             match mimic {
                 Some(m) => child_node
                     .joint
@@ -248,6 +267,18 @@ where
             .set_joint_position_clamped(position);
     }
 
+    /// Set the position from the rotation of the joint relative to its origin
+    ///
+    /// See `Joint::set_joint_position_from_rotation`. This also updates the mimic children.
+    pub fn set_joint_position_from_rotation(
+        &self,
+        rotation: &UnitQuaternion<T>,
+    ) -> Result<UnitQuaternion<T>, Error> {
+        let (position, residual) = self.joint().decompose_rotation(rotation)?;
+        self.set_joint_position(position)?;
+        Ok(residual)
+    }
+
     #[inline]
     pub fn set_joint_position_unchecked(&self, position: T) {
         self.0
@@ -257,6 +288,13 @@ where
             .set_joint_position_unchecked(position);
     }
 
+    /// Set the velocity of the joint
+    ///
+    /// It returns Err if this is fixed joint.
+    pub fn set_joint_velocity(&self, velocity: T) -> Result<(), Error> {
+        self.lock().joint.set_joint_velocity(velocity)
+    }
+
     pub(crate) fn parent_world_transform(&self) -> Option<Isometry3<T>> {
         //match self.0.borrow().parent {
         match self.parent() {
@@ -317,9 +355,54 @@ where
     }
 
     pub fn set_mimic_parent(&self, parent: &Node<T>, mimic: Mimic<T>) {
+        self.set_mimic_mapping(parent, mimic.into());
+    }
+
+    /// Set the mimic parent with a nonlinear mapping from the position of `parent`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    /// let j0 = NodeBuilder::new()
+    ///     .joint_type(JointType::Linear{axis: Vector3::z_axis()})
+    ///     .into_node();
+    /// let j1 = NodeBuilder::new()
+    ///     .joint_type(JointType::Linear{axis: Vector3::z_axis()})
+    ///     .into_node();
+    /// j1.set_mimic_parent_fn(&j0, |x: f64| x * x);
+    /// j0.set_joint_position(0.5).unwrap();
+    /// assert_eq!(j1.joint_position().unwrap(), 0.25);
+    /// ```
+    pub fn set_mimic_parent_fn<F>(&self, parent: &Node<T>, function: F)
+    where
+        F: Fn(T) -> T + Send + Sync + 'static,
+    {
+        self.set_mimic_mapping(parent, MimicMapping::Nonlinear(Arc::new(function)));
+    }
+
+    /// The mapping from the position of the mimic parent, if this is a mimic joint
+    ///
+    /// It is `MimicMapping::Linear` with `NodeImpl::mimic` set by `set_mimic_parent`,
+    /// or `MimicMapping::Nonlinear` set by `set_mimic_parent_fn`.
+    pub fn mimic_mapping(&self) -> Option<MimicMapping<T>> {
+        self.lock().mimic_mapping()
+    }
+
+    pub(crate) fn set_mimic_mapping(&self, parent: &Node<T>, mimic: MimicMapping<T>) {
         self.lock().mimic_parent = Some(Arc::downgrade(&parent.0));
         parent.lock().mimic_children.push(self.clone());
-        self.lock().mimic = Some(mimic);
+        let mut node = self.lock();
+        match mimic {
+            MimicMapping::Linear(mimic) => {
+                node.mimic = Some(mimic);
+                node.mimic_fn = None;
+            }
+            MimicMapping::Nonlinear(function) => {
+                node.mimic = None;
+                node.mimic_fn = Some(MimicFn(function));
+            }
+        }
     }
 
     pub fn set_link(&self, link: Option<Link<T>>) {
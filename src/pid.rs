@@ -0,0 +1,225 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+use super::chain::*;
+use super::errors::*;
+use na::RealField;
+use nalgebra as na;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use simba::scalar::SubsetOf;
+
+/// Gains of the PID controller used by `Chain::step_pid`
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PidGains<T> {
+    pub kp: T,
+    pub ki: T,
+    pub kd: T,
+}
+
+impl<T> PidGains<T> {
+    pub fn new(kp: T, ki: T, kd: T) -> Self {
+        Self { kp, ki, kd }
+    }
+}
+
+/// State of the PID controller used by `Chain::step_pid`
+///
+/// It keeps the integral of the errors of each joint, so use one controller
+/// for one chain and call `reset()` when the targets or the joint order are changed.
+/// The integral is not accumulated while the joint is stopped by its limit, and
+/// its magnitude is clamped by `max_integral` if it is set (anti-windup).
+#[derive(Debug, Clone, PartialEq)]
+pub struct PidController<T> {
+    pub gains: PidGains<T>,
+    /// Max of the absolute value of the integral of each joint
+    pub max_integral: Option<T>,
+    integrals: Vec<T>,
+}
+
+impl<T> PidController<T>
+where
+    T: RealField,
+{
+    pub fn new(gains: PidGains<T>) -> Self {
+        Self {
+            gains,
+            max_integral: None,
+            integrals: Vec::new(),
+        }
+    }
+
+    /// The integral of the errors of each joint, empty before the first step
+    pub fn integrals(&self) -> &[T] {
+        &self.integrals
+    }
+
+    /// Clear the integral of the errors
+    pub fn reset(&mut self) {
+        self.integrals.clear();
+    }
+}
+
+impl<T> Chain<T>
+where
+    T: RealField + SubsetOf<f64>,
+{
+    /// Move the joints toward `targets` by one step of a PID controller
+    ///
+    /// This is a lightweight helper for demos, not a dynamics simulation.
+    /// The output of the controller is used as the joint velocity (first order model),
+    /// and the positions are integrated by `dt` and clamped by the limits.
+    /// The derivative term uses the current joint velocity instead of the
+    /// derivative of the error. The integral of the errors is kept in `controller`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let l0 = NodeBuilder::new()
+    ///     .joint_type(JointType::Rotational { axis: Vector3::z_axis() })
+    ///     .into_node();
+    /// let chain = Chain::<f64>::from_root(l0);
+    /// let mut controller = PidController::new(PidGains::new(5.0, 0.0, 0.0));
+    /// for _ in 0..1000 {
+    ///     chain.step_pid(&[1.0], &mut controller, 0.01).unwrap();
+    /// }
+    /// assert!((chain.joint_positions()[0] - 1.0).abs() < 0.001);
+    /// ```
+    pub fn step_pid(
+        &self,
+        targets: &[T],
+        controller: &mut PidController<T>,
+        dt: T,
+    ) -> Result<(), Error> {
+        if targets.len() != self.dof() {
+            return Err(Error::SizeMismatchError {
+                input: targets.len(),
+                required: self.dof(),
+            });
+        }
+        if controller.integrals.len() != self.dof() {
+            controller.integrals = vec![T::zero(); self.dof()];
+        }
+        let gains = &controller.gains;
+        let positions = self.joint_positions();
+        let velocities = self.joint_velocities();
+        let mut new_integrals = Vec::with_capacity(self.dof());
+        let mut new_positions = Vec::with_capacity(self.dof());
+        let mut new_velocities = Vec::with_capacity(self.dof());
+        for (i, target) in targets.iter().enumerate() {
+            let error = target.clone() - positions[i].clone();
+            let mut integral = controller.integrals[i].clone() + error.clone() * dt.clone();
+            if let Some(max) = &controller.max_integral {
+                integral = integral.clamp(-max.clone(), max.clone());
+            }
+            let output = gains.kp.clone() * error + gains.ki.clone() * integral.clone()
+                - gains.kd.clone() * velocities[i].clone();
+            new_integrals.push(integral);
+            new_positions.push(positions[i].clone() + output.clone() * dt.clone());
+            new_velocities.push(output);
+        }
+        self.set_joint_positions_clamped(&new_positions);
+        // the velocity is zero and the integral is kept if the joint is stopped by the limit
+        let clamped = self.joint_positions();
+        for (i, (new, old)) in clamped.iter().zip(new_positions.iter()).enumerate() {
+            if new != old {
+                new_velocities[i] = T::zero();
+            } else {
+                controller.integrals[i] = new_integrals[i].clone();
+            }
+        }
+        self.set_joint_velocities(&new_velocities)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::joint::*;
+    use crate::node::*;
+    #[cfg(target_family = "wasm")]
+    use wasm_bindgen_test::wasm_bindgen_test as test;
+
+    #[test]
+    fn test_step_pid() {
+        let l0 = NodeBuilder::new()
+            .joint_type(JointType::Rotational {
+                axis: na::Vector3::z_axis(),
+            })
+            .into_node();
+        let l1 = NodeBuilder::new()
+            .joint_type(JointType::Linear {
+                axis: na::Vector3::x_axis(),
+            })
+            .limits(Some((-0.5..=0.5).into()))
+            .into_node();
+        l1.set_parent(&l0);
+        let chain = Chain::<f64>::from_root(l0);
+        let mut controller = PidController::new(PidGains::new(4.0, 4.0, 0.1));
+        let targets = [-1.0, 1.0];
+        for _ in 0..100 {
+            chain.step_pid(&targets, &mut controller, 0.01).unwrap();
+        }
+        // moving toward the target
+        assert!(chain.joint_positions()[0] < -0.5);
+        assert!(chain.joint_velocities()[0] < 0.0);
+        for _ in 0..1900 {
+            chain.step_pid(&targets, &mut controller, 0.01).unwrap();
+        }
+        // settled
+        let positions = chain.joint_positions();
+        assert!((positions[0] + 1.0).abs() < 1e-6);
+        assert!(chain.joint_velocities()[0].abs() < 1e-6);
+        // limited
+        assert!((positions[1] - 0.5).abs() < f64::EPSILON);
+        assert_eq!(chain.joint_velocities()[1], 0.0);
+        // the integral is not wound up while the joint is stopped by the limit
+        let integral = controller.integrals()[1];
+        assert!(integral < 1.0);
+        chain.step_pid(&targets, &mut controller, 0.01).unwrap();
+        assert_eq!(controller.integrals()[1], integral);
+
+        assert!(chain.step_pid(&[0.0], &mut controller, 0.01).is_err());
+        controller.reset();
+        assert!(controller.integrals().is_empty());
+    }
+
+    #[test]
+    fn test_pid_max_integral() {
+        let l0 = NodeBuilder::new()
+            .joint_type(JointType::Rotational {
+                axis: na::Vector3::z_axis(),
+            })
+            .into_node();
+        let chain = Chain::<f64>::from_root(l0);
+        let mut controller = PidController::new(PidGains::new(1.0, 1.0, 0.0));
+        controller.max_integral = Some(0.2);
+        for _ in 0..100 {
+            chain.step_pid(&[10.0], &mut controller, 0.01).unwrap();
+            assert!(controller.integrals()[0] <= 0.2);
+        }
+        assert_eq!(controller.integrals()[0], 0.2);
+        // the controllers are independent of the chain
+        let other = chain.clone();
+        let mut other_controller = PidController::new(controller.gains.clone());
+        other
+            .step_pid(&[10.0], &mut other_controller, 0.01)
+            .unwrap();
+        assert!(other_controller.integrals()[0] < 0.2);
+    }
+}
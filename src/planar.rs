@@ -0,0 +1,325 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+use super::chain::*;
+use super::errors::*;
+use super::ik::*;
+use super::joint::*;
+use super::node::*;
+use na::{Isometry3, Point2, RealField, Vector3};
+use nalgebra as na;
+use simba::scalar::SubsetOf;
+
+/// Branch of the solutions of planar arms, used by `SerialChain::solve_position_2d_with_elbow`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ElbowConfig {
+    /// The elbow is on the left side of the line from the base to the target
+    Up,
+    /// The elbow is on the right side of the line from the base to the target
+    Down,
+    /// The solution which is the nearest to the current positions
+    Nearest,
+}
+
+/// Helpers for planar arms (like SCARA) which move in the XY plane
+impl<T> SerialChain<T>
+where
+    T: RealField + SubsetOf<f64>,
+{
+    /// Get the position in the XY plane and the heading (rotation around Z) of the end
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let l0 = NodeBuilder::new()
+    ///     .joint_type(JointType::Rotational { axis: Vector3::z_axis() })
+    ///     .into_node();
+    /// let l1 = NodeBuilder::new()
+    ///     .translation(Translation3::new(1.0, 0.0, 0.0))
+    ///     .into_node();
+    /// l1.set_parent(&l0);
+    /// let arm = SerialChain::<f64>::from_end(&l1);
+    /// arm.set_joint_positions(&[std::f64::consts::FRAC_PI_2]).unwrap();
+    /// let (position, heading) = arm.end_pose_2d();
+    /// assert!((position.y - 1.0).abs() < 1e-10);
+    /// assert!((heading - std::f64::consts::FRAC_PI_2).abs() < 1e-10);
+    /// ```
+    pub fn end_pose_2d(&self) -> (Point2<T>, T) {
+        let end = self.calc_end_transform();
+        let x_axis = end.rotation * Vector3::x();
+        (
+            Point2::new(end.translation.x.clone(), end.translation.y.clone()),
+            x_axis.y.clone().atan2(x_axis.x.clone()),
+        )
+    }
+
+    /// Move the end to `target` in the XY plane by `solver`
+    ///
+    /// Only the X and Y positions are constrained, so it works for arms which have two or more
+    /// joints. The Z position of the target is the current Z position of the end.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let l0 = NodeBuilder::new()
+    ///     .joint_type(JointType::Rotational { axis: Vector3::z_axis() })
+    ///     .into_node();
+    /// let l1 = NodeBuilder::new()
+    ///     .translation(Translation3::new(1.0, 0.0, 0.0))
+    ///     .joint_type(JointType::Rotational { axis: Vector3::z_axis() })
+    ///     .into_node();
+    /// let l2 = NodeBuilder::new()
+    ///     .translation(Translation3::new(1.0, 0.0, 0.0))
+    ///     .into_node();
+    /// l1.set_parent(&l0);
+    /// l2.set_parent(&l1);
+    /// let arm = SerialChain::<f64>::from_end(&l2);
+    /// arm.set_joint_positions(&[0.1, 0.5]).unwrap();
+    /// let target = nalgebra::Point2::new(1.0, 1.2);
+    /// let solver = JacobianIkSolver::new(0.0001, 0.001, 0.5, 100);
+    /// arm.solve_position_2d(&solver, &target).unwrap();
+    /// let (position, _) = arm.end_pose_2d();
+    /// assert!((position - target).norm() < 0.001);
+    /// ```
+    pub fn solve_position_2d<S>(&self, solver: &S, target: &Point2<T>) -> Result<(), Error>
+    where
+        S: InverseKinematicsSolver<T>,
+    {
+        let z = self.calc_end_transform().translation.z.clone();
+        let target_pose = Isometry3::translation(target.x.clone(), target.y.clone(), z);
+        let constraints = Constraints {
+            position_z: false,
+            rotation_x: false,
+            rotation_y: false,
+            rotation_z: false,
+            ..Default::default()
+        };
+        solver.solve_with_constraints(self, &target_pose, &constraints)
+    }
+
+    /// Move the end to `target` in the XY plane by `solver` with the elbow configuration
+    ///
+    /// The elbow is the second movable joint. Viewed from the direction in which its axis
+    /// points, `ElbowConfig::Up` is the configuration where the elbow is on the left side of
+    /// the line from the base to the target (the elbow position is negative), and
+    /// `ElbowConfig::Down` is the other one. The first two movable joints must be rotational.
+    /// The solver starts from the current positions with the elbow flipped to the requested side
+    /// and the first joint turned to the target, and `NotConvergedError` is returned
+    /// (and the positions are restored) if the result is on the other side.
+    /// `ElbowConfig::Nearest` is the same as `solve_position_2d`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let l0 = NodeBuilder::new()
+    ///     .joint_type(JointType::Rotational { axis: Vector3::z_axis() })
+    ///     .into_node();
+    /// let l1 = NodeBuilder::new()
+    ///     .translation(Translation3::new(1.0, 0.0, 0.0))
+    ///     .joint_type(JointType::Rotational { axis: Vector3::z_axis() })
+    ///     .into_node();
+    /// let l2 = NodeBuilder::new()
+    ///     .translation(Translation3::new(1.0, 0.0, 0.0))
+    ///     .into_node();
+    /// l1.set_parent(&l0);
+    /// l2.set_parent(&l1);
+    /// let arm = SerialChain::<f64>::from_end(&l2);
+    /// let target = nalgebra::Point2::new(1.0, 1.0);
+    /// let solver = JacobianIkSolver::new(0.0001, 0.001, 0.5, 100);
+    /// arm.solve_position_2d_with_elbow(&solver, &target, ElbowConfig::Up).unwrap();
+    /// assert!(arm.joint_positions()[1] < 0.0);
+    /// arm.solve_position_2d_with_elbow(&solver, &target, ElbowConfig::Down).unwrap();
+    /// assert!(arm.joint_positions()[1] > 0.0);
+    /// ```
+    pub fn solve_position_2d_with_elbow<S>(
+        &self,
+        solver: &S,
+        target: &Point2<T>,
+        elbow: ElbowConfig,
+    ) -> Result<(), Error>
+    where
+        S: InverseKinematicsSolver<T>,
+    {
+        let sign = match elbow {
+            ElbowConfig::Nearest => return self.solve_position_2d(solver, target),
+            ElbowConfig::Up => -T::one(),
+            ElbowConfig::Down => T::one(),
+        };
+        if self.dof() < 2 {
+            return Err(Error::PreconditionError {
+                dof: self.dof(),
+                necessary_dof: 2,
+            });
+        }
+        let orig_positions = self.joint_positions();
+        // Z of the axis (the sign of the rotation in the XY plane) and the position of the joint
+        self.update_transforms();
+        let axis_z_and_position = |node: &Node<T>| {
+            let joint = node.joint();
+            match &joint.joint_type {
+                JointType::Rotational { axis } => {
+                    let trans = joint.world_transform().expect("cache must exist");
+                    Ok(((&trans.rotation * axis).z.clone(), trans.translation.vector))
+                }
+                _ => Err(Error::NotRotationalJointError {
+                    joint_name: joint.name.clone(),
+                }),
+            }
+        };
+        let mut nodes = self.iter_movable_nodes();
+        let (base_axis_z, base) = axis_z_and_position(nodes.next().unwrap())?;
+        let (axis_z, _) = axis_z_and_position(nodes.next().unwrap())?;
+        let side = |position: &T| position.clone().sin() * axis_z.clone() * sign.clone();
+
+        // the seeds and the intermediate solutions are not reported
+        let result = self.without_position_reports(|| {
+            let mut seed = orig_positions.clone();
+            if side(&seed[1]) <= T::zero() {
+                // flip the elbow, and turn the base joint to the target
+                seed[1] = sign.clone()
+                    * axis_z.clone().signum()
+                    * seed[1].clone().abs().max(na::convert(0.5));
+                self.set_joint_positions_clamped(&seed);
+                let (end, _) = self.end_pose_2d();
+                let angle = |x: T, y: T| (y - base.y.clone()).atan2(x - base.x.clone());
+                let diff =
+                    angle(target.x.clone(), target.y.clone()) - angle(end.x.clone(), end.y.clone());
+                seed[0] += diff * base_axis_z.signum();
+            }
+            self.set_joint_positions_clamped(&seed);
+            let result = self.solve_position_2d(solver, target);
+            let eps: T = na::convert(1.0e-6);
+            match result {
+                Ok(()) if side(&self.joint_positions()[1]) > -eps => Ok(()),
+                Ok(()) => {
+                    let (position, _) = self.end_pose_2d();
+                    self.set_joint_positions_unchecked(&orig_positions);
+                    Err(Error::NotConvergedError {
+                        num_tried: 1,
+                        position_diff: Vector3::new(
+                            na::try_convert(target.x.clone() - position.x.clone())
+                                .unwrap_or_default(),
+                            na::try_convert(target.y.clone() - position.y.clone())
+                                .unwrap_or_default(),
+                            0.0,
+                        ),
+                        rotation_diff: Vector3::zeros(),
+                    })
+                }
+                Err(err) => {
+                    self.set_joint_positions_unchecked(&orig_positions);
+                    Err(err)
+                }
+            }
+        });
+        if result.is_ok() {
+            self.report_joint_positions();
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(target_family = "wasm")]
+    use wasm_bindgen_test::wasm_bindgen_test as test;
+
+    #[test]
+    fn test_end_pose_2d() {
+        let (l1, l2) = (0.6, 0.4);
+        let j0 = NodeBuilder::new()
+            .translation(na::Translation3::new(0.0, 0.0, 0.2))
+            .joint_type(JointType::Rotational {
+                axis: Vector3::z_axis(),
+            })
+            .into_node();
+        let j1 = NodeBuilder::new()
+            .translation(na::Translation3::new(l1, 0.0, 0.0))
+            .joint_type(JointType::Rotational {
+                axis: Vector3::z_axis(),
+            })
+            .into_node();
+        let end = NodeBuilder::new()
+            .translation(na::Translation3::new(l2, 0.0, 0.0))
+            .into_node();
+        j1.set_parent(&j0);
+        end.set_parent(&j1);
+        let arm = SerialChain::<f64>::from_end(&end);
+        for (q0, q1) in [(0.0, 0.0), (0.3, -1.2), (-2.0, 2.5), (3.0, 1.0)] {
+            arm.set_joint_positions(&[q0, q1]).unwrap();
+            let (position, heading) = arm.end_pose_2d();
+            let x = l1 * q0.cos() + l2 * (q0 + q1).cos();
+            let y = l1 * q0.sin() + l2 * (q0 + q1).sin();
+            assert!((position - Point2::new(x, y)).norm() < 1e-10);
+            let heading_diff = (heading - (q0 + q1)).sin();
+            assert!(heading_diff.abs() < 1e-10);
+        }
+    }
+
+    #[test]
+    fn test_solve_position_2d_with_elbow() {
+        let j0 = NodeBuilder::new()
+            .joint_type(JointType::Rotational {
+                axis: Vector3::z_axis(),
+            })
+            .into_node();
+        let j1 = NodeBuilder::new()
+            .translation(na::Translation3::new(1.0, 0.0, 0.0))
+            .joint_type(JointType::Rotational {
+                axis: Vector3::z_axis(),
+            })
+            .into_node();
+        let end = NodeBuilder::new()
+            .translation(na::Translation3::new(1.0, 0.0, 0.0))
+            .into_node();
+        j1.set_parent(&j0);
+        end.set_parent(&j1);
+        let arm = SerialChain::<f64>::from_end(&end);
+        let solver = JacobianIkSolver::new(0.0001, 0.001, 0.5, 100);
+        let target = Point2::new(1.2, 0.5);
+        let elbow_position = |arm: &SerialChain<f64>| {
+            arm.update_transforms();
+            j1.world_transform().unwrap().translation.vector
+        };
+        // the line from the base to the target
+        let left_side = |p: &Vector3<f64>| target.x * p.y - target.y * p.x;
+
+        arm.set_joint_positions(&[0.0, 0.3]).unwrap();
+        arm.solve_position_2d_with_elbow(&solver, &target, ElbowConfig::Up)
+            .unwrap();
+        let up = arm.joint_positions();
+        assert!((arm.end_pose_2d().0 - target).norm() < 0.001);
+        assert!(left_side(&elbow_position(&arm)) > 0.0);
+
+        arm.solve_position_2d_with_elbow(&solver, &target, ElbowConfig::Down)
+            .unwrap();
+        let down = arm.joint_positions();
+        assert!((arm.end_pose_2d().0 - target).norm() < 0.001);
+        assert!(left_side(&elbow_position(&arm)) < 0.0);
+        assert!((up[1] + down[1]).abs() < 0.01);
+
+        // the nearest is the current branch
+        arm.solve_position_2d_with_elbow(&solver, &Point2::new(1.0, 0.8), ElbowConfig::Nearest)
+            .unwrap();
+        assert!(arm.joint_positions()[1] > 0.0);
+    }
+}
@@ -0,0 +1,622 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+use super::chain::*;
+use super::errors::*;
+use super::funcs::*;
+use super::joint::*;
+use na::RealField;
+use nalgebra as na;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use simba::scalar::SubsetOf;
+use std::collections::HashMap;
+
+/// Snapshot of the states of the movable joints
+///
+/// This is similar to `sensor_msgs/JointState` of ROS.
+/// All vectors are in the same order as `names`,
+/// and `velocities` and `efforts` can be empty.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct JointState<T> {
+    pub names: Vec<String>,
+    pub positions: Vec<T>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub velocities: Vec<T>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub efforts: Vec<T>,
+}
+
+/// Immutable snapshot of the positions of the movable joints with their names
+///
+/// Unlike `Vec<T>`, it knows which position belongs to which joint,
+/// so it is not applied to a chain whose joints differ or are in a different order.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Configuration<T> {
+    names: Vec<String>,
+    positions: Vec<T>,
+}
+
+impl<T> Configuration<T> {
+    /// The names of the joints
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// The positions of the joints, in the same order as `names()`
+    pub fn positions(&self) -> &[T] {
+        &self.positions
+    }
+}
+
+impl<T> Chain<T>
+where
+    T: RealField + SubsetOf<f64>,
+{
+    /// Get the positions of the movable joints as `Configuration`
+    pub fn configuration(&self) -> Configuration<T> {
+        Configuration {
+            names: self.joint_names(),
+            positions: self.joint_positions(),
+        }
+    }
+
+    /// Set the positions of `configuration`
+    ///
+    /// The names of the joints must be the same as this chain, in the same order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let l0 = NodeBuilder::new()
+    ///     .name("j0")
+    ///     .joint_type(JointType::Rotational { axis: Vector3::z_axis() })
+    ///     .into_node();
+    /// let chain = Chain::<f64>::from_root(l0);
+    /// chain.set_joint_positions(&[0.5]).unwrap();
+    /// let configuration = chain.configuration();
+    /// chain.set_joint_positions(&[0.0]).unwrap();
+    /// chain.apply_configuration(&configuration).unwrap();
+    /// assert_eq!(chain.joint_positions(), vec![0.5]);
+    /// ```
+    pub fn apply_configuration(&self, configuration: &Configuration<T>) -> Result<(), Error> {
+        self.check_configuration(configuration)?;
+        self.set_joint_positions(&configuration.positions)
+    }
+
+    /// Interpolate the configurations `a` (`t = 0`) and `b` (`t = 1`)
+    ///
+    /// The positions are interpolated linearly, but the rotational joints without limits
+    /// (continuous joints) move along the shortest path, which may cross `±pi`.
+    /// The names of the joints of both must be the same as this chain, in the same order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let l0 = NodeBuilder::new()
+    ///     .name("j0")
+    ///     .joint_type(JointType::Linear { axis: Vector3::z_axis() })
+    ///     .into_node();
+    /// let chain = Chain::<f64>::from_root(l0);
+    /// let a = chain.configuration();
+    /// chain.set_joint_positions(&[1.0]).unwrap();
+    /// let b = chain.configuration();
+    /// let middle = chain.interpolate_config(&a, &b, 0.25).unwrap();
+    /// assert_eq!(middle.positions(), &[0.25]);
+    /// ```
+    pub fn interpolate_config(
+        &self,
+        a: &Configuration<T>,
+        b: &Configuration<T>,
+        t: T,
+    ) -> Result<Configuration<T>, Error> {
+        self.check_configuration(a)?;
+        self.check_configuration(b)?;
+        let positions = self
+            .iter_joints()
+            .zip(a.positions.iter().zip(b.positions.iter()))
+            .map(|(joint, (a, b))| {
+                let mut diff = b.clone() - a.clone();
+                if matches!(joint.joint_type, JointType::Rotational { .. })
+                    && joint.limits.is_none()
+                {
+                    diff = wrap_angle(diff);
+                }
+                a.clone() + diff * t.clone()
+            })
+            .collect();
+        Ok(Configuration {
+            names: a.names.clone(),
+            positions,
+        })
+    }
+
+    fn check_configuration(&self, configuration: &Configuration<T>) -> Result<(), Error> {
+        if configuration.names.len() != self.dof() {
+            return Err(Error::SizeMismatchError {
+                input: configuration.names.len(),
+                required: self.dof(),
+            });
+        }
+        if let Some(name) = configuration
+            .names
+            .iter()
+            .zip(self.iter_joints())
+            .find(|(name, joint)| **name != joint.name)
+            .map(|(name, _)| name.clone())
+        {
+            return Err(Error::InvalidJointNameError { joint_name: name });
+        }
+        Ok(())
+    }
+
+    /// Set `positions` temporarily while `f` is called
+    ///
+    /// The previous positions are restored after `f` returns, even if `f` panics.
+    /// `f` can change the positions, they are also restored. The temporary positions
+    /// are not reported to `Telemetry::on_set_joint_positions`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let l0 = NodeBuilder::new()
+    ///     .joint_type(JointType::Rotational { axis: Vector3::z_axis() })
+    ///     .into_node();
+    /// let chain = Chain::<f64>::from_root(l0);
+    /// chain.set_joint_positions(&[0.1]).unwrap();
+    /// let positions = chain.with_positions(&[0.5], |c| c.joint_positions()).unwrap();
+    /// assert_eq!(positions, vec![0.5]);
+    /// assert_eq!(chain.joint_positions(), vec![0.1]);
+    /// ```
+    pub fn with_positions<F, R>(&self, positions: &[T], f: F) -> Result<R, Error>
+    where
+        F: FnOnce(&Self) -> R,
+    {
+        self.without_position_reports(|| {
+            let guard = RestorePositions {
+                chain: self,
+                positions: self.joint_positions(),
+            };
+            self.set_joint_positions(positions)?;
+            Ok(f(guard.chain))
+        })
+    }
+
+    /// Set the home positions of the movable joints by their names
+    ///
+    /// The joints which are not in `positions` keep the current home positions (zero by default).
+    /// The positions are checked by the limits.
+    pub fn set_home_positions(&mut self, positions: &HashMap<String, T>) -> Result<(), Error> {
+        for (name, position) in positions {
+            let node = self
+                .find(name)
+                .filter(|node| node.joint().is_movable())
+                .ok_or_else(|| Error::InvalidJointNameError {
+                    joint_name: name.to_owned(),
+                })?;
+            if let Some(range) = &node.joint().limits {
+                if !range.is_valid(position.clone()) {
+                    return Err(Error::OutOfLimitError {
+                        joint_name: name.to_owned(),
+                        position: na::try_convert(position.clone()).unwrap_or_default(),
+                        max_limit: na::try_convert(range.max.clone()).unwrap_or_default(),
+                        min_limit: na::try_convert(range.min.clone()).unwrap_or_default(),
+                    });
+                }
+            }
+        }
+        for (name, position) in positions {
+            self.home_positions.insert(name.clone(), position.clone());
+        }
+        Ok(())
+    }
+
+    /// Get the home positions of the movable joints, in the same order as `joint_positions()`
+    pub fn home_positions(&self) -> Vec<T> {
+        self.iter_joints()
+            .map(|joint| {
+                self.home_positions
+                    .get(&joint.name)
+                    .cloned()
+                    .unwrap_or_else(T::zero)
+            })
+            .collect()
+    }
+
+    /// Move the movable joints to the home positions
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    /// use std::collections::HashMap;
+    ///
+    /// let l0 = NodeBuilder::new()
+    ///     .name("j0")
+    ///     .joint_type(JointType::Rotational { axis: Vector3::z_axis() })
+    ///     .into_node();
+    /// let l1 = NodeBuilder::new()
+    ///     .name("j1")
+    ///     .joint_type(JointType::Rotational { axis: Vector3::z_axis() })
+    ///     .into_node();
+    /// l1.set_parent(&l0);
+    /// let mut chain = Chain::<f64>::from_root(l0);
+    /// chain.set_home_positions(&HashMap::from([("j1".to_owned(), 0.5)])).unwrap();
+    /// chain.set_joint_positions(&[0.1, 0.2]).unwrap();
+    /// chain.go_home().unwrap();
+    /// assert_eq!(chain.joint_positions(), vec![0.0, 0.5]);
+    /// ```
+    pub fn go_home(&self) -> Result<(), Error> {
+        self.set_joint_positions(&self.home_positions())
+    }
+
+    /// Get the names, positions and velocities of the movable joints
+    ///
+    /// `efforts` is empty because `k` does not have the effort of the joints.
+    pub fn joint_state(&self) -> JointState<T> {
+        JointState {
+            names: self.joint_names(),
+            positions: self.joint_positions(),
+            velocities: self.joint_velocities(),
+            efforts: Vec::new(),
+        }
+    }
+
+    /// Set the positions (and velocities if they are not empty) by the names of the joints
+    ///
+    /// The joints which are not in `state` are not changed. `efforts` is ignored.
+    /// The positions are set by `set_joint_positions()`. All names (and the limits for
+    /// `LimitMode::Error`) are checked first, so nothing is changed if it returns `Err`.
+    /// The mimic joints are skipped, because they are set from their parents
+    /// as `set_joint_positions()`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let l0 = NodeBuilder::new()
+    ///     .name("j0")
+    ///     .joint_type(JointType::Rotational { axis: Vector3::z_axis() })
+    ///     .into_node();
+    /// let chain = Chain::<f64>::from_root(l0);
+    /// let state = JointState {
+    ///     names: vec!["j0".to_owned()],
+    ///     positions: vec![0.5],
+    ///     ..Default::default()
+    /// };
+    /// chain.apply_joint_state(&state).unwrap();
+    /// assert_eq!(chain.joint_positions(), vec![0.5]);
+    /// ```
+    pub fn apply_joint_state(&self, state: &JointState<T>) -> Result<(), Error> {
+        if state.positions.len() != state.names.len() {
+            return Err(Error::SizeMismatchError {
+                input: state.positions.len(),
+                required: state.names.len(),
+            });
+        }
+        if !state.velocities.is_empty() && state.velocities.len() != state.names.len() {
+            return Err(Error::SizeMismatchError {
+                input: state.velocities.len(),
+                required: state.names.len(),
+            });
+        }
+        let nodes = self.iter_movable_nodes().collect::<Vec<_>>();
+        let mut positions = self.joint_positions();
+        let mut velocities = self.joint_velocities();
+        for (i, name) in state.names.iter().enumerate() {
+            let index = nodes
+                .iter()
+                .position(|node| node.joint().name == *name)
+                .ok_or_else(|| Error::InvalidJointNameError {
+                    joint_name: name.to_owned(),
+                })?;
+            if nodes[index].mimic_parent().is_some() {
+                continue;
+            }
+            positions[index] = state.positions[i].clone();
+            if let Some(velocity) = state.velocities.get(i) {
+                velocities[index] = velocity.clone();
+            }
+        }
+        // set_joint_positions() stops at the first error
+        if self.limit_mode() == LimitMode::Error {
+            for (node, position) in nodes.iter().zip(&positions) {
+                let node_impl = node.lock();
+                if node_impl.mimic_parent.is_some() {
+                    continue;
+                }
+                node_impl.joint.check_joint_position(position)?;
+                for child in &node_impl.mimic_children {
+                    let child = child.lock();
+                    if let Some(mimic) = child.mimic_mapping() {
+                        child
+                            .joint
+                            .check_joint_position(&mimic.mimic_position(position.clone()))?;
+                    }
+                }
+            }
+        }
+        self.set_joint_positions(&positions)?;
+        if !state.velocities.is_empty() {
+            self.set_joint_velocities(&velocities)?;
+        }
+        Ok(())
+    }
+}
+
+/// Restore the positions of the chain when it is dropped
+struct RestorePositions<'a, T>
+where
+    T: RealField + SubsetOf<f64>,
+{
+    chain: &'a Chain<T>,
+    positions: Vec<T>,
+}
+
+impl<T> Drop for RestorePositions<'_, T>
+where
+    T: RealField + SubsetOf<f64>,
+{
+    fn drop(&mut self) {
+        self.chain.set_joint_positions_unchecked(&self.positions);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::urdf::sample_chain;
+    #[cfg(target_family = "wasm")]
+    use wasm_bindgen_test::wasm_bindgen_test as test;
+
+    #[test]
+    fn test_joint_state_round_trip() {
+        let chain = sample_chain();
+        let positions = vec![0.0, 0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8, 0.9, -0.1, -0.2];
+        chain.set_joint_positions(&positions).unwrap();
+        chain.set_joint_velocities(&[0.5; 12]).unwrap();
+        let mut state = chain.joint_state();
+        assert_eq!(state.names.len(), 12);
+        assert_eq!(state.names[0], "l_shoulder_yaw");
+        assert!(state.efforts.is_empty());
+
+        chain.set_joint_positions(&[0.0; 12]).unwrap();
+        chain.set_joint_velocities(&[0.0; 12]).unwrap();
+        // the order does not matter
+        state.names.reverse();
+        state.positions.reverse();
+        chain.apply_joint_state(&state).unwrap();
+        assert_eq!(chain.joint_positions(), positions);
+        assert_eq!(chain.joint_velocities(), vec![0.5; 12]);
+
+        // nothing is changed by an invalid state
+        chain.set_joint_positions(&[0.0; 12]).unwrap();
+        let mut invalid = state.clone();
+        invalid.names[11] = "root".to_owned();
+        assert!(chain.apply_joint_state(&invalid).is_err());
+        let mut invalid = state.clone();
+        invalid.positions[11] = 100.0;
+        assert!(matches!(
+            chain.apply_joint_state(&invalid),
+            Err(Error::OutOfLimitError { .. })
+        ));
+        assert_eq!(chain.joint_positions(), vec![0.0; 12]);
+
+        state.names[0] = "root".to_owned();
+        assert!(chain.apply_joint_state(&state).is_err());
+        state.positions.pop();
+        assert!(chain.apply_joint_state(&state).is_err());
+    }
+
+    #[test]
+    fn test_apply_joint_state_limit_mode_and_mimic() {
+        use crate::node::NodeBuilder;
+        let j0 = NodeBuilder::new()
+            .name("j0")
+            .joint_type(JointType::Linear {
+                axis: na::Vector3::z_axis(),
+            })
+            .limits(Some((-1.0..=1.0).into()))
+            .into_node();
+        let j1 = NodeBuilder::new()
+            .name("j1")
+            .joint_type(JointType::Linear {
+                axis: na::Vector3::z_axis(),
+            })
+            .limits(Some((-2.0..=2.0).into()))
+            .into_node();
+        j1.set_parent(&j0);
+        j1.set_mimic_parent(&j0, Mimic::new(2.0, 0.0));
+        let mut chain = Chain::<f64>::from_root(j0);
+
+        // the mimic joint is skipped
+        let state = JointState {
+            names: vec!["j0".to_owned(), "j1".to_owned()],
+            positions: vec![0.5, 0.0],
+            ..Default::default()
+        };
+        chain.apply_joint_state(&state).unwrap();
+        assert_eq!(chain.joint_positions(), vec![0.5, 1.0]);
+        assert_eq!(chain.joint_state().names, state.names);
+
+        // the limits are treated by the limit mode
+        let state = JointState {
+            names: vec!["j0".to_owned()],
+            positions: vec![3.0],
+            ..Default::default()
+        };
+        assert!(matches!(
+            chain.apply_joint_state(&state),
+            Err(Error::OutOfLimitError { .. })
+        ));
+        assert_eq!(chain.joint_positions(), vec![0.5, 1.0]);
+        chain.set_limit_mode(LimitMode::Clamp);
+        chain.apply_joint_state(&state).unwrap();
+        assert_eq!(chain.joint_positions(), vec![1.0, 2.0]);
+        chain.set_limit_mode(LimitMode::Ignore);
+        chain.apply_joint_state(&state).unwrap();
+        assert_eq!(chain.joint_positions()[0], 3.0);
+    }
+
+    #[test]
+    fn test_configuration() {
+        let mut chain = sample_chain();
+        let positions = vec![0.0, 0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8, 0.9, -0.1, -0.2];
+        chain.set_joint_positions(&positions).unwrap();
+        let configuration = chain.configuration();
+        assert_eq!(configuration.positions(), positions.as_slice());
+        assert_eq!(configuration.names()[0], "l_shoulder_yaw");
+
+        let other = chain.clone();
+        other.set_joint_positions(&[0.0; 12]).unwrap();
+        other.apply_configuration(&configuration).unwrap();
+        assert_eq!(other.joint_positions(), positions);
+
+        // different order
+        let mut order = configuration.names().to_vec();
+        order.reverse();
+        chain.reorder_joints(&order).unwrap();
+        assert!(matches!(
+            chain.apply_configuration(&configuration),
+            Err(Error::InvalidJointNameError { .. })
+        ));
+    }
+
+    #[test]
+    fn test_home_positions() {
+        let mut chain = sample_chain();
+        assert_eq!(chain.home_positions(), vec![0.0; 12]);
+        let home = HashMap::from([
+            ("l_elbow_pitch".to_owned(), -0.5),
+            ("r_elbow_pitch".to_owned(), -0.6),
+        ]);
+        chain.set_home_positions(&home).unwrap();
+        chain.set_joint_positions(&[0.3; 12]).unwrap();
+        chain.go_home().unwrap();
+        for (name, position) in chain.joint_names().iter().zip(chain.joint_positions()) {
+            assert_eq!(position, home.get(name).cloned().unwrap_or(0.0));
+        }
+        // kept by clone and reorder
+        let mut other = chain.clone();
+        let mut order = other.joint_names();
+        order.reverse();
+        other.reorder_joints(&order).unwrap();
+        other.set_joint_positions(&[0.3; 12]).unwrap();
+        other.go_home().unwrap();
+        assert_eq!(
+            other.find("l_elbow_pitch").unwrap().joint_position(),
+            Some(-0.5)
+        );
+
+        assert!(chain
+            .set_home_positions(&HashMap::from([("no_exist".to_owned(), 0.0)]))
+            .is_err());
+        assert!(chain
+            .set_home_positions(&HashMap::from([("l_elbow_pitch".to_owned(), 100.0)]))
+            .is_err());
+        assert_eq!(chain.home_positions(), {
+            chain.go_home().unwrap();
+            chain.joint_positions()
+        });
+    }
+
+    #[test]
+    fn test_with_positions() {
+        let chain = sample_chain();
+        let positions = vec![0.0, 0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8, 0.9, -0.1, -0.2];
+        chain.set_joint_positions(&positions).unwrap();
+        let end = chain.find("l_wrist_pitch").unwrap();
+        let moved = chain
+            .with_positions(&[0.5; 12], |chain| {
+                chain.update_transforms();
+                let trans = end.world_transform().unwrap();
+                chain.set_joint_positions(&[0.2; 12]).unwrap();
+                trans
+            })
+            .unwrap();
+        assert_eq!(chain.joint_positions(), positions);
+        chain.update_transforms();
+        assert!(
+            (end.world_transform().unwrap().translation.vector - moved.translation.vector).norm()
+                > 0.01
+        );
+
+        // invalid positions
+        assert!(chain.with_positions(&[10.0; 12], |_| ()).is_err());
+        assert!(chain.with_positions(&[0.0; 3], |_| ()).is_err());
+        assert_eq!(chain.joint_positions(), positions);
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    #[test]
+    fn test_with_positions_panic() {
+        let chain = sample_chain();
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            chain.with_positions(&[0.5; 12], |_| panic!("panic in the closure"))
+        }));
+        assert!(result.is_err());
+        assert_eq!(chain.joint_positions(), vec![0.0; 12]);
+    }
+
+    #[test]
+    fn test_interpolate_config() {
+        use crate::node::NodeBuilder;
+        use na::Vector3;
+
+        let l0 = NodeBuilder::new()
+            .name("continuous")
+            .joint_type(JointType::Rotational {
+                axis: Vector3::z_axis(),
+            })
+            .into_node();
+        let l1 = NodeBuilder::new()
+            .name("limited")
+            .joint_type(JointType::Rotational {
+                axis: Vector3::y_axis(),
+            })
+            .limits(Some((-4.0..=4.0).into()))
+            .into_node();
+        l1.set_parent(&l0);
+        let chain = Chain::<f64>::from_root(l0);
+        chain.set_joint_positions(&[3.0, 3.0]).unwrap();
+        let a = chain.configuration();
+        chain.set_joint_positions(&[-3.0, -3.0]).unwrap();
+        let b = chain.configuration();
+
+        let middle = chain.interpolate_config(&a, &b, 0.5).unwrap();
+        assert_eq!(middle.names(), a.names());
+        // the continuous joint crosses pi, the limited one goes through zero
+        assert!((middle.positions()[0].abs() - std::f64::consts::PI).abs() < 1e-10);
+        assert!(middle.positions()[1].abs() < 1e-10);
+        assert_eq!(chain.interpolate_config(&a, &b, 0.0).unwrap(), a);
+        let end = chain.interpolate_config(&a, &b, 1.0).unwrap();
+        assert!((wrap_angle(end.positions()[0] - b.positions()[0])).abs() < 1e-10);
+        assert_eq!(end.positions()[1], b.positions()[1]);
+
+        let other = sample_chain();
+        assert!(chain
+            .interpolate_config(&a, &other.configuration(), 0.5)
+            .is_err());
+    }
+}
@@ -0,0 +1,79 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+
+use na::{Isometry3, RealField};
+use nalgebra as na;
+use std::fmt;
+use std::sync::Arc;
+
+/// Callbacks to observe the kinematics calculations, for logging or debugging
+///
+/// Set it to a `Chain` by `Chain::set_telemetry` (or `SerialChain::set_telemetry`).
+/// All the methods do nothing by default, so implement only the events you need.
+/// Nothing is called if no telemetry is set.
+///
+/// # Examples
+///
+/// ```
+/// use k::*;
+/// use std::sync::{Arc, Mutex};
+///
+/// #[derive(Default)]
+/// struct Counter(Mutex<usize>);
+///
+/// impl Telemetry<f64> for Counter {
+///     fn on_fk(&self, _transforms: &[Isometry3<f64>]) {
+///         *self.0.lock().unwrap() += 1;
+///     }
+/// }
+///
+/// let mut chain = Chain::<f64>::from_urdf_file("urdf/sample.urdf").unwrap();
+/// let counter = Arc::new(Counter::default());
+/// chain.set_telemetry(counter.clone());
+/// chain.update_transforms();
+/// assert_eq!(*counter.0.lock().unwrap(), 1);
+/// ```
+pub trait Telemetry<T: RealField>: Send + Sync {
+    /// Called when the world transforms are updated by `Chain::update_transforms()`
+    /// or `Chain::update_transforms_from()`, with the world transforms of all joints
+    fn on_fk(&self, _transforms: &[Isometry3<T>]) {}
+    /// Called after each iteration of `JacobianIkSolver`, with the remaining errors
+    fn on_ik_iteration(&self, _iteration: usize, _position_error: T, _rotation_error: T) {}
+    /// Called when the requested position of the joint is out of its limits
+    ///
+    /// `position` is the requested position, before clamping if it is clamped.
+    /// It is not called for the temporary changes inside the calculations,
+    /// for example the iterations of IK and the random seeds of the sampling.
+    fn on_limit_hit(&self, _joint_name: &str, _position: T) {}
+    /// Called after the positions of the joints are changed, with the resulting positions
+    ///
+    /// It is called by `Chain::set_joint_positions()` and `Chain::set_joint_positions_clamped()`
+    /// when they succeed, and by the functions which change the positions as their results,
+    /// for example the IK solvers (once for a solution). It is not called by
+    /// `Chain::set_joint_positions_unchecked()`, nor for the temporary changes inside
+    /// the calculations, for example `Chain::with_positions()` and the iterations of IK.
+    fn on_set_joint_positions(&self, _positions: &[T]) {}
+}
+
+/// Shared telemetry held by `Chain`
+#[derive(Clone)]
+pub(crate) struct SharedTelemetry<T: RealField>(pub(crate) Arc<dyn Telemetry<T>>);
+
+impl<T: RealField> fmt::Debug for SharedTelemetry<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SharedTelemetry")
+    }
+}
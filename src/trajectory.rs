@@ -0,0 +1,395 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+//! Trajectories in the task space (Cartesian) and the joint space
+//!
+//! Interpolation of the poses, and the helpers of the joint trajectories:
+//! blending, finite differences and CSV output.
+use super::errors::*;
+use super::funcs::*;
+use na::{Isometry3, RealField, Translation3};
+use nalgebra as na;
+
+/// Interpolate the poses from `start` to `end` in `steps` intervals
+///
+/// The translation is interpolated linearly and the rotation by SLERP, so the
+/// end moves along the straight line with a constant angular velocity.
+/// It returns `steps + 1` poses. The first one is `start` and the last one is `end`
+/// exactly. If `steps` is zero, only `end` is returned.
+///
+/// # Examples
+///
+/// ```
+/// use k::*;
+///
+/// let start = Isometry3::translation(0.0, 0.0, 0.0);
+/// let end = Isometry3::translation(1.0, 0.0, 0.0);
+/// let poses = k::trajectory::cartesian_interpolate(&start, &end, 4);
+/// assert_eq!(poses.len(), 5);
+/// assert_eq!(poses[1].translation.vector.x, 0.25);
+/// ```
+pub fn cartesian_interpolate<T: RealField>(
+    start: &Isometry3<T>,
+    end: &Isometry3<T>,
+    steps: usize,
+) -> Vec<Isometry3<T>> {
+    if steps == 0 {
+        return vec![end.clone()];
+    }
+    (0..=steps)
+        .map(|i| {
+            if i == 0 {
+                return start.clone();
+            }
+            if i == steps {
+                return end.clone();
+            }
+            let t: T = na::convert(i as f64 / steps as f64);
+            let translation = start
+                .translation
+                .vector
+                .lerp(&end.translation.vector, t.clone());
+            // the shortest path, and lerp if the rotations are (almost) opposite
+            let rotation = start
+                .rotation
+                .try_slerp(&end.rotation, t.clone(), T::default_epsilon())
+                .unwrap_or_else(|| start.rotation.nlerp(&end.rotation, t));
+            Isometry3::from_parts(Translation3::from(translation), rotation)
+        })
+        .collect()
+}
+
+/// Connect the joint trajectories `a` and `b` with a smooth transition
+///
+/// The last `overlap` points of `a` and the first `overlap` points of `b` are cross-faded
+/// with the smoothstep weight, so it returns `a.len() + b.len() - overlap` points.
+/// The points out of the overlap are the same as `a` and `b`. All points must have
+/// the same number of joints, and `overlap` must not exceed the lengths of `a` and `b`.
+///
+/// # Examples
+///
+/// ```
+/// let a = vec![vec![0.0], vec![0.0], vec![0.0]];
+/// let b = vec![vec![1.0], vec![1.0], vec![1.0]];
+/// let blended = k::trajectory::blend(&a, &b, 1).unwrap();
+/// assert_eq!(blended, vec![vec![0.0], vec![0.0], vec![0.5], vec![1.0], vec![1.0]]);
+/// ```
+pub fn blend<T: RealField>(
+    a: &[Vec<T>],
+    b: &[Vec<T>],
+    overlap: usize,
+) -> Result<Vec<Vec<T>>, Error> {
+    let max_overlap = a.len().min(b.len());
+    if overlap > max_overlap {
+        return Err(Error::SizeMismatchError {
+            input: overlap,
+            required: max_overlap,
+        });
+    }
+    if let Some(dof) = a.first().or_else(|| b.first()).map(|p| p.len()) {
+        if let Some(positions) = a.iter().chain(b.iter()).find(|p| p.len() != dof) {
+            return Err(Error::SizeMismatchError {
+                input: positions.len(),
+                required: dof,
+            });
+        }
+    }
+    let head = a.len() - overlap;
+    let mut blended = a[..head].to_vec();
+    for i in 0..overlap {
+        // smoothstep, excluding the ends which are the points of a and b
+        let s: T = na::convert((i + 1) as f64 / (overlap + 1) as f64);
+        let weight = s.clone() * s.clone() * (na::convert::<f64, T>(3.0) - s.clone() - s);
+        blended.push(
+            a[head + i]
+                .iter()
+                .zip(b[i].iter())
+                .map(|(a, b)| a.clone() + (b.clone() - a.clone()) * weight.clone())
+                .collect(),
+        );
+    }
+    blended.extend_from_slice(&b[overlap..]);
+    Ok(blended)
+}
+
+/// Format the joint trajectory `traj` as CSV
+///
+/// The header has the time column `time` if `dt` is given, and `joint_names`.
+/// Each of `traj` is a row, and the time of the `i`-th row is `i * dt`.
+/// The names which contain commas or quotes are quoted.
+///
+/// # Examples
+///
+/// ```
+/// let names = vec!["j0".to_owned(), "j1".to_owned()];
+/// let csv = k::trajectory::to_csv(&names, &[vec![0.0, 1.0], vec![0.5, 1.5]], Some(0.1));
+/// assert_eq!(csv, "time,j0,j1\n0,0,1\n0.1,0.5,1.5\n");
+/// ```
+pub fn to_csv<T: RealField>(joint_names: &[String], traj: &[Vec<T>], dt: Option<T>) -> String {
+    let mut header = Vec::with_capacity(joint_names.len() + 1);
+    if dt.is_some() {
+        header.push("time".to_owned());
+    }
+    header.extend(joint_names.iter().map(|name| csv_field(name)));
+    let mut csv = header.join(",");
+    csv.push('\n');
+    for (i, positions) in traj.iter().enumerate() {
+        let mut row = Vec::with_capacity(positions.len() + 1);
+        if let Some(dt) = &dt {
+            let time = dt.clone() * na::convert(i as f64);
+            row.push(time.to_string());
+        }
+        row.extend(positions.iter().map(|position| position.to_string()));
+        csv.push_str(&row.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+/// Velocities and accelerations of the time-stamped joint trajectory `traj`
+///
+/// Each of `traj` is `(time, positions)`, and the time must increase.
+/// The derivatives are calculated by central differences (one-sided at the both ends),
+/// and it returns `(velocities, accelerations)` for each waypoint.
+/// The differences of the positions are not wrapped. Use [`differentiate_wrapped`]
+/// for the continuous joints whose positions jump between `pi` and `-pi`.
+///
+/// # Examples
+///
+/// ```
+/// let traj = vec![(0.0, vec![0.0]), (0.5, vec![1.0]), (1.0, vec![2.0])];
+/// let (velocities, accelerations) = k::trajectory::differentiate(&traj);
+/// assert_eq!(velocities[1], vec![2.0]);
+/// assert_eq!(accelerations[1], vec![0.0]);
+/// ```
+pub fn differentiate<T: RealField>(traj: &[(T, Vec<T>)]) -> (Vec<Vec<T>>, Vec<Vec<T>>) {
+    let dof = traj.first().map_or(0, |(_, positions)| positions.len());
+    differentiate_wrapped(traj, &vec![false; dof])
+}
+
+/// Velocities and accelerations of the joint trajectory with the wrapped joints
+///
+/// Same as [`differentiate`], but the difference of the `i`-th joint is wrapped into
+/// `(-pi, pi]` only if `wrapped[i]` is true, so the continuous joints crossing `±pi`
+/// do not make a spike. It assumes that the wrapped joints move less than `pi` between
+/// the waypoints. Do not wrap the linear joints. The joints out of `wrapped` are not wrapped.
+///
+/// # Examples
+///
+/// ```
+/// let traj = vec![(0.0, vec![3.0, 3.0]), (1.0, vec![-3.0, -3.0])];
+/// // the first joint is continuous, the second one is linear
+/// let (velocities, _) = k::trajectory::differentiate_wrapped(&traj, &[true, false]);
+/// assert!((velocities[0][0] - (std::f64::consts::TAU - 6.0)).abs() < 1e-10);
+/// assert_eq!(velocities[0][1], -6.0);
+/// ```
+pub fn differentiate_wrapped<T: RealField>(
+    traj: &[(T, Vec<T>)],
+    wrapped: &[bool],
+) -> (Vec<Vec<T>>, Vec<Vec<T>>) {
+    let diff = |from: &[T], to: &[T]| -> Vec<T> {
+        from.iter()
+            .zip(to.iter())
+            .enumerate()
+            .map(|(i, (from, to))| {
+                let d = to.clone() - from.clone();
+                if wrapped.get(i).copied().unwrap_or(false) {
+                    wrap_angle(d)
+                } else {
+                    d
+                }
+            })
+            .collect()
+    };
+    let scale =
+        |values: Vec<T>, dt: T| -> Vec<T> { values.into_iter().map(|v| v / dt.clone()).collect() };
+    let len = traj.len();
+    if len < 2 {
+        let zeros = traj
+            .iter()
+            .map(|(_, positions)| vec![T::zero(); positions.len()])
+            .collect::<Vec<_>>();
+        return (zeros.clone(), zeros);
+    }
+    // the velocities of the intervals between the waypoints
+    let interval_velocities = traj
+        .windows(2)
+        .map(|w| scale(diff(&w[0].1, &w[1].1), w[1].0.clone() - w[0].0.clone()))
+        .collect::<Vec<_>>();
+    let mut velocities = Vec::with_capacity(len);
+    velocities.push(interval_velocities[0].clone());
+    for i in 1..len - 1 {
+        // weighted by the other interval, which is exact for the uneven time steps too
+        let dt0 = traj[i].0.clone() - traj[i - 1].0.clone();
+        let dt1 = traj[i + 1].0.clone() - traj[i].0.clone();
+        let dt = dt0.clone() + dt1.clone();
+        velocities.push(
+            interval_velocities[i - 1]
+                .iter()
+                .zip(interval_velocities[i].iter())
+                .map(|(v0, v1)| (v0.clone() * dt1.clone() + v1.clone() * dt0.clone()) / dt.clone())
+                .collect(),
+        );
+    }
+    velocities.push(interval_velocities[len - 2].clone());
+
+    let mut accelerations = Vec::with_capacity(len);
+    for i in 0..len {
+        let (prev, next) = if i == 0 {
+            (0, 1)
+        } else if i == len - 1 {
+            (len - 3, len - 2)
+        } else {
+            (i - 1, i)
+        };
+        if len == 2 {
+            accelerations.push(vec![T::zero(); traj[i].1.len()]);
+            continue;
+        }
+        // the difference of the interval velocities over the distance of their midpoints
+        let half: T = na::convert(0.5);
+        let dt = (traj[next + 1].0.clone() - traj[prev].0.clone()) * half;
+        let dv = interval_velocities[prev]
+            .iter()
+            .zip(interval_velocities[next].iter())
+            .map(|(v0, v1)| v1.clone() - v0.clone())
+            .collect();
+        accelerations.push(scale(dv, dt));
+    }
+    (velocities, accelerations)
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use na::{UnitQuaternion, Vector3};
+    #[cfg(target_family = "wasm")]
+    use wasm_bindgen_test::wasm_bindgen_test as test;
+
+    #[test]
+    fn test_cartesian_interpolate() {
+        let start = Isometry3::new(Vector3::new(0.1, 0.2, 0.3), Vector3::new(0.0, 0.0, 0.2));
+        let end = Isometry3::new(Vector3::new(1.1, -0.2, 0.5), Vector3::new(0.5, -0.3, 1.0));
+        let poses = cartesian_interpolate(&start, &end, 10);
+        assert_eq!(poses.len(), 11);
+        assert_eq!(poses[0], start);
+        assert_eq!(poses[10], end);
+        let middle = &poses[5];
+        let average = (start.translation.vector + end.translation.vector) / 2.0;
+        assert!((middle.translation.vector - average).norm() < 1e-10);
+        let halfway: UnitQuaternion<f64> = start.rotation.slerp(&end.rotation, 0.5);
+        assert!(middle.rotation.angle_to(&halfway) < 1e-10);
+        assert!(
+            (start.rotation.angle_to(&middle.rotation) - middle.rotation.angle_to(&end.rotation))
+                .abs()
+                < 1e-10
+        );
+        assert_eq!(cartesian_interpolate(&start, &end, 0), vec![end]);
+    }
+    #[test]
+    fn test_to_csv() {
+        let names = vec!["shoulder".to_owned(), "elbow, left".to_owned()];
+        let traj = vec![vec![0.0, 0.25], vec![0.5, -1.0], vec![1.0, 2.0]];
+        let csv = to_csv(&names, &traj, Some(0.5));
+        let lines = csv.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 1 + traj.len());
+        assert_eq!(lines[0], "time,shoulder,\"elbow, left\"");
+        assert_eq!(lines[1], "0,0,0.25");
+        assert_eq!(lines[3], "1,1,2");
+
+        let csv = to_csv(&names, &traj, None);
+        let lines = csv.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], "shoulder,\"elbow, left\"");
+        assert_eq!(lines[2], "0.5,-1");
+    }
+    #[test]
+    fn test_differentiate() {
+        let traj = (0..10)
+            .map(|i| {
+                let t = i as f64 * 0.1_f64;
+                (t, vec![0.5 + 2.0 * t])
+            })
+            .collect::<Vec<_>>();
+        let (velocities, accelerations) = differentiate(&traj);
+        assert_eq!(velocities.len(), 10);
+        assert_eq!(accelerations.len(), 10);
+        for (velocity, acceleration) in velocities.iter().zip(accelerations.iter()) {
+            assert!((velocity[0] - 2.0).abs() < 1e-10);
+            assert!(acceleration[0].abs() < 1e-8);
+        }
+
+        // constant acceleration 3.0 with uneven time steps
+        let traj = [0.0_f64, 0.1, 0.3, 0.4, 0.7]
+            .iter()
+            .map(|&t| (t, vec![1.5 * t * t]))
+            .collect::<Vec<_>>();
+        let (velocities, accelerations) = differentiate(&traj);
+        assert!((velocities[2][0] - 3.0 * 0.3).abs() < 1e-10);
+        for acceleration in &accelerations {
+            assert!((acceleration[0] - 3.0).abs() < 1e-8);
+        }
+
+        // continuous joint crossing pi
+        let traj = vec![(0.0, vec![3.0]), (1.0, vec![-3.0]), (2.0, vec![-2.8])];
+        let (velocities, _) = differentiate_wrapped(&traj, &[true]);
+        assert!((velocities[0][0] - (std::f64::consts::TAU - 6.0)).abs() < 1e-10);
+        assert!(velocities[1][0] > 0.0);
+        // not wrapped by default, like a linear joint moving more than pi
+        let (velocities, _) = differentiate(&traj);
+        assert_eq!(velocities[0][0], -6.0);
+        assert_eq!(differentiate_wrapped(&traj, &[false]).0, velocities);
+
+        assert_eq!(differentiate::<f64>(&[]), (vec![], vec![]));
+        assert_eq!(
+            differentiate(&[(0.0, vec![1.0])]),
+            (vec![vec![0.0]], vec![vec![0.0]])
+        );
+    }
+    #[test]
+    fn test_blend() {
+        let a = (0..10)
+            .map(|i| vec![0.1 * i as f64, 1.0])
+            .collect::<Vec<_>>();
+        let b = (0..10)
+            .map(|i| vec![2.0 - 0.1 * i as f64, -1.0])
+            .collect::<Vec<_>>();
+        let blended = blend(&a, &b, 4).unwrap();
+        assert_eq!(blended.len(), 16);
+        assert_eq!(blended[..6], a[..6]);
+        assert_eq!(blended[10..], b[4..]);
+        // continuous: each step is smaller than half of the jump (2.0) between a and b
+        for w in blended[5..11].windows(2) {
+            assert!((w[1][1] - w[0][1]).abs() < 1.0);
+        }
+        // monotonic cross-fade of the constant joint
+        for w in blended[5..11].windows(2) {
+            assert!(w[1][1] < w[0][1]);
+        }
+
+        assert_eq!(blend(&a, &b, 0).unwrap().len(), 20);
+        assert_eq!(blend(&a, &b, 10).unwrap().len(), 10);
+        assert!(blend(&a, &b, 11).is_err());
+        assert!(blend(&a, &[vec![0.0]], 1).is_err());
+    }
+}
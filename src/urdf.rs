@@ -20,11 +20,13 @@ use super::chain::*;
 use super::joint::*;
 use super::link::*;
 use super::node::*;
+use super::validate::*;
 use na::{Isometry3, Matrix3, RealField};
 use nalgebra as na;
+use quick_xml::events::{BytesStart, Event};
 use simba::scalar::SubsetOf;
-use std::collections::HashMap;
-use std::path::Path;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use tracing::*;
 
 pub const ROOT_JOINT_NAME: &str = "root";
@@ -258,6 +260,10 @@ where
     }
 }
 
+/// Build the chain without checking the duplicated names
+///
+/// The links and the joints which have the same name are not rejected, in contrast to
+/// `Chain::from_urdf_file`. Use `Chain::from_robot_with_policy` to check them.
 impl<T> From<&urdf_rs::Robot> for Chain<T>
 where
     T: RealField + SubsetOf<f64>,
@@ -320,6 +326,7 @@ where
     }
 }
 
+/// Build the chain without checking the duplicated names, see `From<&urdf_rs::Robot>`
 impl<T> From<urdf_rs::Robot> for Chain<T>
 where
     T: RealField + SubsetOf<f64>,
@@ -333,11 +340,580 @@ impl<T> Chain<T>
 where
     T: RealField + SubsetOf<f64>,
 {
+    /// Load the URDF (or xacro) file
+    ///
+    /// The links and the joints which have the same name are rejected by
+    /// `DuplicatePolicy::Error`. Use `UrdfLoader::on_duplicate` to change it.
     pub fn from_urdf_file<P>(path: P) -> Result<Self, urdf_rs::UrdfError>
     where
         P: AsRef<Path>,
     {
-        Ok(urdf_rs::utils::read_urdf_or_xacro(path)?.into())
+        let robot = urdf_rs::utils::read_urdf_or_xacro(path)?;
+        Self::from_robot_with_policy(robot, DuplicatePolicy::default())
+    }
+
+    /// Build the chain from the robot, handling the duplicated names by `policy`
+    ///
+    /// This is the same check as `Chain::from_urdf_file` and `UrdfLoader`, while
+    /// `From<urdf_rs::Robot>` skips it. See `resolve_duplicates`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::urdf::DuplicatePolicy;
+    ///
+    /// let robot = urdf_rs::read_file("urdf/sample.urdf").unwrap();
+    /// let chain = k::Chain::<f64>::from_robot_with_policy(robot, DuplicatePolicy::Error).unwrap();
+    /// assert_eq!(chain.dof(), 12);
+    /// ```
+    pub fn from_robot_with_policy(
+        mut robot: urdf_rs::Robot,
+        policy: DuplicatePolicy,
+    ) -> Result<Self, urdf_rs::UrdfError> {
+        resolve_duplicates(&mut robot, policy)?;
+        Ok(robot.into())
+    }
+
+    /// Reload the robot model from the URDF file, keeping the current joint positions
+    ///
+    /// The positions of the joints whose names exist in the new model are transferred
+    /// (clamped by the new limits). The new joints get zero, and the removed joints are
+    /// dropped. The gravity, the payloads, the base pose and the other settings of this chain
+    /// are kept. The margins of `Chain::apply_limit_margin` are applied to the new limits of
+    /// the joints with the same names, and the order of `Chain::reorder_joints` is kept if
+    /// the new model has the same movable joints. Otherwise the order of the file is used.
+    /// The nodes are replaced, so the `Node`s which are got from this chain before
+    /// are not a part of it any more.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut chain = k::Chain::<f64>::from_urdf_file("urdf/sample.urdf").unwrap();
+    /// chain.set_joint_positions(&[0.1; 12]).unwrap();
+    /// chain.reload_from_urdf("urdf/sample.urdf").unwrap();
+    /// assert_eq!(chain.joint_positions(), vec![0.1; 12]);
+    /// ```
+    pub fn reload_from_urdf<P>(&mut self, path: P) -> Result<(), urdf_rs::UrdfError>
+    where
+        P: AsRef<Path>,
+    {
+        let positions = self
+            .iter_joints()
+            .filter_map(|joint| Some((joint.name.clone(), joint.joint_position()?)))
+            .collect::<HashMap<_, _>>();
+        let margins = self
+            .iter_joints()
+            .filter_map(|joint| Some((joint.name.clone(), joint.limit_margin()?)))
+            .collect::<HashMap<_, _>>();
+        let order = self.joint_names();
+        let base_pose = self.base_pose();
+        let new_chain = Self::from_urdf_file(path)?;
+        new_chain.set_base_pose(base_pose);
+        for node in new_chain.iter() {
+            let name = node.joint().name.clone();
+            if let Some(fraction) = margins.get(&name) {
+                node.lock().joint.set_limit_margin(fraction.clone());
+            }
+            if let Some(position) = positions.get(&name) {
+                node.set_joint_position_clamped(position.clone());
+            }
+        }
+        let mut old_names = order.clone();
+        old_names.sort();
+        let mut new_names = new_chain.joint_names();
+        new_names.sort();
+        self.replace_nodes(new_chain);
+        if old_names == new_names {
+            self.reorder_joints(&order)
+                .expect("the movable joints have the same names");
+        }
+        Ok(())
+    }
+}
+
+/// Loader of URDF files with `<include>` and `package://` path resolution
+///
+/// `<include filename="..."/>` and `<include filename="..."></include>`
+/// (and `<xacro:include>`) elements are replaced
+/// by the contents of the included files, and the filenames of meshes and
+/// textures are resolved against the directory of the file which contains them.
+/// `package://` paths are resolved by the map which is set by `package_path`.
+/// Macros and properties of xacro are not supported.
+///
+/// # Examples
+///
+/// ```no_run
+/// let chain = k::urdf::UrdfLoader::new()
+///     .package_path("my_robot_description", "/opt/my_robot_description")
+///     .load::<f64, _>("robot.urdf")
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct UrdfLoader {
+    package_paths: HashMap<String, PathBuf>,
+    on_duplicate: DuplicatePolicy,
+}
+
+impl UrdfLoader {
+    /// The max depth of the nested includes, to avoid infinite recursion
+    const MAX_INCLUDE_DEPTH: usize = 32;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the root directory of the package `name` for `package://name/...` paths
+    pub fn package_path<P>(mut self, name: &str, path: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        self.package_paths
+            .insert(name.to_owned(), path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set how the links and the joints which have the same name are handled
+    ///
+    /// The default is `DuplicatePolicy::Error`. See `resolve_duplicates`.
+    pub fn on_duplicate(mut self, policy: DuplicatePolicy) -> Self {
+        self.on_duplicate = policy;
+        self
+    }
+
+    /// Resolve `filename` which is written in a file in `base_dir`
+    ///
+    /// `package://` paths of unknown packages and URLs are not changed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::path::Path;
+    ///
+    /// let loader = k::urdf::UrdfLoader::new().package_path("pkg", "/opt/pkg");
+    /// let base = Path::new("/robot/urdf");
+    /// assert_eq!(loader.resolve_path("package://pkg/a.stl", base), "/opt/pkg/a.stl");
+    /// assert_eq!(loader.resolve_path("meshes/a.stl", base), "/robot/urdf/meshes/a.stl");
+    /// assert_eq!(loader.resolve_path("file:///a.stl", base), "/a.stl");
+    /// assert_eq!(loader.resolve_path("package://other/a.stl", base), "package://other/a.stl");
+    /// ```
+    pub fn resolve_path(&self, filename: &str, base_dir: &Path) -> String {
+        if let Some(path) = filename.strip_prefix("package://") {
+            let (package, rest) = path.split_once('/').unwrap_or((path, ""));
+            return match self.package_paths.get(package) {
+                Some(root) => root.join(rest).to_string_lossy().into_owned(),
+                None => filename.to_owned(),
+            };
+        }
+        if let Some(path) = filename.strip_prefix("file://") {
+            return path.to_owned();
+        }
+        if filename.contains("://") || Path::new(filename).is_absolute() {
+            return filename.to_owned();
+        }
+        base_dir.join(filename).to_string_lossy().into_owned()
+    }
+
+    /// Read the URDF file with the includes expanded and the paths resolved
+    pub fn read_robot<P>(&self, path: P) -> Result<urdf_rs::Robot, urdf_rs::UrdfError>
+    where
+        P: AsRef<Path>,
+    {
+        let mut writer = quick_xml::Writer::new(Vec::new());
+        self.expand(path.as_ref(), 0, &mut writer)?;
+        let mut robot = urdf_rs::read_from_string(&String::from_utf8(writer.into_inner())?)?;
+        resolve_duplicates(&mut robot, self.on_duplicate)?;
+        Ok(robot)
+    }
+
+    /// Load the URDF file as `Chain`
+    pub fn load<T, P>(&self, path: P) -> Result<Chain<T>, urdf_rs::UrdfError>
+    where
+        T: RealField + SubsetOf<f64>,
+        P: AsRef<Path>,
+    {
+        Ok(self.read_robot(path)?.into())
+    }
+
+    fn expand(
+        &self,
+        path: &Path,
+        depth: usize,
+        writer: &mut quick_xml::Writer<Vec<u8>>,
+    ) -> Result<(), urdf_rs::UrdfError> {
+        if depth > Self::MAX_INCLUDE_DEPTH {
+            return Err(format!("too deep include of {}", path.display()).into());
+        }
+        let xml = std::fs::read_to_string(path)?;
+        let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+        let is_root = depth == 0;
+        let mut reader = quick_xml::Reader::from_str(&xml);
+        loop {
+            let event = reader.read_event().map_err(|e| e.to_string())?;
+            match event {
+                Event::Eof => break,
+                Event::Decl(_) | Event::DocType(_) if !is_root => {}
+                // the contents of the included file are merged into the parent robot
+                Event::Start(ref e) if !is_root && e.local_name().as_ref() == b"robot" => {}
+                Event::End(ref e) if !is_root && e.local_name().as_ref() == b"robot" => {}
+                Event::Start(ref e) | Event::Empty(ref e)
+                    if e.local_name().as_ref() == b"include" =>
+                {
+                    let filename = filename_attribute(e)?
+                        .ok_or_else(|| format!("include without filename in {}", path.display()))?;
+                    let included = self.resolve_path(&filename, base_dir);
+                    self.expand(Path::new(&included), depth + 1, writer)?;
+                    // the contents of `<include ...>...</include>` are skipped
+                    if let Event::Start(e) = &event {
+                        reader.read_to_end(e.name()).map_err(|e| e.to_string())?;
+                    }
+                }
+                Event::Start(ref e) | Event::Empty(ref e)
+                    if matches!(e.local_name().as_ref(), b"mesh" | b"texture") =>
+                {
+                    let mut resolved =
+                        BytesStart::new(String::from_utf8(e.name().as_ref().to_vec())?);
+                    for attr in e.attributes() {
+                        let attr = attr.map_err(|e| e.to_string())?;
+                        let value = attr.unescape_value().map_err(|e| e.to_string())?;
+                        if attr.key.as_ref() == b"filename" {
+                            let value = self.resolve_path(&value, base_dir);
+                            resolved.push_attribute(("filename", value.as_str()));
+                        } else {
+                            resolved.push_attribute((attr.key.as_ref(), value.as_bytes()));
+                        }
+                    }
+                    let resolved = if matches!(event, Event::Empty(_)) {
+                        Event::Empty(resolved)
+                    } else {
+                        Event::Start(resolved)
+                    };
+                    writer.write_event(resolved)?;
+                }
+                event => writer.write_event(event)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// How the links or the joints which have the same name are handled
+///
+/// Duplicated names are usually mistakes of xacro (e.g. a macro is used twice with
+/// the same prefix), and they make the lookups by name ambiguous.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicatePolicy {
+    /// Fail to load
+    #[default]
+    Error,
+    /// Rename the second and later ones by appending `_1`, `_2`, ...
+    Rename,
+    /// Merge the second and later ones into the first one
+    Merge,
+}
+
+/// Resolve the links and the joints which have the same name by `policy`
+///
+/// - `Error`: returns an error which contains the duplicated names.
+/// - `Rename`: the n-th duplicate is renamed to `name_n` (a larger number is used if it
+///   already exists). The n-th joint whose child is a duplicated link refers to the n-th link
+///   of the name. The parent of the n-th joint of a duplicated joint name refers to the n-th
+///   link of the name, as when a macro is used twice, and the other parents refer to the
+///   first one.
+/// - `Merge`: the visuals and the collisions of the duplicated links are appended to the first
+///   link of the name, and the duplicated joints are removed (the first one is kept).
+///   It returns an error if the duplicated links have different inertials or the duplicated
+///   joints are different, because one of them would be dropped.
+///
+/// # Examples
+///
+/// ```
+/// use k::urdf::{resolve_duplicates, DuplicatePolicy};
+///
+/// let mut robot = urdf_rs::read_from_string(
+///     r#"<robot name="robot">
+///          <link name="base"/>
+///          <link name="arm"/>
+///          <link name="arm"/>
+///          <joint name="j0" type="fixed">
+///            <parent link="base"/>
+///            <child link="arm"/>
+///          </joint>
+///        </robot>"#,
+/// )
+/// .unwrap();
+/// assert!(resolve_duplicates(&mut robot.clone(), DuplicatePolicy::Error).is_err());
+/// resolve_duplicates(&mut robot, DuplicatePolicy::Rename).unwrap();
+/// assert_eq!(robot.links[2].name, "arm_1");
+/// ```
+pub fn resolve_duplicates(
+    robot: &mut urdf_rs::Robot,
+    policy: DuplicatePolicy,
+) -> Result<(), urdf_rs::UrdfError> {
+    let duplicated = |names: Vec<&String>| {
+        let mut seen = HashSet::new();
+        names
+            .into_iter()
+            .filter(|name| !seen.insert(*name))
+            .cloned()
+            .collect::<Vec<_>>()
+    };
+    let duplicated_links = duplicated(robot.links.iter().map(|l| &l.name).collect());
+    let duplicated_joints = duplicated(robot.joints.iter().map(|j| &j.name).collect());
+    if duplicated_links.is_empty() && duplicated_joints.is_empty() {
+        return Ok(());
+    }
+    match policy {
+        DuplicatePolicy::Error => {
+            let names = duplicated_links
+                .iter()
+                .chain(duplicated_joints.iter())
+                .map(|name| name.as_str())
+                .collect::<Vec<_>>();
+            Err(format!("duplicated names: {}", names.join(", ")).into())
+        }
+        DuplicatePolicy::Rename => {
+            let mut link_names = robot
+                .links
+                .iter()
+                .map(|l| l.name.clone())
+                .collect::<HashSet<_>>();
+            // the new names of the duplicated links in the order of appearance
+            let mut renamed_links = HashMap::<String, Vec<String>>::new();
+            for link in &mut robot.links {
+                let names = renamed_links.entry(link.name.clone()).or_default();
+                if !names.is_empty() {
+                    link.name = unique_name(&link.name, &mut link_names);
+                }
+                names.push(link.name.clone());
+            }
+            let mut num_children = HashMap::<String, usize>::new();
+            let mut num_joints = HashMap::<String, usize>::new();
+            for joint in &mut robot.joints {
+                if let Some(names) = renamed_links.get(&joint.child.link) {
+                    let count = num_children.entry(joint.child.link.clone()).or_default();
+                    if let Some(name) = names.get(*count) {
+                        joint.child.link = name.clone();
+                    }
+                    *count += 1;
+                }
+                // the n-th copy of a joint belongs to the n-th copy of its parent link
+                let count = num_joints.entry(joint.name.clone()).or_default();
+                if let Some(names) = renamed_links.get(&joint.parent.link) {
+                    if let Some(name) = names.get(*count).or_else(|| names.first()) {
+                        joint.parent.link = name.clone();
+                    }
+                }
+                *count += 1;
+            }
+            let mut joint_names = robot
+                .joints
+                .iter()
+                .map(|j| j.name.clone())
+                .collect::<HashSet<_>>();
+            let mut seen = HashSet::new();
+            for joint in &mut robot.joints {
+                if !seen.insert(joint.name.clone()) {
+                    joint.name = unique_name(&joint.name, &mut joint_names);
+                }
+            }
+            Ok(())
+        }
+        DuplicatePolicy::Merge => {
+            // urdf_rs types don't implement PartialEq, so they are compared by Debug
+            let conflicts = |a: &dyn std::fmt::Debug, b: &dyn std::fmt::Debug| {
+                format!("{a:?}") != format!("{b:?}")
+            };
+            let mut links: Vec<urdf_rs::Link> = Vec::new();
+            for link in &robot.links {
+                match links.iter_mut().find(|l| l.name == link.name) {
+                    Some(first) => {
+                        if conflicts(&first.inertial, &link.inertial) {
+                            return Err(format!(
+                                "duplicated links {} have different inertials",
+                                link.name
+                            )
+                            .into());
+                        }
+                        first.visual.extend(link.visual.iter().cloned());
+                        first.collision.extend(link.collision.iter().cloned());
+                    }
+                    None => links.push(link.clone()),
+                }
+            }
+            let mut joints: Vec<urdf_rs::Joint> = Vec::new();
+            for joint in &robot.joints {
+                match joints.iter().find(|j| j.name == joint.name) {
+                    Some(first) => {
+                        if conflicts(first, joint) {
+                            return Err(
+                                format!("duplicated joints {} are different", joint.name).into()
+                            );
+                        }
+                    }
+                    None => joints.push(joint.clone()),
+                }
+            }
+            robot.links = links;
+            robot.joints = joints;
+            Ok(())
+        }
+    }
+}
+
+/// `name_n` with the smallest `n` which is not in `names`, and it is added to `names`
+fn unique_name(name: &str, names: &mut HashSet<String>) -> String {
+    let mut n = 1;
+    loop {
+        let candidate = format!("{name}_{n}");
+        if names.insert(candidate.clone()) {
+            return candidate;
+        }
+        n += 1;
+    }
+}
+
+fn filename_attribute(start: &BytesStart<'_>) -> Result<Option<String>, urdf_rs::UrdfError> {
+    for attr in start.attributes() {
+        let attr = attr.map_err(|e| e.to_string())?;
+        if attr.key.as_ref() == b"filename" {
+            return Ok(Some(
+                attr.unescape_value()
+                    .map_err(|e| e.to_string())?
+                    .into_owned(),
+            ));
+        }
+    }
+    Ok(None)
+}
+
+/// Check the URDF model before converting it to `Chain`
+///
+/// It checks that there is exactly one root link, the names of the joints and links
+/// are unique, the axes of the movable joints are not zero, the limits are `lower <= upper`,
+/// the continuous joints do not have limits, the links of the joints exist,
+/// and all links are connected to the root link.
+/// All problems are returned, not only the first one.
+///
+/// # Examples
+///
+/// ```
+/// let urdf_robot = urdf_rs::read_from_string(
+///     r#"<robot name="robot">
+///          <link name="base"/>
+///          <link name="arm"/>
+///          <joint name="j0" type="continuous">
+///            <parent link="base"/>
+///            <child link="arm"/>
+///          </joint>
+///        </robot>"#,
+/// )
+/// .unwrap();
+/// assert!(k::urdf::validate_robot(&urdf_robot).is_ok());
+///
+/// // `urdf/sample.urdf` refers to the link `root` which does not exist
+/// let urdf_robot = urdf_rs::read_file("urdf/sample.urdf").unwrap();
+/// let errors = k::urdf::validate_robot(&urdf_robot).unwrap_err();
+/// assert!(errors.contains(&k::ValidationError::LinkNotFoundError {
+///     joint_name: "l_shoulder_yaw".to_owned(),
+///     link_name: "root".to_owned(),
+/// }));
+/// ```
+pub fn validate_robot(urdf_robot: &urdf_rs::Robot) -> Result<(), Vec<ValidationError>> {
+    let mut errors = Vec::new();
+    let mut link_names = HashSet::new();
+    for l in &urdf_robot.links {
+        if !link_names.insert(l.name.as_str()) {
+            errors.push(ValidationError::DuplicateNameError {
+                name: l.name.clone(),
+            });
+        }
+    }
+    let mut joint_names = HashSet::new();
+    let mut children = HashMap::<&str, Vec<&str>>::new();
+    for j in &urdf_robot.joints {
+        if !joint_names.insert(j.name.as_str()) {
+            errors.push(ValidationError::DuplicateNameError {
+                name: j.name.clone(),
+            });
+        }
+        for link_name in [&j.parent.link, &j.child.link] {
+            if !link_names.contains(link_name.as_str()) {
+                errors.push(ValidationError::LinkNotFoundError {
+                    joint_name: j.name.clone(),
+                    link_name: link_name.clone(),
+                });
+            }
+        }
+        children
+            .entry(j.parent.link.as_str())
+            .or_default()
+            .push(j.child.link.as_str());
+        let is_movable = matches!(
+            j.joint_type,
+            urdf_rs::JointType::Revolute
+                | urdf_rs::JointType::Continuous
+                | urdf_rs::JointType::Prismatic
+        );
+        if is_movable && j.axis.xyz.iter().all(|v| *v == 0.0) {
+            errors.push(ValidationError::ZeroAxisError {
+                joint_name: j.name.clone(),
+            });
+        }
+        let has_limits = matches!(
+            j.joint_type,
+            urdf_rs::JointType::Revolute | urdf_rs::JointType::Prismatic
+        );
+        if has_limits && j.limit.lower > j.limit.upper {
+            errors.push(ValidationError::InvalidLimitsError {
+                joint_name: j.name.clone(),
+                min: j.limit.lower,
+                max: j.limit.upper,
+            });
+        }
+        // the limits of continuous joints are used by the conversion to `Chain`,
+        // so they must not be set
+        if j.joint_type == urdf_rs::JointType::Continuous && j.limit.lower != j.limit.upper {
+            errors.push(ValidationError::ContinuousWithLimitsError {
+                joint_name: j.name.clone(),
+            });
+        }
+    }
+    let child_links = urdf_robot
+        .joints
+        .iter()
+        .map(|j| j.child.link.as_str())
+        .collect::<HashSet<_>>();
+    let roots = urdf_robot
+        .links
+        .iter()
+        .filter(|l| !child_links.contains(l.name.as_str()))
+        .map(|l| l.name.clone())
+        .collect::<Vec<_>>();
+    if roots.len() != 1 {
+        errors.push(ValidationError::RootError {
+            roots: roots.clone(),
+        });
+    }
+    if let Some(root) = roots.first() {
+        let mut connected = HashSet::new();
+        let mut stack = vec![root.as_str()];
+        while let Some(link_name) = stack.pop() {
+            if connected.insert(link_name) {
+                stack.extend(children.get(link_name).into_iter().flatten());
+            }
+        }
+        for l in &urdf_robot.links {
+            if !connected.contains(l.name.as_str()) {
+                errors.push(ValidationError::DisconnectedError {
+                    name: l.name.clone(),
+                });
+            }
+        }
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
     }
 }
 
@@ -385,6 +961,12 @@ pub fn joint_to_link_map(urdf_robot: &urdf_rs::Robot) -> HashMap<String, String>
 // https://github.com/openrr/urdf-rs/pull/3/files#diff-0fb2eeea3273a4c9b3de69ee949567f546dc8c06b1e190336870d00b54ea0979L36-L38
 const DEFAULT_MESH_SCALE: [f64; 3] = [1.0f64; 3];
 
+/// Chain of `urdf/sample.urdf` for the tests
+#[cfg(test)]
+pub(crate) fn sample_chain() -> Chain<f64> {
+    Chain::from(urdf_rs::read_from_string(include_str!("../urdf/sample.urdf")).unwrap())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -422,4 +1004,284 @@ mod tests {
         assert_eq!(names[0], "root");
         assert_eq!(names[1], "l_shoulder_yaw");
     }
+
+    #[cfg(not(target_family = "wasm"))]
+    #[test]
+    fn test_loader_include_and_package_path() {
+        let dir = std::env::temp_dir().join(format!("k_test_urdf_loader_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("parts")).unwrap();
+        std::fs::write(
+            dir.join("robot.urdf"),
+            r#"<?xml version="1.0"?>
+<robot xmlns:xacro="http://www.ros.org/wiki/xacro" name="robot">
+  <link name="base"/>
+  <include filename="parts/arm.urdf"/>
+  <xacro:include filename="parts/hand.urdf">
+  </xacro:include>
+</robot>"#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("parts/arm.urdf"),
+            r#"<?xml version="1.0"?>
+<robot xmlns:xacro="http://www.ros.org/wiki/xacro" name="arm">
+  <link name="arm">
+    <visual>
+      <geometry><mesh filename="package://my_pkg/meshes/arm.stl"/></geometry>
+    </visual>
+    <collision>
+      <geometry><mesh filename="meshes/arm_collision.stl"/></geometry>
+    </collision>
+  </link>
+  <joint name="shoulder" type="continuous">
+    <parent link="base"/>
+    <child link="arm"/>
+  </joint>
+</robot>"#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("parts/hand.urdf"),
+            r#"<?xml version="1.0"?>
+<robot name="hand">
+  <link name="hand"/>
+  <joint name="wrist" type="continuous">
+    <parent link="arm"/>
+    <child link="hand"/>
+  </joint>
+</robot>"#,
+        )
+        .unwrap();
+
+        let chain = UrdfLoader::new()
+            .package_path("my_pkg", "/opt/my_pkg")
+            .load::<f64, _>(dir.join("robot.urdf"))
+            .unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(chain.dof(), 2);
+        assert_eq!(
+            chain.find("wrist").unwrap().parent().unwrap().joint().name,
+            "shoulder"
+        );
+        let node = chain.find("shoulder").unwrap();
+        let link = node.link();
+        let link = link.as_ref().unwrap();
+        match &link.visuals[0].geometry {
+            Geometry::Mesh { filename, .. } => assert_eq!(filename, "/opt/my_pkg/meshes/arm.stl"),
+            g => panic!("unexpected geometry {g:?}"),
+        }
+        match &link.collisions[0].geometry {
+            Geometry::Mesh { filename, .. } => assert_eq!(
+                Path::new(filename),
+                dir.join("parts/meshes/arm_collision.stl")
+            ),
+            g => panic!("unexpected geometry {g:?}"),
+        }
+    }
+
+    const DUPLICATED_URDF: &str = r#"<robot name="robot">
+  <link name="base"/>
+  <link name="finger">
+    <visual><geometry><sphere radius="0.1"/></geometry></visual>
+  </link>
+  <link name="finger">
+    <visual><geometry><box size="0.1 0.1 0.1"/></geometry></visual>
+  </link>
+  <joint name="finger_joint" type="continuous">
+    <parent link="base"/>
+    <child link="finger"/>
+  </joint>
+  <joint name="finger_joint" type="continuous">
+    <origin xyz="0 0.1 0"/>
+    <parent link="base"/>
+    <child link="finger"/>
+  </joint>
+</robot>"#;
+
+    #[cfg(not(target_family = "wasm"))]
+    #[test]
+    fn test_reload_from_urdf() {
+        let mut chain = Chain::<f64>::from_urdf_file("urdf/sample.urdf").unwrap();
+        chain.set_gravity(na::Vector3::new(0.0, 0.0, -1.0));
+        let base = na::Isometry3::translation(0.5, 0.0, 0.0);
+        chain.set_base_pose(base);
+        let positions = (0..chain.dof())
+            .map(|i| 0.1 * i as f64 - 0.5)
+            .collect::<Vec<_>>();
+        chain.set_joint_positions(&positions).unwrap();
+        let old_root = chain.iter().next().unwrap().clone();
+        chain.reload_from_urdf("urdf/sample.urdf").unwrap();
+        assert_eq!(chain.joint_positions(), positions);
+        assert_eq!(chain.gravity(), &na::Vector3::new(0.0, 0.0, -1.0));
+        assert_eq!(chain.base_pose(), base);
+        assert!(chain.iter().next().unwrap() != &old_root);
+        assert!(chain.reload_from_urdf("no_exist.urdf").is_err());
+        assert_eq!(chain.joint_positions(), positions);
+    }
+
+    #[test]
+    fn test_reload_from_urdf_keeps_order_and_margin() {
+        let mut chain = Chain::<f64>::from_urdf_file("urdf/sample.urdf").unwrap();
+        let mut order = chain.joint_names();
+        order.reverse();
+        chain.reorder_joints(&order).unwrap();
+        chain.apply_limit_margin(0.5);
+        let limits = chain.joint_limit_vectors();
+        chain.reload_from_urdf("urdf/sample.urdf").unwrap();
+        assert_eq!(chain.joint_names(), order);
+        let new_limits = chain.joint_limit_vectors();
+        for (a, b) in new_limits
+            .0
+            .iter()
+            .chain(&new_limits.1)
+            .zip(limits.0.iter().chain(&limits.1))
+        {
+            assert!((a - b).abs() < 1e-10);
+        }
+        // the margin is replaced, not applied twice
+        chain.apply_limit_margin(1.0);
+        let original = Chain::<f64>::from_urdf_file("urdf/sample.urdf").unwrap();
+        let mut original_limits = original.joint_limit_vectors();
+        original_limits.0.reverse();
+        original_limits.1.reverse();
+        let new_limits = chain.joint_limit_vectors();
+        for (a, b) in new_limits
+            .0
+            .iter()
+            .chain(&new_limits.1)
+            .zip(original_limits.0.iter().chain(&original_limits.1))
+        {
+            assert!((a - b).abs() < 1e-10);
+        }
+    }
+
+    #[test]
+    fn test_duplicate_policy_error() {
+        let mut robot = urdf_rs::read_from_string(DUPLICATED_URDF).unwrap();
+        let err = resolve_duplicates(&mut robot, DuplicatePolicy::Error).unwrap_err();
+        assert!(err.to_string().contains("finger, finger_joint"));
+        // nothing is changed if there are no duplicates
+        let mut robot = urdf_rs::read_from_string(include_str!("../urdf/sample.urdf")).unwrap();
+        resolve_duplicates(&mut robot, DuplicatePolicy::Error).unwrap();
+        assert_eq!(robot.links.len(), 13);
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    #[test]
+    fn test_duplicate_policy_from_file() {
+        let path =
+            std::env::temp_dir().join(format!("k_test_duplicated_{}.urdf", std::process::id()));
+        std::fs::write(&path, DUPLICATED_URDF).unwrap();
+        let by_chain = Chain::<f64>::from_urdf_file(&path);
+        let by_loader = UrdfLoader::new().load::<f64, _>(&path);
+        let renamed = UrdfLoader::new()
+            .on_duplicate(DuplicatePolicy::Rename)
+            .load::<f64, _>(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert!(by_chain.is_err());
+        assert!(by_loader.is_err());
+        assert_eq!(renamed.unwrap().dof(), 2);
+    }
+
+    #[test]
+    fn test_duplicate_policy_from_robot() {
+        let robot = urdf_rs::read_from_string(DUPLICATED_URDF).unwrap();
+        assert!(
+            Chain::<f64>::from_robot_with_policy(robot.clone(), DuplicatePolicy::Error).is_err()
+        );
+        let renamed = Chain::<f64>::from_robot_with_policy(robot, DuplicatePolicy::Rename).unwrap();
+        assert_eq!(renamed.dof(), 2);
+    }
+
+    #[test]
+    fn test_duplicate_policy_rename() {
+        let mut robot = urdf_rs::read_from_string(DUPLICATED_URDF).unwrap();
+        resolve_duplicates(&mut robot, DuplicatePolicy::Rename).unwrap();
+        assert!(validate_robot(&robot).is_ok());
+        let chain = Chain::<f64>::from(&robot);
+        assert_eq!(chain.dof(), 2);
+        assert_eq!(
+            chain
+                .find("finger_joint_1")
+                .unwrap()
+                .link()
+                .as_ref()
+                .unwrap()
+                .name,
+            "finger_1"
+        );
+        assert_eq!(
+            chain.find_link("finger").unwrap().joint().name,
+            "finger_joint"
+        );
+
+        // the parents are also renamed when a macro is used twice
+        let mut robot = urdf_rs::read_from_string(
+            r#"<robot name="robot">
+  <link name="base"/>
+  <link name="arm"/>
+  <link name="hand"/>
+  <link name="arm"/>
+  <link name="hand"/>
+  <joint name="arm_joint" type="continuous">
+    <parent link="base"/>
+    <child link="arm"/>
+  </joint>
+  <joint name="hand_joint" type="continuous">
+    <parent link="arm"/>
+    <child link="hand"/>
+  </joint>
+  <joint name="arm_joint" type="continuous">
+    <origin xyz="0 0.1 0"/>
+    <parent link="base"/>
+    <child link="arm"/>
+  </joint>
+  <joint name="hand_joint" type="continuous">
+    <parent link="arm"/>
+    <child link="hand"/>
+  </joint>
+</robot>"#,
+        )
+        .unwrap();
+        resolve_duplicates(&mut robot, DuplicatePolicy::Rename).unwrap();
+        assert!(validate_robot(&robot).is_ok());
+        let hand_joint = &robot.joints[3];
+        assert_eq!(hand_joint.name, "hand_joint_1");
+        assert_eq!(hand_joint.parent.link, "arm_1");
+        assert_eq!(hand_joint.child.link, "hand_1");
+        assert_eq!(robot.joints[1].parent.link, "arm");
+        let chain = Chain::<f64>::from(&robot);
+        assert_eq!(
+            chain
+                .find("hand_joint_1")
+                .unwrap()
+                .parent()
+                .unwrap()
+                .joint()
+                .name,
+            "arm_joint_1"
+        );
+    }
+
+    #[test]
+    fn test_duplicate_policy_merge() {
+        // the duplicated joints have different origins
+        let mut robot = urdf_rs::read_from_string(DUPLICATED_URDF).unwrap();
+        let err = resolve_duplicates(&mut robot, DuplicatePolicy::Merge).unwrap_err();
+        assert!(err.to_string().contains("finger_joint"));
+        let mut robot =
+            urdf_rs::read_from_string(&DUPLICATED_URDF.replace(r#"<origin xyz="0 0.1 0"/>"#, ""))
+                .unwrap();
+        resolve_duplicates(&mut robot, DuplicatePolicy::Merge).unwrap();
+        assert!(validate_robot(&robot).is_ok());
+        assert_eq!(robot.links.len(), 2);
+        assert_eq!(robot.joints.len(), 1);
+        let chain = Chain::<f64>::from(&robot);
+        assert_eq!(chain.dof(), 1);
+        let node = chain.find("finger_joint").unwrap();
+        assert_eq!(node.joint().origin().translation.vector.y, 0.0);
+        let link = node.link();
+        let link = link.as_ref().unwrap();
+        assert_eq!(link.visuals.len(), 2);
+    }
 }
@@ -0,0 +1,153 @@
+/*
+  Copyright 2017 Takashi Ogura
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+use super::chain::*;
+use super::joint::*;
+use na::RealField;
+use nalgebra as na;
+use simba::scalar::SubsetOf;
+use std::collections::HashSet;
+use thiserror::Error;
+
+/// A problem of the model which is found by `Chain::validate` or `urdf::validate_robot`
+#[derive(Debug, Clone, PartialEq, Error)]
+#[non_exhaustive]
+pub enum ValidationError {
+    #[error("must have exactly one root, but has {}: {:?}", roots.len(), roots)]
+    RootError { roots: Vec<String> },
+    #[error("{} is defined more than once", name)]
+    DuplicateNameError { name: String },
+    #[error("axis of joint {} is zero", joint_name)]
+    ZeroAxisError { joint_name: String },
+    #[error(
+        "joint {} has invalid limits: min = {} > max = {}",
+        joint_name,
+        min,
+        max
+    )]
+    InvalidLimitsError {
+        joint_name: String,
+        min: f64,
+        max: f64,
+    },
+    #[error("link {} of joint {} is not found", link_name, joint_name)]
+    LinkNotFoundError {
+        joint_name: String,
+        link_name: String,
+    },
+    #[error("{} is not connected to the root", name)]
+    DisconnectedError { name: String },
+    #[error(
+        "continuous joint {} has limits, use revolute joint if it is limited",
+        joint_name
+    )]
+    ContinuousWithLimitsError { joint_name: String },
+}
+
+impl<T> Chain<T>
+where
+    T: RealField + SubsetOf<f64>,
+{
+    /// Check the structure and the joints of the chain
+    ///
+    /// It checks that there is exactly one root, the names of the joints are unique,
+    /// the axes are not zero, the limits are `min <= max`, and all nodes are connected
+    /// to the root. All problems are returned, not only the first one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use k::*;
+    ///
+    /// let l0 = NodeBuilder::new()
+    ///     .name("j")
+    ///     .joint_type(JointType::Rotational { axis: Vector3::z_axis() })
+    ///     .into_node();
+    /// let l1 = NodeBuilder::new()
+    ///     .name("j")
+    ///     .joint_type(JointType::Rotational { axis: Vector3::z_axis() })
+    ///     .limits(Some(k::joint::Range { min: 1.0, max: -1.0 }))
+    ///     .into_node();
+    /// l1.set_parent(&l0);
+    /// let chain = Chain::<f64>::from_root(l0);
+    /// assert_eq!(chain.validate().unwrap_err().len(), 2);
+    /// ```
+    pub fn validate(&self) -> Result<(), Vec<ValidationError>> {
+        let mut errors = Vec::new();
+        let roots = self
+            .iter()
+            .filter(|node| match node.parent() {
+                Some(parent) => !self.iter().any(|n| *n == parent),
+                None => true,
+            })
+            .collect::<Vec<_>>();
+        if roots.len() != 1 {
+            errors.push(ValidationError::RootError {
+                roots: roots.iter().map(|node| node.joint().name.clone()).collect(),
+            });
+        }
+        let mut names = HashSet::new();
+        for node in self.iter() {
+            let joint = node.joint();
+            if !names.insert(joint.name.clone()) {
+                errors.push(ValidationError::DuplicateNameError {
+                    name: joint.name.clone(),
+                });
+            }
+            validate_joint(&joint, &mut errors);
+        }
+        if let Some(root) = roots.first() {
+            for node in self.iter() {
+                if *node != **root && !node.iter_ancestors().any(|a| a == **root) {
+                    errors.push(ValidationError::DisconnectedError {
+                        name: node.joint().name.clone(),
+                    });
+                }
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+fn validate_joint<T>(joint: &Joint<T>, errors: &mut Vec<ValidationError>)
+where
+    T: RealField + SubsetOf<f64>,
+{
+    match &joint.joint_type {
+        JointType::Rotational { axis } | JointType::Linear { axis } => {
+            // zero axis becomes NaN by normalization
+            let norm = axis.norm();
+            if !norm.is_finite() || norm < na::convert(0.5) {
+                errors.push(ValidationError::ZeroAxisError {
+                    joint_name: joint.name.clone(),
+                });
+            }
+        }
+        JointType::Fixed => {}
+    }
+    if let Some(range) = &joint.limits {
+        if range.min > range.max {
+            errors.push(ValidationError::InvalidLimitsError {
+                joint_name: joint.name.clone(),
+                min: na::try_convert(range.min.clone()).unwrap_or_default(),
+                max: na::try_convert(range.max.clone()).unwrap_or_default(),
+            });
+        }
+    }
+}
@@ -66,3 +66,240 @@ fn test_clone() {
             .collect::<Vec<_>>()
     );
 }
+
+#[test]
+fn test_chain_builder() {
+    let robot = urdf_rs::read_from_string(include_str!("../urdf/sample.urdf")).unwrap();
+    let tree = k::Chain::<f64>::from(&robot);
+    let mut builder = k::ChainBuilder::new().add_joint(
+        k::Joint::new(k::urdf::ROOT_JOINT_NAME, k::JointType::Fixed),
+        None,
+    );
+    // add in the reverse order to check that the order does not matter
+    for j in robot.joints.iter().rev() {
+        let parent = robot
+            .joints
+            .iter()
+            .find(|p| p.child.link == j.parent.link)
+            .map_or(k::urdf::ROOT_JOINT_NAME, |p| p.name.as_str());
+        builder = builder.add_joint(j.into(), Some(parent));
+    }
+    let built = builder.finalize().unwrap();
+    assert_eq!(built.dof(), tree.dof());
+    let names = |c: &k::Chain<f64>| {
+        c.iter()
+            .map(|n| {
+                let parent = n.parent().map(|p| p.joint().name.clone());
+                let name = n.joint().name.clone();
+                (name, parent)
+            })
+            .collect::<Vec<_>>()
+    };
+    let mut built_names = names(&built);
+    let mut tree_names = names(&tree);
+    built_names.sort();
+    tree_names.sort();
+    assert_eq!(built_names, tree_names);
+
+    let positions = vec![0.0, 0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8, 0.9, -0.1, -0.2];
+    tree.set_joint_positions(&positions).unwrap();
+    tree.update_transforms();
+    for node in built.iter() {
+        let name = node.joint().name.clone();
+        let position = tree.find(&name).unwrap().joint_position();
+        if let Some(position) = position {
+            node.set_joint_position(position).unwrap();
+        }
+    }
+    built.update_transforms();
+    for node in built.iter() {
+        let name = node.joint().name.clone();
+        assert_eq!(
+            node.world_transform(),
+            tree.find(&name).unwrap().world_transform()
+        );
+    }
+}
+
+#[test]
+fn test_chain_builder_error() {
+    let joint = |name: &str| k::Joint::<f64>::new(name, k::JointType::Fixed);
+    // two roots
+    assert!(k::ChainBuilder::new()
+        .add_joint(joint("a"), None)
+        .add_joint(joint("b"), None)
+        .finalize()
+        .is_err());
+    // orphan
+    assert!(k::ChainBuilder::new()
+        .add_joint(joint("a"), None)
+        .add_joint(joint("b"), Some("c"))
+        .finalize()
+        .is_err());
+    // cycle
+    assert!(matches!(
+        k::ChainBuilder::new()
+            .add_joint(joint("a"), None)
+            .add_joint(joint("b"), Some("c"))
+            .add_joint(joint("c"), Some("b"))
+            .finalize(),
+        Err(k::Error::InvalidTreeError { reason }) if reason == "there is a cycle"
+    ));
+    // cycle without root
+    assert!(matches!(
+        k::ChainBuilder::new()
+            .add_joint(joint("b"), Some("c"))
+            .add_joint(joint("c"), Some("b"))
+            .finalize(),
+        Err(k::Error::InvalidTreeError { reason }) if reason.contains("exactly one root")
+    ));
+    // self parent
+    assert!(k::ChainBuilder::new()
+        .add_joint(joint("a"), None)
+        .add_joint(joint("b"), Some("b"))
+        .finalize()
+        .is_err());
+    // duplicated
+    assert!(k::ChainBuilder::new()
+        .add_joint(joint("a"), None)
+        .add_joint(joint("a"), Some("a"))
+        .finalize()
+        .is_err());
+}
+
+#[test]
+fn test_chain_from_parent_map() {
+    let joint = |name: &str| {
+        k::NodeBuilder::<f64>::new()
+            .name(name)
+            .translation(k::Translation3::new(0.0, 0.0, 0.1))
+            .joint_type(k::JointType::Rotational {
+                axis: k::Vector3::y_axis(),
+            })
+            .finalize()
+    };
+    let link = |name: &str| k::link::LinkBuilder::new().name(name).finalize();
+    // base -> (left, right), left -> hand
+    let joints = vec![
+        (joint("hand"), Some(link("hand_link"))),
+        (joint("left"), Some(link("left_link"))),
+        (joint("base"), None),
+        (joint("right"), Some(link("right_link"))),
+    ];
+    let parents = std::collections::HashMap::from([
+        ("hand".to_owned(), "left".to_owned()),
+        ("left".to_owned(), "base".to_owned()),
+        ("right".to_owned(), "base".to_owned()),
+    ]);
+    let built = k::Chain::from_parent_map(joints.clone(), &parents).unwrap();
+
+    let base: k::Node<f64> = joint("base").into();
+    let left: k::Node<f64> = joint("left").into();
+    let right: k::Node<f64> = joint("right").into();
+    let hand: k::Node<f64> = joint("hand").into();
+    left.set_link(Some(link("left_link")));
+    right.set_link(Some(link("right_link")));
+    hand.set_link(Some(link("hand_link")));
+    left.set_parent(&base);
+    right.set_parent(&base);
+    hand.set_parent(&left);
+    let manual = k::Chain::from_root(base);
+
+    assert_eq!(built.dof(), manual.dof());
+    let topology = |c: &k::Chain<f64>| {
+        let mut topology = c
+            .iter()
+            .map(|n| {
+                let parent = n.parent().map(|p| p.joint().name.clone());
+                let link = n.link().as_ref().map(|l| l.name.clone());
+                (n.joint().name.clone(), parent, link)
+            })
+            .collect::<Vec<_>>();
+        topology.sort();
+        topology
+    };
+    assert_eq!(topology(&built), topology(&manual));
+    assert!(built.find_link("hand_link").is_some());
+
+    // cycle between left and hand, and no root
+    let mut parents = parents;
+    parents.insert("base".to_owned(), "hand".to_owned());
+    assert!(matches!(
+        k::Chain::from_parent_map(joints, &parents),
+        Err(k::Error::InvalidTreeError { .. })
+    ));
+}
+
+#[test]
+fn test_validate_urdf() {
+    let robot = urdf_rs::read_from_string(
+        r#"<robot name="robot">
+  <link name="base"/>
+  <link name="l1"/>
+  <link name="l2"/>
+  <link name="floating"/>
+  <joint name="j1" type="revolute">
+    <parent link="base"/>
+    <child link="l1"/>
+    <axis xyz="0 0 1"/>
+    <limit lower="1.0" upper="-1.0" effort="1" velocity="1"/>
+  </joint>
+  <joint name="j1" type="revolute">
+    <parent link="l1"/>
+    <child link="l2"/>
+    <axis xyz="0 0 1"/>
+    <limit lower="-1.0" upper="1.0" effort="1" velocity="1"/>
+  </joint>
+</robot>"#,
+    )
+    .unwrap();
+    let errors = k::urdf::validate_robot(&robot).unwrap_err();
+    assert!(errors.contains(&k::ValidationError::DuplicateNameError {
+        name: "j1".to_owned()
+    }));
+    assert!(errors.contains(&k::ValidationError::InvalidLimitsError {
+        joint_name: "j1".to_owned(),
+        min: 1.0,
+        max: -1.0,
+    }));
+    assert!(errors.contains(&k::ValidationError::RootError {
+        roots: vec!["base".to_owned(), "floating".to_owned()]
+    }));
+    assert!(errors.contains(&k::ValidationError::DisconnectedError {
+        name: "floating".to_owned()
+    }));
+    assert_eq!(errors.len(), 4);
+
+    let chain = k::Chain::<f64>::from(
+        urdf_rs::read_from_string(include_str!("../urdf/sample.urdf")).unwrap(),
+    );
+    assert!(chain.validate().is_ok());
+}
+
+#[test]
+fn test_validate_urdf_continuous_with_limits() {
+    let robot = urdf_rs::read_from_string(
+        r#"<robot name="robot">
+  <link name="base"/>
+  <link name="l1"/>
+  <joint name="j1" type="continuous">
+    <parent link="base"/>
+    <child link="l1"/>
+    <axis xyz="0 0 1"/>
+    <limit lower="-1.0" upper="1.0" effort="1" velocity="1"/>
+  </joint>
+</robot>"#,
+    )
+    .unwrap();
+    let errors = k::urdf::validate_robot(&robot).unwrap_err();
+    assert_eq!(
+        errors,
+        vec![k::ValidationError::ContinuousWithLimitsError {
+            joint_name: "j1".to_owned()
+        }]
+    );
+    assert_eq!(
+        errors[0].to_string(),
+        "continuous joint j1 has limits, use revolute joint if it is limited"
+    );
+}
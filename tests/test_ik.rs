@@ -176,3 +176,661 @@ fn ik_fk7_with_constraints() {
         assert!((angles[6] - end_angles[6]).abs() < f32::EPSILON);
     }
 }
+
+#[test]
+fn ik_fk7_with_ignored_joint_square() {
+    let arm = create_joint_with_link_array7();
+    let angles = vec![0.8, 0.2, 0.3, -1.5, 0.2, -0.3, 0.1];
+    arm.set_joint_positions(&angles).unwrap();
+    let target = arm.end_transform();
+    // the ignored joint in the middle is at the goal, and the six others solve the pose
+    arm.set_joint_positions(&[0.7, 0.3, 0.3, -1.4, 0.1, -0.2, 0.0])
+        .unwrap();
+    let solver = k::JacobianIkSolver::new(0.001, 0.001, 0.5, 100);
+    let constraints = k::Constraints {
+        ignored_joint_names: vec!["shoulder_yaw".to_string()],
+        ..Default::default()
+    };
+    solver
+        .solve_with_constraints(&arm, &target, &constraints)
+        .unwrap();
+    assert_eq!(arm.joint_positions()[2], angles[2]);
+    let end = arm.end_transform();
+    assert!((end.translation.vector - target.translation.vector).norm() < 0.002);
+    assert!(end.rotation.angle_to(&target.rotation) < 0.002);
+}
+
+/// Arm with the limits `[-2, 2]`, made of the first `dof` (up to 7) joints of a 7 DOF arm
+fn create_limited_arm(dof: usize) -> k::SerialChain<f64> {
+    let axes = [
+        Vector3::y_axis(),
+        Vector3::x_axis(),
+        Vector3::z_axis(),
+        Vector3::y_axis(),
+        Vector3::z_axis(),
+        Vector3::y_axis(),
+        Vector3::x_axis(),
+    ];
+    let translations = [
+        Translation3::new(0.0, 0.0, 0.0),
+        Translation3::new(0.0, 0.1, 0.0),
+        Translation3::new(0.0, 0.0, -0.30),
+        Translation3::new(0.0, 0.0, -0.15),
+        Translation3::new(0.0, 0.0, -0.15),
+        Translation3::new(0.0, 0.0, -0.15),
+        Translation3::new(0.0, 0.0, -0.10),
+    ];
+    let nodes = axes[..dof]
+        .iter()
+        .zip(translations.iter())
+        .map(|(axis, translation)| {
+            k::NodeBuilder::new()
+                .joint_type(k::JointType::Rotational { axis: *axis })
+                .translation(*translation)
+                .limits(Some((-2.0..=2.0).into()))
+                .into_node()
+        })
+        .collect::<Vec<_>>();
+    for i in 1..nodes.len() {
+        nodes[i].set_parent(&nodes[i - 1]);
+    }
+    k::SerialChain::from_end(nodes.last().unwrap())
+}
+
+#[test]
+fn ik_random_restarts() {
+    let arm = create_limited_arm(6);
+    let angles = vec![1.9, 0.76, -0.29, -1.3, -0.98, 1.85];
+    arm.set_joint_positions(&angles).unwrap();
+    let target = arm.end_transform();
+    arm.set_joint_positions(&[0.0; 6]).unwrap();
+
+    let mut solver = k::JacobianIkSolver::new(0.001, 0.001, 0.5, 30);
+    assert!(solver.solve(&arm, &target).is_err());
+    assert_eq!(arm.joint_positions(), vec![0.0; 6]);
+
+    solver.restarts = 10;
+    solver.solve(&arm, &target).unwrap();
+    for position in arm.joint_positions() {
+        assert!((-2.0..=2.0).contains(&position));
+    }
+    let diff = arm.end_transform().translation.vector - target.translation.vector;
+    assert!(diff.norm() < 0.001);
+    let solved = arm.joint_positions();
+
+    // the same seed gives the same result
+    arm.set_joint_positions(&[0.0; 6]).unwrap();
+    solver.solve(&arm, &target).unwrap();
+    assert_eq!(arm.joint_positions(), solved);
+    solver.restart_seed = 1;
+    let solve_from_zero = || {
+        arm.set_joint_positions(&[0.0; 6]).unwrap();
+        let is_ok = solver.solve(&arm, &target).is_ok();
+        (is_ok, arm.joint_positions())
+    };
+    assert_eq!(solve_from_zero(), solve_from_zero());
+}
+
+#[test]
+fn ik_random_restarts_all_failed() {
+    let arm = create_limited_arm(6);
+    let init_angles = vec![0.1, 0.2, -0.3, 0.4, -0.5, 0.6];
+    arm.set_joint_positions(&init_angles).unwrap();
+    // out of reach
+    let target = k::Isometry3::translation(100.0, 0.0, 0.0);
+    let mut solver = k::JacobianIkSolver::new(0.001, 0.001, 0.5, 10);
+    solver.restarts = 5;
+    assert!(solver.solve(&arm, &target).is_err());
+    // the positions at the start are kept, not the closest attempt
+    assert_eq!(arm.joint_positions(), init_angles);
+}
+
+#[test]
+fn ik_orientation_tolerance() {
+    let arm = create_joint_with_link_array6();
+    let angles = vec![0.8, 0.2, 0.0, -1.2, 0.0, 0.1];
+    arm.set_joint_positions(&angles).unwrap();
+    let target = arm.end_transform();
+    let init_angles = vec![0.4, 0.1, 0.1, -1.0, 0.1, 0.1];
+
+    // the minimum number of the iterations to converge
+    let num_iterations = |orientation_tolerance: f64| {
+        (1..100)
+            .find(|num_max_try| {
+                let mut solver = k::JacobianIkSolver::new(0.0, 0.0, 0.5, *num_max_try);
+                solver.set_tolerances(0.001, orientation_tolerance);
+                arm.set_joint_positions(&init_angles).unwrap();
+                solver.solve(&arm, &target).is_ok()
+            })
+            .unwrap()
+    };
+    let tight = num_iterations(0.0001);
+    let loose = num_iterations(0.1);
+    assert!(loose < tight);
+    // converged by the magnitudes of the pose error
+    let (distance, angle) = arm.pose_error_magnitudes(&target);
+    assert!(distance < 0.001 && angle < 0.1);
+}
+
+#[test]
+fn ik_locked_joints() {
+    let arm = create_joint_with_link_array7();
+    let angles = vec![0.8, 0.2, 0.0, -1.5, 0.0, -0.3, 0.0];
+    arm.set_joint_positions(&angles).unwrap();
+    let target = arm.end_transform();
+    let init_angles = vec![0.5, 0.1, 0.0, -1.2, 0.0, -0.1, 0.0];
+    arm.set_joint_positions(&init_angles).unwrap();
+
+    let solver = k::JacobianIkSolver::new(0.001, 0.001, 0.5, 100);
+    let locked = ["wrist_roll".to_owned()].into_iter().collect();
+    solver
+        .solve_with_locked_joints(&arm, &target, &locked)
+        .unwrap();
+    let end_angles = arm.joint_positions();
+    assert_eq!(end_angles[6], init_angles[6]);
+    assert_ne!(end_angles[0], init_angles[0]);
+    let (distance, angle) = arm.pose_error_magnitudes(&target);
+    assert!(distance < 0.001);
+    assert!(angle < 0.001);
+}
+
+#[test]
+fn ik_solve_ik_with_clone() {
+    let arm = create_joint_with_link_array6();
+    let chain = k::Chain::from_root(arm.iter().next().unwrap().clone());
+    let angles = vec![0.8, 0.2, 0.0, -1.2, 0.0, 0.1];
+    arm.set_joint_positions(&angles).unwrap();
+    let target = arm.end_transform();
+    let initial = vec![0.4, 0.1, 0.1, -1.0, 0.1, 0.1];
+    arm.set_joint_positions(&initial).unwrap();
+
+    let solver: Box<dyn k::InverseKinematicsSolver<f64>> =
+        Box::new(k::JacobianIkSolver::new(0.001, 0.001, 0.8, 100));
+    let solved = k::solve_ik(&chain, "wrist_pitch", &target, solver.as_ref()).unwrap();
+    assert_eq!(chain.joint_positions(), initial);
+    arm.set_joint_positions(&solved).unwrap();
+    let (distance, angle) = arm.pose_error_magnitudes(&target);
+    assert!(distance < 0.001);
+    assert!(angle < 0.001);
+    assert!(k::solve_ik(&chain, "no_exist", &target, solver.as_ref()).is_err());
+}
+
+#[test]
+fn ik_joint_limit_avoidance() {
+    // track a target moving along a line, and sum the joint limit cost over the track
+    let track = |solver: &k::JacobianIkSolver<f64>| {
+        let arm = create_limited_arm(7);
+        arm.set_joint_positions(&[0.5, 0.2, 1.6, -1.2, -1.5, 0.3, 0.1])
+            .unwrap();
+        let mut target = arm.end_transform();
+        let mut cost = 0.0;
+        for _ in 0..20 {
+            target.translation.vector.y += 0.005;
+            solver.solve(&arm, &target).unwrap();
+            cost += arm
+                .joint_positions()
+                .iter()
+                .map(|q| (q / 4.0).powi(2))
+                .sum::<f64>();
+        }
+        cost
+    };
+    let mut solver = k::JacobianIkSolver::new(0.0001, 0.0001, 0.5, 100);
+    let cost_without = track(&solver);
+    let arm = create_limited_arm(7);
+    solver.set_nullspace_function(Box::new(
+        k::create_joint_limit_avoidance_nullspace_function(&arm, 1.0),
+    ));
+    let cost_with = track(&solver);
+    assert!(cost_with < cost_without);
+}
+
+#[test]
+fn ik_least_norm() {
+    let solve = |least_norm: bool| {
+        let arm = create_limited_arm(7);
+        let start = [0.5, 0.2, 1.6, -1.2, -1.5, 0.3, 0.1];
+        arm.set_joint_positions(&start).unwrap();
+        let mut target = arm.end_transform();
+        target.translation.vector.x -= 0.3;
+        target.translation.vector.z += 0.3;
+        let mut solver = k::JacobianIkSolver::new(0.0001, 0.0001, 0.5, 100);
+        solver.least_norm = least_norm;
+        let constraints = k::Constraints {
+            rotation_x: false,
+            rotation_y: false,
+            rotation_z: false,
+            ..Default::default()
+        };
+        solver
+            .solve_with_constraints(&arm, &target, &constraints)
+            .unwrap();
+        let (position_error, _) = arm.pose_error_magnitudes(&target);
+        assert!(position_error < 0.001);
+        arm.joint_positions()
+            .iter()
+            .zip(start.iter())
+            .map(|(q, s)| (q - s).powi(2))
+            .sum::<f64>()
+            .sqrt()
+    };
+    let change_default = solve(false);
+    let change_least_norm = solve(true);
+    assert!(change_least_norm < change_default);
+}
+
+#[test]
+fn ik_least_norm_independent_of_position_tolerance() {
+    // the result of the least norm mode does not depend on the position tolerance
+    let solve = |position_tolerance: f64| {
+        let arm = create_limited_arm(7);
+        arm.set_joint_positions(&[0.5, 0.2, 1.6, -1.2, -1.5, 0.3, 0.1])
+            .unwrap();
+        let mut target = arm.end_transform();
+        target.translation.vector.x -= 0.3;
+        let mut solver = k::JacobianIkSolver::new(position_tolerance, 0.0001, 0.5, 200);
+        solver.set_least_norm(1.0e-6);
+        let constraints = k::Constraints {
+            rotation_x: false,
+            rotation_y: false,
+            rotation_z: false,
+            ..Default::default()
+        };
+        solver
+            .solve_with_constraints(&arm, &target, &constraints)
+            .unwrap();
+        arm.joint_positions()
+    };
+    let loose = solve(1.0e-3);
+    let tight = solve(1.0e-5);
+    let diff = loose
+        .iter()
+        .zip(tight.iter())
+        .map(|(a, b)| (a - b).powi(2))
+        .sum::<f64>()
+        .sqrt();
+    assert!(diff < 0.01, "{loose:?} {tight:?}");
+}
+
+#[test]
+fn ik_tool_transform() {
+    let mut arm = create_joint_with_link_array6();
+    let tool = k::Isometry3::translation(0.0, 0.05, -0.2);
+    arm.set_tool_transform(tool);
+    let positions = vec![0.5, 0.2, 0.1, -1.0, 0.3, -0.3];
+    arm.set_joint_positions(&positions).unwrap();
+
+    // FK reaches the tool tip
+    let flange = *arm.update_transforms().last().unwrap();
+    let tip = arm.end_transform();
+    assert!((tip.translation.vector - (flange * tool).translation.vector).norm() < 1e-10);
+
+    // the linear rows of the jacobian are the velocity of the tool tip
+    let j = k::jacobian(&arm);
+    let eps = 1.0e-6;
+    for i in 0..arm.dof() {
+        let mut moved = positions.clone();
+        moved[i] += eps;
+        arm.set_joint_positions(&moved).unwrap();
+        let velocity = (arm.end_transform().translation.vector - tip.translation.vector) / eps;
+        for r in 0..3 {
+            assert!((velocity[r] - j[(r, i)]).abs() < 1e-4);
+        }
+    }
+
+    // IK moves the tool tip to the target
+    arm.set_joint_positions(&positions).unwrap();
+    let mut target = tip;
+    target.translation.vector.x += 0.05;
+    target.translation.vector.z -= 0.05;
+    let solver = k::JacobianIkSolver::new(0.0001, 0.001, 0.5, 100);
+    solver.solve(&arm, &target).unwrap();
+    let (position_error, rotation_error) = arm.pose_error_magnitudes(&target);
+    assert!(position_error < 0.0001);
+    assert!(rotation_error < 0.001);
+    let flange = *arm.update_transforms().last().unwrap();
+    assert!((flange.translation.vector - target.translation.vector).norm() > 0.1);
+}
+
+#[test]
+fn ik_solve_all_planar_branches() {
+    let j0 = k::NodeBuilder::new()
+        .name("shoulder")
+        .joint_type(k::JointType::Rotational {
+            axis: Vector3::z_axis(),
+        })
+        .into_node();
+    let j1 = k::NodeBuilder::new()
+        .name("elbow")
+        .translation(Translation3::new(1.0, 0.0, 0.0))
+        .joint_type(k::JointType::Rotational {
+            axis: Vector3::z_axis(),
+        })
+        .into_node();
+    let hand = k::NodeBuilder::new()
+        .name("hand")
+        .translation(Translation3::new(1.0, 0.0, 0.0))
+        .into_node();
+    connect![j0 => j1 => hand];
+    let arm = k::SerialChain::<f64>::from_end(&hand);
+    arm.set_joint_positions(&[0.1, 0.5]).unwrap();
+    let target = k::Isometry3::translation(1.2, 0.5, 0.0);
+    let constraints = k::Constraints {
+        position_z: false,
+        rotation_x: false,
+        rotation_y: false,
+        rotation_z: false,
+        ..Default::default()
+    };
+    let solutions = arm.solve_all_with_constraints(&target, &constraints);
+    assert_eq!(solutions.len(), 2);
+    // elbow up and down
+    assert!(solutions[0][1] * solutions[1][1] < 0.0);
+    assert!((solutions[0][1] + solutions[1][1]).abs() < 0.01);
+    assert_eq!(arm.joint_positions(), vec![0.1, 0.5]);
+    for solution in &solutions {
+        arm.set_joint_positions(solution).unwrap();
+        let reached = arm.end_transform().translation.vector;
+        assert!((reached - target.translation.vector).norm() < 0.01);
+    }
+}
+
+#[derive(Default)]
+struct RecordingTelemetry {
+    iterations: std::sync::Mutex<Vec<(usize, f64, f64)>>,
+    limit_hits: std::sync::Mutex<Vec<String>>,
+}
+
+impl k::Telemetry<f64> for RecordingTelemetry {
+    fn on_ik_iteration(&self, iteration: usize, position_error: f64, rotation_error: f64) {
+        self.iterations
+            .lock()
+            .unwrap()
+            .push((iteration, position_error, rotation_error));
+    }
+    fn on_limit_hit(&self, joint_name: &str, _position: f64) {
+        self.limit_hits.lock().unwrap().push(joint_name.to_owned());
+    }
+}
+
+#[test]
+fn ik_telemetry() {
+    let mut arm = create_limited_arm(6);
+    let telemetry = std::sync::Arc::new(RecordingTelemetry::default());
+    arm.set_telemetry(telemetry.clone());
+    arm.set_joint_positions(&[0.2, 0.3, 0.1, -0.8, 0.2, -0.3])
+        .unwrap();
+    let solver = k::JacobianIkSolver::new(0.001, 0.005, 0.5, 50);
+
+    // not converged: one event per iteration
+    let mut target = arm.end_transform();
+    target.translation.vector.z += 10.0;
+    assert!(solver.solve(&arm, &target).is_err());
+    let iterations = telemetry.iterations.lock().unwrap().clone();
+    assert_eq!(iterations.len(), 50);
+    for (i, (iteration, _, _)) in iterations.iter().enumerate() {
+        assert_eq!(i, *iteration);
+    }
+    assert!(!telemetry.limit_hits.lock().unwrap().is_empty());
+
+    // converged: the last event is within the tolerances
+    telemetry.iterations.lock().unwrap().clear();
+    let mut target = arm.end_transform();
+    target.translation.vector.x += 0.02;
+    solver.solve(&arm, &target).unwrap();
+    let iterations = telemetry.iterations.lock().unwrap().clone();
+    assert!(!iterations.is_empty() && iterations.len() < 50);
+    let (_, position_error, rotation_error) = *iterations.last().unwrap();
+    assert!(position_error < 0.001 && rotation_error < 0.005);
+}
+
+#[test]
+fn ik_orientation_workspace() {
+    let mut arm = create_limited_arm(6);
+    // the end is 0.1 away from the wrist, so its direction is constrained at the edge of the reach
+    arm.set_tool_transform(k::Isometry3::translation(0.0, 0.0, -0.1));
+    // the largest angle between the directions of the tool
+    let reach_cone = |position: na::Point3<f64>| {
+        let orientations = arm.orientation_workspace(&position, 30);
+        assert!(!orientations.is_empty());
+        let directions = orientations
+            .iter()
+            .map(|q| q * -Vector3::z())
+            .collect::<Vec<_>>();
+        let mut max_angle = 0.0f64;
+        for a in &directions {
+            for b in &directions {
+                max_angle = max_angle.max(a.angle(b));
+            }
+        }
+        max_angle
+    };
+    // the arm is 0.85 long and hangs down from (0, 0.1, 0)
+    let central = reach_cone(na::Point3::new(0.2, 0.1, -0.4));
+    let edge = reach_cone(na::Point3::new(0.0, 0.1, -0.849));
+    assert!(central > edge * 2.0);
+}
+
+#[test]
+fn ik_follow_cartesian_path() {
+    let arm = create_limited_arm(6);
+    let positions = vec![0.2, 0.3, 0.1, -0.8, 0.2, -0.3];
+    arm.set_joint_positions(&positions).unwrap();
+    let start = arm.end_transform();
+    let mut end = start;
+    end.translation.vector.x += 0.1;
+    end.translation.vector.y -= 0.05;
+    let waypoints = k::trajectory::cartesian_interpolate(&start, &end, 20);
+    let solver = k::JacobianIkSolver::new(0.0001, 0.001, 0.5, 100);
+    let trajectory = arm.follow_cartesian_path(&waypoints, &solver).unwrap();
+    assert_eq!(trajectory.len(), waypoints.len());
+    // continuous
+    let mut prev = &positions;
+    for q in &trajectory {
+        for (a, b) in q.iter().zip(prev.iter()) {
+            assert!((a - b).abs() < 0.1);
+        }
+        prev = q;
+    }
+    // on the straight line
+    for (q, waypoint) in trajectory.iter().zip(waypoints.iter()) {
+        arm.set_joint_positions(q).unwrap();
+        let reached = arm.end_transform().translation.vector;
+        assert!((reached - waypoint.translation.vector).norm() < 0.001);
+    }
+
+    // unreachable waypoint
+    arm.set_joint_positions(&positions).unwrap();
+    let mut far = end;
+    far.translation.vector.z -= 10.0;
+    let waypoints = vec![start, end, far];
+    match arm.follow_cartesian_path(&waypoints, &solver) {
+        Err(k::Error::UnreachableWaypointError { index, .. }) => assert_eq!(index, 2),
+        other => panic!("unexpected {other:?}"),
+    }
+    assert_eq!(arm.joint_positions(), positions);
+}
+
+#[test]
+fn ik_broyden_update() {
+    let positions = vec![0.2, 0.3, 0.1, -0.8, 0.2, -0.3];
+    let solve = |broyden_interval: usize| {
+        let arm = create_limited_arm(6);
+        arm.set_joint_positions(&positions).unwrap();
+        let mut target = arm.end_transform();
+        target.translation.vector.x += 0.1;
+        target.translation.vector.z += 0.05;
+        let mut solver = k::JacobianIkSolver::new(0.0001, 0.001, 0.5, 200);
+        solver.broyden_interval = broyden_interval;
+        solver.solve(&arm, &target).unwrap();
+        arm.joint_positions()
+    };
+    // the number of the jacobian evaluations is tested in the unit tests of ik.rs
+    let full = solve(0);
+    let broyden = solve(5);
+    for (a, b) in full.iter().zip(broyden.iter()) {
+        assert!((a - b).abs() < 0.01);
+    }
+}
+
+#[test]
+fn ik_joint_weights() {
+    let start = [0.5, 0.2, 1.6, -1.2, -1.5, 0.3, 0.1];
+    let solve = |joint_weights: Vec<f64>| {
+        let arm = create_limited_arm(7);
+        arm.set_joint_positions(&start).unwrap();
+        let mut target = arm.end_transform();
+        target.translation.vector.x -= 0.1;
+        target.translation.vector.y += 0.1;
+        let mut solver = k::JacobianIkSolver::new(0.0001, 0.0001, 0.5, 100);
+        solver.joint_weights = joint_weights;
+        let constraints = k::Constraints {
+            rotation_x: false,
+            rotation_y: false,
+            rotation_z: false,
+            ..Default::default()
+        };
+        solver
+            .solve_with_constraints(&arm, &target, &constraints)
+            .unwrap();
+        let (position_error, _) = arm.pose_error_magnitudes(&target);
+        assert!(position_error < 0.001);
+        (arm.joint_positions()[0] - start[0]).abs()
+    };
+    let change_default = solve(vec![]);
+    let change_weighted = solve(vec![100.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0]);
+    assert!(change_weighted < change_default * 0.5);
+
+    let arm = create_limited_arm(7);
+    let mut solver = k::JacobianIkSolver::<f64>::default();
+    solver.joint_weights = vec![1.0; 3];
+    assert!(solver.solve(&arm, &arm.end_transform()).is_err());
+    for invalid in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+        solver.joint_weights = vec![1.0, 1.0, invalid, 1.0, 1.0, 1.0, 1.0];
+        assert!(matches!(
+            solver.solve(&arm, &arm.end_transform()),
+            Err(k::Error::InvalidParameterError { .. })
+        ));
+    }
+}
+
+/// Planar 3 links arm with a spherical obstacle fixed to the base and a sphere at the elbow
+fn create_planar_arm_with_obstacle(obstacle: Vector3<f64>) -> k::SerialChain<f64> {
+    use k::link::*;
+
+    let sphere = |name: &str, center: Vector3<f64>, radius: f64| {
+        LinkBuilder::new()
+            .name(name)
+            .add_collision(Collision::new(
+                name.to_owned(),
+                na::Isometry3::translation(center.x, center.y, center.z),
+                Geometry::Sphere { radius },
+            ))
+            .finalize()
+    };
+    let rotational = |name: &str, x: f64| {
+        k::NodeBuilder::new()
+            .name(name)
+            .translation(Translation3::new(x, 0.0, 0.0))
+            .joint_type(k::JointType::Rotational {
+                axis: Vector3::z_axis(),
+            })
+            .into_node()
+    };
+    let j0 = rotational("j0", 0.0);
+    let j1 = rotational("j1", 1.0);
+    let j2 = rotational("j2", 1.0);
+    let tip = k::NodeBuilder::new()
+        .name("tip")
+        .translation(Translation3::new(1.0, 0.0, 0.0))
+        .into_node();
+    // the links are placed at the frames of their parent joints
+    j0.set_link(Some(sphere("obstacle", obstacle, 0.2)));
+    j2.set_link(Some(sphere("elbow", Vector3::new(1.0, 0.0, 0.0), 0.1)));
+    connect![j0 => j1 => j2 => tip];
+    k::SerialChain::from_end(&tip)
+}
+
+#[test]
+fn ik_self_collision_margin() {
+    let start = [0.3, 0.6, 0.4];
+    let constraints = k::Constraints {
+        position_z: false,
+        rotation_x: false,
+        rotation_y: false,
+        rotation_z: false,
+        ..Default::default()
+    };
+    let target = na::Isometry3::translation(1.5, 1.2, 0.0);
+    let solver = k::JacobianIkSolver::new(0.0001, 0.001, 0.5, 200);
+
+    // the elbow of the default solution
+    let arm = create_planar_arm_with_obstacle(Vector3::new(10.0, 0.0, 0.0));
+    arm.set_joint_positions(&start).unwrap();
+    solver
+        .solve_with_constraints(&arm, &target, &constraints)
+        .unwrap();
+    let elbow = arm.update_transforms()[2].translation.vector;
+
+    // put the obstacle near there, then the default solution collides
+    let arm = create_planar_arm_with_obstacle(elbow + Vector3::new(0.05, 0.0, 0.0));
+    arm.set_joint_positions(&start).unwrap();
+    solver
+        .solve_with_constraints(&arm, &target, &constraints)
+        .unwrap();
+    assert!(arm.min_self_distance().unwrap().0 < 0.0);
+
+    let mut solver = k::JacobianIkSolver::new(0.0001, 0.001, 0.5, 200);
+    solver.self_collision_margin = Some(0.05);
+    arm.set_joint_positions(&start).unwrap();
+    solver
+        .solve_with_constraints(&arm, &target, &constraints)
+        .unwrap();
+    let (position_error, _) = arm.pose_error_magnitudes(&target);
+    assert!(position_error < 0.001);
+    assert!(arm.min_self_distance().unwrap().0 >= 0.05);
+}
+
+#[test]
+fn ik_self_collision_margin_urdf() {
+    use k::link::*;
+
+    let chain = k::Chain::<f64>::from(
+        urdf_rs::read_from_string(include_str!("../urdf/sample.urdf")).unwrap(),
+    );
+    // the boxes of the visuals as the collisions, which overlap at the joints
+    for link_name in [
+        "l_shoulder1",
+        "l_shoulder2",
+        "l_shoulder3",
+        "l_elbow1",
+        "l_wrist1",
+        "l_wrist2",
+    ] {
+        let node = chain.find_link(link_name).unwrap();
+        let mut link = node.link().clone().unwrap();
+        link.collisions = link
+            .visuals
+            .iter()
+            .map(|v| Collision::new(v.name.clone(), *v.origin(), v.geometry.clone()))
+            .collect();
+        node.set_link(Some(link));
+    }
+    let arm = k::SerialChain::from_end_to_root(
+        chain.find("l_wrist_pitch").unwrap(),
+        chain.find("l_shoulder_yaw").unwrap(),
+    );
+    // the world transforms of the parents of the arm
+    chain.update_transforms();
+    arm.set_joint_positions(&[0.2, 0.2, 0.0, -1.0, 0.0, 0.0])
+        .unwrap();
+    let target = arm.end_transform();
+    assert!(arm.min_self_distance().unwrap().0 > 0.0);
+
+    let mut solver = k::JacobianIkSolver::new(0.001, 0.005, 0.5, 100);
+    solver.self_collision_margin = Some(0.01);
+    arm.set_joint_positions(&[0.0, 0.0, 0.0, -0.5, 0.0, 0.0])
+        .unwrap();
+    solver.solve(&arm, &target).unwrap();
+    assert!(arm.min_self_distance().unwrap().0 >= 0.01);
+}